@@ -0,0 +1,126 @@
+//! A container for the per-node results of any number of analyses.
+//! Each analysis registers its side table (a `NodeVec`, a `BitSet`,
+//! ...) under an `AnalysisKey`, which names the table and fixes its
+//! type; consumers then look tables up by key, and `dump` writes out
+//! everything known about each node in a single pass.
+
+use super::{Graph, NodeIndex};
+use super::bit_set::BitSet;
+use super::node_vec::NodeVec;
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::{self, Debug, Write};
+use core::marker::PhantomData;
+
+#[cfg(test)]
+mod test;
+
+/// Names a side table of type `T`.
+pub struct AnalysisKey<T> {
+    pub name: &'static str,
+    data: PhantomData<fn() -> T>,
+}
+
+impl<T> AnalysisKey<T> {
+    pub const fn new(name: &'static str) -> Self {
+        AnalysisKey { name, data: PhantomData }
+    }
+}
+
+impl<T> Clone for AnalysisKey<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AnalysisKey<T> {}
+
+/// A table with an entry for (some of) the nodes in a graph.
+pub trait SideTable<G: Graph>: Any {
+    /// Writes the entry for `node`, returning false if there is none.
+    fn dump_node(&self, node: G::Node, out: &mut dyn Write) -> Result<bool, fmt::Error>;
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<G: Graph + 'static, T: Debug + 'static> SideTable<G> for NodeVec<G, T> {
+    fn dump_node(&self, node: G::Node, out: &mut dyn Write) -> Result<bool, fmt::Error> {
+        if node.as_usize() >= self.len() {
+            return Ok(false);
+        }
+        write!(out, "{:?}", self[node])?;
+        Ok(true)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl<G: Graph + 'static> SideTable<G> for BitSet<G> {
+    fn dump_node(&self, node: G::Node, out: &mut dyn Write) -> Result<bool, fmt::Error> {
+        let bits = self.bits(node);
+        let set: Vec<_> = (0..self.bits_per_node()).filter(|&i| bits.get(i)).collect();
+        write!(out, "{:?}", set)?;
+        Ok(true)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct AnalysisResults<G: Graph> {
+    tables: Vec<(&'static str, Box<dyn SideTable<G>>)>,
+}
+
+impl<G: Graph + 'static> AnalysisResults<G> {
+    pub fn new() -> Self {
+        AnalysisResults { tables: vec![] }
+    }
+
+    /// Registers `table` under `key`. Panics if some table was already
+    /// registered under the same name.
+    pub fn insert<T: SideTable<G>>(&mut self, key: AnalysisKey<T>, table: T) {
+        assert!(self.tables.iter().all(|&(name, _)| name != key.name),
+                "analysis `{}` registered twice",
+                key.name);
+        self.tables.push((key.name, Box::new(table)));
+    }
+
+    pub fn get<T: SideTable<G>>(&self, key: AnalysisKey<T>) -> Option<&T> {
+        self.tables
+            .iter()
+            .find(|&&(name, _)| name == key.name)
+            .map(|(_, table)| {
+                table.as_any()
+                     .downcast_ref()
+                     .unwrap_or_else(|| panic!("analysis `{}` has the wrong type", key.name))
+            })
+    }
+
+    /// Writes, for each node of `graph`, the entries of every table,
+    /// in the order in which the tables were registered.
+    pub fn dump(&self, graph: &G, out: &mut dyn Write) -> Result<(), fmt::Error> {
+        for index in 0..graph.num_nodes() {
+            let node = G::Node::from(index);
+            writeln!(out, "{:?}:", node)?;
+            for &(name, ref table) in &self.tables {
+                let mut entry = String::new();
+                if table.dump_node(node, &mut entry)? {
+                    writeln!(out, "  {}: {}", name, entry)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<G: Graph + 'static> Default for AnalysisResults<G> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,75 @@
+use test::TestGraph;
+use dominators::dominators;
+use node_vec::NodeVec;
+use bit_set::BitSet;
+
+use super::*;
+
+const IMMEDIATE_DOMINATORS: AnalysisKey<NodeVec<TestGraph, Option<usize>>> =
+    AnalysisKey::new("immediate-dominators");
+
+const MARKS: AnalysisKey<BitSet<TestGraph>> = AnalysisKey::new("marks");
+
+fn diamond() -> TestGraph {
+    TestGraph::new(0, &[
+        (0, 1),
+        (0, 2),
+        (1, 3),
+        (2, 3),
+    ])
+}
+
+#[test]
+fn insert_and_get() {
+    let graph = diamond();
+    let mut results = AnalysisResults::new();
+    assert!(results.get(IMMEDIATE_DOMINATORS).is_none());
+
+    let dominators = dominators(&graph);
+    let idoms = NodeVec::from_fn(&graph, |n| dominators.all_immediate_dominators()[n]);
+    results.insert(IMMEDIATE_DOMINATORS, idoms);
+
+    let idoms = results.get(IMMEDIATE_DOMINATORS).unwrap();
+    assert_eq!(idoms[3], Some(0));
+}
+
+#[test]
+fn dump() {
+    let graph = diamond();
+    let mut results = AnalysisResults::new();
+
+    let dominators = dominators(&graph);
+    results.insert(IMMEDIATE_DOMINATORS,
+                   NodeVec::from_fn(&graph, |n| dominators.all_immediate_dominators()[n]));
+
+    let mut marks = BitSet::new(&graph, 3);
+    marks.insert(1, 0);
+    marks.insert(1, 2);
+    results.insert(MARKS, marks);
+
+    let mut out = String::new();
+    results.dump(&graph, &mut out).unwrap();
+    assert_eq!(out, "\
+0:
+  immediate-dominators: Some(0)
+  marks: []
+1:
+  immediate-dominators: Some(0)
+  marks: [0, 2]
+2:
+  immediate-dominators: Some(0)
+  marks: []
+3:
+  immediate-dominators: Some(0)
+  marks: []
+");
+}
+
+#[test]
+#[should_panic(expected = "registered twice")]
+fn duplicate_key() {
+    let graph = diamond();
+    let mut results = AnalysisResults::new();
+    results.insert(MARKS, BitSet::new(&graph, 1));
+    results.insert(MARKS, BitSet::new(&graph, 1));
+}
@@ -0,0 +1,63 @@
+//! Breadth-first search: the nodes reachable from a given node, in
+//! order of their distance from it, along with that distance (the
+//! number of edges on a shortest path).
+
+use super::Graph;
+use super::node_vec::NodeVec;
+use super::vec_graph::VecGraph;
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+#[cfg(test)]
+mod test;
+
+pub fn bfs<G: Graph>(graph: &G, start_node: G::Node) -> Bfs<G> {
+    let mut depths: NodeVec<G, Option<usize>> = NodeVec::from_default(graph);
+    let mut order = Vec::with_capacity(graph.num_nodes());
+    let mut queue = VecDeque::new();
+    depths[start_node] = Some(0);
+    queue.push_back(start_node);
+    while let Some(node) = queue.pop_front() {
+        order.push(node);
+        let depth = depths[node].unwrap() + 1;
+        for successor in graph.successors(node) {
+            if depths[successor].is_none() {
+                depths[successor] = Some(depth);
+                queue.push_back(successor);
+            }
+        }
+    }
+    Bfs { order, depths }
+}
+
+/// Searches the graph with the given `edges` breadth-first from
+/// `start`; see `VecGraph`.
+pub fn bfs_from_edges(start: usize, edges: &[(usize, usize)]) -> Bfs<VecGraph> {
+    let graph = VecGraph::new(start, edges);
+    bfs(&graph, start)
+}
+
+pub struct Bfs<G: Graph> {
+    order: Vec<G::Node>,
+    depths: NodeVec<G, Option<usize>>,
+}
+
+impl<G: Graph> Bfs<G> {
+    /// The nodes reached, in the order the search visited them: by
+    /// depth, and nodes of the same depth in the order of the edges
+    /// that reached them. The first is the start node.
+    pub fn order(&self) -> &[G::Node] {
+        &self.order
+    }
+
+    /// The number of edges on a shortest path from the start node to
+    /// `node`, or `None` if `node` cannot be reached from it.
+    pub fn depth(&self, node: G::Node) -> Option<usize> {
+        self.depths[node]
+    }
+
+    pub fn is_reachable(&self, node: G::Node) -> bool {
+        self.depths[node].is_some()
+    }
+}
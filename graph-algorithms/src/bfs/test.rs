@@ -0,0 +1,36 @@
+use test::TestGraph;
+
+use super::*;
+
+#[test]
+fn diamond_with_shortcut() {
+    // 0 -> 1 -> 2 -> 3, with 0 -> 4 -> 3 and 5 -> 3 unreachable
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (1, 2),
+        (2, 3),
+        (0, 4),
+        (4, 3),
+        (5, 3),
+    ]);
+    let bfs = bfs(&graph, 0);
+    assert_eq!(bfs.order(), &[0, 1, 4, 2, 3]);
+    assert_eq!(bfs.depth(0), Some(0));
+    assert_eq!(bfs.depth(2), Some(2));
+    assert_eq!(bfs.depth(3), Some(2));
+    assert_eq!(bfs.depth(5), None);
+    assert!(!bfs.is_reachable(5));
+}
+
+#[test]
+fn loop_from_inner_node() {
+    // 0 -> 1 <-> 2: from 2, only the loop is reachable
+    let bfs = bfs_from_edges(0, &[(0, 1), (1, 2), (2, 1)]);
+    assert_eq!(bfs.order(), &[0, 1, 2]);
+
+    let graph = TestGraph::new(0, &[(0, 1), (1, 2), (2, 1)]);
+    let from_inner = super::bfs(&graph, 2);
+    assert_eq!(from_inner.order(), &[2, 1]);
+    assert_eq!(from_inner.depth(1), Some(1));
+    assert_eq!(from_inner.depth(0), None);
+}
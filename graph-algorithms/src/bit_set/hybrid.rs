@@ -0,0 +1,126 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem;
+
+use super::{BitBuf, BitSlice, NodeBitSet};
+use super::super::{Graph, NodeIndex};
+
+/// The most bits a row stores as indices; a row with more is stored
+/// as words. Eight indices take up as much room as the words of 512
+/// bits on 64-bit targets.
+const SPARSE_MAX: usize = 8;
+
+/// A set of bits for each node, like `BitSet`, for when most of the
+/// sets hold only a few bits out of many: with thousands of loans,
+/// storing the loans in scope at each block as words takes up space
+/// quadratic in the size of the function. Each row starts out as a
+/// sorted vector of the bits that are set, and switches to words once
+/// it holds more than `SPARSE_MAX` of them.
+pub struct HybridBitSet<G: Graph> {
+    bits_per_node: usize,
+    rows: Vec<HybridRow>,
+    graph: PhantomData<G>,
+}
+
+enum HybridRow {
+    /// The bits that are set, in increasing order.
+    Sparse(Vec<usize>),
+    Dense(BitBuf),
+}
+
+impl<G: Graph> HybridBitSet<G> {
+    /// Whether the bits of `node` are stored as words.
+    pub fn is_dense(&self, node: G::Node) -> bool {
+        match self.rows[node.as_usize()] {
+            HybridRow::Sparse(_) => false,
+            HybridRow::Dense(_) => true,
+        }
+    }
+
+    fn check_bit(&self, bit: usize) {
+        debug_assert!(bit < self.bits_per_node,
+                      "bit {} is out of range for a HybridBitSet with {} bits per node",
+                      bit, self.bits_per_node);
+    }
+}
+
+impl<G: Graph> NodeBitSet<G> for HybridBitSet<G> {
+    fn new(graph: &G, bits_per_node: usize) -> Self {
+        HybridBitSet {
+            bits_per_node,
+            rows: (0..graph.num_nodes()).map(|_| HybridRow::Sparse(vec![])).collect(),
+            graph: PhantomData,
+        }
+    }
+
+    fn bits_per_node(&self) -> usize {
+        self.bits_per_node
+    }
+
+    fn heap_size(&self) -> usize {
+        let contents: usize = self.rows
+            .iter()
+            .map(|row| match *row {
+                HybridRow::Sparse(ref bits) => bits.len() * mem::size_of::<usize>(),
+                HybridRow::Dense(ref buf) => buf.heap_size(),
+            })
+            .sum();
+        self.rows.len() * mem::size_of::<HybridRow>() + contents
+    }
+
+    fn is_set(&self, node: G::Node, bit: usize) -> bool {
+        match self.rows[node.as_usize()] {
+            HybridRow::Sparse(ref bits) => bits.binary_search(&bit).is_ok(),
+            HybridRow::Dense(ref buf) => buf.get(bit),
+        }
+    }
+
+    fn insert(&mut self, node: G::Node, bit: usize) -> bool {
+        self.check_bit(bit);
+        let bits_per_node = self.bits_per_node;
+        let row = &mut self.rows[node.as_usize()];
+        let dense = match *row {
+            HybridRow::Dense(ref mut buf) => return buf.set(bit),
+            HybridRow::Sparse(ref mut bits) => {
+                match bits.binary_search(&bit) {
+                    Ok(_) => return false,
+                    Err(index) => bits.insert(index, bit),
+                }
+                if bits.len() <= SPARSE_MAX {
+                    return true;
+                }
+                let mut buf = BitBuf::new(bits_per_node);
+                for &bit in bits.iter() {
+                    buf.set(bit);
+                }
+                buf
+            }
+        };
+        *row = HybridRow::Dense(dense);
+        true
+    }
+
+    fn insert_bits_from_slice(&mut self, node: G::Node, bits: BitSlice) -> bool {
+        if let HybridRow::Dense(ref mut buf) = self.rows[node.as_usize()] {
+            return buf.set_from(bits);
+        }
+        let mut changed = false;
+        for bit in bits.iter() {
+            changed |= self.insert(node, bit);
+        }
+        changed
+    }
+
+    fn union_into(&self, node: G::Node, buf: &mut BitBuf) -> bool {
+        match self.rows[node.as_usize()] {
+            HybridRow::Sparse(ref bits) => {
+                let mut changed = false;
+                for &bit in bits {
+                    changed |= buf.set(bit);
+                }
+                changed
+            }
+            HybridRow::Dense(ref row) => buf.set_from(row.as_slice()),
+        }
+    }
+}
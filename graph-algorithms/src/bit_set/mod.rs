@@ -1,8 +1,17 @@
-use std::marker::PhantomData;
-use std::mem;
+use alloc::vec::Vec;
+use core::iter;
+use core::marker::PhantomData;
+use core::mem;
+use core::slice;
 
 use super::{Graph, NodeIndex};
 
+mod hybrid;
+#[cfg(test)]
+mod test;
+
+pub use self::hybrid::HybridBitSet;
+
 type Word = u32;
 
 pub struct BitSet<G: Graph> {
@@ -23,13 +32,21 @@ impl<G: Graph> BitSet<G> {
         }
     }
 
+    pub fn bits_per_node(&self) -> usize {
+        self.bits_per_node
+    }
+
     fn index(&self, node: G::Node) -> usize {
         node.as_usize() * words(self.bits_per_node)
     }
 
+    /// The number of bytes the bits of all the nodes take up.
+    pub fn heap_size(&self) -> usize {
+        self.words.len() * mem::size_of::<Word>()
+    }
+
     pub fn empty_buf(&self) -> BitBuf {
-        let words = words(self.bits_per_node);
-        BitBuf { words: vec![0; words] }
+        BitBuf::new(self.bits_per_node)
     }
 
     pub fn bits(&self, node: G::Node) -> BitSlice {
@@ -78,14 +95,82 @@ impl<G: Graph> BitSet<G> {
     }
 }
 
-#[derive(Copy, Clone)]
+/// A set of bits for each node of a graph, as the dataflow analyses
+/// keep them, whichever way the bits are stored: `BitSet` stores the
+/// bits of every node as words, which is fastest when the sets are
+/// dense, while `HybridBitSet` only stores the bits that are set until
+/// there are many of them.
+pub trait NodeBitSet<G: Graph> {
+    fn new(graph: &G, bits_per_node: usize) -> Self;
+
+    fn bits_per_node(&self) -> usize;
+
+    /// The number of bytes the bits of all the nodes take up.
+    fn heap_size(&self) -> usize;
+
+    fn empty_buf(&self) -> BitBuf {
+        BitBuf::new(self.bits_per_node())
+    }
+
+    fn is_set(&self, node: G::Node, bit: usize) -> bool;
+
+    fn insert(&mut self, node: G::Node, bit: usize) -> bool;
+
+    fn insert_bits_from_slice(&mut self, node: G::Node, bits: BitSlice) -> bool;
+
+    /// Sets in `buf` each bit that is set for `node`.
+    fn union_into(&self, node: G::Node, buf: &mut BitBuf) -> bool;
+
+    /// A buffer holding a copy of the bits of `node`.
+    fn to_buf(&self, node: G::Node) -> BitBuf {
+        let mut buf = self.empty_buf();
+        self.union_into(node, &mut buf);
+        buf
+    }
+}
+
+impl<G: Graph> NodeBitSet<G> for BitSet<G> {
+    fn new(graph: &G, bits_per_node: usize) -> Self {
+        BitSet::new(graph, bits_per_node)
+    }
+
+    fn bits_per_node(&self) -> usize {
+        self.bits_per_node
+    }
+
+    fn heap_size(&self) -> usize {
+        BitSet::heap_size(self)
+    }
+
+    fn is_set(&self, node: G::Node, bit: usize) -> bool {
+        BitSet::is_set(self, node, bit)
+    }
+
+    fn insert(&mut self, node: G::Node, bit: usize) -> bool {
+        BitSet::insert(self, node, bit)
+    }
+
+    fn insert_bits_from_slice(&mut self, node: G::Node, bits: BitSlice) -> bool {
+        BitSet::insert_bits_from_slice(self, node, bits)
+    }
+
+    fn union_into(&self, node: G::Node, buf: &mut BitBuf) -> bool {
+        buf.set_from(self.bits(node))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct BitSlice<'a> {
     words: &'a [Word]
 }
 
 impl<'a> BitSlice<'a> {
+    /// A buffer holding a copy of these bits. A slice does not know
+    /// how many bits it was created for, so the buffer has room for
+    /// every bit of its words.
     pub fn to_buf(self) -> BitBuf {
         BitBuf {
+            bits: self.words.len() * WORD_BITS,
             words: self.words.to_vec()
         }
     }
@@ -95,13 +180,141 @@ impl<'a> BitSlice<'a> {
         let old_value = self.words[word];
         (old_value & (1 << bit)) != 0
     }
+
+    /// The indices of the bits that are set, in increasing order.
+    /// Words with no bit set are skipped at once, so this is much
+    /// faster than testing each bit when the bits are sparse.
+    pub fn iter(self) -> SetBits<'a> {
+        SetBits {
+            words: self.words.iter(),
+            offset: 0,
+            word: 0,
+        }
+    }
+
+    /// The lowest bit that is set here but not in `other`, if any.
+    pub fn first_not_in(self, other: BitSlice) -> Option<usize> {
+        self.words
+            .iter()
+            .zip(other.words.iter().chain(iter::repeat(&0)))
+            .enumerate()
+            .find(|&(_, (a, b))| a & !b != 0)
+            .map(|(word, (a, b))| word * WORD_BITS + (a & !b).trailing_zeros() as usize)
+    }
+
+    /// The lowest bit that is set both here and in `other`, if any.
+    pub fn first_common(self, other: BitSlice) -> Option<usize> {
+        self.words
+            .iter()
+            .zip(other.words)
+            .enumerate()
+            .find(|&(_, (a, b))| a & b != 0)
+            .map(|(word, (a, b))| word * WORD_BITS + (a & b).trailing_zeros() as usize)
+    }
+}
+
+/// The iterator returned by `BitSlice::iter()`.
+pub struct SetBits<'a> {
+    words: slice::Iter<'a, Word>,
+
+    /// The index of the lowest bit of `word`.
+    offset: usize,
+
+    /// The bits of the current word that have not been yielded yet.
+    word: Word,
+}
+
+impl<'a> Iterator for SetBits<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.word == 0 {
+            self.word = *self.words.next()?;
+            self.offset += WORD_BITS;
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        // clear the lowest set bit
+        self.word &= self.word - 1;
+        Some(self.offset - WORD_BITS + bit)
+    }
+}
+
+/// A matrix of bits with a fixed number of rows and columns, each row
+/// stored as the words of a `BitSlice`, so that a row can be combined
+/// with other bit sets over the same columns.
+pub struct BitMatrix {
+    columns: usize,
+    words: Vec<Word>,
 }
 
+impl BitMatrix {
+    pub fn new(rows: usize, columns: usize) -> Self {
+        BitMatrix { columns, words: vec![0; rows * words(columns)] }
+    }
+
+    /// The number of bytes the bits of all the rows take up.
+    pub fn heap_size(&self) -> usize {
+        self.words.len() * mem::size_of::<Word>()
+    }
+
+    pub fn row(&self, row: usize) -> BitSlice {
+        let start = row * words(self.columns);
+        BitSlice { words: &self.words[start..start + words(self.columns)] }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> bool {
+        self.row(row).get(column)
+    }
+
+    pub fn set(&mut self, row: usize, column: usize) -> bool {
+        debug_assert!(column < self.columns,
+                      "column {} is out of range for a BitMatrix with {} columns",
+                      column, self.columns);
+        let (word, bit) = words_bits(column);
+        let index = row * words(self.columns) + word;
+        let old_value = self.words[index];
+        let new_value = old_value | (1 << bit);
+        self.words[index] = new_value;
+        old_value != new_value
+    }
+}
+
+/// A set of bits with room for a fixed number of them, its capacity.
+/// Setting or killing a bit beyond the capacity is a bug (checked in
+/// debug builds); a buffer that has to hold more bits than it was
+/// created for, because the analysis added some since, must `grow`
+/// first.
+#[derive(Clone)]
 pub struct BitBuf {
+    bits: usize,
     words: Vec<Word>,
 }
 
 impl BitBuf {
+    pub fn new(bits: usize) -> Self {
+        BitBuf { bits, words: vec![0; words(bits)] }
+    }
+
+    /// The number of bits the buffer has room for.
+    pub fn capacity(&self) -> usize {
+        self.bits
+    }
+
+    /// Makes room for at least `bits` bits; the new bits are clear.
+    /// Never shrinks the buffer.
+    pub fn grow(&mut self, bits: usize) {
+        if bits > self.bits {
+            self.bits = bits;
+            self.words.resize(words(bits), 0);
+        }
+    }
+
+    /// The number of bytes the bits take up: a whole number of words,
+    /// however many bits the buffer has room for.
+    pub fn heap_size(&self) -> usize {
+        self.words.len() * mem::size_of::<Word>()
+    }
+
     pub fn as_slice(&self) -> BitSlice {
         BitSlice { words: &self.words }
     }
@@ -121,6 +334,9 @@ impl BitBuf {
     fn mutate<OP>(&mut self, index: usize, op: OP) -> bool
         where OP: FnOnce(/* value */ Word, /* mask */ Word) -> Word
     {
+        debug_assert!(index < self.bits,
+                      "bit {} is out of range for a BitBuf with capacity {} (grow it first)",
+                      index, self.bits);
         let (word, bit) = words_bits(index);
         let old_value = self.words[word];
         let new_value = op(old_value, 1 << bit);
@@ -129,20 +345,43 @@ impl BitBuf {
     }
 
     pub fn set_from(&mut self, bits: BitSlice) -> bool {
+        self.check_fits(bits);
         set_from(&mut self.words, bits)
     }
 
+    /// Clears every bit that is set in `bits`.
+    pub fn kill_from(&mut self, bits: BitSlice) -> bool {
+        self.check_fits(bits);
+        let mut changed = false;
+        for (out_word, in_word) in self.words.iter_mut().zip(bits.words) {
+            let old_value = *out_word;
+            let new_value = old_value & !*in_word;
+            *out_word = new_value;
+            changed |= old_value != new_value;
+        }
+        changed
+    }
+
     pub fn clear(&mut self) {
         for p in &mut self.words {
             *p = 0;
         }
     }
+
+    /// Combining with a longer slice would silently ignore its extra
+    /// words.
+    fn check_fits(&self, bits: BitSlice) {
+        debug_assert!(bits.words.len() <= self.words.len(),
+                      "a slice of {} words does not fit in a BitBuf with capacity {} (grow it first)",
+                      bits.words.len(), self.bits);
+    }
 }
 
+const WORD_BITS: usize = mem::size_of::<Word>() * 8;
+
 #[inline]
 fn words_bits(x: usize) -> (usize, usize) {
-    let d = mem::size_of::<Word>() * 8;
-    (x / d, x % d)
+    (x / WORD_BITS, x % WORD_BITS)
 }
 
 #[inline]
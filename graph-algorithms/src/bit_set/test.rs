@@ -0,0 +1,145 @@
+use test::TestGraph;
+
+use super::*;
+
+#[test]
+fn grow_keeps_bits() {
+    let mut buf = BitBuf::new(3);
+    buf.set(2);
+    buf.grow(70);
+    assert_eq!(buf.capacity(), 70);
+    assert!(buf.get(2));
+    assert!(!buf.get(69));
+    assert!(buf.set(69));
+    assert!(buf.get(69));
+}
+
+#[test]
+fn grow_never_shrinks() {
+    let mut buf = BitBuf::new(40);
+    buf.grow(10);
+    assert_eq!(buf.capacity(), 40);
+    buf.set(39);
+}
+
+#[test]
+fn heap_size_rounds_up_to_words() {
+    assert_eq!(BitBuf::new(0).heap_size(), 0);
+    assert_eq!(BitBuf::new(1).heap_size(), 4);
+    assert_eq!(BitBuf::new(33).heap_size(), 8);
+    let mut buf = BitBuf::new(3);
+    buf.grow(70);
+    assert_eq!(buf.heap_size(), 12);
+    assert_eq!(BitMatrix::new(3, 40).heap_size(), 24);
+}
+
+#[test]
+#[should_panic(expected = "out of range for a BitBuf with capacity 3")]
+fn set_beyond_capacity() {
+    let mut buf = BitBuf::new(3);
+    buf.set(3);
+}
+
+#[test]
+#[should_panic(expected = "does not fit in a BitBuf with capacity 3")]
+fn set_from_longer_slice() {
+    let mut buf = BitBuf::new(3);
+    let mut other = BitBuf::new(40);
+    other.set(35);
+    buf.set_from(other.as_slice());
+}
+
+#[test]
+fn first_common() {
+    let mut a = BitBuf::new(70);
+    let mut b = BitBuf::new(70);
+    a.set(3);
+    a.set(65);
+    b.set(65);
+    assert_eq!(a.as_slice().first_common(b.as_slice()), Some(65));
+    b.set(3);
+    assert_eq!(a.as_slice().first_common(b.as_slice()), Some(3));
+    assert_eq!(a.as_slice().first_common(BitBuf::new(70).as_slice()), None);
+}
+
+#[test]
+fn first_not_in() {
+    let mut a = BitBuf::new(70);
+    let mut b = BitBuf::new(70);
+    assert_eq!(a.as_slice().first_not_in(b.as_slice()), None);
+    a.set(3);
+    a.set(65);
+    b.set(3);
+    assert_eq!(a.as_slice().first_not_in(b.as_slice()), Some(65));
+    assert_eq!(b.as_slice().first_not_in(a.as_slice()), None);
+    assert_eq!(a.as_slice().first_not_in(BitBuf::new(3).as_slice()), Some(3));
+}
+
+#[test]
+fn matrix_rows_are_separate() {
+    let mut matrix = BitMatrix::new(3, 40);
+    assert!(matrix.set(1, 35));
+    assert!(!matrix.set(1, 35));
+    assert!(matrix.get(1, 35));
+    assert!(!matrix.get(0, 35));
+    assert!(!matrix.get(2, 35));
+    let mut buf = BitBuf::new(40);
+    buf.set(35);
+    assert_eq!(matrix.row(1).first_common(buf.as_slice()), Some(35));
+    assert_eq!(matrix.row(2).first_common(buf.as_slice()), None);
+}
+
+#[test]
+fn iter_set_bits() {
+    let mut buf = BitBuf::new(100);
+    assert_eq!(buf.as_slice().iter().count(), 0);
+    for &bit in &[0, 5, 31, 32, 64, 99] {
+        buf.set(bit);
+    }
+    assert_eq!(buf.as_slice().iter().collect::<Vec<_>>(), vec![0, 5, 31, 32, 64, 99]);
+
+    let mut matrix = BitMatrix::new(2, 40);
+    matrix.set(1, 33);
+    assert_eq!(matrix.row(0).iter().next(), None);
+    assert_eq!(matrix.row(1).iter().collect::<Vec<_>>(), vec![33]);
+}
+
+#[test]
+fn hybrid_becomes_dense() {
+    let graph = TestGraph::new(0, &[(0, 1)]);
+    let mut set: HybridBitSet<TestGraph> = NodeBitSet::new(&graph, 100);
+    for bit in 0..9 {
+        assert!(!set.is_dense(0));
+        assert!(set.insert(0, bit * 10));
+        assert!(!set.insert(0, bit * 10));
+    }
+    assert!(set.is_dense(0));
+    assert!(!set.is_dense(1));
+    for bit in 0..9 {
+        assert!(set.is_set(0, bit * 10));
+        assert!(!set.is_set(0, bit * 10 + 1));
+        assert!(!set.is_set(1, bit * 10));
+    }
+}
+
+#[test]
+fn hybrid_matches_bit_set() {
+    let graph = TestGraph::new(0, &[(0, 1), (1, 2)]);
+    let mut dense: BitSet<TestGraph> = BitSet::new(&graph, 70);
+    let mut hybrid: HybridBitSet<TestGraph> = NodeBitSet::new(&graph, 70);
+    let mut buf = BitBuf::new(70);
+    for &bit in &[3, 69, 32, 3, 5] {
+        buf.set(bit);
+        assert_eq!(NodeBitSet::insert(&mut hybrid, 1, bit), dense.insert(1, bit));
+    }
+    for &bit in &[0, 1, 2, 4, 6, 7, 8, 9, 10, 11] {
+        buf.set(bit);
+    }
+    assert_eq!(hybrid.insert_bits_from_slice(2, buf.as_slice()),
+               dense.insert_bits_from_slice(2, buf.as_slice()));
+    assert!(hybrid.is_dense(2));
+    assert!(!hybrid.insert_bits_from_slice(2, buf.as_slice()));
+    for node in 0..3 {
+        assert_eq!(hybrid.to_buf(node).as_slice(), dense.bits(node));
+    }
+}
@@ -1,8 +1,11 @@
 use super::Graph;
 use super::iterate::reverse_post_order;
 use super::node_vec::NodeVec;
+use super::transpose::TransposedGraph;
+use super::vec_graph::VecGraph;
 
-use std::fmt;
+use alloc::vec::Vec;
+use core::fmt;
 
 #[cfg(test)]
 mod test;
@@ -15,6 +18,22 @@ pub fn dominators<G: Graph>(graph: &G)
     dominators_given_rpo(graph, &rpo)
 }
 
+/// Computes the dominators of the graph with the given `edges`, rooted
+/// at `start`; see `VecGraph`.
+pub fn dominators_from_edges(start: usize, edges: &[(usize, usize)]) -> Dominators<VecGraph> {
+    dominators(&VecGraph::new(start, edges))
+}
+
+/// Computes the post-dominators of `graph`, i.e., the dominators of
+/// its transpose. The transposed graph starts from a virtual exit
+/// node (see `TransposedGraph::with_virtual_exit`), which is
+/// therefore the root of the result.
+pub fn post_dominators<G: Graph>(graph: G)
+                                 -> Dominators<TransposedGraph<G>>
+{
+    dominators(&TransposedGraph::with_virtual_exit(graph))
+}
+
 pub fn dominators_given_rpo<G: Graph>(graph: &G,
                                       rpo: &[G::Node])
                                       -> Dominators<G>
@@ -61,6 +80,43 @@ pub fn dominators_given_rpo<G: Graph>(graph: &G,
     }
 }
 
+/// Computes the dominance frontier of each node: the nodes `m` such
+/// that `node` dominates a predecessor of `m` but does not strictly
+/// dominate `m` itself. These are the merge points at which, in SSA
+/// form, a definition in `node` would need a phi. Uses the algorithm
+/// of Cooper, Harvey and Kennedy: starting from each predecessor of a
+/// merge point, walk up the dominator tree until reaching the merge
+/// point's immediate dominator.
+pub fn dominance_frontiers<G: Graph>(graph: &G,
+                                     dominators: &Dominators<G>)
+                                     -> NodeVec<G, Vec<G::Node>>
+{
+    let mut frontiers: NodeVec<G, Vec<G::Node>> = NodeVec::from_default(graph);
+    for index in 0..graph.num_nodes() {
+        let node = G::Node::from(index);
+        if !dominators.is_reachable(node) {
+            continue;
+        }
+        let preds: Vec<_> = graph.predecessors(node)
+                                 .filter(|&pred| dominators.is_reachable(pred))
+                                 .collect();
+        if preds.len() < 2 {
+            continue;
+        }
+        let idom = dominators.immediate_dominator(node);
+        for pred in preds {
+            let mut runner = pred;
+            while runner != idom {
+                if !frontiers[runner].contains(&node) {
+                    frontiers[runner].push(node);
+                }
+                runner = dominators.immediate_dominator(runner);
+            }
+        }
+    }
+    frontiers
+}
+
 fn intersect_opt<G: Graph>(post_order_rank: &NodeVec<G, usize>,
                            immediate_dominators: &NodeVec<G, Option<G::Node>>,
                            node1: Option<G::Node>,
@@ -140,6 +196,12 @@ impl<G: Graph> Dominators<G> {
         &self.immediate_dominators
     }
 
+    /// The dominance frontier of each node of `graph`, whose dominators
+    /// these are; see the free function `dominance_frontiers`.
+    pub fn dominance_frontiers(&self, graph: &G) -> NodeVec<G, Vec<G::Node>> {
+        dominance_frontiers(graph, self)
+    }
+
     pub fn dominator_tree(&self) -> DominatorTree<G> {
         let mut children: NodeVec<G, Vec<G::Node>> =
             NodeVec::from_default_with_len(self.immediate_dominators.len());
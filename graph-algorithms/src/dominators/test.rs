@@ -41,3 +41,102 @@ fn paper() {
                  Some(6), Some(6), Some(6)]);
 }
 
+
+#[test]
+fn post_dominators_diamond() {
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (0, 2),
+        (1, 3),
+        (2, 3),
+    ]);
+
+    // node 4 is the virtual exit
+    let post_dominators = post_dominators(&graph);
+    assert_eq!(&post_dominators.all_immediate_dominators().vec[..],
+               &[Some(3),
+                 Some(3),
+                 Some(3),
+                 Some(4),
+                 Some(4)]);
+}
+
+#[test]
+fn post_dominators_multiple_exits() {
+    // 0 -> 1 -> 2 -> 4
+    //      v
+    //      3
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (1, 2),
+        (1, 3),
+        (2, 4),
+    ]);
+
+    // neither exit (3 or 4) post-dominates 1; only the virtual exit
+    // (5) does
+    let post_dominators = post_dominators(&graph);
+    assert_eq!(&post_dominators.all_immediate_dominators().vec[..],
+               &[Some(1),
+                 Some(5),
+                 Some(4),
+                 Some(5),
+                 Some(5),
+                 Some(5)]);
+}
+
+#[test]
+fn dominance_frontiers_diamond() {
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (0, 2),
+        (1, 3),
+        (2, 3),
+    ]);
+
+    let dominators = dominators(&graph);
+    let frontiers = dominance_frontiers(&graph, &dominators);
+    assert_eq!(&frontiers.vec[..],
+               &[vec![], vec![3], vec![3], vec![]]);
+}
+
+#[test]
+fn dominance_frontiers_loop() {
+    // 0 -> 1 -> 2 -> 1, 2 -> 3
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (1, 2),
+        (2, 1),
+        (2, 3),
+    ]);
+
+    let dominators = dominators(&graph);
+    let frontiers = dominance_frontiers(&graph, &dominators);
+    assert_eq!(&frontiers.vec[..],
+               &[vec![], vec![1], vec![1], vec![]]);
+}
+
+#[test]
+fn dominance_frontiers_method() {
+    // 0 -> 1 -> 2 -> 1, 2 -> 3, 0 -> 3
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (1, 2),
+        (2, 1),
+        (2, 3),
+        (0, 3),
+    ]);
+
+    let dominators = dominators(&graph);
+    let frontiers = dominators.dominance_frontiers(&graph);
+    assert_eq!(&frontiers.vec[..],
+               &[vec![], vec![1, 3], vec![1, 3], vec![]]);
+    assert_eq!(frontiers.vec, dominance_frontiers(&graph, &dominators).vec);
+}
+
+#[test]
+fn from_edges() {
+    let dominators = dominators_from_edges(0, &[(0, 1), (0, 2), (1, 3), (2, 3), (3, 4)]);
+    assert_eq!(&dominators.all_immediate_dominators().vec[..],
+               &[Some(0), Some(0), Some(0), Some(0), Some(3)]);
+}
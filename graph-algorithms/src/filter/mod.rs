@@ -0,0 +1,95 @@
+//! A view of a graph that hides the nodes failing a predicate, along
+//! with every edge into or out of them. Node indices are unchanged
+//! (so `num_nodes` is that of the base graph), but hidden nodes have
+//! no edges and are therefore unreachable from the start node.
+//!
+//! The filter is copied into each successor/predecessor iterator, so
+//! it must be `Copy`; a closure that only borrows its environment is.
+
+use super::*;
+
+#[cfg(test)]
+mod test;
+
+pub struct FilteredGraph<G: Graph, F: Fn(G::Node) -> bool> {
+    base_graph: G,
+    filter: F,
+}
+
+impl<G: Graph, F: Fn(G::Node) -> bool> FilteredGraph<G, F> {
+    pub fn new(base_graph: G, filter: F) -> Self {
+        assert!(filter(base_graph.start_node()),
+                "FilteredGraph: start node {:?} is filtered out",
+                base_graph.start_node());
+        FilteredGraph { base_graph, filter }
+    }
+
+    pub fn contains(&self, node: G::Node) -> bool {
+        (self.filter)(node)
+    }
+}
+
+impl<G: Graph, F: Fn(G::Node) -> bool + Copy> Graph for FilteredGraph<G, F> {
+    type Node = G::Node;
+
+    fn num_nodes(&self) -> usize {
+        self.base_graph.num_nodes()
+    }
+
+    fn start_node(&self) -> Self::Node {
+        self.base_graph.start_node()
+    }
+
+    fn predecessors<'graph>(&'graph self, node: Self::Node)
+                            -> <Self as GraphPredecessors<'graph>>::Iter {
+        FilteredNodes::new(self.base_graph.predecessors(node), self.filter, self.contains(node))
+    }
+
+    fn successors<'graph>(&'graph self, node: Self::Node)
+                          -> <Self as GraphSuccessors<'graph>>::Iter {
+        FilteredNodes::new(self.base_graph.successors(node), self.filter, self.contains(node))
+    }
+}
+
+impl<'graph, G: Graph, F: Fn(G::Node) -> bool + Copy> GraphPredecessors<'graph>
+    for FilteredGraph<G, F>
+{
+    type Item = G::Node;
+    type Iter = FilteredNodes<<G as GraphPredecessors<'graph>>::Iter, F>;
+}
+
+impl<'graph, G: Graph, F: Fn(G::Node) -> bool + Copy> GraphSuccessors<'graph>
+    for FilteredGraph<G, F>
+{
+    type Item = G::Node;
+    type Iter = FilteredNodes<<G as GraphSuccessors<'graph>>::Iter, F>;
+}
+
+/// The neighbors of a node in a `FilteredGraph`: those neighbors in
+/// the base graph that pass the filter, or nothing at all if the node
+/// itself is hidden.
+pub struct FilteredNodes<I, F> {
+    base_iter: I,
+    filter: F,
+    visible: bool,
+}
+
+impl<I, F> FilteredNodes<I, F> {
+    fn new(base_iter: I, filter: F, visible: bool) -> Self {
+        FilteredNodes { base_iter, filter, visible }
+    }
+}
+
+impl<I, F> Iterator for FilteredNodes<I, F>
+    where I: Iterator, I::Item: Copy, F: Fn(I::Item) -> bool
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if !self.visible {
+            return None;
+        }
+        let filter = &self.filter;
+        self.base_iter.find(|&node| filter(node))
+    }
+}
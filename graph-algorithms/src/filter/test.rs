@@ -0,0 +1,65 @@
+use test::TestGraph;
+use dominators::dominators;
+use loop_tree::loop_tree;
+use reachable::reachable;
+
+use super::*;
+
+#[test]
+fn hidden_nodes_have_no_edges() {
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (0, 2),
+        (1, 3),
+        (2, 3),
+    ]);
+    let filtered = FilteredGraph::new(&graph, |node| node != 2);
+
+    assert_eq!(filtered.num_nodes(), 4);
+    assert_eq!(filtered.successors(0).collect::<Vec<_>>(), vec![1]);
+    assert_eq!(filtered.predecessors(3).collect::<Vec<_>>(), vec![1]);
+    assert_eq!(filtered.successors(2).count(), 0);
+    assert_eq!(filtered.predecessors(2).count(), 0);
+
+    let reachability = reachable(&filtered);
+    assert!(reachability.can_reach(0, 3));
+    assert!(!reachability.can_reach(0, 2));
+}
+
+#[test]
+fn dominators_without_cleanup() {
+    // 1 and 2 both reach 3, but 2 is a cleanup block; once it is
+    // hidden, 3 is dominated by 1
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (0, 2),
+        (1, 3),
+        (2, 3),
+    ]);
+    let filtered = FilteredGraph::new(&graph, |node| node != 2);
+
+    let dominators = dominators(&filtered);
+    assert_eq!(&dominators.all_immediate_dominators().vec[..],
+               &[Some(0),
+                 Some(0),
+                 None, // <-- hidden
+                 Some(1)]);
+}
+
+#[test]
+fn loop_tree_without_cleanup() {
+    // the edge 2 -> 1 closes a loop, but only through the hidden node 2
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (1, 2),
+        (2, 1),
+        (1, 3),
+    ]);
+    assert_eq!(loop_tree(&graph).loop_head_of_node(1), Some(1));
+
+    let filtered = FilteredGraph::new(&graph, |node| node != 2);
+    let loop_tree = loop_tree(&filtered);
+    assert_eq!(loop_tree.loop_head_of_node(0), None);
+    assert_eq!(loop_tree.loop_head_of_node(1), None);
+    assert_eq!(loop_tree.loop_head_of_node(3), None);
+}
@@ -1,6 +1,8 @@
 use super::Graph;
 use super::node_vec::NodeVec;
 
+use alloc::vec::Vec;
+
 #[cfg(test)]
 mod test;
 
@@ -12,29 +14,170 @@ pub fn post_order_from_to<G: Graph>(graph: &G,
                                     start_node: G::Node,
                                     end_node: Option<G::Node>)
                                     -> Vec<G::Node> {
-    let mut visited: NodeVec<G, bool> = NodeVec::from_default(graph);
-    let mut result: Vec<G::Node> = Vec::with_capacity(graph.num_nodes());
+    let mut walk = PostOrderWalk {
+        visited: NodeVec::from_default(graph),
+        result: Vec::with_capacity(graph.num_nodes()),
+    };
     if let Some(end_node) = end_node {
-        visited[end_node] = true;
+        walk.visited[end_node] = true;
+    }
+    if !walk.visited[start_node] {
+        depth_first_walk(graph, start_node, &mut walk);
+    }
+    walk.result
+}
+
+struct PostOrderWalk<G: Graph> {
+    visited: NodeVec<G, bool>,
+    result: Vec<G::Node>,
+}
+
+impl<G: Graph> DepthFirstVisitor<G> for PostOrderWalk<G> {
+    fn discover(&mut self, node: G::Node) {
+        self.visited[node] = true;
+    }
+
+    fn examine_edge(&mut self, _source: G::Node, target: G::Node) -> bool {
+        !self.visited[target]
+    }
+
+    fn finish(&mut self, node: G::Node) {
+        self.result.push(node);
+    }
+}
+
+/// Callbacks for `depth_first_walk`.
+pub trait DepthFirstVisitor<G: Graph> {
+    /// Invoked when the search first enters `node`, before any of its
+    /// successors are examined.
+    fn discover(&mut self, _node: G::Node) {}
+
+    /// Invoked for each edge `source -> target` out of the node on
+    /// top of the stack. Returns true if the search should descend
+    /// into `target`; the visitor is responsible for ensuring that it
+    /// does not do so for a node that was already discovered.
+    fn examine_edge(&mut self, source: G::Node, target: G::Node) -> bool;
+
+    /// Invoked once every successor of `node` has been examined (and
+    /// any that were descended into have themselves finished).
+    fn finish(&mut self, node: G::Node);
+}
+
+/// Depth-first search from `start_node`, driven by `visitor`. The
+/// search uses an explicit stack rather than recursion, so it can
+/// handle graphs with very long paths.
+pub fn depth_first_walk<G, V>(graph: &G, start_node: G::Node, visitor: &mut V)
+    where G: Graph, V: DepthFirstVisitor<G>
+{
+    visitor.discover(start_node);
+    let mut stack = vec![(start_node, graph.successors(start_node))];
+    while let Some(&mut (node, ref mut successors)) = stack.last_mut() {
+        match successors.next() {
+            Some(successor) => {
+                if visitor.examine_edge(node, successor) {
+                    visitor.discover(successor);
+                    stack.push((successor, graph.successors(successor)));
+                }
+            }
+            None => {
+                stack.pop();
+                visitor.finish(node);
+            }
+        }
     }
-    post_order_walk(graph, start_node, &mut result, &mut visited);
-    result
 }
 
-fn post_order_walk<G: Graph>(graph: &G,
-                             node: G::Node,
-                             result: &mut Vec<G::Node>,
-                             visited: &mut NodeVec<G, bool>) {
-    if visited[node] {
-        return;
+/// Callbacks for `depth_first_search`. Unlike `depth_first_walk`, the
+/// search keeps track of which nodes it has seen, and reports each edge
+/// it examines as one of four kinds; a visitor implements only the
+/// callbacks it cares about.
+pub trait DepthFirstSearchVisitor<G: Graph> {
+    /// Invoked when the search first enters `node`, before any of its
+    /// successors are examined.
+    fn discover(&mut self, _node: G::Node) {}
+
+    /// An edge to a node not discovered yet, which the search then
+    /// descends into.
+    fn tree_edge(&mut self, _source: G::Node, _target: G::Node) {}
+
+    /// An edge to a node still on the stack (possibly `source`
+    /// itself): the edge closes a cycle.
+    fn back_edge(&mut self, _source: G::Node, _target: G::Node) {}
+
+    /// An edge to a finished node that was discovered after `source`,
+    /// so is one of its descendants in the search tree.
+    fn forward_edge(&mut self, _source: G::Node, _target: G::Node) {}
+
+    /// An edge to a finished node that was discovered before `source`,
+    /// in a subtree the search has already left.
+    fn cross_edge(&mut self, _source: G::Node, _target: G::Node) {}
+
+    /// Invoked once every successor of `node` has been examined (and
+    /// any that were descended into have themselves finished).
+    fn finish(&mut self, _node: G::Node) {}
+}
+
+/// Depth-first search from `start_node`, reporting each node and edge
+/// it reaches to `visitor`. Like `depth_first_walk`, it uses an
+/// explicit stack rather than recursion.
+pub fn depth_first_search<G, V>(graph: &G, start_node: G::Node, visitor: &mut V)
+    where G: Graph, V: DepthFirstSearchVisitor<G>
+{
+    let mut search = Search {
+        states: NodeVec::from_elem(graph, &SearchState::Undiscovered),
+        num_discovered: 0,
+        visitor,
+    };
+    depth_first_walk(graph, start_node, &mut search);
+}
+
+#[derive(Copy, Clone)]
+enum SearchState {
+    Undiscovered,
+
+    /// On the stack, with the number of nodes discovered before it.
+    Active(usize),
+
+    /// Finished, with the number of nodes discovered before it.
+    Finished(usize),
+}
+
+struct Search<'v, G: Graph, V: 'v> {
+    states: NodeVec<G, SearchState>,
+    num_discovered: usize,
+    visitor: &'v mut V,
+}
+
+impl<'v, G: Graph, V: DepthFirstSearchVisitor<G>> DepthFirstVisitor<G> for Search<'v, G, V> {
+    fn discover(&mut self, node: G::Node) {
+        self.states[node] = SearchState::Active(self.num_discovered);
+        self.num_discovered += 1;
+        self.visitor.discover(node);
     }
-    visited[node] = true;
 
-    for successor in graph.successors(node) {
-        post_order_walk(graph, successor, result, visited);
+    fn examine_edge(&mut self, source: G::Node, target: G::Node) -> bool {
+        match self.states[target] {
+            SearchState::Undiscovered => {
+                self.visitor.tree_edge(source, target);
+                return true;
+            }
+            SearchState::Active(_) => self.visitor.back_edge(source, target),
+            SearchState::Finished(target_index) => match self.states[source] {
+                SearchState::Active(source_index) if source_index < target_index => {
+                    self.visitor.forward_edge(source, target)
+                }
+                _ => self.visitor.cross_edge(source, target),
+            },
+        }
+        false
     }
 
-    result.push(node);
+    fn finish(&mut self, node: G::Node) {
+        if let SearchState::Active(index) = self.states[node] {
+            self.states[node] = SearchState::Finished(index);
+        }
+        self.visitor.finish(node);
+    }
 }
 
 pub fn reverse_post_order<G: Graph>(graph: &G, start_node: G::Node) -> Vec<G::Node> {
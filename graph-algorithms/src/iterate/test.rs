@@ -43,3 +43,109 @@ fn rev_post_order_inner_loop() {
     assert_eq!(result, vec![4, 6, 2, 3]);
 }
 
+
+#[test]
+fn long_chain_post_order() {
+    // deep enough that a recursive walk would overflow the stack
+    let n = 100_000;
+    let edges: Vec<_> = (0..n).map(|i| (i, i + 1)).collect();
+    let graph = TestGraph::new(0, &edges);
+
+    let result = post_order_from(&graph, 0);
+    assert_eq!(result.len(), n + 1);
+    assert_eq!(result[0], n);
+    assert_eq!(result[n], 0);
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Event {
+    Discover(usize),
+    Tree(usize, usize),
+    Back(usize, usize),
+    Forward(usize, usize),
+    Cross(usize, usize),
+    Finish(usize),
+}
+
+struct Events(Vec<Event>);
+
+impl DepthFirstSearchVisitor<TestGraph> for Events {
+    fn discover(&mut self, node: usize) {
+        self.0.push(Event::Discover(node));
+    }
+
+    fn tree_edge(&mut self, source: usize, target: usize) {
+        self.0.push(Event::Tree(source, target));
+    }
+
+    fn back_edge(&mut self, source: usize, target: usize) {
+        self.0.push(Event::Back(source, target));
+    }
+
+    fn forward_edge(&mut self, source: usize, target: usize) {
+        self.0.push(Event::Forward(source, target));
+    }
+
+    fn cross_edge(&mut self, source: usize, target: usize) {
+        self.0.push(Event::Cross(source, target));
+    }
+
+    fn finish(&mut self, node: usize) {
+        self.0.push(Event::Finish(node));
+    }
+}
+
+#[test]
+fn search_classifies_edges() {
+    // 0 -> 1 <-> 2 -> 3 (with a self-loop), plus 0 -> 3 and 0 -> 4 -> 2
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (1, 2),
+        (2, 3),
+        (3, 3),
+        (2, 1),
+        (0, 3),
+        (0, 4),
+        (4, 2),
+    ]);
+
+    let mut events = Events(vec![]);
+    depth_first_search(&graph, 0, &mut events);
+    assert_eq!(events.0, vec![
+        Event::Discover(0),
+        Event::Tree(0, 1),
+        Event::Discover(1),
+        Event::Tree(1, 2),
+        Event::Discover(2),
+        Event::Tree(2, 3),
+        Event::Discover(3),
+        Event::Back(3, 3),
+        Event::Finish(3),
+        Event::Back(2, 1),
+        Event::Finish(2),
+        Event::Finish(1),
+        Event::Forward(0, 3),
+        Event::Tree(0, 4),
+        Event::Discover(4),
+        Event::Cross(4, 2),
+        Event::Finish(4),
+        Event::Finish(0),
+    ]);
+}
+
+#[test]
+fn long_chain_search() {
+    let n = 100_000;
+    let edges: Vec<_> = (0..n).map(|i| (i, i + 1)).collect();
+    let graph = TestGraph::new(0, &edges);
+
+    struct Count(usize);
+    impl DepthFirstSearchVisitor<TestGraph> for Count {
+        fn tree_edge(&mut self, _source: usize, _target: usize) {
+            self.0 += 1;
+        }
+    }
+    let mut count = Count(0);
+    depth_first_search(&graph, 0, &mut count);
+    assert_eq!(count.0, n);
+}
@@ -1,14 +1,28 @@
-use std::fmt::Debug;
-use std::hash::Hash;
+// Only `core` and `alloc` are used, so that the algorithms can be
+// reused in `no_std` code.
+#![cfg_attr(not(test), no_std)]
 
+#[macro_use]
+extern crate alloc;
+#[cfg(test)]
+extern crate core;
+
+use core::fmt::Debug;
+use core::hash::Hash;
+
+pub mod analysis;
+pub mod bfs;
 pub mod bit_set;
 pub mod dominators;
+pub mod filter;
 pub mod iterate;
 pub mod loop_tree;
 pub mod reachable;
+pub mod scc;
 mod reference;
 pub mod node_vec;
 pub mod transpose;
+pub mod vec_graph;
 
 #[cfg(test)]
 mod test;
@@ -43,3 +57,6 @@ pub trait NodeIndex: Copy + Debug + Eq + Ord + Hash + Into<usize> + From<usize>
     }
 }
 
+impl NodeIndex for usize {
+}
+
@@ -1,5 +1,6 @@
 use super::Graph;
 use super::dominators::{Dominators, dominators};
+use super::vec_graph::VecGraph;
 
 
 #[cfg(test)]
@@ -7,13 +8,19 @@ mod test;
 mod tree;
 mod walk;
 
-pub use self::tree::LoopTree;
+pub use self::tree::{LoopId, LoopTree};
 
 pub fn loop_tree<G: Graph>(graph: &G) -> LoopTree<G> {
     let dominators = dominators(graph);
     loop_tree_given(graph, &dominators)
 }
 
+/// Computes the loop tree of the graph with the given `edges`, rooted
+/// at `start`; see `VecGraph`.
+pub fn loop_tree_from_edges(start: usize, edges: &[(usize, usize)]) -> LoopTree<VecGraph> {
+    loop_tree(&VecGraph::new(start, edges))
+}
+
 pub fn loop_tree_given<G: Graph>(graph: &G,
                                  dominators: &Dominators<G>)
                                  -> LoopTree<G>
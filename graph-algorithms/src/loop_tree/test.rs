@@ -1,5 +1,5 @@
 use test::TestGraph;
-use super::loop_tree;
+use super::{loop_tree, loop_tree_from_edges};
 
 #[test]
 fn test1() {
@@ -141,3 +141,31 @@ fn wacked() {
     let outer_loop_id = loop_tree.loop_id(0).unwrap();
     assert_eq!(loop_tree.loop_exits(outer_loop_id), &[3]);
 }
+
+#[test]
+fn long_loop_body() {
+    // 0 -> 1 -> 2 -> ... -> n -> 1, with n large enough that a
+    // recursive head walk would overflow the stack
+    let n = 100_000;
+    let mut edges: Vec<_> = (0..n).map(|i| (i, i + 1)).collect();
+    edges.push((n, 1));
+    let graph = TestGraph::new(0, &edges);
+
+    let loop_tree = loop_tree(&graph);
+    assert_eq!(loop_tree.loop_head_of_node(0), None);
+    assert_eq!(loop_tree.loop_head_of_node(1), Some(1));
+    assert_eq!(loop_tree.loop_head_of_node(n / 2), Some(1));
+    assert_eq!(loop_tree.loop_head_of_node(n), Some(1));
+}
+
+#[test]
+fn from_edges() {
+    // 0 -> 1 -> 2 -> 3
+    //      ^    v
+    //      +--- 4
+    let loop_tree = loop_tree_from_edges(0, &[(0, 1), (1, 2), (2, 3), (2, 4), (4, 1)]);
+    assert_eq!(loop_tree.loop_head_of_node(0), None);
+    assert_eq!(loop_tree.loop_head_of_node(2), Some(1));
+    assert_eq!(loop_tree.loop_head_of_node(4), Some(1));
+    assert_eq!(loop_tree.loop_exits(loop_tree.loop_id(1).unwrap()), &[3]);
+}
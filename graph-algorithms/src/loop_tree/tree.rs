@@ -1,6 +1,8 @@
 use super::super::Graph;
 use super::super::node_vec::NodeVec;
 
+use alloc::vec::Vec;
+
 pub struct LoopTree<G: Graph> {
     loop_ids: NodeVec<G, Option<LoopId>>,
     loop_infos: Vec<LoopInfo<G>>,
@@ -1,10 +1,12 @@
 use super::tree::*;
 use super::super::Graph;
 use super::super::dominators::Dominators;
+use super::super::iterate::{DepthFirstVisitor, depth_first_walk};
 use super::super::node_vec::NodeVec;
 
-use std::collections::HashSet;
-use std::default::Default;
+use alloc::vec::Vec;
+use core::default::Default;
+use core::mem;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 enum NodeState {
@@ -26,6 +28,47 @@ pub struct LoopTreeWalk<'walk, G: Graph + 'walk> {
     dominators: &'walk Dominators<G>,
     state: NodeVec<G, NodeState>,
     loop_tree: LoopTree<G>,
+
+    /// During the head walk, the loops reached via backedges by each
+    /// node on the DFS stack (innermost node last).
+    reached_loops: Vec<LoopSet>,
+}
+
+/// A set of loops. These sets hold the loops reached by a single
+/// node, which are few, so a vector does as well as a hash set.
+#[derive(Default)]
+struct LoopSet {
+    loops: Vec<LoopId>,
+}
+
+impl LoopSet {
+    fn len(&self) -> usize {
+        self.loops.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.loops.is_empty()
+    }
+
+    fn iter(&self) -> ::core::slice::Iter<LoopId> {
+        self.loops.iter()
+    }
+
+    fn insert(&mut self, loop_id: LoopId) {
+        if !self.loops.contains(&loop_id) {
+            self.loops.push(loop_id);
+        }
+    }
+
+    fn remove(&mut self, loop_id: LoopId) {
+        self.loops.retain(|&l| l != loop_id);
+    }
+
+    fn union_with(&mut self, other: LoopSet) {
+        for loop_id in other.loops {
+            self.insert(loop_id);
+        }
+    }
 }
 
 impl<'walk, G: Graph> LoopTreeWalk<'walk, G> {
@@ -37,84 +80,18 @@ impl<'walk, G: Graph> LoopTreeWalk<'walk, G> {
             dominators: dominators,
             state: NodeVec::from_default(graph),
             loop_tree: LoopTree::new(graph),
+            reached_loops: vec![],
         }
     }
 
     pub fn compute_loop_tree(mut self) -> LoopTree<G> {
-        self.head_walk(self.graph.start_node());
+        let graph = self.graph;
+        depth_first_walk(graph, graph.start_node(), &mut self);
+        assert!(self.reached_loops.is_empty());
         self.exit_walk(self.graph.start_node());
         self.loop_tree
     }
 
-    /// First walk: identify loop heads and loop parents. This uses a
-    /// variant of Tarjan's SCC algorithm. Basically, we do a
-    /// depth-first search. Each time we encounter a backedge, the
-    /// target of that backedge is a loop-head, so we make a
-    /// corresponding loop, if we haven't done so already. We then track
-    /// the set of loops that `node` was able to reach via backedges.
-    /// The innermost such loop is the loop-id of `node`, and we then
-    /// return the set for use by the predecessor of `node`.
-    fn head_walk(&mut self,
-                 node: G::Node)
-                 -> HashSet<LoopId> {
-        assert_eq!(self.state[node], NotYetStarted);
-        self.state[node] = InProgress(None);
-
-        // Walk our successors and collect the set of backedges they
-        // reach.
-        let mut set = HashSet::new();
-        for successor in self.graph.successors(node) {
-            match self.state[successor] {
-                NotYetStarted => {
-                    set.extend(self.head_walk(successor));
-                }
-                InProgress(opt_loop_id) => {
-                    // Backedge. Successor is a loop-head.
-                    if let Some(loop_id) = opt_loop_id {
-                        set.insert(loop_id);
-                    } else {
-                        set.insert(self.promote_to_loop_head(successor));
-                    }
-                }
-                FinishedHeadWalk => {
-                    // Cross edge.
-                }
-                EnqueuedExitWalk => {
-                    unreachable!()
-                }
-            }
-        }
-
-        self.state[node] = FinishedHeadWalk;
-
-        // Assign a loop-id to this node. This will be the innermost
-        // loop that we could reach.
-        match self.innermost(&set) {
-            Some(loop_id) => {
-                self.loop_tree.set_loop_id(node, Some(loop_id));
-
-                // Check if we are the loop head. In that case, we
-                // should remove ourselves from the returned set,
-                // since our parent in the spanning tree is not a
-                // member of this loop.
-                let loop_head = self.loop_tree.loop_head(loop_id);
-                if node == loop_head {
-                    set.remove(&loop_id);
-
-                    // Now the next-innermost loop is the parent of this loop.
-                    let parent_loop_id = self.innermost(&set);
-                    self.loop_tree.set_parent(loop_id, parent_loop_id);
-                }
-            }
-            None => {
-                assert!(set.is_empty());
-                assert!(self.loop_tree.loop_id(node).is_none()); // all none by default
-            }
-        }
-
-        set
-    }
-
     fn exit_walk(&mut self, node: G::Node) {
         let mut stack = vec![node];
 
@@ -157,9 +134,9 @@ impl<'walk, G: Graph> LoopTreeWalk<'walk, G> {
         loop_id
     }
 
-    fn innermost(&self, set: &HashSet<LoopId>) -> Option<LoopId> {
+    fn innermost(&self, set: &LoopSet) -> Option<LoopId> {
         let mut innermost = None;
-        for &loop_id1 in set {
+        for &loop_id1 in set.iter() {
             if let Some(loop_id2) = innermost {
                 if self.is_inner_loop_of(loop_id1, loop_id2) {
                     innermost = Some(loop_id1);
@@ -213,3 +190,82 @@ impl<'walk, G: Graph> LoopTreeWalk<'walk, G> {
         }
     }
 }
+
+/// First walk: identify loop heads and loop parents. This uses a
+/// variant of Tarjan's SCC algorithm. Basically, we do a depth-first
+/// search. Each time we encounter a backedge, the target of that
+/// backedge is a loop-head, so we make a corresponding loop, if we
+/// haven't done so already. We then track the set of loops that
+/// `node` was able to reach via backedges. The innermost such loop is
+/// the loop-id of `node`, and we then merge the set into the set of
+/// the predecessor of `node` in the spanning tree.
+impl<'walk, G: Graph> DepthFirstVisitor<G> for LoopTreeWalk<'walk, G> {
+    fn discover(&mut self, node: G::Node) {
+        assert_eq!(self.state[node], NotYetStarted);
+        self.state[node] = InProgress(None);
+        self.reached_loops.push(LoopSet::default());
+    }
+
+    fn examine_edge(&mut self, _node: G::Node, successor: G::Node) -> bool {
+        match self.state[successor] {
+            NotYetStarted => {
+                return true;
+            }
+            InProgress(opt_loop_id) => {
+                // Backedge. Successor is a loop-head.
+                let loop_id = match opt_loop_id {
+                    Some(loop_id) => loop_id,
+                    None => self.promote_to_loop_head(successor),
+                };
+                self.reached_loops.last_mut().unwrap().insert(loop_id);
+            }
+            FinishedHeadWalk => {
+                // Cross edge.
+            }
+            EnqueuedExitWalk => {
+                unreachable!()
+            }
+        }
+        false
+    }
+
+    fn finish(&mut self, node: G::Node) {
+        let mut set = self.reached_loops.pop().unwrap();
+
+        self.state[node] = FinishedHeadWalk;
+
+        // Assign a loop-id to this node. This will be the innermost
+        // loop that we could reach.
+        match self.innermost(&set) {
+            Some(loop_id) => {
+                self.loop_tree.set_loop_id(node, Some(loop_id));
+
+                // Check if we are the loop head. In that case, we
+                // should remove ourselves from the set we hand to our
+                // parent, since our parent in the spanning tree is
+                // not a member of this loop.
+                let loop_head = self.loop_tree.loop_head(loop_id);
+                if node == loop_head {
+                    set.remove(loop_id);
+
+                    // Now the next-innermost loop is the parent of this loop.
+                    let parent_loop_id = self.innermost(&set);
+                    self.loop_tree.set_parent(loop_id, parent_loop_id);
+                }
+            }
+            None => {
+                assert!(set.is_empty());
+                assert!(self.loop_tree.loop_id(node).is_none()); // all none by default
+            }
+        }
+
+        // Merge into our parent's set, always folding the smaller of
+        // the two sets into the larger.
+        if let Some(parent_set) = self.reached_loops.last_mut() {
+            if parent_set.len() < set.len() {
+                mem::swap(parent_set, &mut set);
+            }
+            parent_set.union_with(set);
+        }
+    }
+}
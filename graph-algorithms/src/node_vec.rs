@@ -1,7 +1,8 @@
-use std::default::Default;
-use std::marker::PhantomData;
-use std::ops::{Index, IndexMut};
-pub use std::slice::Iter;
+use alloc::vec::Vec;
+use core::default::Default;
+use core::marker::PhantomData;
+use core::ops::{Index, IndexMut};
+pub use core::slice::Iter;
 
 use super::Graph;
 
@@ -4,6 +4,7 @@
 use super::{Graph, NodeIndex};
 use super::bit_set::BitSet;
 use super::iterate::reverse_post_order;
+use super::vec_graph::VecGraph;
 
 #[cfg(test)]
 mod test;
@@ -14,6 +15,12 @@ pub fn reachable<G: Graph>(graph: &G)
     reachable_given_rpo(graph, &reverse_post_order)
 }
 
+/// Computes which nodes of the graph with the given `edges` can reach
+/// which others, from `start`; see `VecGraph`.
+pub fn reachable_from_edges(start: usize, edges: &[(usize, usize)]) -> Reachability<VecGraph> {
+    reachable(&VecGraph::new(start, edges))
+}
+
 pub fn reachable_given_rpo<G: Graph>(graph: &G,
                                      reverse_post_order: &[G::Node])
                                      -> Reachability<G> {
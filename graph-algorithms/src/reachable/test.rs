@@ -52,3 +52,13 @@ fn test2() {
     assert!(!reachable.can_reach(33, 35));
     assert!(!reachable.can_reach(35, 33));
 }
+
+#[test]
+fn from_edges() {
+    // node 3 is only reachable from itself
+    let reachable = reachable_from_edges(0, &[(0, 1), (1, 2), (2, 1), (3, 2)]);
+    assert!((0..3).all(|i| reachable.can_reach(0, i)));
+    assert!(reachable.can_reach(2, 1));
+    assert!(!reachable.can_reach(1, 0));
+    assert!(!reachable.can_reach(0, 3));
+}
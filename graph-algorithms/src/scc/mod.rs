@@ -0,0 +1,219 @@
+//! Strongly-connected components, by Tarjan's algorithm. The search is
+//! a `depth_first_walk`, so it uses an explicit stack rather than
+//! recursion, and covers every node of the graph, not only those
+//! reachable from the start node.
+
+use super::{Graph, GraphPredecessors, GraphSuccessors, NodeIndex};
+use super::iterate::{DepthFirstVisitor, depth_first_walk};
+use super::node_vec::NodeVec;
+use super::vec_graph::VecGraph;
+
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::iter;
+use core::slice;
+
+#[cfg(test)]
+mod test;
+
+pub fn sccs<G: Graph>(graph: &G) -> Sccs<G> {
+    let mut walk = TarjanWalk {
+        index: NodeVec::from_default(graph),
+        lowlink: NodeVec::from_default(graph),
+        on_stack: NodeVec::from_default(graph),
+        next_index: 0,
+        path: vec![],
+        stack: vec![],
+        scc_of: NodeVec::from_default(graph),
+        members: vec![],
+    };
+    for node in (0..graph.num_nodes()).map(G::Node::from) {
+        if walk.index[node].is_none() {
+            depth_first_walk(graph, node, &mut walk);
+        }
+    }
+
+    let scc_of: NodeVec<G, SccIndex> =
+        NodeVec::from_fn(graph, |node| walk.scc_of[node].unwrap());
+    let num_sccs = walk.members.len();
+    let mut condensation = Condensation {
+        start_node: scc_of[graph.start_node()],
+        successors: vec![vec![]; num_sccs],
+        predecessors: vec![vec![]; num_sccs],
+    };
+    for node in (0..graph.num_nodes()).map(G::Node::from) {
+        let source = scc_of[node];
+        for target in graph.successors(node).map(|successor| scc_of[successor]) {
+            if target != source && !condensation.successors[source.index].contains(&target) {
+                condensation.successors[source.index].push(target);
+                condensation.predecessors[target.index].push(source);
+            }
+        }
+    }
+
+    Sccs { scc_of, members: walk.members, condensation }
+}
+
+/// Computes the strongly-connected components of the graph with the
+/// given `edges`; see `VecGraph`.
+pub fn sccs_from_edges(start: usize, edges: &[(usize, usize)]) -> Sccs<VecGraph> {
+    sccs(&VecGraph::new(start, edges))
+}
+
+/// The strongly-connected components of a graph. They are numbered in
+/// the order Tarjan's algorithm completes them, which is a reverse
+/// topological order: every edge of the condensation goes from a
+/// component to one with a smaller index.
+pub struct Sccs<G: Graph> {
+    scc_of: NodeVec<G, SccIndex>,
+    members: Vec<Vec<G::Node>>,
+    condensation: Condensation,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SccIndex {
+    index: usize
+}
+
+impl<G: Graph> Sccs<G> {
+    pub fn num_sccs(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn scc(&self, node: G::Node) -> SccIndex {
+        self.scc_of[node]
+    }
+
+    /// The nodes of `scc`, in the order the search found them.
+    pub fn members(&self, scc: SccIndex) -> &[G::Node] {
+        &self.members[scc.index]
+    }
+
+    /// The graph with a node for each component, and an edge from one
+    /// component to another if any node of the first has an edge to a
+    /// node of the second. It has no cycles, and in particular no
+    /// edge from a component to itself; its start node is the
+    /// component of the start node of the graph.
+    pub fn condensation(&self) -> &Condensation {
+        &self.condensation
+    }
+}
+
+/// See `Sccs::condensation`.
+pub struct Condensation {
+    start_node: SccIndex,
+    successors: Vec<Vec<SccIndex>>,
+    predecessors: Vec<Vec<SccIndex>>,
+}
+
+impl Graph for Condensation {
+    type Node = SccIndex;
+
+    fn num_nodes(&self) -> usize {
+        self.successors.len()
+    }
+
+    fn start_node(&self) -> SccIndex {
+        self.start_node
+    }
+
+    fn predecessors<'graph>(&'graph self, node: SccIndex)
+                            -> <Self as GraphPredecessors<'graph>>::Iter {
+        self.predecessors[node.index].iter().cloned()
+    }
+
+    fn successors<'graph>(&'graph self, node: SccIndex)
+                          -> <Self as GraphSuccessors<'graph>>::Iter {
+        self.successors[node.index].iter().cloned()
+    }
+}
+
+impl<'graph> GraphPredecessors<'graph> for Condensation {
+    type Item = SccIndex;
+    type Iter = iter::Cloned<slice::Iter<'graph, SccIndex>>;
+}
+
+impl<'graph> GraphSuccessors<'graph> for Condensation {
+    type Item = SccIndex;
+    type Iter = iter::Cloned<slice::Iter<'graph, SccIndex>>;
+}
+
+impl NodeIndex for SccIndex {}
+
+impl From<usize> for SccIndex {
+    fn from(index: usize) -> SccIndex {
+        SccIndex { index }
+    }
+}
+
+impl From<SccIndex> for usize {
+    fn from(scc: SccIndex) -> usize {
+        scc.index
+    }
+}
+
+struct TarjanWalk<G: Graph> {
+    /// The order in which each node was discovered.
+    index: NodeVec<G, Option<usize>>,
+
+    /// The smallest index of a node on `stack` known to be reachable
+    /// from each node.
+    lowlink: NodeVec<G, usize>,
+    on_stack: NodeVec<G, bool>,
+    next_index: usize,
+
+    /// The nodes being searched, from the root of the search to the
+    /// node on top of the stack of `depth_first_walk`.
+    path: Vec<G::Node>,
+
+    /// The nodes discovered whose component is not yet complete.
+    stack: Vec<G::Node>,
+    scc_of: NodeVec<G, Option<SccIndex>>,
+    members: Vec<Vec<G::Node>>,
+}
+
+impl<G: Graph> DepthFirstVisitor<G> for TarjanWalk<G> {
+    fn discover(&mut self, node: G::Node) {
+        self.index[node] = Some(self.next_index);
+        self.lowlink[node] = self.next_index;
+        self.next_index += 1;
+        self.path.push(node);
+        self.stack.push(node);
+        self.on_stack[node] = true;
+    }
+
+    fn examine_edge(&mut self, source: G::Node, target: G::Node) -> bool {
+        match self.index[target] {
+            None => true,
+            Some(index) => {
+                if self.on_stack[target] {
+                    self.lowlink[source] = min(self.lowlink[source], index);
+                }
+                false
+            }
+        }
+    }
+
+    fn finish(&mut self, node: G::Node) {
+        self.path.pop();
+        if let Some(&parent) = self.path.last() {
+            self.lowlink[parent] = min(self.lowlink[parent], self.lowlink[node]);
+        }
+
+        if Some(self.lowlink[node]) == self.index[node] {
+            let scc = SccIndex { index: self.members.len() };
+            let mut members = vec![];
+            loop {
+                let member = self.stack.pop().unwrap();
+                self.on_stack[member] = false;
+                self.scc_of[member] = Some(scc);
+                members.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            members.reverse();
+            self.members.push(members);
+        }
+    }
+}
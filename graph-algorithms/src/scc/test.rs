@@ -0,0 +1,76 @@
+use test::TestGraph;
+
+use super::*;
+
+#[test]
+fn loop_with_exit() {
+    // 0 -> 1 -> 2 -> 3
+    //      ^    v
+    //      6 <- 4 -> 5
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (1, 2),
+        (2, 3),
+        (2, 4),
+        (4, 5),
+        (4, 6),
+        (6, 1),
+    ]);
+    let sccs = sccs(&graph);
+    assert_eq!(sccs.num_sccs(), 4);
+    assert_eq!(sccs.members(sccs.scc(1)), &[1, 2, 4, 6]);
+    assert!([2, 4, 6].iter().all(|&node| sccs.scc(node) == sccs.scc(1)));
+    assert_eq!(sccs.members(sccs.scc(0)), &[0]);
+    assert_eq!(sccs.members(sccs.scc(3)), &[3]);
+    assert_eq!(sccs.members(sccs.scc(5)), &[5]);
+
+    let condensation = sccs.condensation();
+    assert_eq!(condensation.start_node(), sccs.scc(0));
+    let mut successors: Vec<_> = condensation.successors(sccs.scc(1)).collect();
+    successors.sort();
+    let mut expected = vec![sccs.scc(3), sccs.scc(5)];
+    expected.sort();
+    assert_eq!(successors, expected);
+    assert_eq!(condensation.predecessors(sccs.scc(1)).collect::<Vec<_>>(), vec![sccs.scc(0)]);
+}
+
+#[test]
+fn reverse_topological_order() {
+    // 0 -> 1 <-> 2 -> 3 <-> 4, with 5 -> 0 unreachable from the start
+    let graph = TestGraph::new(0, &[
+        (0, 1),
+        (1, 2),
+        (2, 1),
+        (2, 3),
+        (3, 4),
+        (4, 3),
+        (5, 0),
+    ]);
+    let sccs = sccs(&graph);
+    assert_eq!(sccs.num_sccs(), 4);
+    let condensation = sccs.condensation();
+    for scc in (0..sccs.num_sccs()).map(SccIndex::from) {
+        assert!(condensation.successors(scc).all(|successor| successor < scc));
+    }
+    assert_eq!(sccs.members(sccs.scc(5)), &[5]);
+}
+
+#[test]
+fn self_loop() {
+    let sccs = sccs_from_edges(0, &[(0, 0), (0, 1)]);
+    assert_eq!(sccs.num_sccs(), 2);
+    assert_eq!(sccs.members(sccs.scc(0)), &[0]);
+    assert_eq!(sccs.condensation().successors(sccs.scc(0)).collect::<Vec<_>>(),
+               vec![sccs.scc(1)]);
+    assert_eq!(sccs.condensation().successors(sccs.scc(1)).count(), 0);
+}
+
+#[test]
+fn long_cycle() {
+    // too deep for a recursive search
+    let n = 100_000;
+    let edges: Vec<_> = (0..n).map(|i| (i, (i + 1) % n)).collect();
+    let sccs = sccs_from_edges(0, &edges);
+    assert_eq!(sccs.num_sccs(), 1);
+    assert_eq!(sccs.members(sccs.scc(0)).len(), n);
+}
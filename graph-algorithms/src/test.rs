@@ -3,7 +3,7 @@ use std::cmp::max;
 use std::slice;
 use std::iter;
 
-use super::{Graph, GraphPredecessors, GraphSuccessors, NodeIndex};
+use super::{Graph, GraphPredecessors, GraphSuccessors};
 
 pub struct TestGraph {
     num_nodes: usize,
@@ -65,6 +65,3 @@ impl<'graph> GraphSuccessors<'graph> for TestGraph {
     type Item = usize;
     type Iter = iter::Cloned<slice::Iter<'graph, usize>>;
 }
-
-impl NodeIndex for usize {
-}
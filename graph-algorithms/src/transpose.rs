@@ -1,8 +1,20 @@
 use super::*;
 
+use alloc::vec;
+use alloc::vec::Vec;
+
 pub struct TransposedGraph<G: Graph> {
     base_graph: G,
     start_node: G::Node,
+    virtual_exit: Option<VirtualExit<G::Node>>,
+}
+
+/// A node added after the last node of the base graph, which is the
+/// start node of the transposed graph. It has an edge to each of the
+/// `exits` of the base graph, i.e., its nodes without successors.
+struct VirtualExit<N> {
+    node: N,
+    exits: Vec<N>,
 }
 
 impl<G: Graph> TransposedGraph<G> {
@@ -12,7 +24,39 @@ impl<G: Graph> TransposedGraph<G> {
     }
 
     pub fn with_start(base_graph: G, start_node: G::Node) -> Self {
-        TransposedGraph { base_graph: base_graph, start_node: start_node }
+        TransposedGraph { base_graph, start_node, virtual_exit: None }
+    }
+
+    /// Transposes `base_graph`, starting from a synthesized node that
+    /// is a predecessor (in the transposed graph) of every node that
+    /// has no successors in `base_graph`. This is what you want for
+    /// computing post-dominators of a graph with several exits. The
+    /// synthesized node is `virtual_exit()`, whose index is the
+    /// `num_nodes()` of the base graph.
+    pub fn with_virtual_exit(base_graph: G) -> Self {
+        let num_nodes = base_graph.num_nodes();
+        let node = G::Node::from(num_nodes);
+        let exits = (0..num_nodes)
+            .map(G::Node::from)
+            .filter(|&n| base_graph.successors(n).next().is_none())
+            .collect();
+        TransposedGraph {
+            base_graph,
+            start_node: node,
+            virtual_exit: Some(VirtualExit { node, exits }),
+        }
+    }
+
+    /// The node synthesized by `with_virtual_exit`, if any.
+    pub fn virtual_exit(&self) -> Option<G::Node> {
+        self.virtual_exit.as_ref().map(|v| v.node)
+    }
+
+    fn is_exit(&self, node: G::Node) -> bool {
+        match self.virtual_exit {
+            Some(ref v) => v.exits.binary_search(&node).is_ok(),
+            None => false,
+        }
     }
 }
 
@@ -20,7 +64,8 @@ impl<G: Graph> Graph for TransposedGraph<G> {
     type Node = G::Node;
 
     fn num_nodes(&self) -> usize {
-        self.base_graph.num_nodes()
+        let extra = if self.virtual_exit.is_some() { 1 } else { 0 };
+        self.base_graph.num_nodes() + extra
     }
 
     fn start_node(&self) -> Self::Node {
@@ -29,21 +74,59 @@ impl<G: Graph> Graph for TransposedGraph<G> {
 
     fn predecessors<'graph>(&'graph self, node: Self::Node)
                             -> <Self as GraphPredecessors<'graph>>::Iter {
-        self.base_graph.successors(node)
+        if self.virtual_exit() == Some(node) {
+            return TransposedNodes::new(None, vec![]);
+        }
+        let extra = if self.is_exit(node) {
+            self.virtual_exit().into_iter().collect()
+        } else {
+            vec![]
+        };
+        TransposedNodes::new(Some(self.base_graph.successors(node)), extra)
     }
 
     fn successors<'graph>(&'graph self, node: Self::Node)
                           -> <Self as GraphSuccessors<'graph>>::Iter {
-        self.base_graph.predecessors(node)
+        match self.virtual_exit {
+            Some(ref v) if v.node == node => TransposedNodes::new(None, v.exits.clone()),
+            _ => TransposedNodes::new(Some(self.base_graph.predecessors(node)), vec![]),
+        }
     }
 }
 
 impl<'graph, G: Graph> GraphPredecessors<'graph> for TransposedGraph<G> {
     type Item = G::Node;
-    type Iter = <G as GraphSuccessors<'graph>>::Iter;
+    type Iter = TransposedNodes<<G as GraphSuccessors<'graph>>::Iter, G::Node>;
 }
 
 impl<'graph, G: Graph> GraphSuccessors<'graph> for TransposedGraph<G> {
     type Item = G::Node;
-    type Iter = <G as GraphPredecessors<'graph>>::Iter;
+    type Iter = TransposedNodes<<G as GraphPredecessors<'graph>>::Iter, G::Node>;
+}
+
+/// The neighbors of a node in a `TransposedGraph`: its neighbors in
+/// the base graph (if it is a node of the base graph), followed by
+/// any edges to or from the virtual exit.
+pub struct TransposedNodes<I, N> {
+    base_iter: Option<I>,
+    extra: vec::IntoIter<N>,
+}
+
+impl<I, N> TransposedNodes<I, N> {
+    fn new(base_iter: Option<I>, extra: Vec<N>) -> Self {
+        TransposedNodes { base_iter, extra: extra.into_iter() }
+    }
+}
+
+impl<I: Iterator<Item = N>, N> Iterator for TransposedNodes<I, N> {
+    type Item = N;
+
+    fn next(&mut self) -> Option<N> {
+        if let Some(ref mut base_iter) = self.base_iter {
+            if let Some(node) = base_iter.next() {
+                return Some(node);
+            }
+        }
+        self.extra.next()
+    }
 }
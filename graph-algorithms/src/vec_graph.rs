@@ -0,0 +1,67 @@
+//! A graph given by a list of edges, for using the algorithms of this
+//! crate without implementing `Graph` for a graph of one's own. The
+//! nodes are `usize` indices, numbered from 0, and every node up to
+//! the largest index named by an edge (or the start node) exists.
+
+use super::*;
+
+use alloc::vec::Vec;
+use core::cmp::max;
+use core::iter;
+use core::slice;
+
+pub struct VecGraph {
+    start_node: usize,
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+impl VecGraph {
+    pub fn new(start_node: usize, edges: &[(usize, usize)]) -> Self {
+        let num_nodes = edges
+            .iter()
+            .fold(start_node + 1, |n, &(source, target)| max(n, max(source, target) + 1));
+        let mut graph = VecGraph {
+            start_node,
+            successors: vec![vec![]; num_nodes],
+            predecessors: vec![vec![]; num_nodes],
+        };
+        for &(source, target) in edges {
+            graph.successors[source].push(target);
+            graph.predecessors[target].push(source);
+        }
+        graph
+    }
+}
+
+impl Graph for VecGraph {
+    type Node = usize;
+
+    fn num_nodes(&self) -> usize {
+        self.successors.len()
+    }
+
+    fn start_node(&self) -> usize {
+        self.start_node
+    }
+
+    fn predecessors<'graph>(&'graph self, node: usize)
+                            -> <Self as GraphPredecessors<'graph>>::Iter {
+        self.predecessors[node].iter().cloned()
+    }
+
+    fn successors<'graph>(&'graph self, node: usize)
+                          -> <Self as GraphSuccessors<'graph>>::Iter {
+        self.successors[node].iter().cloned()
+    }
+}
+
+impl<'graph> GraphPredecessors<'graph> for VecGraph {
+    type Item = usize;
+    type Iter = iter::Cloned<slice::Iter<'graph, usize>>;
+}
+
+impl<'graph> GraphSuccessors<'graph> for VecGraph {
+    type Item = usize;
+    type Iter = iter::Cloned<slice::Iter<'graph, usize>>;
+}
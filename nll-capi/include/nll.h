@@ -0,0 +1,63 @@
+/* C interface to the NLL prototype; see `nll-capi/src/lib.rs`. */
+
+#ifndef NLL_H
+#define NLL_H
+
+#include <stdbool.h>
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct NllProgram NllProgram;
+typedef struct NllAnalysis NllAnalysis;
+
+/* Byte offsets into the text of the program. */
+typedef struct NllSpan {
+    size_t lo;
+    size_t hi;
+} NllSpan;
+
+typedef struct NllDiagnostic {
+    const char *code;     /* like "E0502", or NULL */
+    const char *message;
+    const char *point;    /* like "START/1" */
+    bool has_span;
+    NllSpan span;
+    size_t num_notes;
+} NllDiagnostic;
+
+typedef struct NllNote {
+    const char *message;
+    const char *point;    /* or NULL */
+    bool has_span;
+    NllSpan span;
+} NllNote;
+
+/* Parses `text`. `path`, if not NULL, is the file it was read from;
+   includes are resolved relative to it. On failure, returns NULL and
+   stores a message in `*error` (if `error` is not NULL), to be freed
+   with `nll_string_free`. */
+NllProgram *nll_parse(const char *text, const char *path, char **error);
+void nll_program_free(NllProgram *program);
+
+/* Runs the analysis, reporting every error found as a diagnostic.
+   Fails (as `nll_parse` does) if an assertion does not hold. */
+NllAnalysis *nll_analyze(const NllProgram *program, char **error);
+void nll_analysis_free(NllAnalysis *analysis);
+
+/* The strings in the structs filled in by these functions belong to
+   the analysis and live until it is freed. They return false if the
+   index is out of range. */
+size_t nll_num_diagnostics(const NllAnalysis *analysis);
+bool nll_diagnostic(const NllAnalysis *analysis, size_t index, NllDiagnostic *out);
+bool nll_note(const NllAnalysis *analysis, size_t diagnostic, size_t index, NllNote *out);
+
+void nll_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NLL_H */
@@ -0,0 +1,314 @@
+//! A C interface to the analysis, so that it can be embedded in tools
+//! that are not written in Rust. The declarations are in
+//! `include/nll.h`.
+//!
+//! A program is parsed with `nll_parse` and analyzed with
+//! `nll_analyze`; the diagnostics of the resulting `NllAnalysis` are
+//! then read, one at a time, with `nll_diagnostic` and `nll_note`. The
+//! strings in those structs belong to the `NllAnalysis`, and live
+//! until it is freed. Functions that can fail return null and store
+//! an error message, which the caller frees with `nll_string_free`.
+
+extern crate nll;
+extern crate nll_repr;
+
+use nll::env::{Environment, Point};
+use nll::errors::{ReportedError, SourceFile};
+use nll::graph::{self, FuncGraph};
+use nll::options::Options;
+use nll::regionck;
+use nll_repr::repr::{Func, Span};
+use std::any::Any;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+#[cfg(test)]
+mod test;
+
+/// A parsed program.
+pub struct NllProgram {
+    name: String,
+    text: String,
+    func: Func,
+}
+
+/// The results of analyzing a program.
+pub struct NllAnalysis {
+    diagnostics: Vec<Diagnostic>,
+}
+
+struct Diagnostic {
+    code: Option<CString>,
+    message: CString,
+    point: CString,
+    span: Option<Span>,
+    notes: Vec<Note>,
+}
+
+struct Note {
+    message: CString,
+    point: Option<CString>,
+    span: Option<Span>,
+}
+
+/// Byte offsets into the text of the program.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NllSpan {
+    pub lo: usize,
+    pub hi: usize,
+}
+
+#[repr(C)]
+pub struct NllDiagnostic {
+    /// The error code, like `E0502`, or null if there is none.
+    pub code: *const c_char,
+    pub message: *const c_char,
+
+    /// The point where the error occurs, like `START/1`.
+    pub point: *const c_char,
+
+    /// The span of the action at `point`; meaningful only if
+    /// `has_span` is set.
+    pub has_span: bool,
+    pub span: NllSpan,
+
+    pub num_notes: usize,
+}
+
+#[repr(C)]
+pub struct NllNote {
+    pub message: *const c_char,
+
+    /// The point the note refers to, or null if there is none.
+    pub point: *const c_char,
+
+    /// As in `NllDiagnostic`.
+    pub has_span: bool,
+    pub span: NllSpan,
+}
+
+/// Parses `text`. If `path` is not null, it is the path of the file
+/// that `text` was read from; `include` directives are resolved
+/// relative to it, and it is used to name the file in diagnostics.
+///
+/// # Safety
+///
+/// `text` and `path` must be nul-terminated strings (`path` may be
+/// null), and `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn nll_parse(text: *const c_char,
+                                   path: *const c_char,
+                                   error: *mut *mut c_char)
+                                   -> *mut NllProgram {
+    let result = catch_panics(|| {
+        let text = CStr::from_ptr(text)
+            .to_str()
+            .map_err(|_| "not UTF-8".to_string())?;
+        if path.is_null() {
+            let func = Func::parse(text)?;
+            return Ok(NllProgram { name: "<input>".to_string(), text: text.to_string(), func });
+        }
+        let name = CStr::from_ptr(path)
+            .to_str()
+            .map_err(|_| "path is not UTF-8".to_string())?;
+        let func = Func::parse_file(Path::new(name), text)?;
+        Ok(NllProgram { name: name.to_string(), text: text.to_string(), func })
+    });
+    into_raw_or_error(result, error)
+}
+
+/// # Safety
+///
+/// `program` must be null or returned by `nll_parse`, and not freed
+/// before.
+#[no_mangle]
+pub unsafe extern "C" fn nll_program_free(program: *mut NllProgram) {
+    if !program.is_null() {
+        drop(Box::from_raw(program));
+    }
+}
+
+/// Runs the region and borrow checks, with the default options. The
+/// `//!` comments in the program are ignored: every error that is
+/// found is reported as a diagnostic. Fails only if one of the
+/// program's assertions does not hold.
+///
+/// # Safety
+///
+/// `program` must have been returned by `nll_parse`, and `error` must
+/// be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn nll_analyze(program: *const NllProgram,
+                                     error: *mut *mut c_char)
+                                     -> *mut NllAnalysis {
+    let program = &*program;
+    let result = catch_panics(|| analyze(program, &Options::default()));
+    into_raw_or_error(result, error)
+}
+
+/// # Safety
+///
+/// `analysis` must be null or returned by `nll_analyze`, and not
+/// freed before.
+#[no_mangle]
+pub unsafe extern "C" fn nll_analysis_free(analysis: *mut NllAnalysis) {
+    if !analysis.is_null() {
+        drop(Box::from_raw(analysis));
+    }
+}
+
+/// # Safety
+///
+/// `analysis` must have been returned by `nll_analyze`.
+#[no_mangle]
+pub unsafe extern "C" fn nll_num_diagnostics(analysis: *const NllAnalysis) -> usize {
+    (*analysis).diagnostics.len()
+}
+
+/// Fills in `out` with the diagnostic at `index`, returning false if
+/// there is no such diagnostic.
+///
+/// # Safety
+///
+/// `analysis` must have been returned by `nll_analyze`, and `out` must
+/// be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn nll_diagnostic(analysis: *const NllAnalysis,
+                                        index: usize,
+                                        out: *mut NllDiagnostic)
+                                        -> bool {
+    let analysis = &*analysis;
+    let diagnostic = match analysis.diagnostics.get(index) {
+        Some(diagnostic) => diagnostic,
+        None => return false,
+    };
+    *out = NllDiagnostic {
+        code: diagnostic.code.as_ref().map_or(ptr::null(), |c| c.as_ptr()),
+        message: diagnostic.message.as_ptr(),
+        point: diagnostic.point.as_ptr(),
+        has_span: diagnostic.span.is_some(),
+        span: to_c_span(diagnostic.span),
+        num_notes: diagnostic.notes.len(),
+    };
+    true
+}
+
+/// Fills in `out` with note `index` of the diagnostic at `diagnostic`,
+/// returning false if there is no such note.
+///
+/// # Safety
+///
+/// As for `nll_diagnostic`.
+#[no_mangle]
+pub unsafe extern "C" fn nll_note(analysis: *const NllAnalysis,
+                                  diagnostic: usize,
+                                  index: usize,
+                                  out: *mut NllNote)
+                                  -> bool {
+    let analysis = &*analysis;
+    let note = match analysis.diagnostics.get(diagnostic).and_then(|d| d.notes.get(index)) {
+        Some(note) => note,
+        None => return false,
+    };
+    *out = NllNote {
+        message: note.message.as_ptr(),
+        point: note.point.as_ref().map_or(ptr::null(), |p| p.as_ptr()),
+        has_span: note.span.is_some(),
+        span: to_c_span(note.span),
+    };
+    true
+}
+
+/// Frees an error message returned by one of the other functions.
+///
+/// # Safety
+///
+/// `string` must be null or an error message that was not freed
+/// before.
+#[no_mangle]
+pub unsafe extern "C" fn nll_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+fn analyze(program: &NllProgram, options: &Options) -> Result<NllAnalysis, String> {
+    let graph = FuncGraph::new(program.func.clone());
+    let source = SourceFile {
+        name: &program.name,
+        text: &program.text,
+    };
+    graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        let errors = regionck::diagnostics(&env, options, &source).map_err(|e| e.to_string())?;
+        let diagnostics = errors.iter().map(|error| to_diagnostic(&env, error)).collect();
+        Ok(NllAnalysis { diagnostics })
+    })
+}
+
+/// Must be called within `graph::with_graph`, so that the points are
+/// rendered with the names of their blocks.
+fn to_diagnostic(env: &Environment, error: &ReportedError) -> Diagnostic {
+    let point_string = |point: Point| c_string(format!("{:?}", point));
+    Diagnostic {
+        code: error.code.map(|code| c_string(code.to_string())),
+        message: c_string(error.message.clone()),
+        point: point_string(error.point),
+        span: env.action_span(error.point),
+        notes: error.notes
+            .iter()
+            .map(|note| Note {
+                message: c_string(note.message.clone()),
+                point: note.point.map(&point_string),
+                span: note.point.and_then(|p| env.action_span(p)),
+            })
+            .collect(),
+    }
+}
+
+fn to_c_span(span: Option<Span>) -> NllSpan {
+    span.map_or(NllSpan::default(), |span| NllSpan { lo: span.lo, hi: span.hi })
+}
+
+/// Interior nul bytes cannot be represented, so they are dropped.
+fn c_string(string: String) -> CString {
+    CString::new(string.replace('\0', "")).unwrap()
+}
+
+/// Unwinding into C is undefined behavior, so a panic in the analysis
+/// is turned into an error.
+fn catch_panics<T, F>(f: F) -> Result<T, String>
+where
+    F: FnOnce() -> Result<T, String>,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => Err(format!("internal error: {}", panic_message(&*payload))),
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "panic"
+    }
+}
+
+unsafe fn into_raw_or_error<T>(result: Result<T, String>, error: *mut *mut c_char) -> *mut T {
+    match result {
+        Ok(value) => Box::into_raw(Box::new(value)),
+        Err(message) => {
+            if !error.is_null() {
+                *error = c_string(message).into_raw();
+            }
+            ptr::null_mut()
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use super::*;
+
+use std::ffi::{CStr, CString};
+use std::ptr;
+
+const PROGRAM: &str = "
+struct Vec<+> {
+  field: 0
+}
+
+let foo: Vec<()>;
+let p: &'p Vec<()>;
+let q: &'q mut Vec<()>;
+
+block START {
+    foo = use();
+    p = &'b1 foo;
+    q = &'b2 mut foo;
+    use(p);
+    use(q);
+}
+";
+
+unsafe fn string(s: *const c_char) -> String {
+    CStr::from_ptr(s).to_str().unwrap().to_string()
+}
+
+unsafe fn parse(text: &str) -> Result<*mut NllProgram, String> {
+    let text = CString::new(text).unwrap();
+    let mut error = ptr::null_mut();
+    let program = nll_parse(text.as_ptr(), ptr::null(), &mut error);
+    if program.is_null() {
+        let message = string(error);
+        nll_string_free(error);
+        Err(message)
+    } else {
+        Ok(program)
+    }
+}
+
+#[test]
+fn diagnostics() {
+    unsafe {
+        let program = parse(PROGRAM).unwrap();
+        let mut error = ptr::null_mut();
+        let analysis = nll_analyze(program, &mut error);
+        assert!(!analysis.is_null());
+        assert_eq!(nll_num_diagnostics(analysis), 1);
+
+        let mut diagnostic: NllDiagnostic = ::std::mem::zeroed();
+        assert!(nll_diagnostic(analysis, 0, &mut diagnostic));
+        assert_eq!(string(diagnostic.code), "E0502");
+        assert!(string(diagnostic.message).contains("`foo` is borrowed as shared"));
+        assert_eq!(string(diagnostic.point), "START/2");
+        assert!(diagnostic.has_span);
+        assert_eq!(&PROGRAM[diagnostic.span.lo..diagnostic.span.hi], "q = &'b2 mut foo;");
+        assert_eq!(diagnostic.num_notes, 2);
+
+        let mut note: NllNote = ::std::mem::zeroed();
+        assert!(nll_note(analysis, 0, 0, &mut note));
+        assert_eq!(string(note.point), "START/1");
+        assert_eq!(&PROGRAM[note.span.lo..note.span.hi], "p = &'b1 foo;");
+        assert!(nll_note(analysis, 0, 1, &mut note));
+        assert_eq!(string(note.message),
+                   "the borrow is still live along this path: START/1 -> START/2");
+        assert!(note.point.is_null());
+        assert!(!nll_note(analysis, 0, 2, &mut note));
+        assert!(!nll_diagnostic(analysis, 1, &mut diagnostic));
+
+        nll_analysis_free(analysis);
+        nll_program_free(program);
+    }
+}
+
+#[test]
+fn parse_error() {
+    unsafe {
+        let error = parse("block START {").unwrap_err();
+        assert!(error.contains("parse error"), "{}", error);
+    }
+}
+
+#[test]
+fn every_parse_error() {
+    unsafe {
+        let error = parse("let a: ();\nblock START {\n    a = = use();\n    use(a;\n}\n").unwrap_err();
+        let errors: Vec<_> = error.lines().collect();
+        assert_eq!(errors.len(), 2, "{}", error);
+        assert!(errors[0].starts_with("parse error at 3:9 (offset 33), expected one of `&`, `(`"),
+                "{}", errors[0]);
+        assert_eq!(errors[1], "parse error at 4:10 (offset 51), expected `)`");
+    }
+}
+
+#[test]
+fn failed_assertion() {
+    unsafe {
+        let program = parse(&format!("{}\nassert 'p == {{ START/0 }};\n", PROGRAM)).unwrap();
+        let mut error = ptr::null_mut();
+        let analysis = nll_analyze(program, &mut error);
+        assert!(analysis.is_null());
+        assert!(string(error).contains("errors found"));
+        nll_string_free(error);
+        nll_program_free(program);
+    }
+}
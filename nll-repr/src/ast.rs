@@ -0,0 +1,988 @@
+//! A stable AST of `.nll` programs, for code outside this workspace:
+//! importers, fuzzers and bindings. The types in `repr` are the IR of
+//! the analyses, and change whenever an analysis needs them to; these
+//! change only along with `VERSION`, following semver:
+//!
+//! - a new kind of action, type, constraint or assertion is a new
+//!   variant, which is not a breaking change, since the enums are
+//!   `#[non_exhaustive]` (so a `match` on one needs a `_` arm);
+//! - any other change, like a new field, is breaking, and bumps
+//!   `VERSION`.
+//!
+//! Names are plain strings, with the `'` of regions, and the encodings
+//! of the IR are spelled out: `*p` is `Path::Deref`, not a field named
+//! `*`. `Func::from_repr` converts a parsed function to the AST, and
+//! `Func::into_repr` converts one back for the analyses, checking what
+//! the parser would (see `repr::Func::check`); as for a parsed
+//! function, struct types are left to the analysis, which reports a
+//! mismatch as an error of the program. Block parameters are lowered
+//! into variables and assignments as a function is parsed, so they
+//! never appear here.
+
+use intern;
+use repr;
+use std::collections::BTreeMap;
+
+/// The version of the AST; see the module docs for when it changes.
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Func {
+    pub structs: Vec<StructDecl>,
+
+    /// The free regions, as in `for<'a: 'b>;`.
+    pub regions: Vec<RegionDecl>,
+    pub vars: Vec<VarDecl>,
+
+    /// The blocks, each defined once, one of which is `START`.
+    pub blocks: Vec<Block>,
+    pub assertions: Vec<Assertion>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructDecl {
+    pub name: String,
+    pub params: Vec<StructParam>,
+    pub fields: Vec<FieldDecl>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StructParam {
+    pub kind: ParamKind,
+    pub variance: Variance,
+    pub may_dangle: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParamKind {
+    Region,
+    Type,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Variance {
+    Co,
+    Contra,
+    In,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldDecl {
+    pub name: String,
+    pub ty: Ty,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionDecl {
+    pub name: String,
+    pub outlives: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VarDecl {
+    pub name: String,
+    pub ty: Ty,
+    pub kind: VarKind,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VarKind {
+    /// `let x: T;`
+    Local,
+
+    /// `static g: T;`
+    Static,
+
+    /// `static mut g: T;`
+    StaticMut,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Ty {
+    /// `&'a T` or `&'a mut T`.
+    Ref(Region, Mutability, Box<Ty>),
+
+    /// `*const T` or `*mut T`.
+    RawPtr(Mutability, Box<Ty>),
+    Unit,
+    Int,
+
+    /// `Foo<'a, T>`
+    Struct(String, Vec<TyParam>),
+
+    /// A parameter of the struct being declared, by De Bruijn index:
+    /// the last one is `0`.
+    Bound(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Region {
+    Named(String),
+
+    /// A region parameter of the struct being declared; see
+    /// `Ty::Bound`.
+    Bound(usize),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TyParam {
+    Region(Region),
+    Ty(Ty),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Mutability {
+    Shared,
+    Mut,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Block {
+    pub name: String,
+    pub actions: Vec<Action>,
+
+    /// The blocks named by the `goto`, if any.
+    pub successors: Vec<String>,
+
+    /// Marked `#[scope_exit]`.
+    pub scope_exit: bool,
+
+    /// The error codes named by `#[allow(...)]` on the block.
+    pub allow: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Action {
+    pub kind: ActionKind,
+
+    /// The bytes of the source that the action was parsed from.
+    pub span: Option<Span>,
+
+    /// `#[label="..."]`
+    pub label: Option<String>,
+
+    /// The error codes named by `#[allow(...)]` on the action.
+    pub allow: Vec<String>,
+
+    /// Within an `unsafe { ... }` group.
+    pub in_unsafe_block: bool,
+
+    /// The message of the `//!` comment after the action, if any: an
+    /// error containing it is expected there.
+    pub expected_error: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub lo: usize,
+    pub hi: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ActionKind {
+    /// `p = use(q, &'a r);`
+    Init(Path, Vec<Operand>),
+
+    /// `p = &'a q;` or `p = &'a mut q;`
+    Borrow(Path, String, Mutability, Path),
+
+    /// `p = q;`
+    Assign(Path, Path),
+
+    /// `p = const 3;`
+    Const(Path, Constant),
+
+    /// `'a: 'b;`, or a more complex constraint.
+    Constraint(Constraint),
+
+    /// `use(p);`
+    Use(Path),
+
+    /// `drop(p);`
+    Drop(Path),
+
+    /// `p = closure(capture x by ref 'c, capture y by move);`
+    Closure(Path, Vec<Capture>),
+
+    /// `p = &raw const q;` or `p = &raw mut q;`
+    RawBorrow(Path, Mutability, Path),
+
+    /// `read(p);` or `read(shallow p);`
+    Read(Path, Depth),
+
+    /// `write(p);` or `write(deep p);`
+    Write(Path, Depth),
+
+    /// `StorageDead(v);`
+    StorageDead(String),
+
+    /// `noop;`
+    Noop,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Depth {
+    Shallow,
+    Deep,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Constant {
+    Int(usize),
+    Named(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Operand {
+    Path(Path),
+
+    /// `&'a q`, or `&'a mut q`.
+    Borrow(String, Mutability, Path),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capture {
+    pub path: Path,
+    pub mode: CaptureMode,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CaptureMode {
+    /// `capture p by ref 'c` or `capture p by ref 'c mut`.
+    ByRef(String, Mutability),
+
+    /// `capture p by move`
+    ByMove,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Path {
+    Var(String),
+
+    /// `p.f`
+    Field(Box<Path>, String),
+
+    /// `*p`
+    Deref(Box<Path>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Constraint {
+    /// `forall<'a, 'b> C`
+    ForAll(Vec<String>, Box<Constraint>),
+
+    /// `exists<'a, 'b> C`
+    Exists(Vec<String>, Box<Constraint>),
+
+    /// `if ('a: 'b, ...) C`
+    Implies(Vec<Outlives>, Box<Constraint>),
+
+    /// `(C1, C2, ...)`
+    All(Vec<Constraint>),
+    Outlives(Outlives),
+}
+
+/// `'sup: 'sub`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Outlives {
+    pub sup: String,
+    pub sub: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Assertion {
+    /// `assert 'a == {B/0, B/1};`
+    Eq(String, Vec<Point>),
+    In(String, Point),
+    NotIn(String, Point),
+
+    /// `assert live(x) at B;`: a variable, live on entry to a block.
+    Live(String, String),
+    NotLive(String, String),
+
+    /// `assert region_live('a) at B;`
+    RegionLive(String, String),
+    RegionNotLive(String, String),
+
+    /// `assert region_live('a) at B/3;`
+    RegionLiveAt(String, Point),
+    RegionNotLiveAt(String, Point),
+
+    /// `assert dominates(B1, B2);`
+    Dominates(String, String),
+    NotDominates(String, String),
+
+    /// `assert loop_head(B);`
+    LoopHead(String),
+    NotLoopHead(String),
+
+    /// `expect 2 errors;`
+    ErrorCount(usize),
+
+    /// `assert error at B/3;`
+    ErrorAt(Point),
+    NoErrorAt(Point),
+
+    /// `'a <= {B/0, B/1};`
+    UpperBound(String, Vec<Point>),
+}
+
+/// A point of the function, like `B/3` (the start of an action) or
+/// `Mid(B/3)`, or the end of a free region in the caller, like `'a/0`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Point {
+    Code(String, usize),
+    EndOfFn(String, usize),
+    Mid(String, usize),
+}
+
+impl Func {
+    pub fn from_repr(func: &repr::Func) -> Self {
+        Func {
+            structs: func.structs.iter().map(StructDecl::from).collect(),
+            regions: func.regions
+                .iter()
+                .map(|decl| RegionDecl {
+                    name: decl.name.to_string(),
+                    outlives: names(&decl.outlives),
+                })
+                .collect(),
+            vars: func.decls
+                .iter()
+                .map(|decl| VarDecl {
+                    name: decl.var.to_string(),
+                    ty: Ty::from(&*decl.ty),
+                    kind: match decl.kind {
+                        repr::VariableKind::Local => VarKind::Local,
+                        repr::VariableKind::Static => VarKind::Static,
+                        repr::VariableKind::StaticMut => VarKind::StaticMut,
+                    },
+                })
+                .collect(),
+            blocks: func.data.values().map(Block::from).collect(),
+            assertions: func.assertions.iter().map(Assertion::from).collect(),
+        }
+    }
+
+    /// The function as the analyses take it, if it is well-formed.
+    pub fn into_repr(&self) -> Result<repr::Func, String> {
+        let mut data = BTreeMap::new();
+        for block in &self.blocks {
+            let block = repr::BasicBlockData::from(block);
+            if data.contains_key(&block.name) {
+                return Err(format!("block `{}` is defined twice", block.name));
+            }
+            data.insert(block.name, block);
+        }
+        let func = repr::Func {
+            decls: self.vars
+                .iter()
+                .map(|decl| repr::VariableDecl {
+                    var: repr::Variable::from(&decl.name[..]),
+                    ty: Box::new(repr::Ty::from(&decl.ty)),
+                    kind: match decl.kind {
+                        VarKind::Local => repr::VariableKind::Local,
+                        VarKind::Static => repr::VariableKind::Static,
+                        VarKind::StaticMut => repr::VariableKind::StaticMut,
+                    },
+                })
+                .collect(),
+            structs: self.structs.iter().map(repr::StructDecl::from).collect(),
+            regions: self.regions
+                .iter()
+                .map(|decl| repr::RegionDecl {
+                    name: repr::RegionName::from(&decl.name[..]),
+                    outlives: region_names(&decl.outlives),
+                })
+                .collect(),
+            data,
+            assertions: self.assertions.iter().map(repr::Assertion::from).collect(),
+        };
+        func.check()?;
+        Ok(func)
+    }
+}
+
+fn names<T: ToString>(names: &[T]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn region_names(names: &[String]) -> Vec<repr::RegionName> {
+    names.iter().map(|name| repr::RegionName::from(&name[..])).collect()
+}
+
+impl<'a> From<&'a repr::StructDecl> for StructDecl {
+    fn from(decl: &'a repr::StructDecl) -> Self {
+        StructDecl {
+            name: decl.name.to_string(),
+            params: decl.parameters
+                .iter()
+                .map(|param| StructParam {
+                    kind: match param.kind {
+                        repr::Kind::Region => ParamKind::Region,
+                        repr::Kind::Type => ParamKind::Type,
+                    },
+                    variance: match param.variance {
+                        repr::Variance::Co => Variance::Co,
+                        repr::Variance::Contra => Variance::Contra,
+                        repr::Variance::In => Variance::In,
+                    },
+                    may_dangle: param.may_dangle,
+                })
+                .collect(),
+            fields: decl.fields
+                .iter()
+                .map(|field| FieldDecl { name: field.name.to_string(), ty: Ty::from(&*field.ty) })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> From<&'a StructDecl> for repr::StructDecl {
+    fn from(decl: &'a StructDecl) -> Self {
+        repr::StructDecl {
+            name: repr::StructName::from(&decl.name[..]),
+            parameters: decl.params
+                .iter()
+                .map(|param| repr::StructParameter {
+                    kind: match param.kind {
+                        ParamKind::Region => repr::Kind::Region,
+                        ParamKind::Type => repr::Kind::Type,
+                    },
+                    variance: match param.variance {
+                        Variance::Co => repr::Variance::Co,
+                        Variance::Contra => repr::Variance::Contra,
+                        Variance::In => repr::Variance::In,
+                    },
+                    may_dangle: param.may_dangle,
+                })
+                .collect(),
+            fields: decl.fields
+                .iter()
+                .map(|field| repr::FieldDecl {
+                    name: repr::FieldName::from(&field.name[..]),
+                    ty: Box::new(repr::Ty::from(&field.ty)),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> From<&'a repr::Ty> for Ty {
+    fn from(ty: &'a repr::Ty) -> Self {
+        match *ty {
+            repr::Ty::Ref(region, kind, ref t) => {
+                Ty::Ref(Region::from(region), Mutability::from(kind), Box::new(Ty::from(&**t)))
+            }
+            repr::Ty::RawPtr(kind, ref t) => {
+                Ty::RawPtr(Mutability::from(kind), Box::new(Ty::from(&**t)))
+            }
+            repr::Ty::Unit => Ty::Unit,
+            repr::Ty::Int => Ty::Int,
+            repr::Ty::Struct(name, ref params) => {
+                let params = params
+                    .iter()
+                    .map(|param| match *param {
+                        repr::TyParameter::Region(region) => TyParam::Region(Region::from(region)),
+                        repr::TyParameter::Ty(ref t) => TyParam::Ty(Ty::from(&**t)),
+                    })
+                    .collect();
+                Ty::Struct(name.to_string(), params)
+            }
+            repr::Ty::Bound(index) => Ty::Bound(index),
+        }
+    }
+}
+
+impl<'a> From<&'a Ty> for repr::Ty {
+    fn from(ty: &'a Ty) -> Self {
+        match *ty {
+            Ty::Ref(ref region, kind, ref t) => {
+                repr::Ty::Ref(repr::Region::from(region),
+                              repr::BorrowKind::from(kind),
+                              Box::new(repr::Ty::from(&**t)))
+            }
+            Ty::RawPtr(kind, ref t) => {
+                repr::Ty::RawPtr(repr::BorrowKind::from(kind), Box::new(repr::Ty::from(&**t)))
+            }
+            Ty::Unit => repr::Ty::Unit,
+            Ty::Int => repr::Ty::Int,
+            Ty::Struct(ref name, ref params) => {
+                let params = params
+                    .iter()
+                    .map(|param| match *param {
+                        TyParam::Region(ref region) => {
+                            repr::TyParameter::Region(repr::Region::from(region))
+                        }
+                        TyParam::Ty(ref t) => repr::TyParameter::Ty(Box::new(repr::Ty::from(t))),
+                    })
+                    .collect();
+                repr::Ty::Struct(repr::StructName::from(&name[..]), params)
+            }
+            Ty::Bound(index) => repr::Ty::Bound(index),
+        }
+    }
+}
+
+impl From<repr::Region> for Region {
+    fn from(region: repr::Region) -> Self {
+        match region {
+            repr::Region::Free(name) => Region::Named(name.to_string()),
+            repr::Region::Bound(index) => Region::Bound(index),
+        }
+    }
+}
+
+impl<'a> From<&'a Region> for repr::Region {
+    fn from(region: &'a Region) -> Self {
+        match *region {
+            Region::Named(ref name) => repr::Region::Free(repr::RegionName::from(&name[..])),
+            Region::Bound(index) => repr::Region::Bound(index),
+        }
+    }
+}
+
+impl From<repr::BorrowKind> for Mutability {
+    fn from(kind: repr::BorrowKind) -> Self {
+        match kind {
+            repr::BorrowKind::Shared => Mutability::Shared,
+            repr::BorrowKind::Mut => Mutability::Mut,
+        }
+    }
+}
+
+impl From<Mutability> for repr::BorrowKind {
+    fn from(mutability: Mutability) -> Self {
+        match mutability {
+            Mutability::Shared => repr::BorrowKind::Shared,
+            Mutability::Mut => repr::BorrowKind::Mut,
+        }
+    }
+}
+
+impl From<repr::Depth> for Depth {
+    fn from(depth: repr::Depth) -> Self {
+        match depth {
+            repr::Depth::Shallow => Depth::Shallow,
+            repr::Depth::Deep => Depth::Deep,
+        }
+    }
+}
+
+impl From<Depth> for repr::Depth {
+    fn from(depth: Depth) -> Self {
+        match depth {
+            Depth::Shallow => repr::Depth::Shallow,
+            Depth::Deep => repr::Depth::Deep,
+        }
+    }
+}
+
+impl<'a> From<&'a repr::BasicBlockData> for Block {
+    fn from(block: &'a repr::BasicBlockData) -> Self {
+        Block {
+            name: block.name.to_string(),
+            actions: block.actions.iter().map(Action::from).collect(),
+            successors: names(&block.successors),
+            scope_exit: block.scope_exit,
+            allow: block.allow.clone(),
+        }
+    }
+}
+
+impl<'a> From<&'a Block> for repr::BasicBlockData {
+    fn from(block: &'a Block) -> Self {
+        repr::BasicBlockData {
+            name: repr::BasicBlock::from(&block.name[..]),
+            actions: block.actions.iter().map(repr::Action::from).collect(),
+            successors: block.successors.iter().map(|s| repr::BasicBlock::from(&s[..])).collect(),
+            params: vec![],
+            successor_args: vec![],
+            scope_exit: block.scope_exit,
+            allow: block.allow.clone(),
+        }
+    }
+}
+
+impl<'a> From<&'a repr::Action> for Action {
+    fn from(action: &'a repr::Action) -> Self {
+        Action {
+            kind: ActionKind::from(&action.kind),
+            span: action.span.map(|span| Span { lo: span.lo, hi: span.hi }),
+            label: action.label.clone(),
+            allow: action.allow.clone(),
+            in_unsafe_block: action.in_unsafe_block,
+            expected_error: action.should_have_error.as_ref().map(|e| e.string.clone()),
+        }
+    }
+}
+
+impl<'a> From<&'a Action> for repr::Action {
+    fn from(action: &'a Action) -> Self {
+        repr::Action {
+            kind: repr::ActionKind::from(&action.kind),
+            span: action.span.map(|span| repr::Span { lo: span.lo, hi: span.hi }),
+            label: action.label.clone(),
+            allow: action.allow.clone(),
+            in_unsafe_block: action.in_unsafe_block,
+            should_have_error: action.expected_error
+                .as_ref()
+                .map(|string| repr::ExpectedError { string: string.clone() }),
+        }
+    }
+}
+
+impl<'a> From<&'a repr::ActionKind> for ActionKind {
+    fn from(kind: &'a repr::ActionKind) -> Self {
+        match *kind {
+            repr::ActionKind::Init(ref dest, ref operands) => {
+                let operands = operands
+                    .iter()
+                    .map(|operand| match *operand {
+                        repr::Operand::Path(ref path) => Operand::Path(Path::from(&**path)),
+                        repr::Operand::Borrow(region, kind, ref path) => {
+                            Operand::Borrow(region.to_string(),
+                                            Mutability::from(kind),
+                                            Path::from(&**path))
+                        }
+                    })
+                    .collect();
+                ActionKind::Init(Path::from(&**dest), operands)
+            }
+            repr::ActionKind::Borrow(ref dest, region, kind, ref source) => {
+                ActionKind::Borrow(Path::from(&**dest),
+                                   region.to_string(),
+                                   Mutability::from(kind),
+                                   Path::from(&**source))
+            }
+            repr::ActionKind::Assign(ref dest, ref source) => {
+                ActionKind::Assign(Path::from(&**dest), Path::from(&**source))
+            }
+            repr::ActionKind::Const(ref dest, constant) => {
+                let constant = match constant {
+                    repr::Constant::Int(n) => Constant::Int(n),
+                    repr::Constant::Named(name) => Constant::Named(name.to_string()),
+                };
+                ActionKind::Const(Path::from(&**dest), constant)
+            }
+            repr::ActionKind::Constraint(ref c) => ActionKind::Constraint(Constraint::from(&**c)),
+            repr::ActionKind::Use(ref path) => ActionKind::Use(Path::from(&**path)),
+            repr::ActionKind::Drop(ref path) => ActionKind::Drop(Path::from(&**path)),
+            repr::ActionKind::Closure(ref dest, ref captures) => {
+                let captures = captures
+                    .iter()
+                    .map(|capture| Capture {
+                        path: Path::from(&*capture.path),
+                        mode: match capture.mode {
+                            repr::CaptureMode::ByRef(region, kind) => {
+                                CaptureMode::ByRef(region.to_string(), Mutability::from(kind))
+                            }
+                            repr::CaptureMode::ByMove => CaptureMode::ByMove,
+                        },
+                    })
+                    .collect();
+                ActionKind::Closure(Path::from(&**dest), captures)
+            }
+            repr::ActionKind::RawBorrow(ref dest, kind, ref source) => {
+                ActionKind::RawBorrow(Path::from(&**dest),
+                                      Mutability::from(kind),
+                                      Path::from(&**source))
+            }
+            repr::ActionKind::Read(ref path, depth) => {
+                ActionKind::Read(Path::from(&**path), Depth::from(depth))
+            }
+            repr::ActionKind::Write(ref path, depth) => {
+                ActionKind::Write(Path::from(&**path), Depth::from(depth))
+            }
+            repr::ActionKind::StorageDead(var) => ActionKind::StorageDead(var.to_string()),
+            repr::ActionKind::Noop => ActionKind::Noop,
+        }
+    }
+}
+
+impl<'a> From<&'a ActionKind> for repr::ActionKind {
+    fn from(kind: &'a ActionKind) -> Self {
+        let path = |path: &Path| Box::new(repr::Path::from(path));
+        match *kind {
+            ActionKind::Init(ref dest, ref operands) => {
+                let operands = operands
+                    .iter()
+                    .map(|operand| match *operand {
+                        Operand::Path(ref p) => repr::Operand::Path(path(p)),
+                        Operand::Borrow(ref region, kind, ref p) => {
+                            repr::Operand::Borrow(repr::RegionName::from(&region[..]),
+                                                  repr::BorrowKind::from(kind),
+                                                  path(p))
+                        }
+                    })
+                    .collect();
+                repr::ActionKind::Init(path(dest), operands)
+            }
+            ActionKind::Borrow(ref dest, ref region, kind, ref source) => {
+                repr::ActionKind::Borrow(path(dest),
+                                         repr::RegionName::from(&region[..]),
+                                         repr::BorrowKind::from(kind),
+                                         path(source))
+            }
+            ActionKind::Assign(ref dest, ref source) => {
+                repr::ActionKind::Assign(path(dest), path(source))
+            }
+            ActionKind::Const(ref dest, ref constant) => {
+                let constant = match *constant {
+                    Constant::Int(n) => repr::Constant::Int(n),
+                    Constant::Named(ref name) => repr::Constant::Named(intern::intern(name)),
+                };
+                repr::ActionKind::Const(path(dest), constant)
+            }
+            ActionKind::Constraint(ref c) => {
+                repr::ActionKind::Constraint(Box::new(repr::Constraint::from(c)))
+            }
+            ActionKind::Use(ref p) => repr::ActionKind::Use(path(p)),
+            ActionKind::Drop(ref p) => repr::ActionKind::Drop(path(p)),
+            ActionKind::Closure(ref dest, ref captures) => {
+                let captures = captures
+                    .iter()
+                    .map(|capture| repr::Capture {
+                        path: path(&capture.path),
+                        mode: match capture.mode {
+                            CaptureMode::ByRef(ref region, kind) => {
+                                repr::CaptureMode::ByRef(repr::RegionName::from(&region[..]),
+                                                         repr::BorrowKind::from(kind))
+                            }
+                            CaptureMode::ByMove => repr::CaptureMode::ByMove,
+                        },
+                    })
+                    .collect();
+                repr::ActionKind::Closure(path(dest), captures)
+            }
+            ActionKind::RawBorrow(ref dest, kind, ref source) => {
+                repr::ActionKind::RawBorrow(path(dest), repr::BorrowKind::from(kind), path(source))
+            }
+            ActionKind::Read(ref p, depth) => {
+                repr::ActionKind::Read(path(p), repr::Depth::from(depth))
+            }
+            ActionKind::Write(ref p, depth) => {
+                repr::ActionKind::Write(path(p), repr::Depth::from(depth))
+            }
+            ActionKind::StorageDead(ref var) => {
+                repr::ActionKind::StorageDead(repr::Variable::from(&var[..]))
+            }
+            ActionKind::Noop => repr::ActionKind::Noop,
+        }
+    }
+}
+
+impl<'a> From<&'a repr::Path> for Path {
+    fn from(path: &'a repr::Path) -> Self {
+        match *path {
+            repr::Path::Var(var) => Path::Var(var.to_string()),
+            repr::Path::Extension(ref base, name) => {
+                let base = Box::new(Path::from(&**base));
+                if name == repr::FieldName::star() {
+                    Path::Deref(base)
+                } else {
+                    Path::Field(base, name.to_string())
+                }
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a Path> for repr::Path {
+    fn from(path: &'a Path) -> Self {
+        match *path {
+            Path::Var(ref var) => repr::Path::var(var),
+            Path::Field(ref base, ref name) => repr::Path::from(&**base).field(name),
+            Path::Deref(ref base) => repr::Path::from(&**base).deref(),
+        }
+    }
+}
+
+impl<'a> From<&'a repr::Constraint> for Constraint {
+    fn from(c: &'a repr::Constraint) -> Self {
+        match *c {
+            repr::Constraint::ForAll(ref regions, ref c) => {
+                Constraint::ForAll(names(regions), Box::new(Constraint::from(&**c)))
+            }
+            repr::Constraint::Exists(ref regions, ref c) => {
+                Constraint::Exists(names(regions), Box::new(Constraint::from(&**c)))
+            }
+            repr::Constraint::Implies(ref assumptions, ref c) => {
+                Constraint::Implies(assumptions.iter().map(|&a| Outlives::from(a)).collect(),
+                                    Box::new(Constraint::from(&**c)))
+            }
+            repr::Constraint::All(ref cs) => {
+                Constraint::All(cs.iter().map(Constraint::from).collect())
+            }
+            repr::Constraint::Outlives(c) => Constraint::Outlives(Outlives::from(c)),
+        }
+    }
+}
+
+impl<'a> From<&'a Constraint> for repr::Constraint {
+    fn from(c: &'a Constraint) -> Self {
+        match *c {
+            Constraint::ForAll(ref regions, ref c) => {
+                repr::Constraint::ForAll(region_names(regions),
+                                         Box::new(repr::Constraint::from(&**c)))
+            }
+            Constraint::Exists(ref regions, ref c) => {
+                repr::Constraint::Exists(region_names(regions),
+                                         Box::new(repr::Constraint::from(&**c)))
+            }
+            Constraint::Implies(ref assumptions, ref c) => {
+                repr::Constraint::Implies(
+                    assumptions.iter().map(repr::OutlivesConstraint::from).collect(),
+                    Box::new(repr::Constraint::from(&**c)),
+                )
+            }
+            Constraint::All(ref cs) => {
+                repr::Constraint::All(cs.iter().map(repr::Constraint::from).collect())
+            }
+            Constraint::Outlives(ref c) => {
+                repr::Constraint::Outlives(repr::OutlivesConstraint::from(c))
+            }
+        }
+    }
+}
+
+impl From<repr::OutlivesConstraint> for Outlives {
+    fn from(c: repr::OutlivesConstraint) -> Self {
+        Outlives { sup: c.sup.to_string(), sub: c.sub.to_string() }
+    }
+}
+
+impl<'a> From<&'a Outlives> for repr::OutlivesConstraint {
+    fn from(c: &'a Outlives) -> Self {
+        repr::OutlivesConstraint {
+            sup: repr::RegionName::from(&c.sup[..]),
+            sub: repr::RegionName::from(&c.sub[..]),
+        }
+    }
+}
+
+impl<'a> From<&'a repr::Assertion> for Assertion {
+    fn from(assertion: &'a repr::Assertion) -> Self {
+        use repr::Assertion as A;
+        match *assertion {
+            A::Eq(region, ref literal) => {
+                Assertion::Eq(region.to_string(), literal.points.iter().map(Point::from).collect())
+            }
+            A::In(region, ref point) => Assertion::In(region.to_string(), Point::from(point)),
+            A::NotIn(region, ref point) => Assertion::NotIn(region.to_string(), Point::from(point)),
+            A::Live(var, block) => Assertion::Live(var.to_string(), block.to_string()),
+            A::NotLive(var, block) => Assertion::NotLive(var.to_string(), block.to_string()),
+            A::RegionLive(region, block) => {
+                Assertion::RegionLive(region.to_string(), block.to_string())
+            }
+            A::RegionNotLive(region, block) => {
+                Assertion::RegionNotLive(region.to_string(), block.to_string())
+            }
+            A::RegionLiveAt(region, ref point) => {
+                Assertion::RegionLiveAt(region.to_string(), Point::from(point))
+            }
+            A::RegionNotLiveAt(region, ref point) => {
+                Assertion::RegionNotLiveAt(region.to_string(), Point::from(point))
+            }
+            A::Dominates(a, b) => Assertion::Dominates(a.to_string(), b.to_string()),
+            A::NotDominates(a, b) => Assertion::NotDominates(a.to_string(), b.to_string()),
+            A::LoopHead(block) => Assertion::LoopHead(block.to_string()),
+            A::NotLoopHead(block) => Assertion::NotLoopHead(block.to_string()),
+            A::ErrorCount(n) => Assertion::ErrorCount(n),
+            A::ErrorAt(ref point) => Assertion::ErrorAt(Point::from(point)),
+            A::NoErrorAt(ref point) => Assertion::NoErrorAt(Point::from(point)),
+            A::UpperBound(region, ref literal) => {
+                let points = literal.points.iter().map(Point::from).collect();
+                Assertion::UpperBound(region.to_string(), points)
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a Assertion> for repr::Assertion {
+    fn from(assertion: &'a Assertion) -> Self {
+        use repr::Assertion as A;
+        let region = |name: &String| repr::RegionName::from(&name[..]);
+        let block = |name: &String| repr::BasicBlock::from(&name[..]);
+        let var = |name: &String| repr::Variable::from(&name[..]);
+        let point = repr::Point::from;
+        match *assertion {
+            Assertion::Eq(ref r, ref points) => {
+                A::Eq(region(r), repr::RegionLiteral { points: points.iter().map(point).collect() })
+            }
+            Assertion::In(ref r, ref p) => A::In(region(r), point(p)),
+            Assertion::NotIn(ref r, ref p) => A::NotIn(region(r), point(p)),
+            Assertion::Live(ref v, ref b) => A::Live(var(v), block(b)),
+            Assertion::NotLive(ref v, ref b) => A::NotLive(var(v), block(b)),
+            Assertion::RegionLive(ref r, ref b) => A::RegionLive(region(r), block(b)),
+            Assertion::RegionNotLive(ref r, ref b) => A::RegionNotLive(region(r), block(b)),
+            Assertion::RegionLiveAt(ref r, ref p) => A::RegionLiveAt(region(r), point(p)),
+            Assertion::RegionNotLiveAt(ref r, ref p) => A::RegionNotLiveAt(region(r), point(p)),
+            Assertion::Dominates(ref a, ref b) => A::Dominates(block(a), block(b)),
+            Assertion::NotDominates(ref a, ref b) => A::NotDominates(block(a), block(b)),
+            Assertion::LoopHead(ref b) => A::LoopHead(block(b)),
+            Assertion::NotLoopHead(ref b) => A::NotLoopHead(block(b)),
+            Assertion::ErrorCount(n) => A::ErrorCount(n),
+            Assertion::ErrorAt(ref p) => A::ErrorAt(point(p)),
+            Assertion::NoErrorAt(ref p) => A::NoErrorAt(point(p)),
+            Assertion::UpperBound(ref r, ref points) => {
+                let points = points.iter().map(point).collect();
+                A::UpperBound(region(r), repr::RegionLiteral { points })
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a repr::Point> for Point {
+    fn from(point: &'a repr::Point) -> Self {
+        match (point.block, point.kind) {
+            (repr::PointName::Code(block), repr::PointKind::Start) => {
+                Point::Code(block.to_string(), point.action)
+            }
+            (repr::PointName::Code(block), repr::PointKind::Mid) => {
+                Point::Mid(block.to_string(), point.action)
+            }
+            (repr::PointName::EndOfFn(region), _) => {
+                Point::EndOfFn(region.to_string(), point.action)
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a Point> for repr::Point {
+    fn from(point: &'a Point) -> Self {
+        match *point {
+            Point::Code(ref block, action) => repr::Point {
+                block: repr::PointName::Code(repr::BasicBlock::from(&block[..])),
+                action,
+                kind: repr::PointKind::Start,
+            },
+            Point::EndOfFn(ref region, action) => repr::Point {
+                block: repr::PointName::EndOfFn(repr::RegionName::from(&region[..])),
+                action,
+                kind: repr::PointKind::Start,
+            },
+            Point::Mid(ref block, action) => repr::Point {
+                block: repr::PointName::Code(repr::BasicBlock::from(&block[..])),
+                action,
+                kind: repr::PointKind::Mid,
+            },
+        }
+    }
+}
@@ -1,6 +1,5 @@
 extern crate lalrpop_intern as intern;
 extern crate lalrpop_util;
-#[macro_use]
-extern crate lazy_static;
 
 pub mod repr;
+pub mod ast;
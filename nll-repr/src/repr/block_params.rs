@@ -0,0 +1,84 @@
+//! Block parameters, an experiment in flow-sensitive typing:
+//!
+//! ```text
+//! block JOIN(r: &'r ()) { ... }
+//! block A { ...; goto JOIN(p); }
+//! ```
+//!
+//! gives each block its own variables, whose types (and regions) are
+//! related to those of the arguments along each edge into it. Once
+//! parsed, the parameters are lowered into ordinary variables, and
+//! the arguments into assignments `r = p;` at the end of the block
+//! with the `goto`, so that the rest of the analysis deals only with
+//! variables and actions. When a `goto` has several targets, the
+//! parameters of all of them are assigned, whichever edge is taken;
+//! the parameters of the targets not taken are simply dead.
+
+use std::collections::{BTreeMap, HashSet};
+use std::mem;
+
+use super::{line_col, Action, ActionKind, BasicBlock, Func, Path, VariableDecl};
+
+/// Lowers the block parameters of `func`, parsed from `text` (see
+/// the module comment).
+pub fn lower(func: &mut Func, text: &str) -> Result<(), String> {
+    let params: BTreeMap<BasicBlock, Vec<VariableDecl>> = func.data
+        .iter_mut()
+        .map(|(&name, block)| (name, mem::take(&mut block.params)))
+        .collect();
+
+    if params.get(&BasicBlock::start()).iter().any(|params| !params.is_empty()) {
+        return Err("the `START` block cannot have parameters, \
+                    since nothing passes it arguments".to_string());
+    }
+
+    let mut declared: HashSet<_> = func.decls.iter().map(|decl| decl.var).collect();
+    for (block, decls) in &params {
+        for decl in decls {
+            if !declared.insert(decl.var) {
+                return Err(format!("parameter `{}` of block `{}` is already declared",
+                                   decl.var, block));
+            }
+            func.decls.push(decl.clone());
+        }
+    }
+
+    for block in func.data.values_mut() {
+        let successor_args = mem::take(&mut block.successor_args);
+        // The parameters assigned so far by this `goto`: an
+        // argument must not read them, as the arguments are meant
+        // to be passed all at once.
+        let mut assigned = vec![];
+        for (target, goto) in block.successors.iter().zip(successor_args) {
+            let (line, col) = line_col(text, goto.span.lo);
+            let target_params = match params.get(target) {
+                Some(target_params) => target_params,
+                None => continue, // reported when the graph is built
+            };
+            if goto.args.len() != target_params.len() {
+                return Err(format!("`goto {}` at {}:{} passes {} argument(s), \
+                                    but `{}` takes {}",
+                                   target, line, col, goto.args.len(),
+                                   target, target_params.len()));
+            }
+            for (param, arg) in target_params.iter().zip(goto.args) {
+                if assigned.contains(&arg.base()) {
+                    return Err(format!("argument `{}` of `goto {}` at {}:{} reads a \
+                                        parameter that the same `goto` assigns",
+                                       arg, target, line, col));
+                }
+                block.actions.push(Action {
+                    kind: ActionKind::Assign(Box::new(Path::Var(param.var)), arg),
+                    span: Some(goto.span),
+                    label: None,
+                    allow: vec![],
+                    in_unsafe_block: false,
+                    should_have_error: None,
+                });
+                assigned.push(param.var);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,372 @@
+//! Building a `Func` in memory, for fuzzers, benchmark generators and
+//! property tests that would otherwise have to format a `.nll` file
+//! and parse it back:
+//!
+//! ```ignore
+//! let mut func = FuncBuilder::new();
+//! func.var("x", TyBuilder::unit());
+//! func.var("p", TyBuilder::unit().shared_ref("'p"));
+//! func.block("START")
+//!     .borrow("p", "'b", BorrowKind::Shared, Path::var("x"))
+//!     .goto(&["END"]);
+//! func.block("END").use_(Path::var("p"));
+//! let func = func.build()?;
+//! ```
+//!
+//! `build` checks what the parser and `FuncGraph` would: that the
+//! function has a `START` block, that every `goto` names a block,
+//! that every variable is declared once, and that every struct type
+//! is declared with the parameters it is used with.
+
+use std::collections::{BTreeMap, HashSet};
+
+use super::{statics, Action, ActionKind, Assertion, BasicBlock, BasicBlockData, BorrowKind,
+            ExpectedError, FieldName, Func, Kind, Operand, Path, Region, RegionDecl, RegionName,
+            StructDecl, StructName, Ty, TyParameter, Variable, VariableDecl, VariableKind};
+
+/// Builds a `Func`, declaration by declaration and block by block.
+#[derive(Default)]
+pub struct FuncBuilder {
+    structs: Vec<StructDecl>,
+    regions: Vec<RegionDecl>,
+    decls: Vec<VariableDecl>,
+    blocks: Vec<BlockBuilder>,
+    assertions: Vec<Assertion>,
+}
+
+impl FuncBuilder {
+    pub fn new() -> Self {
+        FuncBuilder::default()
+    }
+
+    pub fn struct_decl(&mut self, decl: StructDecl) -> &mut Self {
+        self.structs.push(decl);
+        self
+    }
+
+    /// Declares the free region `name`, as in `for<'a: 'b>;`, where
+    /// `outlives` is `['b]`.
+    pub fn region(&mut self, name: &str, outlives: &[&str]) -> &mut Self {
+        self.regions.push(RegionDecl {
+            name: RegionName::from(name),
+            outlives: outlives.iter().map(|&r| RegionName::from(r)).collect(),
+        });
+        self
+    }
+
+    /// Declares the local `let name: ty;`.
+    pub fn var(&mut self, name: &str, ty: TyBuilder) -> &mut Self {
+        self.declare(name, ty, VariableKind::Local)
+    }
+
+    /// Declares the global `static name: ty;` (or `static mut`, with
+    /// `VariableKind::StaticMut`).
+    pub fn declare(&mut self, name: &str, ty: TyBuilder, kind: VariableKind) -> &mut Self {
+        self.decls.push(VariableDecl { var: Variable::from(name), ty: ty.build(), kind });
+        self
+    }
+
+    /// Adds the block `name`, whose actions are then added to the
+    /// returned builder.
+    pub fn block(&mut self, name: &str) -> &mut BlockBuilder {
+        self.blocks.push(BlockBuilder::new(BasicBlock::from(name)));
+        self.blocks.last_mut().unwrap()
+    }
+
+    pub fn assertion(&mut self, assertion: Assertion) -> &mut Self {
+        self.assertions.push(assertion);
+        self
+    }
+
+    /// The function, once its invariants are checked (see
+    /// `Func::check`), along with the struct types of its variables.
+    pub fn build(&self) -> Result<Func, String> {
+        let mut data = BTreeMap::new();
+        for block in &self.blocks {
+            if data.insert(block.data.name, block.data.clone()).is_some() {
+                return Err(format!("block `{}` is defined twice", block.data.name));
+            }
+        }
+        let func = Func {
+            decls: self.decls.clone(),
+            structs: self.structs.clone(),
+            regions: self.regions.clone(),
+            data,
+            assertions: self.assertions.clone(),
+        };
+        for decl in &func.decls {
+            check_ty(&func.structs, &decl.ty)?;
+        }
+        func.check()?;
+        Ok(func)
+    }
+}
+
+impl Func {
+    /// Checks what the parser and `FuncGraph` would, for a function
+    /// that was built rather than parsed: that it has a `START` block,
+    /// that every `goto` names a block, and that every variable is
+    /// declared once. Struct types are checked by the analysis, which
+    /// reports a mismatch as an error of the program.
+    pub fn check(&self) -> Result<(), String> {
+        if !self.data.contains_key(&BasicBlock::start()) {
+            return Err("there is no `START` block".to_string());
+        }
+        for block in self.data.values() {
+            if let Some(target) = block.successors.iter().find(|s| !self.data.contains_key(s)) {
+                return Err(format!("block `{}` jumps to `{}`, which is not defined",
+                                   block.name, target));
+            }
+        }
+
+        let mut declared = HashSet::new();
+        for decl in &self.decls {
+            if !declared.insert(decl.var) {
+                return Err(format!("variable `{}` is declared twice", decl.var));
+            }
+        }
+        for block in self.data.values() {
+            for action in &block.actions {
+                if let Some(var) = action_variables(&action.kind).find(|v| !declared.contains(v)) {
+                    return Err(format!("variable `{}`, used in block `{}`, is not declared",
+                                       var, block.name));
+                }
+            }
+        }
+
+        statics::check(self)
+    }
+}
+
+/// Checks that each struct type in `ty` is declared in `structs`, with
+/// parameters of the kinds it is given.
+fn check_ty(structs: &[StructDecl], ty: &Ty) -> Result<(), String> {
+    match *ty {
+        Ty::Ref(_, _, ref t) | Ty::RawPtr(_, ref t) => check_ty(structs, t),
+        Ty::Unit | Ty::Int | Ty::Bound(_) => Ok(()),
+        Ty::Struct(name, ref params) => {
+            let decl = match structs.iter().find(|s| s.name == name) {
+                Some(decl) => decl,
+                None => return Err(format!("struct `{}` is not declared", name)),
+            };
+            let kinds: Vec<_> = params.iter().map(|p| match *p {
+                TyParameter::Region(_) => Kind::Region,
+                TyParameter::Ty(_) => Kind::Type,
+            }).collect();
+            let expected: Vec<_> = decl.parameters.iter().map(|p| p.kind).collect();
+            if kinds != expected {
+                return Err(format!("struct `{}` takes parameters of kinds {:?}, \
+                                    but `{}` has {:?}",
+                                   name, expected, ty, kinds));
+            }
+            for param in params {
+                if let TyParameter::Ty(ref t) = *param {
+                    check_ty(structs, t)?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Builds the actions of a block, and where it goes next. The
+/// methods are named after the actions they add, as written in a
+/// `.nll` file.
+pub struct BlockBuilder {
+    data: BasicBlockData,
+}
+
+impl BlockBuilder {
+    fn new(name: BasicBlock) -> Self {
+        BlockBuilder {
+            data: BasicBlockData {
+                name,
+                actions: vec![],
+                successors: vec![],
+                params: vec![],
+                successor_args: vec![],
+                scope_exit: false,
+                allow: vec![],
+            },
+        }
+    }
+
+    pub fn action(&mut self, kind: ActionKind) -> &mut Self {
+        self.data.actions.push(Action {
+            kind,
+            span: None,
+            label: None,
+            allow: vec![],
+            in_unsafe_block: false,
+            should_have_error: None,
+        });
+        self
+    }
+
+    /// `dest = use(operands...);`, where each operand is a path (see
+    /// `From<Path> for Operand`) or a borrow.
+    pub fn init(&mut self, dest: &str, operands: Vec<Operand>) -> &mut Self {
+        self.action(ActionKind::Init(Box::new(Path::var(dest)), operands))
+    }
+
+    /// `dest = &'region source;` or `dest = &'region mut source;`
+    pub fn borrow(&mut self, dest: &str, region: &str, kind: BorrowKind, source: Path) -> &mut Self {
+        let dest = Box::new(Path::var(dest));
+        self.action(ActionKind::Borrow(dest, RegionName::from(region), kind, Box::new(source)))
+    }
+
+    /// `dest = source;`
+    pub fn assign(&mut self, dest: Path, source: Path) -> &mut Self {
+        self.action(ActionKind::Assign(Box::new(dest), Box::new(source)))
+    }
+
+    /// `use(path);`
+    pub fn use_(&mut self, path: Path) -> &mut Self {
+        self.action(ActionKind::Use(Box::new(path)))
+    }
+
+    /// `drop(path);`
+    pub fn drop_(&mut self, path: Path) -> &mut Self {
+        self.action(ActionKind::Drop(Box::new(path)))
+    }
+
+    /// `StorageDead(var);`
+    pub fn storage_dead(&mut self, var: &str) -> &mut Self {
+        self.action(ActionKind::StorageDead(Variable::from(var)))
+    }
+
+    /// Marks the last action with `//! message`: the test expects an
+    /// error whose message contains `message` there.
+    pub fn expect_error(&mut self, message: &str) -> &mut Self {
+        let action = self.data.actions.last_mut().expect("no action to expect an error at");
+        action.should_have_error = Some(ExpectedError { string: message.to_string() });
+        self
+    }
+
+    /// Marks the last action as within an `unsafe { ... }` block.
+    pub fn in_unsafe_block(&mut self) -> &mut Self {
+        let action = self.data.actions.last_mut().expect("no action to mark unsafe");
+        action.in_unsafe_block = true;
+        self
+    }
+
+    /// `#[scope_exit]`
+    pub fn scope_exit(&mut self) -> &mut Self {
+        self.data.scope_exit = true;
+        self
+    }
+
+    /// `goto targets...;`
+    pub fn goto(&mut self, targets: &[&str]) -> &mut Self {
+        self.data.successors.extend(targets.iter().map(|&t| BasicBlock::from(t)));
+        self
+    }
+}
+
+/// Builds a type from the inside out: `TyBuilder::unit().shared_ref("'a")`
+/// is `&'a ()`.
+pub struct TyBuilder {
+    ty: Ty,
+}
+
+impl TyBuilder {
+    pub fn unit() -> Self {
+        TyBuilder { ty: Ty::Unit }
+    }
+
+    pub fn int() -> Self {
+        TyBuilder { ty: Ty::Int }
+    }
+
+    /// The `index`th parameter of the struct whose field this is the
+    /// type of, written `0`, `1`, ... in a struct declaration.
+    pub fn param(index: usize) -> Self {
+        TyBuilder { ty: Ty::Bound(index) }
+    }
+
+    /// The struct `name`, whose parameters are then given with
+    /// `region_param` and `ty_param`, in order.
+    pub fn named(name: &str) -> Self {
+        TyBuilder { ty: Ty::Struct(StructName::from(name), vec![]) }
+    }
+
+    pub fn region_param(self, region: &str) -> Self {
+        self.push_param(TyParameter::Region(Region::Free(RegionName::from(region))))
+    }
+
+    pub fn ty_param(self, ty: TyBuilder) -> Self {
+        self.push_param(TyParameter::Ty(ty.build()))
+    }
+
+    fn push_param(mut self, param: TyParameter) -> Self {
+        match self.ty {
+            Ty::Struct(_, ref mut params) => params.push(param),
+            ref ty => panic!("`{}` is not a struct, so it takes no parameters", ty),
+        }
+        self
+    }
+
+    /// `&'region T`, where `T` is the type built so far.
+    pub fn shared_ref(self, region: &str) -> Self {
+        self.reference(region, BorrowKind::Shared)
+    }
+
+    /// `&'region mut T`, where `T` is the type built so far.
+    pub fn mut_ref(self, region: &str) -> Self {
+        self.reference(region, BorrowKind::Mut)
+    }
+
+    fn reference(self, region: &str, kind: BorrowKind) -> Self {
+        let region = Region::Free(RegionName::from(region));
+        TyBuilder { ty: Ty::Ref(region, kind, Box::new(self.ty)) }
+    }
+
+    /// `*const T` (`BorrowKind::Shared`) or `*mut T`.
+    pub fn raw_ptr(self, kind: BorrowKind) -> Self {
+        TyBuilder { ty: Ty::RawPtr(kind, Box::new(self.ty)) }
+    }
+
+    pub fn build(self) -> Box<Ty> {
+        Box::new(self.ty)
+    }
+}
+
+impl Path {
+    /// The variable `name`, as a path.
+    pub fn var(name: &str) -> Path {
+        Path::Var(Variable::from(name))
+    }
+
+    /// `self.name`
+    pub fn field(self, name: &str) -> Path {
+        Path::Extension(Box::new(self), FieldName::from(name))
+    }
+
+    /// `*self`
+    pub fn deref(self) -> Path {
+        Path::Extension(Box::new(self), FieldName::star())
+    }
+}
+
+/// The variables that `kind` names.
+fn action_variables<'a>(kind: &'a ActionKind) -> Box<Iterator<Item = Variable> + 'a> {
+    match *kind {
+        ActionKind::Init(ref a, ref bs) => {
+            Box::new(Some(a.base()).into_iter().chain(bs.iter().map(|b| b.path().base())))
+        }
+        ActionKind::Borrow(ref a, _, _, ref b) |
+        ActionKind::RawBorrow(ref a, _, ref b) |
+        ActionKind::Assign(ref a, ref b) => Box::new(vec![a.base(), b.base()].into_iter()),
+        ActionKind::Closure(ref a, ref captures) => {
+            Box::new(Some(a.base()).into_iter().chain(captures.iter().map(|c| c.path.base())))
+        }
+        ActionKind::Const(ref p, _) |
+        ActionKind::Use(ref p) |
+        ActionKind::Read(ref p, _) |
+        ActionKind::Write(ref p, _) |
+        ActionKind::Drop(ref p) => Box::new(Some(p.base()).into_iter()),
+        ActionKind::StorageDead(v) => Box::new(Some(v).into_iter()),
+        ActionKind::Constraint(_) |
+        ActionKind::Noop => Box::new(None.into_iter()),
+    }
+}
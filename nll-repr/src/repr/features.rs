@@ -0,0 +1,278 @@
+//! Versioning of the `.nll` syntax. A file may begin with a header
+//! like
+//!
+//! ```text
+//! // nll-version: 1
+//! // features: closures, int
+//! ```
+//!
+//! Syntax extensions are tied to `Feature`s; a file may use a feature
+//! only if it was stabilized in (or before) the file's version, or if
+//! the file names it in its `features:` line. A file without an
+//! `nll-version` line gets the current version.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+use super::line_col;
+
+/// The version of the syntax accepted by this parser.
+pub const CURRENT_VERSION: u32 = 3;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Feature {
+    /// `p = closure(capture q by ref 'x mut);`
+    Closures,
+
+    /// The `int` type.
+    Int,
+
+    /// `p = const 22;`
+    Const,
+
+    /// Borrows and by-ref captures without a region, like `p = &q;`.
+    ElidedRegions,
+
+    /// `include "common.nll-decls";`
+    Include,
+
+    /// `use(x); #[label="second read"]`
+    Labels,
+
+    /// `#[scope_exit] block END { ... }`
+    ScopeExit,
+
+    /// `use(p); #[allow(E0502)]` and `#[allow(E0502)] block B { ... }`
+    Allow,
+
+    /// `*mut T`, `*const T`, `p = &raw mut q;` and `unsafe { ... }`
+    Unsafe,
+
+    /// `read(p);`, `read(shallow p);`, `write(p);` and `write(deep p);`
+    ReadWrite,
+
+    /// `expect 2 errors;`
+    ExpectErrors,
+
+    /// `block B(v: &'a T) { ... }` and `goto B(x);`
+    BlockParams,
+
+    /// `static g: T;` and `static mut g: T;`
+    Statics,
+
+    /// `assert dominates(B1, B4);` and `assert loop_head(B2);`
+    GraphAssertions,
+
+    /// `p = use(&'a q, r);`
+    BorrowOperands,
+
+    /// `assert region_live('a) at B1/3;`
+    RegionLiveAt,
+
+    /// `assert error at B1/3;` and `assert no_error at B1/3;`
+    ErrorAssertions,
+
+    /// `'a <= {B1/0..=B1/3};`, and ranges like `B1/0..=B1/3` in any
+    /// region literal
+    RegionBounds,
+
+    /// `Mid(B1/3)` and `Start(B1/3)`, naming one of the two points of
+    /// an action in an assertion
+    MidPoints,
+}
+
+impl Feature {
+    pub const ALL: &'static [Feature] = &[
+        Feature::Closures,
+        Feature::Int,
+        Feature::Const,
+        Feature::ElidedRegions,
+        Feature::Include,
+        Feature::Labels,
+        Feature::ScopeExit,
+        Feature::Allow,
+        Feature::Unsafe,
+        Feature::ReadWrite,
+        Feature::ExpectErrors,
+        Feature::BlockParams,
+        Feature::Statics,
+        Feature::GraphAssertions,
+        Feature::BorrowOperands,
+        Feature::RegionLiveAt,
+        Feature::ErrorAssertions,
+        Feature::RegionBounds,
+        Feature::MidPoints,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Closures => "closures",
+            Feature::Int => "int",
+            Feature::Const => "const",
+            Feature::ElidedRegions => "elided-regions",
+            Feature::Include => "include",
+            Feature::Labels => "labels",
+            Feature::ScopeExit => "scope-exit",
+            Feature::Allow => "allow",
+            Feature::Unsafe => "unsafe",
+            Feature::ReadWrite => "read-write",
+            Feature::ExpectErrors => "expect-errors",
+            Feature::BlockParams => "block-params",
+            Feature::Statics => "statics",
+            Feature::GraphAssertions => "graph-assertions",
+            Feature::BorrowOperands => "borrow-operands",
+            Feature::RegionLiveAt => "region-live-at",
+            Feature::ErrorAssertions => "error-assertions",
+            Feature::RegionBounds => "region-bounds",
+            Feature::MidPoints => "mid-points",
+        }
+    }
+
+    /// The version from which files may use the feature without
+    /// naming it, or `None` if it must always be named.
+    pub fn since(self) -> Option<u32> {
+        match self {
+            Feature::Closures |
+            Feature::Int |
+            Feature::Const |
+            Feature::ElidedRegions |
+            Feature::Include => Some(2),
+            Feature::Labels |
+            Feature::ScopeExit |
+            Feature::Allow |
+            Feature::Unsafe |
+            Feature::ReadWrite |
+            Feature::ExpectErrors |
+            Feature::BlockParams |
+            Feature::Statics |
+            Feature::GraphAssertions |
+            Feature::BorrowOperands |
+            Feature::RegionLiveAt |
+            Feature::ErrorAssertions |
+            Feature::RegionBounds |
+            Feature::MidPoints => Some(3),
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Feature> {
+        Feature::ALL.iter().cloned().find(|f| f.name() == name)
+    }
+}
+
+/// The version and features declared by the header of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub version: u32,
+    pub features: BTreeSet<Feature>,
+}
+
+impl Header {
+    /// Reads the header from the comment lines at the start of `text`.
+    pub fn parse(text: &str) -> Result<Header, String> {
+        let mut header = Header {
+            version: CURRENT_VERSION,
+            features: BTreeSet::new(),
+        };
+        let mut offset = 0;
+        for line in text.split('\n') {
+            let line_offset = offset;
+            offset += line.len() + 1;
+
+            let comment = match line.trim().find("//") {
+                Some(0) => line.trim()[2..].trim(),
+                _ if line.trim().is_empty() => continue,
+                _ => break,
+            };
+            let error = |message: String| {
+                let (line, col) = line_col(text, line_offset);
+                format!("{}:{}: {}", line, col, message)
+            };
+
+            if let Some(version) = value_of(comment, "nll-version") {
+                header.version = match version.parse() {
+                    Ok(v) if v >= 1 && v <= CURRENT_VERSION => v,
+                    _ => {
+                        return Err(error(format!("unsupported nll-version `{}` \
+                                                  (the latest is {})",
+                                                 version,
+                                                 CURRENT_VERSION)))
+                    }
+                };
+            } else if let Some(features) = value_of(comment, "features") {
+                for name in features.split(',').map(|n| n.trim()).filter(|n| !n.is_empty()) {
+                    match Feature::from_name(name) {
+                        Some(feature) => {
+                            header.features.insert(feature);
+                        }
+                        None => {
+                            let known: Vec<_> = Feature::ALL.iter().map(|f| f.name()).collect();
+                            return Err(error(format!("unknown feature `{}` (known features: {})",
+                                                     name,
+                                                     known.join(", "))));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(header)
+    }
+
+    pub fn allows(&self, feature: Feature) -> bool {
+        self.features.contains(&feature) ||
+            feature.since().map_or(false, |since| since <= self.version)
+    }
+
+    /// Checks that each feature in `uses` is allowed, reporting the
+    /// first one (in `text`) that is not.
+    pub fn check(&self, text: &str, uses: &FeatureUses) -> Result<(), String> {
+        let mut uses = uses.uses.borrow().clone();
+        uses.sort_by_key(|&(_, offset)| offset);
+        for (feature, offset) in uses {
+            if self.allows(feature) {
+                continue;
+            }
+            let (line, col) = line_col(text, offset);
+            let available = match feature.since() {
+                Some(since) => format!("it is available from nll-version {}, \
+                                        but this file declares nll-version {}; ",
+                                       since,
+                                       self.version),
+                None => String::new(),
+            };
+            return Err(format!("{}:{}: this syntax requires feature `{}` ({}add `// features: {}` \
+                                to the header to enable it)",
+                               line,
+                               col,
+                               feature.name(),
+                               available,
+                               feature.name()));
+        }
+        Ok(())
+    }
+}
+
+/// `// key: value` yields `value`.
+fn value_of<'a>(comment: &'a str, key: &str) -> Option<&'a str> {
+    if comment.starts_with(key) && comment[key.len()..].trim_start().starts_with(':') {
+        Some(comment[key.len()..].trim_start()[1..].trim())
+    } else {
+        None
+    }
+}
+
+/// Filled in by the parser: each use of a feature, with the offset at
+/// which it occurs.
+#[derive(Default)]
+pub struct FeatureUses {
+    uses: RefCell<Vec<(Feature, usize)>>,
+}
+
+impl FeatureUses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, feature: Feature, offset: usize) {
+        self.uses.borrow_mut().push((feature, offset));
+    }
+}
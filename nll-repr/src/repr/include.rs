@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::{block_params, line_col, parse_versioned, parser, reset_fresh_regions, statics, Decls,
+            Func, Include, RegionDecl, StructDecl};
+
+impl Func {
+    /// Parses `text`, the contents of the file at `path`, resolving
+    /// its `include` directives (and those of the files it includes)
+    /// relative to the directory of the file containing each one.
+    /// Included declarations come before those of the including
+    /// file. A file that is included more than once is only read the
+    /// first time; a file that (indirectly) includes itself is an
+    /// error.
+    pub fn parse_file(path: &Path, text: &str) -> Result<Self, String> {
+        reset_fresh_regions();
+        let (includes, mut func) = parse_versioned(text, parser::parse_Func)?;
+        block_params::lower(&mut func, text)?;
+        statics::check(&func)?;
+        if includes.is_empty() {
+            return Ok(func);
+        }
+
+        let canonical = canonicalize(path)?;
+        let mut resolver = Resolver {
+            stack: vec![(canonical.clone(), path.to_owned())],
+            seen: HashSet::new(),
+            structs: vec![],
+            regions: vec![],
+        };
+        resolver.seen.insert(canonical);
+        resolver.resolve(path, text, &includes)?;
+
+        resolver.structs.extend(func.structs);
+        resolver.regions.extend(func.regions);
+        func.structs = resolver.structs;
+        func.regions = resolver.regions;
+        Ok(func)
+    }
+}
+
+struct Resolver {
+    /// The files currently being included, outermost first, as
+    /// (canonical path, path as written); used to detect cycles.
+    stack: Vec<(PathBuf, PathBuf)>,
+
+    /// The canonical paths of every file read so far.
+    seen: HashSet<PathBuf>,
+
+    structs: Vec<StructDecl>,
+    regions: Vec<RegionDecl>,
+}
+
+impl Resolver {
+    fn resolve(&mut self, from: &Path, from_text: &str, includes: &[Include]) -> Result<(), String> {
+        for include in includes {
+            let (line, col) = line_col(from_text, include.span.lo);
+            let location = format!("{}:{}:{}", from.display(), line, col);
+
+            let path = from.parent().unwrap_or(Path::new("")).join(&include.path);
+            let canonical = canonicalize(&path).map_err(|e| format!("{}: {}", location, e))?;
+
+            if let Some(index) = self.stack.iter().position(|&(ref c, _)| *c == canonical) {
+                let cycle: Vec<_> = self.stack[index..]
+                    .iter()
+                    .map(|&(_, ref p)| p.display().to_string())
+                    .chain(Some(path.display().to_string()))
+                    .collect();
+                return Err(format!("{}: include cycle: {}", location, cycle.join(" -> ")));
+            }
+
+            if !self.seen.insert(canonical.clone()) {
+                continue;
+            }
+
+            let text = read_file(&path).map_err(|e| format!("{}: {}", location, e))?;
+            let decls = Decls::parse(&text).map_err(|e| {
+                format!("{}: {} (included from {})", path.display(), e, location)
+            })?;
+
+            self.stack.push((canonical, path.clone()));
+            self.resolve(&path, &text, &decls.includes)?;
+            self.stack.pop();
+
+            self.structs.extend(decls.structs);
+            self.regions.extend(decls.regions);
+        }
+        Ok(())
+    }
+}
+
+fn canonicalize(path: &Path) -> Result<PathBuf, String> {
+    path.canonicalize().map_err(|e| format!("cannot find `{}`: {}", path.display(), e))
+}
+
+fn read_file(path: &Path) -> Result<String, String> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|e| format!("cannot read `{}`: {}", path.display(), e))?;
+    Ok(text)
+}
@@ -1,11 +1,21 @@
 use intern::{self, InternedString};
 use lalrpop_util::ParseError;
+use std::cell::Cell;
 use std::collections::BTreeMap;
 use std::fmt;
 use std::iter;
-use std::sync::Mutex;
 
+mod block_params;
+mod builder;
+mod features;
+mod include;
 mod parser;
+mod statics;
+mod syntax_errors;
+
+pub use self::builder::{BlockBuilder, FuncBuilder, TyBuilder};
+pub use self::features::{Feature, FeatureUses, Header, CURRENT_VERSION};
+pub use self::syntax_errors::{SyntaxError, SyntaxErrors};
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct BasicBlock {
@@ -18,13 +28,19 @@ impl BasicBlock {
     }
 }
 
+impl<'a> From<&'a str> for BasicBlock {
+    fn from(v: &'a str) -> Self {
+        BasicBlock { name: intern::intern(v) }
+    }
+}
+
 impl fmt::Display for BasicBlock {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(fmt, "{}", self.name)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Func {
     pub decls: Vec<VariableDecl>,
     pub structs: Vec<StructDecl>,
@@ -34,22 +50,119 @@ pub struct Func {
 }
 
 impl Func {
+    /// Parses a function that has no `include` directives; see
+    /// `parse_file` for one that may.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        reset_fresh_regions();
+        let (includes, mut func) = parse_versioned(s, parser::parse_Func)?;
+        if let Some(include) = includes.first() {
+            let (line, col) = line_col(s, include.span.lo);
+            return Err(format!("include at {}:{} needs the path of the including file",
+                               line, col));
+        }
+        block_params::lower(&mut func, s)?;
+        statics::check(&func)?;
+        Ok(func)
+    }
+}
+
+thread_local! {
+    /// The number of regions created by `RegionName::fresh()` since
+    /// the last function was parsed.
+    static FRESH_REGIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Starts numbering fresh regions from `'0` again, for a new
+/// function. Actions parsed later into that function (as by `nll
+/// what-if`) go on from where its own numbering left off.
+fn reset_fresh_regions() {
+    FRESH_REGIONS.with(|count| count.set(0));
+}
+
+impl Func {
+    /// Every syntax error in `s`, in the order they occur; empty if
+    /// `s` parses as a function (which may still be rejected for
+    /// other reasons by `parse`).
+    pub fn syntax_errors(s: &str) -> Vec<SyntaxError> {
+        let uses = FeatureUses::new();
+        match parse_recovering(s, |errors| parser::parse_Func(&uses, errors, s)) {
+            Ok(_) => vec![],
+            Err(errors) => errors,
+        }
+    }
+}
+
+impl ActionKind {
+    /// Parses a single action, like `use(x);`, as written in a block.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        parse_versioned(s, parser::parse_ActionKind)
+    }
+}
+
+/// A file of declarations shared between `.nll` files, pulled in
+/// with `include "path";`.
+#[derive(Clone, Debug)]
+pub struct Decls {
+    pub includes: Vec<Include>,
+    pub structs: Vec<StructDecl>,
+    pub regions: Vec<RegionDecl>,
+}
+
+impl Decls {
     pub fn parse(s: &str) -> Result<Self, String> {
-        let err_loc = match parser::parse_Func(s) {
-            Ok(f) => return Ok(f),
-            Err(ParseError::InvalidToken { location }) => location,
-            Err(ParseError::UnrecognizedToken { token: None, .. }) => s.len(),
-            Err(ParseError::UnrecognizedToken { token: Some((l, _, _)), .. }) => l,
-            Err(ParseError::ExtraToken { token: (l, _, _) }) => l,
-            Err(ParseError::User { .. }) => unimplemented!()
-        };
+        parse_versioned(s, parser::parse_Decls)
+    }
+}
 
-        let line_num = s[..err_loc].lines().count();
-        let col_num = s[..err_loc].lines().last().map(|s| s.len()).unwrap_or(0);
-        Err(format!("parse error at {}:{} (offset {})", line_num, col_num + 1, err_loc))
+/// An `include "path";` directive; `path` is relative to the
+/// directory of the including file.
+#[derive(Clone, Debug)]
+pub struct Include {
+    pub path: String,
+    pub span: Span,
+}
+
+/// Parses `s` with `parse`, then checks that it only uses the
+/// features allowed by its header. If `s` does not parse, every
+/// syntax error in it is reported, one per line.
+fn parse_versioned<'s, T, P, E>(s: &'s str, parse: P) -> Result<T, String>
+    where P: for<'f> FnOnce(&'f FeatureUses, &'f SyntaxErrors, &'s str)
+                            -> Result<T, ParseError<usize, E, ()>>
+{
+    let header = Header::parse(s)?;
+    let uses = FeatureUses::new();
+    let result = parse_recovering(s, |errors| parse(&uses, errors, s)).map_err(|errors| {
+        let errors: Vec<_> = errors.iter().map(|error| error.describe(s)).collect();
+        errors.join("\n")
+    })?;
+    header.check(s, &uses)?;
+    Ok(result)
+}
+
+/// Parses `s` with `parse`, which records the errors it recovers
+/// from; fails with all of them if there are any.
+fn parse_recovering<T, P, E>(s: &str, parse: P) -> Result<T, Vec<SyntaxError>>
+    where P: FnOnce(&SyntaxErrors) -> Result<T, ParseError<usize, E, ()>>
+{
+    let errors = SyntaxErrors::new(s);
+    let (result, error) = match parse(&errors) {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    let errors = errors.finish(error);
+    match result {
+        Some(result) if errors.is_empty() => Ok(result),
+        _ => Err(errors),
     }
 }
 
+/// The 1-based line and column of the byte `offset` in `s`.
+fn line_col(s: &str, offset: usize) -> (usize, usize) {
+    let before = &s[..offset];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    (before.matches('\n').count() + 1, offset - line_start + 1)
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct StructDecl {
     pub name: StructName,
@@ -112,14 +225,61 @@ pub struct StructName {
     name: InternedString
 }
 
+impl<'a> From<&'a str> for StructName {
+    fn from(v: &'a str) -> Self {
+        StructName { name: intern::intern(v) }
+    }
+}
+
+impl fmt::Display for StructName {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "{}", self.name)
+    }
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Ty {
     Ref(Region, BorrowKind, Box<Ty>),
+    /// `*mut T` (`BorrowKind::Mut`) or `*const T` (`BorrowKind::Shared`).
+    /// Raw pointers carry no region, and the loans of paths reached
+    /// through them are not tied to anything they point at.
+    RawPtr(BorrowKind, Box<Ty>),
     Unit,
+    Int,
     Struct(StructName, Vec<TyParameter>),
     Bound(usize),
 }
 
+/// Types are displayed in the notation they are written in, like
+/// `&'a mut Foo<'b, ()>`.
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Ty::Ref(region, BorrowKind::Mut, ref t) => write!(f, "&{} mut {}", region, t),
+            Ty::Ref(region, BorrowKind::Shared, ref t) => write!(f, "&{} {}", region, t),
+            Ty::RawPtr(BorrowKind::Mut, ref t) => write!(f, "*mut {}", t),
+            Ty::RawPtr(BorrowKind::Shared, ref t) => write!(f, "*const {}", t),
+            Ty::Unit => write!(f, "()"),
+            Ty::Int => write!(f, "int"),
+            Ty::Struct(name, ref params) => {
+                write!(f, "{}", name)?;
+                if !params.is_empty() {
+                    write!(f, "<")?;
+                    for (i, param) in params.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{}", param)?;
+                    }
+                    write!(f, ">")?;
+                }
+                Ok(())
+            }
+            Ty::Bound(b) => write!(f, "{}", b),
+        }
+    }
+}
+
 impl Ty {
     pub fn subst(&self, params: &[TyParameter]) -> Ty {
         match *self {
@@ -133,7 +293,9 @@ impl Ty {
                 }
             }
             Ty::Ref(rn, kind, ref t) => Ty::Ref(rn.subst(params), kind, Box::new(t.subst(params))),
+            Ty::RawPtr(kind, ref t) => Ty::RawPtr(kind, Box::new(t.subst(params))),
             Ty::Unit => Ty::Unit,
+            Ty::Int => Ty::Int,
             Ty::Struct(s, ref unsubst_params) => Ty::Struct(
                 s,
                 unsubst_params.iter().map(|p| p.subst(params)).collect()
@@ -141,12 +303,35 @@ impl Ty {
         }
     }
 
+    /// True if the two types are the same but for their regions, so
+    /// that one can be related to the other.
+    pub fn same_shape(&self, other: &Ty) -> bool {
+        match (self, other) {
+            (&Ty::Ref(_, kind_a, ref t_a), &Ty::Ref(_, kind_b, ref t_b)) |
+            (&Ty::RawPtr(kind_a, ref t_a), &Ty::RawPtr(kind_b, ref t_b)) => {
+                kind_a == kind_b && t_a.same_shape(t_b)
+            }
+            (&Ty::Unit, &Ty::Unit) | (&Ty::Int, &Ty::Int) => true,
+            (&Ty::Struct(s_a, ref ps_a), &Ty::Struct(s_b, ref ps_b)) => {
+                s_a == s_b && ps_a.len() == ps_b.len() &&
+                    ps_a.iter().zip(ps_b).all(|pair| match pair {
+                        (&TyParameter::Region(_), &TyParameter::Region(_)) => true,
+                        (&TyParameter::Ty(ref t_a), &TyParameter::Ty(ref t_b)) => t_a.same_shape(t_b),
+                        _ => false,
+                    })
+            }
+            (&Ty::Bound(a), &Ty::Bound(b)) => a == b,
+            _ => false,
+        }
+    }
+
     pub fn walk_regions<'a>(&'a self) -> Box<Iterator<Item = Region> + 'a> {
         match *self {
             Ty::Ref(rn, _kind, ref t) => Box::new(
                 iter::once(rn).chain(t.walk_regions())
             ),
-            Ty::Unit => Box::new(
+            Ty::RawPtr(_kind, ref t) => t.walk_regions(),
+            Ty::Unit | Ty::Int => Box::new(
                 iter::empty()
             ),
             Ty::Struct(_, ref params) => Box::new(
@@ -169,6 +354,15 @@ pub enum Region {
     Bound(usize),
 }
 
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Region::Free(name) => write!(f, "{}", name),
+            Region::Bound(b) => write!(f, "'{}", b),
+        }
+    }
+}
+
 impl Region {
     pub fn subst(self, params: &[TyParameter]) -> Region {
         match self {
@@ -199,6 +393,15 @@ pub enum TyParameter {
     Ty(Box<Ty>),
 }
 
+impl fmt::Display for TyParameter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            TyParameter::Region(region) => write!(f, "{}", region),
+            TyParameter::Ty(ref t) => write!(f, "{}", t),
+        }
+    }
+}
+
 impl TyParameter {
     pub fn subst(&self, params: &[TyParameter]) -> TyParameter {
         match *self {
@@ -213,6 +416,22 @@ pub struct BasicBlockData {
     pub name: BasicBlock,
     pub actions: Vec<Action>,
     pub successors: Vec<BasicBlock>,
+
+    /// The parameters of the block, like `v` in `block B(v: &'a T)`,
+    /// and the arguments that the `goto` passes to those of each
+    /// successor, in the order of `successors`. Both are lowered
+    /// into variables and assignments once the function is parsed
+    /// (see `block_params.rs`), so they are empty from then on.
+    pub params: Vec<VariableDecl>,
+    pub successor_args: Vec<GotoArgs>,
+
+    /// Marked `#[scope_exit]`: with `--elaborate-drops`, the locals
+    /// are dropped at the end of this block.
+    pub scope_exit: bool,
+
+    /// The error codes named by `#[allow(E0502, ...)]`: errors with
+    /// these codes are suppressed anywhere in the block.
+    pub allow: Vec<String>,
 }
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
@@ -233,9 +452,37 @@ impl BorrowKind {
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Action {
     pub kind: ActionKind,
+    pub span: Option<Span>,
+    /// A free-form label, like `#[label="second read"]`, that is
+    /// echoed wherever the action is referred to.
+    pub label: Option<String>,
+    /// The error codes named by `#[allow(E0502, ...)]`, whose errors
+    /// are suppressed at this action.
+    pub allow: Vec<String>,
+    /// Within an `unsafe { ... }` group: raw pointers may be
+    /// dereferenced, and borrow conflicts through them are only
+    /// warnings.
+    pub in_unsafe_block: bool,
     pub should_have_error: Option<ExpectedError>,
 }
 
+/// The arguments that a `goto` passes to one of its targets, like
+/// `(x, y)` in `goto B(x, y);` (none if the target is not followed
+/// by parentheses); `span` covers the target and its arguments.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct GotoArgs {
+    pub args: Vec<Box<Path>>,
+    pub span: Span,
+}
+
+/// Byte offsets into the source text of a `.nll` file. Actions that
+/// are synthesized, rather than parsed, have no span.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Span {
+    pub lo: usize,
+    pub hi: usize,
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct ExpectedError {
     pub string: String,
@@ -243,28 +490,112 @@ pub struct ExpectedError {
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum ActionKind {
-    Init(Box<Path>, Vec<Box<Path>>), // p = use(...)
+    /// `p = use(q, ...)` builds a new value for `p` out of the
+    /// operands. If `p` is a struct with one field per operand, of the
+    /// same shapes, each operand is stored into its field; otherwise,
+    /// each operand with the same shape as `p` may be stored into
+    /// `p`. Any other operand is only read.
+    Init(Box<Path>, Vec<Operand>),
     Borrow(Box<Path>, RegionName, BorrowKind, Box<Path>), // p = &'X q
     Assign(Box<Path>, Box<Path>), // p = q;
+    Const(Box<Path>, Constant), // p = const 3;
     Constraint(Box<Constraint>), // C
     Use(Box<Path>), // use(p);
     Drop(Box<Path>), // drop(p);
 
+    /// `p = closure(capture x by ref 'c, capture y by move);` creates a
+    /// closure and stores it into `p`. Each by-ref capture is a loan of
+    /// the captured path for the given region, just like `&'c x`; each
+    /// by-move capture moves the captured path into the closure.
+    Closure(Box<Path>, Vec<Capture>),
+
+    /// `p = &raw mut q;` or `p = &raw const q;` creates a raw pointer
+    /// to `q`. This accesses `q` like the corresponding borrow, but
+    /// creates no loan.
+    RawBorrow(Box<Path>, BorrowKind, Box<Path>),
+
+    /// `read(p);` (deep) or `read(shallow p);` reads `p`, and does
+    /// nothing else: it neither moves nor copies the value anywhere.
+    /// Like `write`, it lets the borrow check of a single access be
+    /// tested, apart from the other effects of an assignment.
+    Read(Box<Path>, Depth),
+
+    /// `write(p);` (shallow) or `write(deep p);` overwrites `p` with a
+    /// value that comes from nowhere.
+    Write(Box<Path>, Depth),
+
     /// `StorageDead(v)` indicates that the variable is now out of
     /// scope. This is not counted as a use nor a drop; it basically
     /// just pops the stack space. It *is*, however, important to the
     /// borrow checker.
     StorageDead(Variable),
 
-    /// A synthetic action that is inserted into the basic blocks
-    /// representing the end of a skolemized region. There is no
-    /// syntax for this sort of "action"; they are created by the NLL
-    /// logic in `graph.rs`.
-    SkolemizedEnd(RegionName),
-
     Noop,
 }
 
+/// How far a `read` or `write` reaches: a shallow access affects the
+/// memory of the path and of the paths it owns, but nothing behind a
+/// reference; a deep one reaches everything reachable from the path.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Depth {
+    Shallow,
+    Deep,
+}
+
+/// A scalar constant, like the `3` in `p = const 3`. Constants have
+/// no regions and refer to no variables, so they are ignored by the
+/// analyses; only the write to `p` matters.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Constant {
+    Int(usize),
+    Named(InternedString),
+}
+
+/// An operand of `p = use(...)`: a path, whose value is used, or a
+/// borrow like `&'a q`, whose reference is. The borrow is a loan of
+/// `q` issued at the `use`, just like `r = &'a q` would issue one,
+/// but without storing the reference anywhere first -- as a MIR
+/// aggregate `S { f: &x }` does.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Operand {
+    Path(Box<Path>),
+    Borrow(RegionName, BorrowKind, Box<Path>),
+}
+
+impl From<Path> for Operand {
+    fn from(path: Path) -> Self {
+        Operand::Path(Box::new(path))
+    }
+}
+
+impl Operand {
+    /// The path whose value is used, or that is borrowed.
+    pub fn path(&self) -> &Path {
+        match *self {
+            Operand::Path(ref path) | Operand::Borrow(_, _, ref path) => path,
+        }
+    }
+
+    pub fn is_borrow(&self) -> bool {
+        match *self {
+            Operand::Path(_) => false,
+            Operand::Borrow(..) => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Capture {
+    pub path: Box<Path>,
+    pub mode: CaptureMode,
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum CaptureMode {
+    ByRef(RegionName, BorrowKind), // capture p by ref 'c mut
+    ByMove, // capture p by move
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Path { // P =
     Var(Variable), // v
@@ -355,6 +686,20 @@ pub struct Variable {
     name: InternedString,
 }
 
+impl Variable {
+    /// True for the variable holding the return value, which is not
+    /// dropped when the function's scope is exited.
+    pub fn is_return(self) -> bool {
+        self.name.to_string() == "return"
+    }
+}
+
+impl<'a> From<&'a str> for Variable {
+    fn from(v: &'a str) -> Self {
+        Variable { name: intern::intern(v) }
+    }
+}
+
 impl fmt::Display for Variable {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(f, "{}", self.name)
@@ -365,6 +710,27 @@ impl fmt::Display for Variable {
 pub struct VariableDecl {
     pub var: Variable,
     pub ty: Box<Ty>,
+    pub kind: VariableKind,
+}
+
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum VariableKind {
+    /// `let x: T;`
+    Local,
+
+    /// `static g: T;`: a global, which is never freed, and so can be
+    /// borrowed for `'static`; it may not be written.
+    Static,
+
+    /// `static mut g: T;`: a global that may be written, but only
+    /// accessed within an unsafe block.
+    StaticMut,
+}
+
+impl VariableDecl {
+    pub fn is_static(&self) -> bool {
+        self.kind != VariableKind::Local
+    }
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -376,18 +742,65 @@ pub enum Assertion {
     NotLive(Variable, BasicBlock),
     RegionLive(RegionName, BasicBlock),
     RegionNotLive(RegionName, BasicBlock),
+
+    /// `assert region_live('a) at B1/3;`: `'a` is live at the point
+    /// `B1/3`, on entry to its action, rather than only on entry to
+    /// the block.
+    RegionLiveAt(RegionName, Point),
+    RegionNotLiveAt(RegionName, Point),
+
+    /// `assert dominates(B1, B4);`: every path from `START` to `B4`
+    /// goes through `B1`. These check the graph analyses themselves.
+    Dominates(BasicBlock, BasicBlock),
+    NotDominates(BasicBlock, BasicBlock),
+
+    /// `assert loop_head(B2);`: `B2` is the head of a loop.
+    LoopHead(BasicBlock),
+    NotLoopHead(BasicBlock),
+
+    /// `'a <= {B1/0..=B1/3};`: the value inferred for `'a` contains
+    /// nothing outside the literal, though unlike with `assert 'a ==`,
+    /// it need not contain all of it.
+    UpperBound(RegionName, RegionLiteral),
+
+    /// `expect 2 errors;`: the program has exactly this many errors,
+    /// counting only those that are not suppressed.
+    ErrorCount(usize),
+
+    /// `assert error at B1/3;`: some error that is not suppressed is
+    /// reported at `B1/3`. Unlike a `//!` comment, this can name a
+    /// point with no action written in the source, such as one added
+    /// by `--elaborate-drops`, or the end of a block.
+    ErrorAt(Point),
+    NoErrorAt(Point),
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Point {
     pub block: PointName,
     pub action: usize,
+    pub kind: PointKind,
+}
+
+/// Each action has two points: its start, before it has any effect,
+/// where the accesses it makes are checked, and its mid-point, where
+/// its effects take hold. `B/3` is the start of the action, as is
+/// `Start(B/3)`; `Mid(B/3)` is its mid-point. The end of a free region
+/// (`'a/0`) is a single point, always a start.
+#[derive(Copy, Clone, Debug, Hash, PartialOrd, Ord, PartialEq, Eq)]
+pub enum PointKind {
+    Start,
+    Mid,
 }
 
 #[derive(Copy, Clone, Debug, Hash, PartialOrd, Ord, PartialEq, Eq)]
 pub enum PointName {
     Code(BasicBlock),
-    SkolemizedEnd(RegionName),
+
+    /// `'a/0` names the end of the free region `'a` -- i.e., the
+    /// portion of `'a` that lies in the caller, after the function
+    /// returns. (`'static/0` names the `'static` region itself.)
+    EndOfFn(RegionName),
 }
 
 #[derive(Copy, Clone, Debug, Hash, PartialOrd, Ord, PartialEq, Eq)]
@@ -396,15 +809,26 @@ pub struct RegionName {
 }
 
 impl RegionName {
+    /// A region named `'0`, `'1`, ... in the order of the calls since
+    /// the last function was parsed (on this thread), so that the
+    /// names in a function do not depend on what was parsed before it.
     pub fn fresh() -> RegionName {
-        lazy_static! {
-            static ref COUNTER: Mutex<usize> = Mutex::new(0);
-        }
+        let index = FRESH_REGIONS.with(|count| {
+            let index = count.get();
+            count.set(index + 1);
+            index
+        });
+        RegionName { name: intern::intern(&format!("'{}", index)) }
+    }
+
+    /// True if this region was created by `fresh()` (i.e., it was
+    /// written `'_` in the source) rather than named by the user.
+    pub fn is_fresh(self) -> bool {
+        self.name.to_string()[1..].starts_with(|c: char| c.is_digit(10))
+    }
 
-        let mut data = COUNTER.lock().unwrap();
-        let name = intern::intern(&format!("'{}", *data));
-        *data += 1;
-        RegionName { name }
+    pub fn is_static(self) -> bool {
+        self.name.to_string() == "'static"
     }
 }
 
@@ -431,6 +855,12 @@ impl FieldName {
     }
 }
 
+impl<'a> From<&'a str> for FieldName {
+    fn from(v: &'a str) -> Self {
+        FieldName { name: intern::intern(v) }
+    }
+}
+
 impl fmt::Display for FieldName {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(fmt, "{}", self.name)
@@ -0,0 +1,20 @@
+//! Checks on the declarations of statics, `static g: T;` and `static
+//! mut g: T;`. A static outlives every call of the function, so the
+//! only region its type may name is `'static`.
+
+use super::{Func, Region};
+
+pub fn check(func: &Func) -> Result<(), String> {
+    for decl in func.decls.iter().filter(|decl| decl.is_static()) {
+        let non_static = decl.ty.walk_regions().find(|&region| match region {
+            Region::Free(name) => !name.is_static(),
+            Region::Bound(_) => false,
+        });
+        if let Some(region) = non_static {
+            return Err(format!("the type `{}` of static `{}` names the region `{}`, \
+                                but a static may only name `'static`",
+                               decl.ty, decl.var, region));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,103 @@
+//! The syntax errors of a `.nll` file. The parser recovers from an
+//! error in a declaration, an action or an assertion by skipping to
+//! the `;` that ends it (or, for the last action of a block that has
+//! none, to the `}` that ends the block), so that every such error in
+//! the file is reported at once, rather than only the first.
+
+use lalrpop_util::{ErrorRecovery, ParseError};
+use std::cell::RefCell;
+use super::{line_col, Span};
+
+/// A token the parser did not expect (or the end of the input, with
+/// an empty span at the end), along with the tokens it expected there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub span: Span,
+    pub expected: Vec<String>,
+}
+
+impl SyntaxError {
+    /// The error `error` in an input of `len` bytes.
+    fn new<T, E>(len: usize, error: ParseError<usize, T, E>) -> Self {
+        let (span, expected) = match error {
+            ParseError::InvalidToken { location } => (Span { lo: location, hi: location }, vec![]),
+            ParseError::UnrecognizedToken { token: None, expected } => {
+                (Span { lo: len, hi: len }, expected)
+            }
+            ParseError::UnrecognizedToken { token: Some((lo, _, hi)), expected } => {
+                (Span { lo, hi }, expected)
+            }
+            ParseError::ExtraToken { token: (lo, _, hi) } => (Span { lo, hi }, vec![]),
+            ParseError::User { .. } => unimplemented!(),
+        };
+        let mut expected: Vec<_> = expected.iter().map(|token| describe_token(token)).collect();
+        expected.sort();
+        expected.dedup();
+        SyntaxError { span, expected }
+    }
+
+    /// The error as reported for the input `s`, like `parse error at
+    /// 3:5 (offset 40), expected one of `;`, `=``.
+    pub fn describe(&self, s: &str) -> String {
+        let (line_num, col_num) = line_col(s, self.span.lo);
+        let mut message = format!("parse error at {}:{} (offset {})",
+                                  line_num, col_num, self.span.lo);
+        match self.expected.len() {
+            0 => {}
+            1 => message.push_str(&format!(", expected {}", self.expected[0])),
+            _ => message.push_str(&format!(", expected one of {}", self.expected.join(", "))),
+        }
+        message
+    }
+}
+
+/// The name of a token, as the parser gives it, for a person: a
+/// literal token is quoted as written, and a regular expression is
+/// named for what it matches.
+fn describe_token(token: &str) -> String {
+    match token {
+        r##"r#"[a-zA-Z_]\\w*"#"## => "an identifier".to_string(),
+        r##"r#"'[a-zA-Z_]\\w*"#"## => "a region".to_string(),
+        r##"r#"'[0-9]+"#"## => "a bound region".to_string(),
+        r##"r#"[0-9]+"#"## => "a number".to_string(),
+        r##"r#"\"[^\"]*\""#"## => "a string".to_string(),
+        _ if token.starts_with("r#\"//") => "a comment".to_string(),
+        _ if token.starts_with('"') && token.ends_with('"') && token.len() > 1 => {
+            format!("`{}`", &token[1..token.len() - 1])
+        }
+        _ => token.to_string(),
+    }
+}
+
+/// Collects the errors the parser recovered from, as it goes.
+pub struct SyntaxErrors {
+    len: usize,
+    errors: RefCell<Vec<SyntaxError>>,
+}
+
+impl SyntaxErrors {
+    /// Collects the errors in the input `s`.
+    pub fn new(s: &str) -> Self {
+        SyntaxErrors { len: s.len(), errors: RefCell::new(vec![]) }
+    }
+
+    pub fn record<T, E>(&self, recovery: ErrorRecovery<usize, T, E>) {
+        self.errors.borrow_mut().push(SyntaxError::new(self.len, recovery.error));
+    }
+
+    /// Records that the text at `span` parsed, but is not valid: the
+    /// error says that `expected` was, as for an unexpected token.
+    pub fn record_invalid(&self, span: Span, expected: &str) {
+        self.errors.borrow_mut().push(SyntaxError { span, expected: vec![expected.to_string()] });
+    }
+
+    /// The errors recovered from, followed by `error`, that of a
+    /// parse that failed, if any: the parser only gives up once it
+    /// cannot recover.
+    pub fn finish<T, E>(self, error: Option<ParseError<usize, T, E>>) -> Vec<SyntaxError> {
+        let len = self.len;
+        let mut errors = self.errors.into_inner();
+        errors.extend(error.map(|error| SyntaxError::new(len, error)));
+        errors
+    }
+}
@@ -0,0 +1,134 @@
+//! `ast::Func` round-trips every function of the test corpus, and
+//! `into_repr` rejects the functions that `repr::Func::check` does.
+
+extern crate nll_repr;
+
+use nll_repr::ast;
+use nll_repr::repr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The `.nll` files of the corpus, subdirectories included.
+fn corpus() -> Vec<PathBuf> {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../test");
+    let mut dirs = vec![root];
+    let mut files = vec![];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().is_some_and(|e| e == "nll") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn corpus_round_trips() {
+    let mut parsed = 0;
+    for path in corpus() {
+        let text = fs::read_to_string(&path).unwrap();
+        // some inputs are there to test syntax errors
+        let func = match repr::Func::parse_file(&path, &text) {
+            Ok(func) => func,
+            Err(_) => continue,
+        };
+        let ast = ast::Func::from_repr(&func);
+        let back = ast.into_repr().unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+        assert!(back == func, "{} does not round-trip", path.display());
+        parsed += 1;
+    }
+    assert!(parsed > 0);
+}
+
+const PROGRAM: &str = "\
+struct S<'+> {
+  f: &'0 ()
+}
+
+let x: ();
+let s: S<'s>;
+
+block START {
+    x = use();
+    s = use(&'b x);
+    goto END;
+}
+
+block END {
+    use(s);
+}
+";
+
+/// The AST of `PROGRAM`, changed by `change`, converted back.
+fn changed(change: fn(&mut ast::Func)) -> Result<repr::Func, String> {
+    let mut func = ast::Func::from_repr(&repr::Func::parse(PROGRAM).unwrap());
+    change(&mut func);
+    func.into_repr()
+}
+
+fn var(name: &str, ty: ast::Ty, kind: ast::VarKind) -> ast::VarDecl {
+    ast::VarDecl { name: name.to_string(), ty, kind }
+}
+
+#[test]
+fn into_repr_accepts_program() {
+    assert!(changed(|_| ()).is_ok());
+}
+
+#[test]
+fn into_repr_rejects_duplicate_block() {
+    let err = changed(|func| {
+        let block = func.blocks[0].clone();
+        func.blocks.push(block);
+    });
+    assert_eq!(err.unwrap_err(), "block `END` is defined twice");
+}
+
+#[test]
+fn into_repr_rejects_missing_start() {
+    let err = changed(|func| {
+        let start = func.blocks.iter_mut().find(|b| b.name == "START").unwrap();
+        start.name = "BEGIN".to_string();
+    });
+    assert_eq!(err.unwrap_err(), "there is no `START` block");
+}
+
+#[test]
+fn into_repr_rejects_goto_to_undefined_block() {
+    let err = changed(|func| {
+        let start = func.blocks.iter_mut().find(|b| b.name == "START").unwrap();
+        start.successors = vec!["ELSEWHERE".to_string()];
+    });
+    assert_eq!(err.unwrap_err(), "block `START` jumps to `ELSEWHERE`, which is not defined");
+}
+
+#[test]
+fn into_repr_rejects_variable_declared_twice() {
+    let err = changed(|func| func.vars.push(var("x", ast::Ty::Int, ast::VarKind::Local)));
+    assert_eq!(err.unwrap_err(), "variable `x` is declared twice");
+}
+
+#[test]
+fn into_repr_rejects_undeclared_variable() {
+    let err = changed(|func| {
+        let mut action = func.blocks[0].actions[0].clone();
+        action.kind = ast::ActionKind::Use(ast::Path::Var("y".to_string()));
+        func.blocks[0].actions.push(action);
+    });
+    assert_eq!(err.unwrap_err(), "variable `y`, used in block `END`, is not declared");
+}
+
+#[test]
+fn into_repr_rejects_static_naming_a_region() {
+    let err = changed(|func| {
+        let region = ast::Region::Named("'a".to_string());
+        let ty = ast::Ty::Ref(region, ast::Mutability::Shared, Box::new(ast::Ty::Unit));
+        func.vars.push(var("g", ty, ast::VarKind::Static));
+    });
+    assert!(err.unwrap_err().starts_with("the type `&'a ()` of static `g` names the region `'a`"));
+}
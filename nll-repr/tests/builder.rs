@@ -0,0 +1,137 @@
+//! `FuncBuilder` builds the same function as parsing its text, and
+//! `build` rejects the functions that break the invariants it checks.
+
+extern crate nll_repr;
+
+use nll_repr::repr::{BorrowKind, FieldDecl, FieldName, Func, FuncBuilder, Kind, Operand, Path,
+                     Region, StructDecl, StructName, StructParameter, Ty, TyBuilder, Variance,
+                     VariableKind};
+
+const PROGRAM: &str = "\
+struct S<'+> {
+  f: &'0 ()
+}
+
+for<'a: 'b, 'b>;
+
+let x: ();
+let p: &'p ();
+let s: S<'s>;
+
+block START {
+    x = use();
+    p = &'b0 x;
+    s = use(p);
+    goto END;
+}
+
+block END {
+    use(s);
+    drop(s);
+    StorageDead(x);
+}
+";
+
+/// The builder for `PROGRAM`.
+fn program() -> FuncBuilder {
+    let mut func = FuncBuilder::new();
+    func.struct_decl(StructDecl {
+        name: StructName::from("S"),
+        parameters: vec![StructParameter {
+            kind: Kind::Region,
+            variance: Variance::Co,
+            may_dangle: false,
+        }],
+        fields: vec![FieldDecl {
+            name: FieldName::from("f"),
+            ty: Box::new(Ty::Ref(Region::Bound(0), BorrowKind::Shared, TyBuilder::unit().build())),
+        }],
+    });
+    func.region("'a", &["'b"]).region("'b", &[]);
+    func.var("x", TyBuilder::unit())
+        .var("p", TyBuilder::unit().shared_ref("'p"))
+        .var("s", TyBuilder::named("S").region_param("'s"));
+    func.block("START")
+        .init("x", vec![])
+        .borrow("p", "'b0", BorrowKind::Shared, Path::var("x"))
+        .init("s", vec![Operand::from(Path::var("p"))])
+        .goto(&["END"]);
+    func.block("END")
+        .use_(Path::var("s"))
+        .drop_(Path::var("s"))
+        .storage_dead("x");
+    func
+}
+
+#[test]
+fn builds_parsed_program() {
+    let mut parsed = Func::parse(PROGRAM).unwrap();
+    // built actions come from no source text
+    for block in parsed.data.values_mut() {
+        for action in &mut block.actions {
+            action.span = None;
+        }
+    }
+    assert_eq!(program().build().unwrap(), parsed);
+}
+
+#[test]
+fn rejects_duplicate_block() {
+    let mut func = program();
+    func.block("END");
+    assert_eq!(func.build().unwrap_err(), "block `END` is defined twice");
+}
+
+#[test]
+fn rejects_missing_start() {
+    let mut func = FuncBuilder::new();
+    func.var("x", TyBuilder::unit());
+    func.block("BEGIN").use_(Path::var("x"));
+    assert_eq!(func.build().unwrap_err(), "there is no `START` block");
+}
+
+#[test]
+fn rejects_goto_to_undefined_block() {
+    let mut func = program();
+    func.block("LOOP").goto(&["ELSEWHERE"]);
+    assert_eq!(func.build().unwrap_err(),
+               "block `LOOP` jumps to `ELSEWHERE`, which is not defined");
+}
+
+#[test]
+fn rejects_variable_declared_twice() {
+    let mut func = program();
+    func.var("x", TyBuilder::int());
+    assert_eq!(func.build().unwrap_err(), "variable `x` is declared twice");
+}
+
+#[test]
+fn rejects_undeclared_variable() {
+    let mut func = program();
+    func.block("EXIT").use_(Path::var("y").deref());
+    assert_eq!(func.build().unwrap_err(), "variable `y`, used in block `EXIT`, is not declared");
+}
+
+#[test]
+fn rejects_undeclared_struct() {
+    let mut func = program();
+    func.var("t", TyBuilder::named("T"));
+    assert_eq!(func.build().unwrap_err(), "struct `T` is not declared");
+}
+
+#[test]
+fn rejects_struct_with_wrong_parameters() {
+    let mut func = program();
+    func.var("t", TyBuilder::named("S").ty_param(TyBuilder::unit()));
+    assert_eq!(func.build().unwrap_err(),
+               "struct `S` takes parameters of kinds [Region], but `S<()>` has [Type]");
+}
+
+#[test]
+fn rejects_static_naming_a_region() {
+    let mut func = program();
+    func.declare("g", TyBuilder::unit().shared_ref("'a"), VariableKind::Static);
+    assert!(func.build()
+                .unwrap_err()
+                .starts_with("the type `&'a ()` of static `g` names the region `'a`"));
+}
@@ -0,0 +1,33 @@
+//! The parser reports every syntax error in a file, with the tokens it
+//! expected, recovering at the `;` that ends an action, or at the `}`
+//! that ends the block when the action has no `;`.
+
+extern crate nll_repr;
+
+use nll_repr::repr::Func;
+
+fn errors(text: &str) -> Vec<String> {
+    Func::parse(text).unwrap_err().lines().map(|line| line.to_string()).collect()
+}
+
+#[test]
+fn reports_every_error() {
+    let errors = errors("let a: ();\nblock START {\n    a = = use();\n    use(a;\n}\n");
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert!(errors[0].starts_with("parse error at 3:9 (offset 33), expected one of `&`, `(`"),
+            "{}", errors[0]);
+    assert_eq!(errors[1], "parse error at 4:10 (offset 51), expected `)`");
+}
+
+#[test]
+fn missing_semicolon_is_reported_at_end_of_block() {
+    assert_eq!(errors("block START {\n  x = use();\n  use(x)\n}\n"),
+               vec!["parse error at 4:1 (offset 36), expected `;`"]);
+}
+
+#[test]
+fn recovers_at_end_of_block() {
+    let errors = errors("block START {\n    use(x)\n}\nblock END {\n    use(x;\n}\n");
+    assert_eq!(errors, vec!["parse error at 3:1 (offset 25), expected `;`",
+                            "parse error at 5:10 (offset 48), expected `)`"]);
+}
@@ -0,0 +1,205 @@
+//! `nll annotate-diff`: analyzes a program with the region annotations
+//! of the borrows at one point removed, and reports what that changes
+//! -- which errors go away or appear, and which region values change.
+//!
+//! Removing the annotation of `p = &'a q` makes it `p = &q`: the loan
+//! gets a fresh region of its own, no longer tied to the other uses of
+//! `'a` (in the types of variables, other borrows, or constraints).
+//! That shows how much of the result hinges on that one annotation.
+//! Only the regions change, not the actions, so the points of the two
+//! programs are the same.
+
+use env::{Environment, Point};
+use errors::{ReportedError, SourceFile};
+use graph::{self, FuncGraph};
+use nll_repr::repr::{ActionKind, CaptureMode, Operand, RegionName};
+use options::Options;
+use region::Region;
+use regionck::{self, RegionValues};
+use std::error::Error;
+use std::fmt;
+
+pub struct AnnotateDiff {
+    pub point: Point,
+
+    /// Each region annotation removed, with the fresh region that
+    /// replaces it and the value of that region.
+    pub flipped: Vec<(RegionName, RegionName, Region)>,
+
+    /// The errors of the original program that go away.
+    pub fixed_errors: Vec<ReportedError>,
+
+    /// The errors that the original program does not have.
+    pub new_errors: Vec<ReportedError>,
+
+    /// For each region whose value changes, the elements it gains and
+    /// those it loses.
+    pub changed: Vec<(RegionName, Region, Region)>,
+}
+
+/// Replaces the region annotations of the borrows of the action at
+/// `point` with fresh regions, and compares the results of the
+/// analysis against those for `env`. The assertions of the program are
+/// not checked for the modified program, since they may name the
+/// regions removed.
+pub fn annotate_diff(env: &Environment,
+                     options: &Options,
+                     source: &SourceFile,
+                     point: Point)
+                     -> Result<AnnotateDiff, Box<Error>> {
+    let old = regionck::analysis(env, options, source)?;
+
+    let mut func = env.graph.func().clone();
+    func.assertions.clear();
+    let block_name = env.graph.block_data(point.block).name;
+    let mut flipped = vec![];
+    match func.data.get_mut(&block_name).unwrap().actions.get_mut(point.action) {
+        Some(action) => flip(&mut action.kind, &mut flipped),
+        None => try!(Err(format!("{:?} is the end of its block, not an action", point))),
+    }
+    if flipped.is_empty() {
+        try!(Err(format!("the action at {:?} has no borrow with a region annotation", point)));
+    }
+
+    let graph = FuncGraph::new(func);
+    let new = graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        regionck::analysis(&env, options, source)
+    })?;
+
+    let flipped = flipped
+        .into_iter()
+        .map(|(old, fresh)| (old, fresh, value(&new.regions, fresh)))
+        .collect();
+
+    let fixed: Vec<_> = old.errors
+        .iter()
+        .map(|error| !new.errors.iter().any(|new| same_error(new, error)))
+        .collect();
+    let new_errors = new.errors
+        .into_iter()
+        .filter(|error| !old.errors.iter().any(|old| same_error(old, error)))
+        .collect();
+    let fixed_errors = old.errors
+        .into_iter()
+        .zip(fixed)
+        .filter_map(|(error, fixed)| if fixed { Some(error) } else { None })
+        .collect();
+
+    let mut changed = vec![];
+    for (&name, new_region) in &new.regions {
+        let old_region = match old.regions.get(&name) {
+            Some(region) => region,
+            None => continue,
+        };
+        if new_region != old_region {
+            changed.push((name, minus(new_region, old_region), minus(old_region, new_region)));
+        }
+    }
+
+    Ok(AnnotateDiff { point, flipped, fixed_errors, new_errors, changed })
+}
+
+/// Replaces each region annotation of a borrow in `kind` with a fresh
+/// region, recording the pair in `flipped`. Regions that were elided
+/// are fresh already, and are left alone.
+fn flip(kind: &mut ActionKind, flipped: &mut Vec<(RegionName, RegionName)>) {
+    let mut fresh = |region: &mut RegionName| if !region.is_fresh() {
+        let replacement = RegionName::fresh();
+        flipped.push((*region, replacement));
+        *region = replacement;
+    };
+    match *kind {
+        ActionKind::Borrow(_, ref mut region, _, _) => fresh(region),
+        ActionKind::Init(_, ref mut operands) => {
+            for operand in operands {
+                if let Operand::Borrow(ref mut region, _, _) = *operand {
+                    fresh(region);
+                }
+            }
+        }
+        ActionKind::Closure(_, ref mut captures) => {
+            for capture in captures {
+                if let CaptureMode::ByRef(ref mut region, _) = capture.mode {
+                    fresh(region);
+                }
+            }
+        }
+        ActionKind::RawBorrow(..) |
+        ActionKind::Assign(..) |
+        ActionKind::Const(..) |
+        ActionKind::Constraint(..) |
+        ActionKind::Use(..) |
+        ActionKind::Read(..) |
+        ActionKind::Write(..) |
+        ActionKind::Drop(..) |
+        ActionKind::StorageDead(..) |
+        ActionKind::Noop => {}
+    }
+}
+
+fn value(regions: &RegionValues, name: RegionName) -> Region {
+    regions.get(&name).cloned().unwrap_or_else(Region::new)
+}
+
+/// The elements of `a` that are not in `b`.
+fn minus(a: &Region, b: &Region) -> Region {
+    let mut difference = Region::new();
+    for e in a.iter().filter(|&e| !b.contains(e)) {
+        difference.add_element(e);
+    }
+    difference
+}
+
+fn same_error(a: &ReportedError, b: &ReportedError) -> bool {
+    a.point == b.point && a.code == b.code && a.message == b.message
+}
+
+fn write_error(f: &mut fmt::Formatter, error: &ReportedError) -> Result<(), fmt::Error> {
+    match error.code {
+        Some(code) => writeln!(f, "    error[{}] at {:?}: {}", code, error.point, error.message),
+        None => writeln!(f, "    error at {:?}: {}", error.point, error.message),
+    }
+}
+
+/// Must be displayed within `graph::with_graph` for the original
+/// program.
+impl fmt::Display for AnnotateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for &(old, fresh, ref value) in &self.flipped {
+            writeln!(f, "without `{}` at {:?}: the borrow's region `{}` is {}",
+                     old, self.point, fresh, value)?;
+        }
+
+        if self.fixed_errors.is_empty() && self.new_errors.is_empty() {
+            writeln!(f, "  the errors are the same")?;
+        }
+        if !self.fixed_errors.is_empty() {
+            writeln!(f, "  errors that go away:")?;
+            for error in &self.fixed_errors {
+                write_error(f, error)?;
+            }
+        }
+        if !self.new_errors.is_empty() {
+            writeln!(f, "  new errors:")?;
+            for error in &self.new_errors {
+                write_error(f, error)?;
+            }
+        }
+
+        if self.changed.is_empty() {
+            writeln!(f, "  no other region changes")?;
+        } else {
+            writeln!(f, "  regions that change:")?;
+            for &(name, ref gained, ref lost) in &self.changed {
+                if *gained != Region::new() {
+                    writeln!(f, "    `{}` gains {}", name, gained)?;
+                }
+                if *lost != Region::new() {
+                    writeln!(f, "    `{}` loses {}", name, lost)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
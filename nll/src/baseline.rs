@@ -0,0 +1,236 @@
+//! `nll baseline save` and `nll baseline compare`: whether anything
+//! observable changed across a corpus. `save` writes what the analysis
+//! found for each program -- its errors, the value of each region, the
+//! scope of each loan and the result of each assertion, or why it
+//! could not be analyzed -- to a file of JSON lines, one per program.
+//! `compare` analyzes the corpus again and reports, program by
+//! program, how the results differ from those saved: a refactoring of
+//! the solver should report no change at all.
+//!
+//! Only results are recorded, not how they were computed (the work the
+//! solver did, or the ids of the facts behind an error), so that a
+//! change in the latter alone does not count. Programs are identified
+//! by their file name within the corpus directory.
+
+use env::Environment;
+use errors::SourceFile;
+use graph::{self, FuncGraph};
+use nll_repr::repr::{self, Func};
+use options::Options;
+use regionck;
+use rustc_serialize::json::Json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// What the analysis found for one program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Results {
+    pub file: String,
+
+    /// Why the program could not be parsed or analyzed, if it could
+    /// not; the other results are then empty.
+    pub failure: Option<String>,
+    pub errors: BTreeSet<String>,
+    pub regions: BTreeMap<String, String>,
+
+    /// The scope of each loan, in the order of
+    /// `Environment::loan_sites`.
+    pub loans: Vec<String>,
+    pub assertions: Vec<String>,
+}
+
+impl Results {
+    /// The results for a program that could not be parsed or analyzed.
+    pub fn failed(file: &str, failure: String) -> Self {
+        Results {
+            file: file.to_string(),
+            failure: Some(failure),
+            errors: BTreeSet::new(),
+            regions: BTreeMap::new(),
+            loans: vec![],
+            assertions: vec![],
+        }
+    }
+
+    pub fn analyze(file: &str, func: &Func, options: &Options, source: &SourceFile) -> Self {
+        let graph = FuncGraph::new(func.clone());
+        graph::with_graph(&graph, || {
+            let env = &Environment::new(&graph);
+            let analysis = match regionck::analysis(env, options, source) {
+                Ok(analysis) => analysis,
+                Err(err) => return Results::failed(file, err.to_string()),
+            };
+            Results {
+                file: file.to_string(),
+                failure: None,
+                errors: analysis.errors.iter().map(|error| error.to_string()).collect(),
+                regions: analysis.regions
+                    .iter()
+                    .map(|(name, region)| (name.to_string(), region.to_string()))
+                    .collect(),
+                loans: analysis.loan_scopes
+                    .iter()
+                    .map(|loan| {
+                        let kind = match loan.kind {
+                            repr::BorrowKind::Shared => "shared",
+                            repr::BorrowKind::Mut => "mut",
+                        };
+                        format!("{} loan of `{}` at {:?}: {}",
+                                kind, loan.path, loan.issued_at, loan.scope)
+                    })
+                    .collect(),
+                assertions: analysis.assertions
+                    .iter()
+                    .map(|assertion| match assertion.failure {
+                        None => format!("assertion {} holds", assertion.index),
+                        Some(ref failure) => {
+                            format!("assertion {} fails: {}", assertion.index, failure)
+                        }
+                    })
+                    .collect(),
+            }
+        })
+    }
+
+    pub fn to_json(&self) -> Json {
+        let mut record = BTreeMap::new();
+        record.insert("file".to_string(), Json::String(self.file.clone()));
+        record.insert("failure".to_string(), match self.failure {
+            Some(ref failure) => Json::String(failure.clone()),
+            None => Json::Null,
+        });
+        record.insert("errors".to_string(), strings(&self.errors));
+        record.insert("regions".to_string(), Json::Object(
+            self.regions
+                .iter()
+                .map(|(name, value)| (name.clone(), Json::String(value.clone())))
+                .collect(),
+        ));
+        record.insert("loans".to_string(), strings(&self.loans));
+        record.insert("assertions".to_string(), strings(&self.assertions));
+        Json::Object(record)
+    }
+
+    pub fn from_json(json: &Json) -> Result<Self, String> {
+        let invalid = || format!("invalid baseline record: {}", json);
+        let string = |json: &Json| json.as_string().map(|s| s.to_string()).ok_or_else(invalid);
+        let strings = |field: &str| -> Result<Vec<String>, String> {
+            let array = json.find(field).and_then(|a| a.as_array()).ok_or_else(invalid)?;
+            array.iter().map(&string).collect()
+        };
+        let regions = json.find("regions").and_then(|r| r.as_object()).ok_or_else(invalid)?;
+        Ok(Results {
+            file: string(json.find("file").ok_or_else(invalid)?)?,
+            failure: match json.find("failure") {
+                Some(&Json::Null) => None,
+                Some(failure) => Some(string(failure)?),
+                None => return Err(invalid()),
+            },
+            errors: strings("errors")?.into_iter().collect(),
+            regions: regions
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), string(value)?)))
+                .collect::<Result<_, String>>()?,
+            loans: strings("loans")?,
+            assertions: strings("assertions")?,
+        })
+    }
+}
+
+fn strings<'a, I: IntoIterator<Item = &'a String>>(strings: I) -> Json {
+    Json::Array(strings.into_iter().map(|s| Json::String(s.clone())).collect())
+}
+
+/// How the results for the programs of a corpus differ from the
+/// baseline.
+pub struct Comparison {
+    /// The programs whose results changed, with how.
+    pub changed: Vec<(String, Vec<String>)>,
+
+    /// The number of programs in the baseline or the corpus.
+    pub total: usize,
+}
+
+pub fn compare(baseline: &[Results], current: &[Results]) -> Comparison {
+    let old: BTreeMap<_, _> = baseline.iter().map(|r| (&r.file, r)).collect();
+    let new: BTreeMap<_, _> = current.iter().map(|r| (&r.file, r)).collect();
+    let files: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+    let changed = files
+        .iter()
+        .filter_map(|file| {
+            let changes = match (old.get(*file), new.get(*file)) {
+                (Some(old), Some(new)) => changes(old, new),
+                (Some(_), None) => vec!["not in the corpus any more".to_string()],
+                (None, Some(_)) => vec!["not in the baseline".to_string()],
+                (None, None) => unreachable!(),
+            };
+            if changes.is_empty() {
+                None
+            } else {
+                Some((file.to_string(), changes))
+            }
+        })
+        .collect();
+    Comparison { changed, total: files.len() }
+}
+
+/// The differences between `old` and `new`, one per line.
+fn changes(old: &Results, new: &Results) -> Vec<String> {
+    let mut changes = vec![];
+    if old.failure != new.failure {
+        let describe = |failure: &Option<String>| match *failure {
+            Some(ref failure) => format!("fails: {}", failure),
+            None => "analyzed".to_string(),
+        };
+        changes.push(format!("was {}, now {}", describe(&old.failure), describe(&new.failure)));
+    }
+    for error in old.errors.difference(&new.errors) {
+        changes.push(format!("- error {}", error));
+    }
+    for error in new.errors.difference(&old.errors) {
+        changes.push(format!("+ error {}", error));
+    }
+    let names: BTreeSet<_> = old.regions.keys().chain(new.regions.keys()).collect();
+    for name in names {
+        match (old.regions.get(name), new.regions.get(name)) {
+            (Some(old), Some(new)) if old == new => {}
+            (old, new) => {
+                let describe = |value: Option<&String>| {
+                    value.cloned().unwrap_or_else(|| "none".to_string())
+                };
+                changes.push(format!("region `{}` was {}, now {}",
+                                     name, describe(old), describe(new)));
+            }
+        }
+    }
+    compare_lists(&mut changes, "loan", &old.loans, &new.loans);
+    compare_lists(&mut changes, "assertion", &old.assertions, &new.assertions);
+    changes
+}
+
+fn compare_lists(changes: &mut Vec<String>, what: &str, old: &[String], new: &[String]) {
+    if old.len() != new.len() {
+        changes.push(format!("{} {}s, now {}", old.len(), what, new.len()));
+        return;
+    }
+    for (old, new) in old.iter().zip(new) {
+        if old != new {
+            changes.push(format!("was {}, now {}", old, new));
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for (file, changes) in &self.changed {
+            writeln!(f, "{}:", file)?;
+            for change in changes {
+                writeln!(f, "  {}", change)?;
+            }
+        }
+        match self.changed.len() {
+            0 => writeln!(f, "no changes in {} programs", self.total),
+            n => writeln!(f, "{} of {} programs changed", n, self.total),
+        }
+    }
+}
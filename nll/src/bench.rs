@@ -0,0 +1,370 @@
+//! `nll bench-compare`: runs each program through every engine -- each
+//! configuration of the analysis that can accept or reject different
+//! programs -- and tabulates how they compare: whether each accepts
+//! the program, how its errors differ from those of the first engine,
+//! how much work the region solver did, and how much time and memory
+//! the whole analysis took.
+//!
+//! The assertions of a program are not checked, since they generally
+//! hold for only one of the engines.
+
+use env::Environment;
+use errors::{ReportedError, SourceFile};
+use graph::{self, FuncGraph};
+use infer::SolveStats;
+use nll_repr::repr::Func;
+use options::{LoanGranularity, Options, RegionRepr};
+use regionck;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// One way of running the analysis. The first engine in `ENGINES` is
+/// the reference that the others are compared against.
+pub struct Engine {
+    pub name: &'static str,
+
+    /// Overrides the settings of the base options that select the
+    /// engine; the other options are used as given.
+    configure: fn(&mut Options),
+}
+
+pub const ENGINES: &[Engine] = &[
+    Engine {
+        name: "nll",
+        configure: configure_nll,
+    },
+    Engine {
+        name: "region-loans",
+        configure: configure_region_loans,
+    },
+    Engine {
+        name: "no-regionck",
+        configure: configure_no_regionck,
+    },
+    Engine {
+        name: "dense-regions",
+        configure: configure_dense_regions,
+    },
+    Engine {
+        name: "interval-regions",
+        configure: configure_interval_regions,
+    },
+];
+
+fn configure_nll(options: &mut Options) {
+    options.loan_granularity = LoanGranularity::PerBorrow;
+    options.skip_regionck = false;
+    options.region_repr = RegionRepr::Set;
+}
+
+/// As with `--merge-loans-by-region`: one loan per region rather than
+/// per borrow.
+fn configure_region_loans(options: &mut Options) {
+    options.loan_granularity = LoanGranularity::PerRegion;
+    options.skip_regionck = false;
+    options.region_repr = RegionRepr::Set;
+}
+
+/// As with `--no-regionck`: every loan is in scope everywhere.
+fn configure_no_regionck(options: &mut Options) {
+    options.loan_granularity = LoanGranularity::PerBorrow;
+    options.skip_regionck = true;
+    options.region_repr = RegionRepr::Set;
+}
+
+/// As with `--region-repr dense`: the same results as `nll`, with
+/// regions stored as bit sets.
+fn configure_dense_regions(options: &mut Options) {
+    options.loan_granularity = LoanGranularity::PerBorrow;
+    options.skip_regionck = false;
+    options.region_repr = RegionRepr::Dense;
+}
+
+/// As with `--region-repr intervals`: the same results as `nll`, with
+/// regions stored as runs of points.
+fn configure_interval_regions(options: &mut Options) {
+    options.loan_granularity = LoanGranularity::PerBorrow;
+    options.skip_regionck = false;
+    options.region_repr = RegionRepr::Intervals;
+}
+
+/// The outcome of running every engine on one program.
+pub struct Comparison {
+    pub input: String,
+    pub runs: Vec<EngineRun>,
+}
+
+pub struct EngineRun {
+    pub engine: &'static str,
+
+    /// The analysis fails only for malformed programs (e.g., an
+    /// unknown region), in which case it usually fails for every
+    /// engine.
+    pub outcome: Result<RunStats, String>,
+}
+
+pub struct RunStats {
+    /// The errors reported, written out as `error[CODE] at POINT: message`.
+    pub errors: BTreeSet<String>,
+
+    /// `None` if the engine does not run the region solver.
+    pub solve_stats: Option<SolveStats>,
+
+    /// The time taken by the whole analysis, including the solver.
+    pub time: Duration,
+
+    /// The peak number of bytes allocated during the analysis, beyond
+    /// what was allocated before it started; `None` unless the
+    /// program installed `PeakAllocator`.
+    pub peak_memory: Option<usize>,
+}
+
+pub fn compare(input: &str, func: &Func, options: &Options, source: &SourceFile) -> Comparison {
+    let mut func = func.clone();
+    func.assertions.clear();
+    let graph = FuncGraph::new(func);
+    let runs = graph::with_graph(&graph, || {
+        let env = &Environment::new(&graph);
+        ENGINES
+            .iter()
+            .map(|engine| {
+                let mut options = options.clone();
+                (engine.configure)(&mut options);
+                EngineRun {
+                    engine: engine.name,
+                    outcome: run(env, &options, source),
+                }
+            })
+            .collect()
+    });
+    Comparison {
+        input: input.to_string(),
+        runs,
+    }
+}
+
+fn run(env: &Environment, options: &Options, source: &SourceFile) -> Result<RunStats, String> {
+    let baseline = reset_peak();
+    let start = Instant::now();
+    let analysis = regionck::analysis(env, options, source).map_err(|e| e.to_string())?;
+    let time = start.elapsed();
+    let peak_memory = baseline.map(|baseline| PEAK.load(Ordering::Relaxed).saturating_sub(baseline));
+
+    Ok(RunStats {
+        errors: analysis.errors.iter().map(describe_error).collect(),
+        solve_stats: if options.skip_regionck {
+            None
+        } else {
+            Some(analysis.solve_stats)
+        },
+        time,
+        peak_memory,
+    })
+}
+
+fn describe_error(error: &ReportedError) -> String {
+    match error.code {
+        Some(code) => format!("error[{}] at {:?}: {}", code, error.point, error.message),
+        None => format!("error at {:?}: {}", error.point, error.message),
+    }
+}
+
+fn millis(time: Duration) -> String {
+    format!("{:.3}ms", time.as_secs() as f64 * 1e3 + f64::from(time.subsec_nanos()) / 1e6)
+}
+
+fn kibibytes(bytes: Option<usize>) -> String {
+    match bytes {
+        Some(bytes) => format!("{:.1}KiB", bytes as f64 / 1024.0),
+        None => "-".to_string(),
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "{}:", self.input)?;
+        let reference = match self.runs.first().map(|run| &run.outcome) {
+            Some(&Ok(ref stats)) => Some(&stats.errors),
+            _ => None,
+        };
+        writeln!(f,
+                 "  {:<16} {:<9} {:>6} {:>8} {:>11} {:>10} {:>10} {:>10} {:>10}",
+                 "engine",
+                 "result",
+                 "errors",
+                 "diff",
+                 "constraints",
+                 "iterations",
+                 "solve",
+                 "total",
+                 "peak")?;
+        for run in &self.runs {
+            let stats = match run.outcome {
+                Ok(ref stats) => stats,
+                Err(ref message) => {
+                    writeln!(f, "  {:<16} failed: {}", run.engine, message)?;
+                    continue;
+                }
+            };
+            let diff = match reference {
+                Some(reference) if reference == &stats.errors => "=".to_string(),
+                Some(reference) => {
+                    format!("+{} -{}",
+                            stats.errors.difference(reference).count(),
+                            reference.difference(&stats.errors).count())
+                }
+                None => "-".to_string(),
+            };
+            let (constraints, iterations, solve) = match stats.solve_stats {
+                Some(s) => (s.constraints.to_string(), s.iterations.to_string(), millis(s.time)),
+                None => ("-".to_string(), "-".to_string(), "-".to_string()),
+            };
+            writeln!(f,
+                     "  {:<16} {:<9} {:>6} {:>8} {:>11} {:>10} {:>10} {:>10} {:>10}",
+                     run.engine,
+                     if stats.errors.is_empty() { "accepted" } else { "rejected" },
+                     stats.errors.len(),
+                     diff,
+                     constraints,
+                     iterations,
+                     solve,
+                     millis(stats.time),
+                     kibibytes(stats.peak_memory))?;
+        }
+
+        // Spell out the errors on which the engines disagree.
+        let reference = match reference {
+            Some(reference) => reference,
+            None => return Ok(()),
+        };
+        for run in &self.runs[1..] {
+            if let Ok(ref stats) = run.outcome {
+                for error in stats.errors.difference(reference) {
+                    writeln!(f, "  only {}: {}", run.engine, error)?;
+                }
+                for error in reference.difference(&stats.errors) {
+                    writeln!(f, "  not {}: {}", run.engine, error)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Totals over all of the comparisons, for each engine.
+pub struct Summary<'c> {
+    pub comparisons: &'c [Comparison],
+}
+
+impl<'c> fmt::Display for Summary<'c> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "summary of {} inputs:", self.comparisons.len())?;
+        writeln!(f,
+                 "  {:<16} {:>8} {:>8} {:>6} {:>8} {:>11} {:>10} {:>10} {:>10}",
+                 "engine",
+                 "accepted",
+                 "rejected",
+                 "failed",
+                 "differs",
+                 "constraints",
+                 "iterations",
+                 "total",
+                 "max peak")?;
+        for (index, engine) in ENGINES.iter().enumerate() {
+            let (mut accepted, mut rejected, mut failed, mut differs) = (0, 0, 0, 0);
+            let (mut constraints, mut iterations) = (0, 0);
+            let mut time = Duration::new(0, 0);
+            let mut peak_memory = None;
+            for comparison in self.comparisons {
+                let stats = match comparison.runs[index].outcome {
+                    Ok(ref stats) => stats,
+                    Err(_) => {
+                        failed += 1;
+                        continue;
+                    }
+                };
+                if stats.errors.is_empty() {
+                    accepted += 1;
+                } else {
+                    rejected += 1;
+                }
+                if let Ok(ref reference) = comparison.runs[0].outcome {
+                    if reference.errors != stats.errors {
+                        differs += 1;
+                    }
+                }
+                if let Some(s) = stats.solve_stats {
+                    constraints += s.constraints;
+                    iterations += s.iterations;
+                }
+                time += stats.time;
+                peak_memory = peak_memory.max(stats.peak_memory);
+            }
+            writeln!(f,
+                     "  {:<16} {:>8} {:>8} {:>6} {:>8} {:>11} {:>10} {:>10} {:>10}",
+                     engine.name,
+                     accepted,
+                     rejected,
+                     failed,
+                     differs,
+                     constraints,
+                     iterations,
+                     millis(time),
+                     kibibytes(peak_memory))?;
+        }
+        Ok(())
+    }
+}
+
+/// A global allocator that keeps track of the peak number of bytes
+/// allocated, so that `compare` can report the memory used by each
+/// engine. The `nll` binary installs it with `#[global_allocator]`.
+pub struct PeakAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn record_allocation(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(allocated, Ordering::Relaxed);
+}
+
+/// Restarts the peak from the bytes allocated now, which it returns;
+/// `None` if `PeakAllocator` is not installed (nothing has been
+/// allocated through it).
+fn reset_peak() -> Option<usize> {
+    let allocated = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(allocated, Ordering::Relaxed);
+    if allocated > 0 {
+        Some(allocated)
+    } else {
+        None
+    }
+}
+
+unsafe impl GlobalAlloc for PeakAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_allocation(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            record_allocation(new_size);
+        }
+        new_ptr
+    }
+}
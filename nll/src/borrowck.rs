@@ -1,362 +1,779 @@
 use env::{Environment, Point};
-use errors::ErrorReporting;
+use errors::{ErrorReporting, Fact, Note, ReportedError};
+use graph_algorithms::bit_set::{BitMatrix, BitSlice};
 use loans_in_scope::{Loan, LoansInScope};
 use nll_repr::repr;
-use std::error::Error;
-use std::fmt;
+use path_table::{self, PathIndex, PathTable};
+use region;
+use std::collections::HashMap;
 
+/// Checks each action against the loans in scope where it occurs. If
+/// `trace` is given (by `--trace-path`), each check of an access that
+/// touches that path, or of a loan that does, is printed along the
+/// way.
 pub fn borrow_check(env: &Environment,
                     loans_in_scope: &LoansInScope,
+                    trace: Option<PathIndex>,
                     errors: &mut ErrorReporting) {
-    loans_in_scope.walk(env, |point, opt_action, loans| {
-        let borrowck = BorrowCheck { env, point, loans };
+    let paths = loans_in_scope.paths();
+    let loans = loans_in_scope.loans();
+    let conflicts = &ConflictMatrix::new(paths, loans);
+    loans_in_scope.walk_bits(env, |point, opt_action, in_scope| {
+        let borrowck = BorrowCheck { env, paths, point, loans, in_scope, conflicts, trace };
         if let Some(action) = opt_action {
-            if let Err(e) = borrowck.check_action(action) {
-                errors.report_error(point, e.to_string());
+            if !action.in_unsafe_block {
+                let raw_deref = path_table::action_paths(action)
+                    .into_iter()
+                    .find(|path| env.derefs_raw_pointer(path));
+                if let Some(path) = raw_deref {
+                    errors.report(ReportedError {
+                        point,
+                        code: Some("E0133"),
+                        message: format!("dereference of raw pointer in `{}` requires an \
+                                          unsafe block",
+                                         path),
+                        notes: vec![],
+                        labels: HashMap::new(),
+                        facts: vec![],
+                    });
+                    return;
+                }
+
+                let mut_static = path_table::action_paths(action)
+                    .into_iter()
+                    .find(|path| env.var_kind(path.base()) == repr::VariableKind::StaticMut);
+                if let Some(path) = mut_static {
+                    errors.report(ReportedError {
+                        point,
+                        code: Some("E0133"),
+                        message: format!("use of mutable static `{}` requires an unsafe block",
+                                         path.base()),
+                        notes: vec![],
+                        labels: HashMap::new(),
+                        facts: vec![],
+                    });
+                    return;
+                }
+            }
+
+            if let Some((code, message)) = static_mutation(env, action) {
+                errors.report(ReportedError {
+                    point,
+                    code: Some(code),
+                    message,
+                    notes: vec![],
+                    labels: HashMap::new(),
+                    facts: vec![],
+                });
+                return;
+            }
+
+            // Within an unsafe block, the programmer vouches for
+            // accesses through raw pointers.
+            if let Err(e) = borrowck.check(action) {
+                if action.in_unsafe_block && e.through_raw_pointer {
+                    errors.report_warning(e.into_reported_error(env, point));
+                } else {
+                    errors.report(e.into_reported_error(env, point));
+                }
             }
         }
     });
 }
 
+/// The error, if any, that `borrow_check` reports for the action at
+/// `point`; used by `--why-error`.
+pub fn check_point(
+    env: &Environment,
+    loans_in_scope: &LoansInScope,
+    point: Point,
+) -> Option<BorrowError> {
+    let paths = loans_in_scope.paths();
+    let loans = loans_in_scope.loans();
+    let conflicts = &ConflictMatrix::new(paths, loans);
+    let mut error = None;
+    loans_in_scope.walk_block_bits(point.block, |p, opt_action, in_scope| {
+        if p == point {
+            let borrowck = BorrowCheck { env, paths, point, loans, in_scope, conflicts, trace: None };
+            error = opt_action.and_then(|action| borrowck.check(action).err());
+        }
+    });
+    error
+}
+
+/// The errors that accessing `path` at `point`, in the way given by
+/// `kind`, would be reported with: one for each loan in scope there
+/// that the access conflicts with. Unlike the checks that
+/// `borrow_check` makes, `path` need not be accessed anywhere in the
+/// function, so the overlaps are worked out from the paths themselves
+/// rather than looked up in the `PathTable`.
+pub fn check_access(
+    env: &Environment,
+    loans_in_scope: &LoansInScope,
+    point: Point,
+    path: &repr::Path,
+    kind: AccessKind,
+) -> Vec<ReportedError> {
+    let mut errors = vec![];
+    loans_in_scope.walk_block(point.block, |p, _action, loans| {
+        if p != point {
+            return;
+        }
+        for &loan in loans {
+            let overlap = match overlap_of_paths(env, kind, path, loan.path) {
+                Some(overlap) => overlap,
+                None => continue,
+            };
+            if kind.conflicts(loan.kind, overlap) {
+                let mut error = BorrowError::for_access(kind, path, loan);
+                error.through_raw_pointer =
+                    env.derefs_raw_pointer(path) || env.derefs_raw_pointer(loan.path);
+                error.live_path = region::witness_path(env, loan.region, loan.point, point);
+                errors.push(error.into_reported_error(env, point));
+            }
+        }
+    });
+    errors
+}
+
+/// The accesses that `action` makes, in the order in which
+/// `borrow_check` checks them: its destination is written before its
+/// operands are read, moved or borrowed.
+pub fn accesses(action: &repr::Action) -> Vec<(AccessKind, repr::Path)> {
+    let operand = |kind: repr::BorrowKind, path: &repr::Path| match kind {
+        repr::BorrowKind::Shared => (AccessKind::Read, path.clone()),
+        repr::BorrowKind::Mut => (AccessKind::MutBorrow, path.clone()),
+    };
+    let write = |path: &repr::Path| (AccessKind::Write, path.clone());
+    match action.kind {
+        repr::ActionKind::Init(ref a, ref operands) => {
+            Some(write(a))
+                .into_iter()
+                .chain(operands.iter().map(|o| match *o {
+                    repr::Operand::Path(ref b) => operand(repr::BorrowKind::Shared, b),
+                    repr::Operand::Borrow(_, kind, ref b) => operand(kind, b),
+                }))
+                .collect()
+        }
+        repr::ActionKind::Const(ref a, _) => vec![write(a)],
+        repr::ActionKind::Assign(ref a, ref b) => {
+            vec![write(a), operand(repr::BorrowKind::Shared, b)]
+        }
+        repr::ActionKind::Borrow(ref a, _, kind, ref b) |
+        repr::ActionKind::RawBorrow(ref a, kind, ref b) => vec![write(a), operand(kind, b)],
+        repr::ActionKind::Closure(ref a, ref captures) => {
+            Some(write(a))
+                .into_iter()
+                .chain(captures.iter().map(|capture| match capture.mode {
+                    repr::CaptureMode::ByRef(_, kind) => operand(kind, &capture.path),
+                    repr::CaptureMode::ByMove => (AccessKind::Move, (*capture.path).clone()),
+                }))
+                .collect()
+        }
+        repr::ActionKind::Use(ref p) |
+        repr::ActionKind::Read(ref p, repr::Depth::Deep) => vec![(AccessKind::Read, (**p).clone())],
+        repr::ActionKind::Read(ref p, repr::Depth::Shallow) => {
+            vec![(AccessKind::ShallowRead, (**p).clone())]
+        }
+        repr::ActionKind::Write(ref p, repr::Depth::Shallow) => vec![write(p)],
+        repr::ActionKind::Write(ref p, repr::Depth::Deep) => {
+            vec![(AccessKind::DeepWrite, (**p).clone())]
+        }
+        repr::ActionKind::Drop(ref p) => vec![(AccessKind::Drop, (**p).clone())],
+        repr::ActionKind::StorageDead(v) => vec![(AccessKind::StorageDead, repr::Path::Var(v))],
+        repr::ActionKind::Constraint(_) |
+        repr::ActionKind::Noop => vec![],
+    }
+}
+
+/// As `overlap`, for a path that may not be in the
+/// `PathTable`.
+fn overlap_of_paths(
+    env: &Environment,
+    access: AccessKind,
+    path: &repr::Path,
+    loan_path: &repr::Path,
+) -> Option<Overlap> {
+    let loan_extends_access = if access.is_deep() {
+        env.supporting_prefixes(loan_path).contains(&path)
+    } else {
+        env.frozen_by_borrow_of(loan_path).contains(&path)
+    };
+
+    if loan_path == path {
+        Some(Overlap::SamePath)
+    } else if path.prefixes().contains(&loan_path) {
+        Some(Overlap::AccessExtendsLoan)
+    } else if loan_extends_access {
+        Some(Overlap::LoanExtendsAccess)
+    } else {
+        None
+    }
+}
+
+/// The error, if any, for an action that overwrites or mutably
+/// borrows (memory owned by) an immutable static: statics can only
+/// be changed if they are declared `static mut`.
+fn static_mutation(env: &Environment, action: &repr::Action) -> Option<(&'static str, String)> {
+    let (written, mut_borrowed): (Vec<&repr::Path>, Vec<&repr::Path>) = match action.kind {
+        repr::ActionKind::Borrow(ref a, _, repr::BorrowKind::Mut, ref b) |
+        repr::ActionKind::RawBorrow(ref a, repr::BorrowKind::Mut, ref b) => (vec![a], vec![b]),
+        repr::ActionKind::Closure(ref a, ref captures) => {
+            let mut_captures = captures
+                .iter()
+                .filter_map(|capture| match capture.mode {
+                    repr::CaptureMode::ByRef(_, repr::BorrowKind::Mut) => Some(&*capture.path),
+                    repr::CaptureMode::ByRef(_, repr::BorrowKind::Shared) |
+                    repr::CaptureMode::ByMove => None,
+                })
+                .collect();
+            (vec![a], mut_captures)
+        }
+        repr::ActionKind::Init(ref a, ref operands) => {
+            let mut_borrows = operands
+                .iter()
+                .filter_map(|operand| match *operand {
+                    repr::Operand::Borrow(_, repr::BorrowKind::Mut, ref b) => Some(&**b),
+                    repr::Operand::Borrow(_, repr::BorrowKind::Shared, _) |
+                    repr::Operand::Path(_) => None,
+                })
+                .collect();
+            (vec![a], mut_borrows)
+        }
+        repr::ActionKind::Const(ref a, _) |
+        repr::ActionKind::Assign(ref a, _) |
+        repr::ActionKind::Borrow(ref a, ..) |
+        repr::ActionKind::RawBorrow(ref a, ..) |
+        repr::ActionKind::Write(ref a, _) => (vec![a], vec![]),
+        repr::ActionKind::Use(_) |
+        repr::ActionKind::Read(..) |
+        repr::ActionKind::Drop(_) |
+        repr::ActionKind::StorageDead(_) |
+        repr::ActionKind::Constraint(_) |
+        repr::ActionKind::Noop => (vec![], vec![]),
+    };
+
+    // Only the memory of the static itself is immutable, not what it
+    // refers to.
+    let immutable_static = |path: &&repr::Path| {
+        env.var_kind(path.base()) == repr::VariableKind::Static &&
+            !path.prefixes().iter().any(|prefix| prefix.is_deref())
+    };
+    if let Some(path) = written.into_iter().find(&immutable_static) {
+        return Some(("E0594", format!("cannot assign to `{}`, as `{}` is an immutable static",
+                                      path, path.base())));
+    }
+    if let Some(path) = mut_borrowed.into_iter().find(&immutable_static) {
+        return Some(("E0596", format!("cannot borrow `{}` as mutable, as `{}` is an \
+                                       immutable static",
+                                      path, path.base())));
+    }
+    None
+}
+
 struct BorrowCheck<'cx> {
     env: &'cx Environment<'cx>,
+    paths: &'cx PathTable,
     point: Point,
-    loans: &'cx [&'cx Loan<'cx>],
-}
 
-enum Depth {
-    Shallow,
-    Deep,
+    /// All the loans, and which of them are in scope at `point`.
+    loans: &'cx [Loan<'cx>],
+    in_scope: BitSlice<'cx>,
+
+    conflicts: &'cx ConflictMatrix,
+
+    /// The path whose checks are printed, if any.
+    trace: Option<PathIndex>,
 }
 
-enum Mode {
-    Read,
-    Write,
+/// For each path accessed in the function, and each kind of access,
+/// the loans that such an access conflicts with, wherever they are
+/// in scope. Checking an access is then a matter of intersecting its
+/// row with the loans in scope, rather than working out the overlap
+/// of the path with each loan at each point.
+struct ConflictMatrix {
+    bits: BitMatrix,
 }
 
-impl<'cx> BorrowCheck<'cx> {
-    fn check_action(&self, action: &repr::Action) -> Result<(), Box<Error>> {
-        log!("check_action({:?}) at {:?}", action, self.point);
-        match action.kind {
-            repr::ActionKind::Init(ref a, ref bs) => {
-                self.check_shallow_write(a)?;
-                for b in bs {
-                    self.check_read(b)?;
+impl ConflictMatrix {
+    fn new(paths: &PathTable, loans: &[Loan]) -> Self {
+        let mut bits = BitMatrix::new(paths.len() * AccessKind::ALL.len(), loans.len());
+        for path_index in paths.indices() {
+            for &access in AccessKind::ALL {
+                let row = ConflictMatrix::row_index(access, path_index);
+                for (loan_index, loan) in loans.iter().enumerate() {
+                    let overlap = match overlap(paths, access, path_index, loan) {
+                        Some(overlap) => overlap,
+                        None => continue,
+                    };
+                    if access.conflicts(loan.kind, overlap) {
+                        bits.set(row, loan_index);
+                    }
                 }
             }
-            repr::ActionKind::Assign(ref a, ref b) => {
-                self.check_shallow_write(a)?;
-                self.check_read(b)?;
-            }
-            repr::ActionKind::Borrow(ref a, _, repr::BorrowKind::Shared, ref b) => {
-                self.check_shallow_write(a)?;
-                self.check_read(b)?;
-            }
-            repr::ActionKind::Borrow(ref a, _, repr::BorrowKind::Mut, ref b) => {
-                self.check_shallow_write(a)?;
-                self.check_mut_borrow(b)?;
-            }
-            repr::ActionKind::Constraint(_) => {}
-            repr::ActionKind::Use(ref p) => {
-                self.check_read(p)?;
-            }
-            repr::ActionKind::Drop(ref p) => {
-                self.check_move(p)?;
-            }
-            repr::ActionKind::StorageDead(p) => {
-                self.check_storage_dead(p)?;
-            }
-            repr::ActionKind::SkolemizedEnd(_) |
-            repr::ActionKind::Noop => {}
         }
+        ConflictMatrix { bits }
+    }
 
-        Ok(())
+    fn row_index(access: AccessKind, path_index: PathIndex) -> usize {
+        path_index.as_usize() * AccessKind::ALL.len() + access as usize
     }
 
-    /// `use(x)` may access `x` and (by going through the produced
-    /// value) anything reachable from `x`.
-    fn check_read(&self, path: &repr::Path) -> Result<(), Box<Error>> {
-        self.check_borrows(Depth::Deep, Mode::Read, path)
+    /// The loans that `access` of the path at `path_index` conflicts
+    /// with.
+    fn conflicts(&self, access: AccessKind, path_index: PathIndex) -> BitSlice {
+        self.bits.row(ConflictMatrix::row_index(access, path_index))
     }
+}
+
+/// The ways in which an action accesses a path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AccessKind {
+    /// `use(x)` (or a shared borrow of `x`) may read `x` and, by going
+    /// through the produced value, anything reachable from `x`.
+    Read,
+
+    /// `read(shallow x)` reads the memory of `x` itself, but nothing
+    /// reachable through a reference in it.
+    ShallowRead,
+
+    /// `x = ...` overwrites `x` without reading it.
+    Write,
+
+    /// `write(deep x)` overwrites `x` and everything reachable from
+    /// it.
+    DeepWrite,
+
+    /// `&mut x` may read and mutate `x`, and anything reachable from
+    /// it.
+    MutBorrow,
+
+    /// Moving `x` makes everything reachable from it available
+    /// elsewhere. This is stricter than both writes and `StorageDead`.
+    /// In particular, you **can** write to a variable `x` that contains
+    /// an `&mut` value when `*x` is borrowed, but you **cannot** move
+    /// `x`: moving it would make the `&mut` available in the new
+    /// location, but writing (and storage-dead) both kill it forever.
+    Move,
+
+    /// `drop(x)` may run a destructor, which can reach anything
+    /// reachable from `x`; it is checked like a move.
+    Drop,
+
+    /// `StorageDead(x)` frees the memory of `x`. Having something
+    /// like `*x` borrowed is ok, since freeing `x` does not affect the
+    /// memory `*x` refers to.
+    StorageDead,
+}
+
+/// How the path of a loan overlaps the path that an action accesses.
+/// Which overlaps are considered at all depends on the access (see
+/// `overlap`); whether an overlap is a conflict depends on
+/// the access and the kind of the loan (see `AccessKind::conflicts`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Overlap {
+    /// The loan is for the accessed path itself: `&a.b`, then `a.b`.
+    SamePath,
+
+    /// The accessed path is a proper prefix of the loan's path, and
+    /// accessing it would reach the borrowed data: `&a.b.c`, then
+    /// `a.b`. For a deep access, this means the accessed path is a
+    /// supporting prefix of the loan's (so `&(*r).f`, then `r`, does
+    /// not overlap when `r` is a shared reference); for a shallow
+    /// access, that the loan freezes the accessed path (so
+    /// dereferences of any kind are not followed).
+    LoanExtendsAccess,
+
+    /// The loan's path is a proper prefix of the accessed path:
+    /// `&a.b`, then `a.b.c`. The access reaches the data through the
+    /// borrowed path, whatever the types along the way.
+    AccessExtendsLoan,
+}
 
-    /// `x = ...` overwrites `x` (without reading it) and prevents any
-    /// further reads from that path.
-    fn check_shallow_write(&self, path: &repr::Path) -> Result<(), Box<Error>> {
-        self.check_borrows(Depth::Shallow, Mode::Write, path)
+impl Overlap {
+    /// The name of the rule by which the paths overlap, as printed by
+    /// `--trace-path`.
+    fn rule(self, access: AccessKind) -> &'static str {
+        match self {
+            Overlap::SamePath | Overlap::AccessExtendsLoan => "intersect",
+            Overlap::LoanExtendsAccess if access.is_deep() => "supporting-prefix",
+            Overlap::LoanExtendsAccess => "freeze",
+        }
     }
+}
 
-    /// `&mut x` may mutate `x`, but it can also *read* from `x`, and
-    /// mutate things reachable from `x`.
-    fn check_mut_borrow(&self, path: &repr::Path) -> Result<(), Box<Error>> {
-        self.check_borrows(Depth::Deep, Mode::Write, path)
+impl AccessKind {
+    /// Every kind of access, in declaration order.
+    const ALL: &'static [AccessKind] = &[
+        AccessKind::Read,
+        AccessKind::ShallowRead,
+        AccessKind::Write,
+        AccessKind::DeepWrite,
+        AccessKind::MutBorrow,
+        AccessKind::Move,
+        AccessKind::Drop,
+        AccessKind::StorageDead,
+    ];
+
+    /// Deep accesses reach everything that is reachable from the
+    /// accessed path; shallow ones affect only the path's own memory.
+    fn is_deep(self) -> bool {
+        match self {
+            AccessKind::Read |
+            AccessKind::DeepWrite |
+            AccessKind::MutBorrow |
+            AccessKind::Move |
+            AccessKind::Drop => true,
+            AccessKind::ShallowRead | AccessKind::Write | AccessKind::StorageDead => false,
+        }
     }
 
-    fn check_borrows(&self,
-                     depth: Depth,
-                     access_mode: Mode,
-                     path: &repr::Path)
-                     -> Result<(), Box<Error>> {
-        let loans: Vec<_> = match depth {
-            Depth::Shallow => self.find_loans_that_freeze(path).collect(),
-            Depth::Deep => self.find_loans_that_intersect(path).collect(),
-        };
+    /// True if this access, overlapping a loan of kind `loan_kind`
+    /// as described by `overlap`, is an error.
+    fn conflicts(self, loan_kind: repr::BorrowKind, overlap: Overlap) -> bool {
+        match (self, loan_kind, overlap) {
+            // Reading is compatible with shared loans, however the
+            // paths overlap: a shared reborrow of data that is only
+            // shared-borrowed, however deep in the prefix chain the
+            // original borrow is, can be read freely.
+            (AccessKind::Read, repr::BorrowKind::Shared, _) |
+            (AccessKind::ShallowRead, repr::BorrowKind::Shared, _) => false,
 
-        for loan in loans {
-            match access_mode {
-                Mode::Read => match loan.kind {
-                    repr::BorrowKind::Shared => { /* Ok */ }
-                    repr::BorrowKind::Mut => {
-                        return Err(Box::new(BorrowError::for_read(
-                            self.point,
-                            path,
-                            &loan.path,
-                            loan.point,
-                        )));
-                    }
-                },
-
-                Mode::Write => {
-                    return Err(Box::new(BorrowError::for_write(
-                        self.point,
-                        path,
-                        &loan.path,
-                        loan.point,
-                    )));
-                },
-            }
+            // Reading data that someone holds a `&mut` to is not.
+            (AccessKind::Read, repr::BorrowKind::Mut, Overlap::SamePath) |
+            (AccessKind::Read, repr::BorrowKind::Mut, Overlap::LoanExtendsAccess) |
+            (AccessKind::Read, repr::BorrowKind::Mut, Overlap::AccessExtendsLoan) |
+            (AccessKind::ShallowRead, repr::BorrowKind::Mut, _) => true,
+
+            // A `&mut` borrow conflicts with loans of either kind. Two
+            // mutable borrows are reported with a different error
+            // code than a mutable borrow of shared data, but either
+            // way, every overlap counts.
+            (AccessKind::MutBorrow, _, _) => true,
+
+            // Moving out of borrowed data, overwriting a frozen path,
+            // and freeing frozen memory are errors, whatever the kind
+            // of the loan.
+            (AccessKind::Move, _, _) |
+            (AccessKind::Drop, _, _) |
+            (AccessKind::Write, _, _) |
+            (AccessKind::DeepWrite, _, _) |
+            (AccessKind::StorageDead, _, _) => true,
         }
+    }
+}
 
-        Ok(())
+impl<'cx> BorrowCheck<'cx> {
+    /// Like `check_action`, but also finds a path along which the
+    /// conflicting loan stays in scope from the borrow to here.
+    fn check(&self, action: &repr::Action) -> Result<(), BorrowError> {
+        self.check_action(action).map_err(|mut error| {
+            let loan = self.loans
+                .iter()
+                .enumerate()
+                .find(|&(loan_index, loan)| {
+                    self.in_scope.get(loan_index) && loan.point == error.loan_point &&
+                        loan.region_name == error.loan_region
+                })
+                .map(|(_, loan)| loan)
+                .expect("conflicting loan not in scope");
+            error.live_path = region::witness_path(self.env, loan.region, loan.point, self.point);
+            error
+        })
     }
 
-    /// Cannot move from a path `p` if:
-    /// - `p` is borrowed;
-    /// - some subpath `p.foo` is borrowed;
-    /// - some prefix of `p` is borrowed.
-    ///
-    /// Note that this is stricter than both *writes* and
-    /// *storage-dead*. In particular, you **can** write to a variable
-    /// `x` that contains an `&mut` value when `*x` is borrowed, but
-    /// you **cannot** move `x`. This is because moving it would make
-    /// the `&mut` available in the new location, but writing (and
-    /// storage-dead) both kill it forever.
-    fn check_move(&self, path: &repr::Path) -> Result<(), Box<Error>> {
-        log!(
-            "check_move of {:?} at {:?} with loans={:#?}",
-            path,
-            self.point,
-            self.loans
-        );
-        for loan in self.find_loans_that_intersect(path) {
-            return Err(Box::new(BorrowError::for_move(
-                self.point,
-                path,
-                &loan.path,
-                loan.point,
-            )));
+    fn check_action(&self, action: &repr::Action) -> Result<(), BorrowError> {
+        log!("check_action({:?}) at {:?}", action, self.point);
+        for (access, path) in accesses(action) {
+            self.check_access(access, &path)?;
         }
         Ok(())
     }
 
-    /// Cannot free a local variable `var` if:
-    /// - data interior to `var` is borrowed.
-    ///
-    /// In particular, having something like `*var` borrowed is ok.
-    fn check_storage_dead(&self, var: repr::Variable) -> Result<(), Box<Error>> {
+    /// Reports the first loan in scope that conflicts with `access`
+    /// of `path`.
+    fn check_access(&self, access: AccessKind, path: &repr::Path) -> Result<(), BorrowError> {
+        let path_index = self.paths.index(path);
+        if let Some(traced) = self.trace {
+            self.trace_access(traced, access, path, path_index);
+        }
+        let conflicts = self.conflicts.conflicts(access, path_index);
+        let loan = match conflicts.first_common(self.in_scope) {
+            Some(loan_index) => &self.loans[loan_index],
+            None => return Ok(()),
+        };
         log!(
-            "check_storage_dead of {:?} at {:?} with loans={:#?}",
-            var,
-            self.point,
-            self.loans
+            "check_access: {:?} of {} conflicts with loan of {}",
+            access,
+            path,
+            loan.path
         );
-        for loan in self.find_loans_that_freeze(&repr::Path::Var(var)) {
-            return Err(Box::new(BorrowError::for_storage_dead(
-                self.point,
-                var,
-                &loan.path,
-                loan.point,
-            )));
-        }
-        Ok(())
+        let mut error = BorrowError::for_access(access, path, loan);
+        error.through_raw_pointer =
+            self.env.derefs_raw_pointer(path) || self.env.derefs_raw_pointer(loan.path);
+        Err(error)
     }
 
-    /// A loan L *intersects* a path P if either:
-    ///
-    /// - the loan is for the path P; or,
-    /// - the path P can be extended to reach the data in the loan; or,
-    /// - the loan path can be extended to reach the data in P.
-    ///
-    /// So, for example, is the path P is `a.b.c`, then:
-    ///
-    /// - a loan of `a.b.c` intersects P;
-    /// - a loan of `a.b.c.d` intersects P, because (e.g.) after reading P
-    ///   you have also read `a.b.c.d`;
-    /// - a loan of `a.b` intersects P, because you can use the
-    ///   reference to access the data at P.
-    fn find_loans_that_intersect<'a>(
-        &'a self,
-        path: &'a repr::Path,
-    ) -> impl Iterator<Item = &'a Loan> + 'a {
-        let path_prefixes = path.prefixes();
-        self.loans.iter().cloned().filter(move |loan| {
-            // accessing `a.b.c` intersects a loan of `a.b.c` or `a.b`...
-            path_prefixes.contains(&loan.path) ||
-
-            // ...as well as a loan of `a.b.c.d`
-                self.env.supporting_prefixes(&loan.path).contains(&path)
-        })
-    }
+    /// Prints how `access` of `path` is checked against each loan in
+    /// scope, if the access or the loan touches the path at `traced`
+    /// -- that is, if one of the paths is a prefix of the other.
+    fn trace_access(
+        &self,
+        traced: PathIndex,
+        access: AccessKind,
+        path: &repr::Path,
+        path_index: PathIndex,
+    ) {
+        let touches = |index| {
+            self.paths.is_prefix_of(traced, index) || self.paths.is_prefix_of(index, traced)
+        };
+        let access_touches = touches(path_index);
+        let mut decisions = vec![];
+        for (loan_index, loan) in self.loans.iter().enumerate() {
+            if !self.in_scope.get(loan_index) || !(access_touches || touches(loan.path_index)) {
+                continue;
+            }
+            let decision = match overlap(self.paths, access, path_index, loan) {
+                Some(overlap) if access.conflicts(loan.kind, overlap) => {
+                    format!("{} matched: conflict", overlap.rule(access))
+                }
+                Some(overlap) => {
+                    format!("{} matched: no conflict with a shared loan", overlap.rule(access))
+                }
+                None if access.is_deep() => {
+                    "neither intersect nor supporting-prefix matched: no conflict".to_string()
+                }
+                None => "neither intersect nor freeze matched: no conflict".to_string(),
+            };
+            let kind = match loan.kind {
+                repr::BorrowKind::Shared => "shared",
+                repr::BorrowKind::Mut => "mutable",
+            };
+            decisions.push(format!("  {} loan of `{}` issued at {}: {}",
+                                   kind, loan.path, self.env.describe_point(loan.point), decision));
+        }
+        if !access_touches && decisions.is_empty() {
+            return;
+        }
 
-    /// Helper for `check_write` and `check_storage_dead`: finds if
-    /// there is a loan that "freezes" the given path -- that is, a
-    /// loan that would make modifying the `path` (or freeing it)
-    /// illegal. This is slightly more permissive than the rules
-    /// around move and reads, precisely because overwriting or
-    /// freeing `path` makes the previous value unavailable from that
-    /// point on.
-    fn find_loans_that_freeze<'a>(
-        &'a self,
-        path: &repr::Path)
-        -> impl Iterator<Item = &'a Loan> + 'a
-    {
-        let path: repr::Path = path.clone();
-        self.loans.iter().cloned().filter(move |loan| {
-            let prefixes = path.prefixes();
-
-            // If you have borrowed `a.b`, this prevents writes to `a`
-            // or `a.b`:
-            let frozen_paths = self.frozen_by_borrow_of(&loan.path);
-            frozen_paths.contains(&&path) ||
-
-                // If you have borrowed `a.b`, this prevents writes to
-                // `a.b.c`:
-                prefixes.contains(&loan.path)
-        })
+        println!("trace `{}`: {:?} of `{}` at {}",
+                 self.paths.path(traced), access, path, self.env.describe_point(self.point));
+        if decisions.is_empty() {
+            println!("  no loans in scope");
+        }
+        for decision in decisions {
+            println!("{}", decision);
+        }
     }
+}
 
-    /// If `path` is mutably borrowed, returns a vector of paths which -- if
-    /// moved or if the storage went away -- would invalidate this
-    /// reference.
-    fn frozen_by_borrow_of<'a>(&self, mut path: &'a repr::Path) -> Vec<&'a repr::Path> {
-        let mut result = vec![];
-        loop {
-            result.push(path);
-            match *path {
-                repr::Path::Var(_) => return result,
-                repr::Path::Extension(ref base_path, field_name) => {
-                    match *self.env.path_ty(base_path) {
-                        // If you borrowed `*r`, writing to `r` does
-                        // not actually affect the memory at `*r`, so
-                        // we can stop iterating backwards now.
-                        repr::Ty::Ref(_, _, _) => {
-                            assert_eq!(field_name, repr::FieldName::star());
-                            return result;
-                        }
-
-                        // If you have borrowed `a.b`, then writing to
-                        // `a` would overwrite `a.b`, which is
-                        // disallowed.
-                        repr::Ty::Struct(..) => {
-                            path = base_path;
-                        }
-
-                        repr::Ty::Unit => panic!("unit has no fields"),
-                        repr::Ty::Bound(..) => panic!("unexpected bound type"),
-                    }
-                }
-            }
-        }
+/// How `loan` overlaps the path at `path_index`, if at all, as far as
+/// `access` is concerned.
+fn overlap(
+    paths: &PathTable,
+    access: AccessKind,
+    path_index: PathIndex,
+    loan: &Loan,
+) -> Option<Overlap> {
+    let loan_extends_access = if access.is_deep() {
+        paths.is_supporting_prefix_of(path_index, loan.path_index)
+    } else {
+        paths.is_frozen_by_borrow_of(path_index, loan.path_index)
+    };
+
+    if loan.path_index == path_index {
+        Some(Overlap::SamePath)
+    } else if paths.is_prefix_of(loan.path_index, path_index) {
+        Some(Overlap::AccessExtendsLoan)
+    } else if loan_extends_access {
+        Some(Overlap::LoanExtendsAccess)
+    } else {
+        None
     }
 }
 
 #[derive(Debug)]
 pub struct BorrowError {
-    description: String,
+    pub kind: BorrowErrorKind,
+    pub message: String,
+    pub loan_path: String,
+    pub loan_site: usize,
+    pub loan_point: Point,
+    pub loan_region: repr::RegionName,
+
+    /// A path from the borrow to the point of the error, along which
+    /// the loan is in scope; see `region::witness_path()`.
+    pub live_path: Option<Vec<Point>>,
+
+    /// True if the access or the loan goes through the dereference of
+    /// a raw pointer, in which case an unsafe block only warns about
+    /// the conflict.
+    pub through_raw_pointer: bool,
 }
 
-impl BorrowError {
-    fn for_move(
-        point: Point,
-        path: &repr::Path,
-        loan_path: &repr::Path,
-        loan_point: Point,
-    ) -> Self {
-        BorrowError {
-            description: format!(
-                "point {:?} cannot move `{}` because `{}` is borrowed (at point `{:?}`)",
-                point,
-                path,
-                loan_path,
-                loan_point
-            ),
+/// The different sorts of conflicts that the borrow checker can
+/// report. Each has its own error code, which is included in the
+/// message, so that a `//!` annotation in a test can name the code to
+/// distinguish (say) a `&mut` conflicting with another `&mut` from a
+/// `&mut` conflicting with a shared borrow.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BorrowErrorKind {
+    /// `&mut p` while some intersecting path is mutably borrowed.
+    MutBorrowWhileMutBorrowed,
+
+    /// `&mut p` while some intersecting path is shared borrowed.
+    MutBorrowWhileSharedBorrowed,
+
+    /// Reading `p` while some intersecting path is mutably borrowed.
+    UseWhileMutBorrowed,
+
+    /// Overwriting `p` while it is frozen by a borrow.
+    WriteWhileBorrowed,
+
+    /// Moving `p` while some intersecting path is borrowed.
+    MoveWhileBorrowed,
+
+    /// `StorageDead(v)` while data owned by `v` is borrowed.
+    StorageDeadWhileBorrowed,
+}
+
+impl BorrowErrorKind {
+    pub fn code(self) -> &'static str {
+        match self {
+            BorrowErrorKind::MutBorrowWhileMutBorrowed => "E0499",
+            BorrowErrorKind::MutBorrowWhileSharedBorrowed => "E0502",
+            BorrowErrorKind::UseWhileMutBorrowed => "E0503",
+            BorrowErrorKind::MoveWhileBorrowed => "E0505",
+            BorrowErrorKind::WriteWhileBorrowed => "E0506",
+            BorrowErrorKind::StorageDeadWhileBorrowed => "E0597",
         }
     }
 
-    fn for_read(
-        point: Point,
-        path: &repr::Path,
-        loan_path: &repr::Path,
-        loan_point: Point,
-    ) -> Self {
-        BorrowError {
-            description: format!(
-                "point {:?} cannot read `{}` because `{}` is mutably borrowed (at point `{:?}`)",
-                point,
-                path,
-                loan_path,
-                loan_point
-            ),
+    /// The access rule that was broken, as explained by `--why-error`.
+    pub fn rule(self) -> &'static str {
+        match self {
+            BorrowErrorKind::MutBorrowWhileMutBorrowed |
+            BorrowErrorKind::MutBorrowWhileSharedBorrowed => {
+                "a path may not be mutably borrowed while it, a prefix of it, or a path \
+                 reachable from it is borrowed"
+            }
+            BorrowErrorKind::UseWhileMutBorrowed => {
+                "a path may not be read while it, a prefix of it, or a path reachable \
+                 from it is mutably borrowed"
+            }
+            BorrowErrorKind::WriteWhileBorrowed => {
+                "a path may not be overwritten while it, a prefix of it, or a path \
+                 whose value it owns is borrowed"
+            }
+            BorrowErrorKind::MoveWhileBorrowed => {
+                "a path may not be moved while it, a prefix of it, or a path reachable \
+                 from it is borrowed"
+            }
+            BorrowErrorKind::StorageDeadWhileBorrowed => {
+                "a variable may not go out of scope while data it owns is borrowed"
+            }
         }
     }
+}
 
-    fn for_write(
-        point: Point,
-        path: &repr::Path,
-        loan_path: &repr::Path,
-        loan_point: Point,
-    ) -> Self {
+impl BorrowError {
+    fn new(kind: BorrowErrorKind, message: String, loan: &Loan) -> Self {
         BorrowError {
-            description: format!(
-                "point {:?} cannot write `{}` because `{}` is borrowed (at point `{:?}`)",
-                point,
-                path,
-                loan_path,
-                loan_point
-            ),
+            kind,
+            message,
+            loan_path: loan.path.to_string(),
+            loan_site: loan.site,
+            loan_point: loan.point,
+            loan_region: loan.region_name,
+            live_path: None,
+            through_raw_pointer: false,
         }
     }
 
-    fn for_storage_dead(
-        point: Point,
-        var: repr::Variable,
-        loan_path: &repr::Path,
-        loan_point: Point,
-    ) -> Self {
-        BorrowError {
-            description: format!(
-                "point {:?} cannot kill storage for `{}` \
-                 because `{}` is borrowed (at point `{:?}`)",
-                point,
-                var,
-                loan_path,
-                loan_point
-            ),
+    fn into_reported_error(self, env: &Environment, point: Point) -> ReportedError {
+        let mut notes = vec![
+            Note {
+                point: Some(self.loan_point),
+                message: format!("borrow of `{}` occurs here", self.loan_path),
+            },
+        ];
+        if let Some(ref path) = self.live_path {
+            notes.push(Note {
+                point: None,
+                message: format!("the borrow is still live along this path: {}",
+                                 env.describe_path(path)),
+            });
+        }
+        ReportedError {
+            point,
+            code: Some(self.kind.code()),
+            message: self.message,
+            notes,
+            labels: HashMap::new(),
+            facts: vec![Fact::Loan(self.loan_site)],
         }
     }
-}
 
-impl Error for BorrowError {
-    fn description(&self) -> &str {
-        &self.description
+    fn for_access(access: AccessKind, path: &repr::Path, loan: &Loan) -> Self {
+        match access {
+            AccessKind::Read | AccessKind::ShallowRead => BorrowError::for_read(path, loan),
+            AccessKind::Write | AccessKind::DeepWrite => BorrowError::for_write(path, loan),
+            AccessKind::MutBorrow => BorrowError::for_mut_borrow(path, loan),
+            AccessKind::Move | AccessKind::Drop => BorrowError::for_move(path, loan),
+            AccessKind::StorageDead => BorrowError::for_storage_dead(path, loan),
+        }
     }
 
-    fn cause(&self) -> Option<&Error> {
-        None
+    fn for_move(path: &repr::Path, loan: &Loan) -> Self {
+        BorrowError::new(
+            BorrowErrorKind::MoveWhileBorrowed,
+            format!("cannot move `{}` because `{}` is borrowed", path, loan.path),
+            loan,
+        )
+    }
+
+    fn for_read(path: &repr::Path, loan: &Loan) -> Self {
+        BorrowError::new(
+            BorrowErrorKind::UseWhileMutBorrowed,
+            format!("cannot read `{}` because `{}` is mutably borrowed", path, loan.path),
+            loan,
+        )
+    }
+
+    fn for_write(path: &repr::Path, loan: &Loan) -> Self {
+        BorrowError::new(
+            BorrowErrorKind::WriteWhileBorrowed,
+            format!("cannot write `{}` because `{}` is borrowed", path, loan.path),
+            loan,
+        )
+    }
+
+    fn for_mut_borrow(path: &repr::Path, loan: &Loan) -> Self {
+        let (kind, loan_kind_str) = match loan.kind {
+            repr::BorrowKind::Mut => (BorrowErrorKind::MutBorrowWhileMutBorrowed, "mutable"),
+            repr::BorrowKind::Shared => (BorrowErrorKind::MutBorrowWhileSharedBorrowed, "shared"),
+        };
+        BorrowError::new(
+            kind,
+            format!(
+                "cannot borrow `{}` as mutable because `{}` is borrowed as {}",
+                path,
+                loan.path,
+                loan_kind_str
+            ),
+            loan,
+        )
     }
-}
 
-impl fmt::Display for BorrowError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{}", self.description)
+    fn for_storage_dead(path: &repr::Path, loan: &Loan) -> Self {
+        BorrowError::new(
+            BorrowErrorKind::StorageDeadWhileBorrowed,
+            format!(
+                "cannot kill storage for `{}` because `{}` is borrowed",
+                path,
+                loan.path
+            ),
+            loan,
+        )
     }
 }
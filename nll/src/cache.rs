@@ -0,0 +1,141 @@
+//! An on-disk cache of analysis results (`--cache-dir`), so that
+//! re-running over an unchanged corpus skips the analysis of the
+//! files that have not changed.
+//!
+//! Entries are keyed by a hash of the parsed `Func` and the options,
+//! and record only the outcome of checking the file: the lines the
+//! check printed (its warnings, and the assertions that failed), and
+//! whether it passed, or else the error it failed with. The results
+//! of the analysis -- the region values, the loans in scope, the
+//! errors found -- are not stored, so the runs that print any of them
+//! (see `prints_analysis_output` in `main.rs`) do not use the cache.
+//! The hash is computed from the `Debug` output of the `Func` rather
+//! than its `Hash` impl, since the latter depends on the order in
+//! which names happen to be interned. (The anonymous regions (`'_`)
+//! of a file are numbered from the start of the file, so a
+//! file's key does not depend on the files processed before it.)
+
+use nll_repr::repr::Func;
+use options::Options;
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+/// Bumped whenever the format of the entries, or the meaning of the
+/// results, changes.
+const CACHE_VERSION: u64 = 2;
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+/// What a cached run of the analysis produced.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Outcome {
+    /// The lines that the check printed, in order (see
+    /// `regionck::region_check`).
+    pub output: Vec<String>,
+
+    /// The error message that was reported, if the check failed.
+    pub error: Option<String>,
+}
+
+impl Cache {
+    pub fn new(dir: &str) -> Result<Self, String> {
+        let dir = PathBuf::from(dir);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("cannot create cache directory `{}`: {}", dir.display(), e))?;
+        Ok(Cache { dir })
+    }
+
+    /// The key under which the results for `func`, analyzed with
+    /// `options`, are stored.
+    pub fn key(func: &Func, options: &Options) -> String {
+        let mut hasher = StableHasher::new();
+        hasher.write(format!("{} {}", CACHE_VERSION, env!("CARGO_PKG_VERSION")).as_bytes());
+        hasher.write(format!("{:?}", options).as_bytes());
+        hasher.write(format!("{:?}", func).as_bytes());
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// The outcome stored under `key`, if any. An entry that cannot be
+    /// read is treated as missing.
+    pub fn lookup(&self, key: &str) -> Option<Outcome> {
+        let mut text = String::new();
+        File::open(self.path(key)).ok()?.read_to_string(&mut text).ok()?;
+        let json = Json::from_str(&text).ok()?;
+        if json.find("key").and_then(|k| k.as_string()) != Some(key) {
+            return None;
+        }
+        let output = json.find("output")?
+            .as_array()?
+            .iter()
+            .map(|line| line.as_string().map(|line| line.to_string()))
+            .collect::<Option<_>>()?;
+        let error = match *json.find("error")? {
+            Json::Null => None,
+            Json::String(ref message) => Some(message.clone()),
+            _ => return None,
+        };
+        Some(Outcome { output, error })
+    }
+
+    /// Stores `outcome` under `key`. The entry is written to a
+    /// temporary file first, so that a concurrent run never reads a
+    /// partial entry.
+    pub fn store(&self, key: &str, outcome: &Outcome) -> Result<(), String> {
+        let mut entry = BTreeMap::new();
+        entry.insert("key".to_string(), Json::String(key.to_string()));
+        entry.insert("output".to_string(),
+                     Json::Array(outcome.output.iter().cloned().map(Json::String).collect()));
+        entry.insert("error".to_string(), match outcome.error {
+            None => Json::Null,
+            Some(ref message) => Json::String(message.clone()),
+        });
+
+        let path = self.path(key);
+        let tmp_path = path.with_extension("tmp");
+        File::create(&tmp_path)
+            .and_then(|mut file| write!(file, "{}", Json::Object(entry)))
+            .and_then(|()| fs::rename(&tmp_path, &path))
+            .map_err(|e| format!("cannot write cache entry `{}`: {}", path.display(), e))
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// 64-bit FNV-1a, which (unlike `DefaultHasher`) is guaranteed to give
+/// the same result in every build.
+pub struct StableHasher {
+    hash: u64,
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        StableHasher { hash: 0xcbf2_9ce4_8422_2325 }
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= u64::from(byte);
+            self.hash = self.hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
@@ -0,0 +1,154 @@
+//! A cheap estimate of how much work the analysis of a function will
+//! take, computed before running it, so that a batch run over many
+//! functions can report (and, with limits, skip) the ones that are too
+//! big to analyze in reasonable time.
+//!
+//! The region solver dominates: each pass over the constraints may
+//! copy up to every point into a region, so its work grows with the
+//! number of constraints times the number of points.
+
+use env::Environment;
+use graph::BasicBlockIndex;
+use graph_algorithms::Graph;
+use nll_repr::repr;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Complexity {
+    pub blocks: usize,
+    pub points: usize,
+    pub variables: usize,
+    pub loans: usize,
+
+    /// The number of outlives constraints that region inference is
+    /// expected to generate: one for each region related by an
+    /// action (ignoring that an invariant position generates two),
+    /// plus one for each reference that a borrow goes through.
+    pub constraints: usize,
+}
+
+impl Complexity {
+    pub fn estimate(env: &Environment) -> Self {
+        let mut complexity = Complexity {
+            blocks: env.graph.num_nodes(),
+            variables: env.graph.decls().len(),
+            ..Complexity::default()
+        };
+        for index in 0..complexity.blocks {
+            let block_data = env.graph.block_data(BasicBlockIndex::from(index));
+            complexity.points += block_data.actions.len() + 1;
+            for action in &block_data.actions {
+                complexity.constraints += action_constraints(env, &action.kind);
+                complexity.loans += match action.kind {
+                    repr::ActionKind::Borrow(..) => 1,
+                    repr::ActionKind::Closure(_, ref captures) => {
+                        captures.iter().filter(|c| c.mode != repr::CaptureMode::ByMove).count()
+                    }
+                    repr::ActionKind::Init(_, ref operands) => {
+                        operands.iter().filter(|o| o.is_borrow()).count()
+                    }
+                    _ => 0,
+                };
+            }
+        }
+        complexity
+    }
+
+    /// An estimate of the work the region solver does in one pass
+    /// over the constraints.
+    pub fn cost(&self) -> usize {
+        self.constraints.saturating_mul(self.points)
+    }
+}
+
+/// The estimated number of constraints generated by an action. The
+/// actions that store into `p` relate the regions in the type of `p`,
+/// and borrows make the regions of the references they go through
+/// outlive the loan. A path whose type is unknown is counted as having
+/// no regions, and left for the analysis to report.
+fn action_constraints(env: &Environment, kind: &repr::ActionKind) -> usize {
+    let dest_regions = |path: &repr::Path| match env.try_path_ty(path) {
+        Ok(ty) => ty.walk_regions().count(),
+        Err(_) => 0,
+    };
+    let derefs = |path: &repr::Path| match env.try_path_ty(path) {
+        Ok(_) => env.supporting_prefixes(path).into_iter().filter(|p| p.is_deref()).count(),
+        Err(_) => 0,
+    };
+    match *kind {
+        repr::ActionKind::Borrow(ref p, _, _, ref q) => dest_regions(p) + derefs(q),
+        repr::ActionKind::RawBorrow(ref p, ..) |
+        repr::ActionKind::Assign(ref p, _) => dest_regions(p),
+        repr::ActionKind::Init(ref p, ref operands) => {
+            let borrows = operands.iter().map(|operand| match *operand {
+                repr::Operand::Borrow(_, _, ref q) => derefs(q),
+                repr::Operand::Path(_) => 0,
+            });
+            dest_regions(p) * operands.len() + borrows.sum::<usize>()
+        }
+        repr::ActionKind::Closure(ref p, ref captures) => dest_regions(p) * captures.len(),
+        repr::ActionKind::Constraint(ref c) => outlives_constraints(c),
+        repr::ActionKind::Const(..) |
+        repr::ActionKind::Use(..) |
+        repr::ActionKind::Read(..) |
+        repr::ActionKind::Write(..) |
+        repr::ActionKind::Drop(..) |
+        repr::ActionKind::StorageDead(..) |
+        repr::ActionKind::Noop => 0,
+    }
+}
+
+fn outlives_constraints(c: &repr::Constraint) -> usize {
+    match *c {
+        repr::Constraint::ForAll(_, ref c) |
+        repr::Constraint::Exists(_, ref c) |
+        repr::Constraint::Implies(_, ref c) => outlives_constraints(c),
+        repr::Constraint::All(ref cs) => cs.iter().map(outlives_constraints).sum(),
+        repr::Constraint::Outlives(_) => 1,
+    }
+}
+
+impl fmt::Display for Complexity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} blocks, {} points, {} variables, {} loans, ~{} constraints (cost {})",
+            self.blocks,
+            self.points,
+            self.variables,
+            self.loans,
+            self.constraints,
+            self.cost()
+        )
+    }
+}
+
+/// Upper bounds on the complexity of the functions to analyze, set
+/// with `--max-blocks` and `--max-constraints`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Limits {
+    pub max_blocks: Option<usize>,
+    pub max_constraints: Option<usize>,
+}
+
+impl Limits {
+    /// Checks `complexity` against the limits, returning why the
+    /// function should be skipped if it exceeds any of them.
+    pub fn check(&self, complexity: &Complexity) -> Result<(), String> {
+        if let Some(max_blocks) = self.max_blocks {
+            if complexity.blocks > max_blocks {
+                return Err(format!("{} blocks exceed --max-blocks {}", complexity.blocks, max_blocks));
+            }
+        }
+        if let Some(max_constraints) = self.max_constraints {
+            if complexity.constraints > max_constraints {
+                return Err(format!(
+                    "~{} constraints exceed --max-constraints {}",
+                    complexity.constraints,
+                    max_constraints
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+//! `nll corpus` and `nll query`: statistics over a whole corpus of
+//! programs. `nll corpus` analyzes each program and writes what it
+//! found to a database of JSON lines, one record per line; `nll query`
+//! then answers questions like "how many loans have regions spanning
+//! more than one loop?" from the database, without analyzing anything
+//! again.
+//!
+//! Every record has a `kind` and the `file` it comes from:
+//!
+//! - `function`: one per program, with its `blocks`, `points`,
+//!   `variables`, `loops`, `loans`, `errors` and `assertions`, and the
+//!   `failure` that stopped the analysis (`null` if it ran to
+//!   completion);
+//! - `loan`: one per borrow (or by-ref capture), with its `id`, the
+//!   `point` where it is issued, its `region`, `borrow_kind` (`shared`
+//!   or `mut`) and `path`, and the `points`, `blocks` and `loops` its
+//!   region spans; `escapes` is true if the region outlives the
+//!   function body;
+//! - `error`: one per error, with its `code` (`null` if it has none),
+//!   `point` and `message`;
+//! - `assertion`: one per assertion checked, with its `index` in the
+//!   program, whether it `holds`, and the `failure` if not (`null` if
+//!   it does).
+//!
+//! Errors and assertions also list the facts they rest on (see
+//! `errors::Fact`), by id: the `loans` (the `id` of their `loan`
+//! record), the `constraints` that carried a point into a region (by
+//! their index, in the order they are generated) and the `live_bits`
+//! that make a variable or region live at it (the bits of the
+//! variables being used come first, in declaration order, then those
+//! of the variables being dropped, then those of the loans whose
+//! references do not escape, in `id` order). The ids are stable for a
+//! given program.
+
+use env::{Environment, Point};
+use errors::{Fact, SourceFile};
+use graph::{self, BasicBlockIndex, FuncGraph};
+use graph_algorithms::Graph;
+use nll_repr::repr::{self, Func};
+use options::Options;
+use regionck;
+use rustc_serialize::json::Json;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// The records for one program.
+pub fn records(input: &str, func: &Func, options: &Options, source: &SourceFile) -> Vec<Json> {
+    let graph = FuncGraph::new(func.clone());
+    let options = &Options { provenance: true, ..options.clone() };
+    graph::with_graph(&graph, || {
+        let env = &Environment::new(&graph);
+        let record = |kind: &str| {
+            let mut record = BTreeMap::new();
+            record.insert("kind".to_string(), Json::String(kind.to_string()));
+            record.insert("file".to_string(), Json::String(input.to_string()));
+            record
+        };
+
+        let all_blocks: Vec<_> = (0..env.graph.num_nodes()).map(BasicBlockIndex::from).collect();
+        let mut function = record("function");
+        function.insert("blocks".to_string(), number(all_blocks.len()));
+        let points: usize =
+            all_blocks.iter().map(|&b| Point::points_in_block(env.end_point(b).action)).sum();
+        function.insert("points".to_string(), number(points));
+        function.insert("variables".to_string(), number(env.graph.decls().len()));
+        function.insert("loops".to_string(), number(loops(env, all_blocks.iter().cloned()).len()));
+
+        let analysis = match regionck::analysis(env, options, source) {
+            Ok(analysis) => analysis,
+            Err(err) => {
+                function.insert("failure".to_string(), Json::String(err.to_string()));
+                return vec![Json::Object(function)];
+            }
+        };
+        function.insert("failure".to_string(), Json::Null);
+        function.insert("errors".to_string(), number(analysis.errors.len()));
+        function.insert("assertions".to_string(), number(analysis.assertions.len()));
+
+        let mut records = vec![];
+        for (id, site) in env.loan_sites().into_iter().enumerate() {
+            let mut loan = record("loan");
+            loan.insert("id".to_string(), number(id));
+            loan.insert("point".to_string(), Json::String(format!("{:?}", site.point)));
+            loan.insert("region".to_string(), Json::String(site.region_name.to_string()));
+            loan.insert("borrow_kind".to_string(), Json::String(match site.kind {
+                repr::BorrowKind::Shared => "shared".to_string(),
+                repr::BorrowKind::Mut => "mut".to_string(),
+            }));
+            loan.insert("path".to_string(), Json::String(site.path.to_string()));
+            if let Some(region) = analysis.regions.get(&site.region_name) {
+                let points: Vec<_> = region.locations().collect();
+                let blocks: BTreeSet<_> = points.iter().map(|p| p.block).collect();
+                loan.insert("points".to_string(), number(points.len()));
+                loan.insert("blocks".to_string(), number(blocks.len()));
+                loan.insert("loops".to_string(), number(loops(env, blocks).len()));
+                loan.insert(
+                    "escapes".to_string(),
+                    Json::Boolean(region.universal_elements().next().is_some()),
+                );
+            }
+            records.push(Json::Object(loan));
+        }
+        function.insert("loans".to_string(), number(records.len()));
+
+        for error in &analysis.errors {
+            let mut record = record("error");
+            record.insert("code".to_string(), match error.code {
+                Some(code) => Json::String(code.to_string()),
+                None => Json::Null,
+            });
+            record.insert("point".to_string(), Json::String(format!("{:?}", error.point)));
+            record.insert("message".to_string(), Json::String(error.message.clone()));
+            insert_facts(&mut record, &error.facts);
+            records.push(Json::Object(record));
+        }
+
+        for assertion in &analysis.assertions {
+            let mut record = record("assertion");
+            record.insert("index".to_string(), number(assertion.index));
+            record.insert("holds".to_string(), Json::Boolean(assertion.failure.is_none()));
+            record.insert("failure".to_string(), match assertion.failure {
+                Some(ref failure) => Json::String(failure.clone()),
+                None => Json::Null,
+            });
+            insert_facts(&mut record, &assertion.facts);
+            records.push(Json::Object(record));
+        }
+
+        records.insert(0, Json::Object(function));
+        records
+    })
+}
+
+fn number(n: usize) -> Json {
+    Json::U64(n as u64)
+}
+
+/// Adds the ids of `facts` to `record`, as a list for each kind.
+fn insert_facts(record: &mut BTreeMap<String, Json>, facts: &[Fact]) {
+    let ids = |kind: fn(Fact) -> Option<usize>| {
+        Json::Array(facts.iter().filter_map(|&fact| kind(fact)).map(number).collect())
+    };
+    record.insert("loans".to_string(), ids(|fact| match fact {
+        Fact::Loan(id) => Some(id),
+        _ => None,
+    }));
+    record.insert("constraints".to_string(), ids(|fact| match fact {
+        Fact::Constraint(id) => Some(id),
+        _ => None,
+    }));
+    record.insert("live_bits".to_string(), ids(|fact| match fact {
+        Fact::Live(id) => Some(id),
+        _ => None,
+    }));
+}
+
+/// The loops containing any of `blocks`, identified by their heads.
+fn loops<I>(env: &Environment, blocks: I) -> BTreeSet<BasicBlockIndex>
+where
+    I: IntoIterator<Item = BasicBlockIndex>,
+{
+    let mut heads = BTreeSet::new();
+    for block in blocks {
+        if let Some(loop_id) = env.loop_tree.loop_id(block) {
+            heads.insert(env.loop_tree.loop_head(loop_id));
+            for parent in env.loop_tree.parents(loop_id) {
+                heads.insert(env.loop_tree.loop_head(parent));
+            }
+        }
+    }
+    heads
+}
+
+/// A query over the records of a database, like
+/// `count loan where loops > 1 and borrow_kind = mut`: either `count`
+/// or `list` the records of a kind, optionally only those whose fields
+/// satisfy every condition. A value that parses as a number is
+/// compared as a number, anything else as a string (so `null`, `true`
+/// and `false` match the JSON values); a record without the field
+/// never satisfies a condition on it.
+pub struct Query {
+    list: bool,
+    kind: String,
+    conditions: Vec<Condition>,
+}
+
+struct Condition {
+    field: String,
+    op: Op,
+    value: Json,
+}
+
+#[derive(Copy, Clone)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Query {
+    pub fn parse(text: &str) -> Result<Query, String> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let usage = || {
+            format!(
+                "invalid query `{}`: expected `count|list KIND [where FIELD OP VALUE [and ...]]`",
+                text
+            )
+        };
+        let list = match words.first() {
+            Some(&"count") => false,
+            Some(&"list") => true,
+            _ => return Err(usage()),
+        };
+        let kind = match words.get(1) {
+            Some(kind) => kind.to_string(),
+            None => return Err(usage()),
+        };
+
+        let mut conditions = vec![];
+        let mut rest = &words[2..];
+        if !rest.is_empty() {
+            if rest[0] != "where" {
+                return Err(usage());
+            }
+            loop {
+                if rest.len() < 4 {
+                    return Err(usage());
+                }
+                let op = match rest[2] {
+                    "=" | "==" => Op::Eq,
+                    "!=" => Op::Ne,
+                    "<" => Op::Lt,
+                    "<=" => Op::Le,
+                    ">" => Op::Gt,
+                    ">=" => Op::Ge,
+                    _ => return Err(usage()),
+                };
+                let value = match rest[3].parse::<f64>() {
+                    Ok(n) => Json::F64(n),
+                    Err(_) => Json::String(rest[3].to_string()),
+                };
+                conditions.push(Condition { field: rest[1].to_string(), op, value });
+                rest = &rest[4..];
+                match rest.first() {
+                    None => break,
+                    Some(&"and") => {}
+                    Some(_) => return Err(usage()),
+                }
+            }
+        }
+
+        Ok(Query { list, kind, conditions })
+    }
+
+    pub fn run<'r>(&self, records: &'r [Json]) -> QueryResult<'r> {
+        let matches = records
+            .iter()
+            .filter(|record| {
+                record.find("kind").and_then(|k| k.as_string()) == Some(&self.kind) &&
+                    self.conditions.iter().all(|c| c.holds(record))
+            })
+            .collect();
+        QueryResult { list: self.list, matches }
+    }
+}
+
+impl Condition {
+    fn holds(&self, record: &Json) -> bool {
+        let field = match record.find(&self.field) {
+            Some(field) => field,
+            None => return false,
+        };
+        let text = match *field {
+            Json::String(ref s) => Some(s.clone()),
+            Json::Boolean(b) => Some(b.to_string()),
+            Json::Null => Some("null".to_string()),
+            _ => None,
+        };
+        let ordering = match self.value {
+            Json::F64(b) => field.as_f64().and_then(|a| a.partial_cmp(&b)),
+            Json::String(ref b) => text.map(|a| a.cmp(b)),
+            _ => None,
+        };
+        let ordering = match ordering {
+            Some(ordering) => ordering,
+            None => return false,
+        };
+        match self.op {
+            Op::Eq => ordering == Ordering::Equal,
+            Op::Ne => ordering != Ordering::Equal,
+            Op::Lt => ordering == Ordering::Less,
+            Op::Le => ordering != Ordering::Greater,
+            Op::Gt => ordering == Ordering::Greater,
+            Op::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+/// The records matching a query: printed as their number for `count`,
+/// and one per line for `list`.
+pub struct QueryResult<'r> {
+    list: bool,
+    pub matches: Vec<&'r Json>,
+}
+
+impl<'r> fmt::Display for QueryResult<'r> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if self.list {
+            for record in &self.matches {
+                writeln!(f, "{}", record)?;
+            }
+            Ok(())
+        } else {
+            writeln!(f, "{}", self.matches.len())
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! `--emit-csv <dir>`: the results of the analysis as flat tables, for
+//! analysis in a spreadsheet or with pandas. For an input `foo.nll`,
+//! three files are written to the directory:
+//!
+//! - `foo.liveness.csv`: `point,var,live,drop_live`, one row for each
+//!   variable at each point, saying whether its current value will be
+//!   used, and whether it will be dropped, later on;
+//! - `foo.loans.csv`: `point,loan,in_scope`, one row for each loan at
+//!   each point, the loans being numbered as in `--dump-loans`;
+//! - `foo.regions.csv`: `region,point`, one row for each element of
+//!   each region (an element beyond the function body is written like
+//!   `end('a)`).
+//!
+//! Points are listed in reverse post-order, and booleans are written
+//! `true` or `false`. Inputs with the same file name overwrite each
+//! other's tables.
+
+use env::{Environment, Point};
+use liveness::Liveness;
+use loans_in_scope::LoanScope;
+use regionck::RegionValues;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub fn write_tables(
+    env: &Environment,
+    liveness: &Liveness,
+    loan_scopes: &[LoanScope],
+    regions: &RegionValues,
+    dir: &str,
+    input: &str,
+) -> Result<(), Box<Error>> {
+    fs::create_dir_all(dir)?;
+    let stem = match Path::new(input).file_stem() {
+        Some(stem) => stem.to_string_lossy().into_owned(),
+        None => return Err(format!("cannot name the tables for input `{}`", input).into()),
+    };
+    let table = |name: &str, header: &str| -> Result<BufWriter<File>, Box<Error>> {
+        let path = Path::new(dir).join(format!("{}.{}.csv", stem, name));
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "{}", header)?;
+        Ok(file)
+    };
+    let points = points(env);
+
+    // `walk` visits the points of each block backwards, so gather the
+    // live variables first and write them out in order.
+    let mut live_at = HashMap::new();
+    liveness.walk(|point, _action, live_bits| {
+        let live: HashSet<_> = liveness.live_variables(live_bits).collect();
+        let drop_live: HashSet<_> = liveness.drop_live_variables(live_bits).collect();
+        live_at.insert(point, (live, drop_live));
+    });
+    let mut file = table("liveness", "point,var,live,drop_live")?;
+    for &point in &points {
+        let (live, drop_live) = &live_at[&point];
+        for decl in env.graph.decls() {
+            writeln!(
+                file,
+                "{},{},{},{}",
+                field(format!("{:?}", point)),
+                field(decl.var.to_string()),
+                live.contains(&decl.var),
+                drop_live.contains(&decl.var)
+            )?;
+        }
+    }
+    file.flush()?;
+
+    let mut file = table("loans", "point,loan,in_scope")?;
+    for &point in &points {
+        for (index, loan) in loan_scopes.iter().enumerate() {
+            writeln!(
+                file,
+                "{},L{},{}",
+                field(format!("{:?}", point)),
+                index,
+                loan.scope.may_contain(point)
+            )?;
+        }
+    }
+    file.flush()?;
+
+    let mut file = table("regions", "region,point")?;
+    for (name, region) in regions {
+        for point in region.locations() {
+            writeln!(file, "{},{}", field(name.to_string()), field(format!("{:?}", point)))?;
+        }
+        for element in region.universal_elements() {
+            writeln!(file, "{},{}", field(name.to_string()), field(format!("{:?}", element)))?;
+        }
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Every point of the function, in reverse post-order.
+fn points(env: &Environment) -> Vec<Point> {
+    env.all_points().collect()
+}
+
+/// Quotes `text` if it contains anything that would break up the row.
+fn field(text: String) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
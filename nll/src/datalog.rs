@@ -0,0 +1,7 @@
+//! The rules of region inference and of the loans-in-scope analysis,
+//! written out as a Soufflé program (`--emit-datalog-rules`), so that
+//! they can be compared against -- or swapped in for -- a Datalog
+//! formulation. See `rules.dl` for the input facts it expects.
+
+/// The text of the program.
+pub const RULES: &'static str = include_str!("rules.dl");
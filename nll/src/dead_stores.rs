@@ -0,0 +1,81 @@
+//! `--warn-dead-stores`: warns about each store to a local variable
+//! whose value is neither read nor dropped afterwards, as rustc's
+//! `unused_assignments` lint does. Liveness only tracks whole
+//! variables, so a store to a field is dead if nothing reads any part
+//! of the variable after it; stores through a dereference write to
+//! memory the variable does not own, and are never reported.
+
+use env::{Environment, Point};
+use errors::{ErrorReporting, ReportedError};
+use graph_algorithms::bit_set::BitBuf;
+use liveness::{BitKind, Liveness};
+use nll_repr::repr;
+use std::collections::HashMap;
+
+/// The code the warnings are reported with, so that
+/// `#[allow(unused_assignments)]` silences them.
+const CODE: &str = "unused_assignments";
+
+pub fn report_dead_stores(env: &Environment, liveness: &Liveness, errors: &mut ErrorReporting) {
+    // Liveness walks each block backwards, starting from its end, so
+    // the bits seen last are those live at the mid-point of the
+    // action at hand, after it.
+    let mut live_after: Option<BitBuf> = None;
+    liveness.walk(|point, action, live_on_entry| {
+        if let (Some(action), Some(live_after)) = (action, live_after.as_ref()) {
+            if let Some(dest) = store_destination(env, action) {
+                let var = dest.base();
+                let live = |bk| live_after.get(liveness.bit_index(bk));
+                if !live(BitKind::VariableUsed(var)) && !live(BitKind::VariableDrop(var)) {
+                    errors.report_warning(dead_store(point, dest));
+                }
+            }
+        }
+        live_after = Some(live_on_entry.to_buf());
+    });
+}
+
+/// The path that `action` stores into, if it is (part of) a local
+/// variable.
+fn store_destination<'a>(env: &Environment, action: &'a repr::Action) -> Option<&'a repr::Path> {
+    let dest = match action.kind {
+        repr::ActionKind::Init(ref dest, _) |
+        repr::ActionKind::Borrow(ref dest, ..) |
+        repr::ActionKind::Assign(ref dest, _) |
+        repr::ActionKind::Const(ref dest, _) |
+        repr::ActionKind::Closure(ref dest, _) |
+        repr::ActionKind::RawBorrow(ref dest, ..) |
+        repr::ActionKind::Write(ref dest, _) => dest,
+        repr::ActionKind::Constraint(..) |
+        repr::ActionKind::Use(..) |
+        repr::ActionKind::Drop(..) |
+        repr::ActionKind::Read(..) |
+        repr::ActionKind::StorageDead(..) |
+        repr::ActionKind::Noop => return None,
+    };
+    if dest.prefixes().into_iter().any(|prefix| prefix.is_deref()) {
+        return None;
+    }
+    match env.var_kind(dest.base()) {
+        repr::VariableKind::Local => Some(dest),
+        repr::VariableKind::Static | repr::VariableKind::StaticMut => None,
+    }
+}
+
+fn dead_store(point: Point, dest: &repr::Path) -> ReportedError {
+    let message = match *dest {
+        repr::Path::Var(var) => format!("value assigned to `{}` is never read", var),
+        repr::Path::Extension(..) => {
+            format!("value assigned to `{}` is never read, nor is the rest of `{}`",
+                    dest, dest.base())
+        }
+    };
+    ReportedError {
+        point,
+        code: Some(CODE),
+        message,
+        notes: vec![],
+        labels: HashMap::new(),
+        facts: vec![],
+    }
+}
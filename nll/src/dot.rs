@@ -0,0 +1,106 @@
+//! `--dump-dot`: the control-flow graph in Graphviz's DOT format. The
+//! blocks of each loop are drawn in a cluster, labeled with the head
+//! of the loop, and the clusters of nested loops are nested within
+//! one another; back edges, which go from within a loop to its head,
+//! are drawn in red. Each block lists its actions and its `goto`.
+
+use env::Environment;
+use graph::BasicBlockIndex;
+use graph_algorithms::Graph;
+use graph_algorithms::loop_tree::LoopId;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+pub fn dot(env: &Environment, source_text: &str) -> String {
+    // The blocks directly within each loop (`None` for those outside
+    // of any loop), and the loops directly within each loop.
+    let mut blocks: BTreeMap<Option<LoopId>, Vec<BasicBlockIndex>> = BTreeMap::new();
+    let mut loops: BTreeMap<Option<LoopId>, BTreeSet<LoopId>> = BTreeMap::new();
+    for &block in &env.reverse_post_order {
+        let loop_id = env.loop_tree.loop_id(block);
+        blocks.entry(loop_id).or_default().push(block);
+        let mut child = loop_id;
+        while let Some(loop_id) = child {
+            let parent = env.loop_tree.parent(loop_id);
+            loops.entry(parent).or_default().insert(loop_id);
+            child = parent;
+        }
+    }
+
+    let mut out = String::new();
+    writeln!(out, "digraph {{").unwrap();
+    writeln!(out, "  node [shape=box, fontname=monospace];").unwrap();
+    cluster(env, source_text, &blocks, &loops, None, 1, &mut out);
+    for &block in &env.reverse_post_order {
+        for successor in env.graph.successors(block) {
+            let back_edge = env.dominators.is_dominated_by(block, successor);
+            let attributes = if back_edge { " [color=red]" } else { "" };
+            writeln!(out, "  \"{:?}\" -> \"{:?}\"{};", block, successor, attributes).unwrap();
+        }
+    }
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// Writes the blocks directly within `loop_id`, and the clusters of
+/// the loops directly within it.
+fn cluster(
+    env: &Environment,
+    source_text: &str,
+    blocks: &BTreeMap<Option<LoopId>, Vec<BasicBlockIndex>>,
+    loops: &BTreeMap<Option<LoopId>, BTreeSet<LoopId>>,
+    loop_id: Option<LoopId>,
+    indent: usize,
+    out: &mut String,
+) {
+    for &block in blocks.get(&loop_id).into_iter().flatten() {
+        writeln!(
+            out,
+            "{0:1$}\"{2:?}\" [label=\"{3}\"];",
+            "",
+            indent * 2,
+            block,
+            escape(&block_label(env, source_text, block))
+        ).unwrap();
+    }
+    for &child in loops.get(&loop_id).into_iter().flatten() {
+        let head = env.loop_tree.loop_head(child);
+        writeln!(out, "{0:1$}subgraph \"cluster_{2:?}\" {{", "", indent * 2, head).unwrap();
+        writeln!(out, "{0:1$}label=\"loop {2:?}\";", "", (indent + 1) * 2, head).unwrap();
+        cluster(env, source_text, blocks, loops, Some(child), indent + 1, out);
+        writeln!(out, "{0:1$}}}", "", indent * 2).unwrap();
+    }
+}
+
+/// The name of the block, one line for each of its actions (as
+/// written in the source, when known) and its `goto`, if any.
+fn block_label(env: &Environment, source_text: &str, block: BasicBlockIndex) -> String {
+    let mut label = format!("{:?}\n", block);
+    for (index, action) in env.graph.block_data(block).actions.iter().enumerate() {
+        match action.span {
+            Some(span) => writeln!(label, "{}: {}", index, &source_text[span.lo..span.hi]),
+            None => writeln!(label, "{}: {:?}", index, action.kind),
+        }.unwrap();
+    }
+    let successors: Vec<_> = env.graph.successors(block).map(|s| format!("{:?}", s)).collect();
+    if !successors.is_empty() {
+        writeln!(label, "goto {};", successors.join(" ")).unwrap();
+    }
+    label
+}
+
+/// Escapes `text` for a DOT string, with every line left-justified.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\l"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
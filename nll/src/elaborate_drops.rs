@@ -0,0 +1,35 @@
+use nll_repr::repr::{Action, ActionKind, Func, Path};
+
+/// Appends, to each block marked `#[scope_exit]`, a `drop(v);
+/// StorageDead(v);` pair for every local variable `v` (other than
+/// `return` and the statics), in reverse declaration order -- the order in which
+/// rustc drops the locals of a scope. Dropping a reference is a no-op
+/// as far as liveness is concerned, so every variable gets a `drop`,
+/// whatever its type.
+pub fn elaborate_drops(func: &mut Func) {
+    let vars: Vec<_> = func.decls
+        .iter()
+        .rev()
+        .filter(|decl| !decl.is_static())
+        .map(|decl| decl.var)
+        .filter(|var| !var.is_return())
+        .collect();
+
+    for block in func.data.values_mut().filter(|block| block.scope_exit) {
+        for &var in &vars {
+            block.actions.push(synthesized(ActionKind::Drop(Box::new(Path::Var(var)))));
+            block.actions.push(synthesized(ActionKind::StorageDead(var)));
+        }
+    }
+}
+
+fn synthesized(kind: ActionKind) -> Action {
+    Action {
+        kind,
+        span: None,
+        label: None,
+        allow: vec![],
+        in_unsafe_block: false,
+        should_have_error: None,
+    }
+}
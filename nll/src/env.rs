@@ -1,11 +1,13 @@
 use graph::{BasicBlockIndex, FuncGraph};
-use graph_algorithms::Graph;
+use graph_algorithms::{Graph, NodeIndex};
+use graph_algorithms::analysis::{AnalysisKey, AnalysisResults};
 use graph_algorithms::dominators::{self, Dominators, DominatorTree};
 use graph_algorithms::iterate::reverse_post_order;
 use graph_algorithms::loop_tree::{self, LoopTree};
+use graph_algorithms::node_vec::NodeVec;
 use graph_algorithms::reachable::{self, Reachability};
 use nll_repr::repr;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 
 pub struct Environment<'func> {
@@ -17,12 +19,94 @@ pub struct Environment<'func> {
     pub reverse_post_order: Vec<BasicBlockIndex>,
     pub var_map: HashMap<repr::Variable, &'func repr::VariableDecl>,
     pub struct_map: HashMap<repr::StructName, &'func repr::StructDecl>,
+
+    /// Per-block results of analyses beyond those above; see the
+    /// `*_ANALYSIS` keys.
+    pub analyses: AnalysisResults<FuncGraph>,
+
+    /// For each anonymous region (written `'_`), a description of the
+    /// construct that introduced it, so that diagnostics can say
+    /// something more useful than `'3`.
+    pub region_origins: HashMap<repr::RegionName, String>,
 }
 
+/// The immediate dominator of each block (the start block is its own).
+pub const IMMEDIATE_DOMINATOR_ANALYSIS: AnalysisKey<NodeVec<FuncGraph, Option<BasicBlockIndex>>> =
+    AnalysisKey::new("immediate-dominator");
+
+/// The immediate post-dominator of each block; `None` if that is the
+/// virtual exit, or if the block cannot reach an exit.
+pub const IMMEDIATE_POST_DOMINATOR_ANALYSIS: AnalysisKey<NodeVec<FuncGraph, Option<BasicBlockIndex>>> =
+    AnalysisKey::new("immediate-post-dominator");
+
+/// The head of the innermost loop containing each block, if any.
+pub const LOOP_HEAD_ANALYSIS: AnalysisKey<NodeVec<FuncGraph, Option<BasicBlockIndex>>> =
+    AnalysisKey::new("loop-head");
+
+pub use nll_repr::repr::PointKind;
+
+/// A point in the control-flow graph. Each action has two: its start,
+/// where the accesses it makes are checked against the loans in scope,
+/// and its mid-point, where its effects take hold -- the loans it
+/// issues come into scope, and the value it stores must be valid. The
+/// end of a block (where its terminator goes) has the two as well, so
+/// that the points of a block run `Start(0), Mid(0), ..., Start(n),
+/// Mid(n)`, where `n` is the number of actions; this is the order of
+/// `Point` too.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Point {
     pub block: BasicBlockIndex,
     pub action: usize,
+    pub kind: PointKind,
+}
+
+impl Point {
+    /// The start of the `action`th action of `block`.
+    pub fn start(block: BasicBlockIndex, action: usize) -> Self {
+        Point { block, action, kind: PointKind::Start }
+    }
+
+    /// The mid-point of the same action.
+    pub fn mid(self) -> Self {
+        Point { kind: PointKind::Mid, ..self }
+    }
+
+    /// The start of the same action.
+    pub fn action_start(self) -> Self {
+        Point { kind: PointKind::Start, ..self }
+    }
+
+    pub fn is_mid(self) -> bool {
+        self.kind == PointKind::Mid
+    }
+
+    /// The position of the point among those of its block.
+    pub fn index_in_block(self) -> usize {
+        self.action * 2 + self.is_mid() as usize
+    }
+
+    /// The inverse of `index_in_block`.
+    pub fn from_index_in_block(block: BasicBlockIndex, index: usize) -> Self {
+        let point = Point::start(block, index / 2);
+        if index % 2 == 1 { point.mid() } else { point }
+    }
+
+    /// The number of points of a block with `actions` actions.
+    pub fn points_in_block(actions: usize) -> usize {
+        (actions + 1) * 2
+    }
+}
+
+/// An action that creates a loan: a borrow `dest = &'region path`, a
+/// closure `dest` capturing `path` by reference, or an init `dest =
+/// use(&'region path, ...)`.
+#[derive(Copy, Clone, Debug)]
+pub struct LoanSite<'func> {
+    pub point: Point,
+    pub dest: &'func repr::Path,
+    pub region_name: repr::RegionName,
+    pub kind: repr::BorrowKind,
+    pub path: &'func repr::Path,
 }
 
 impl<'func> Environment<'func> {
@@ -38,6 +122,8 @@ impl<'func> Environment<'func> {
             .iter()
             .map(|sd| (sd.name, sd))
             .collect();
+        let region_origins = region_origins(graph, &rpo);
+        let analyses = analyses(graph, &dominators, &loop_tree);
 
         Environment {
             graph: graph,
@@ -48,14 +134,75 @@ impl<'func> Environment<'func> {
             reverse_post_order: rpo,
             var_map: var_map,
             struct_map: struct_map,
+            region_origins: region_origins,
+            analyses: analyses,
         }
     }
 
+    /// Formats a region name for use in diagnostics; anonymous regions
+    /// also mention where they came from.
+    pub fn describe_region(&self, name: repr::RegionName) -> String {
+        match self.region_origins.get(&name) {
+            Some(origin) => format!("`{}` (from {})", name, origin),
+            None => format!("`{}`", name),
+        }
+    }
+
+    /// The span of the action at `point`, if there is one (and it
+    /// was not synthesized).
+    pub fn action_span(&self, point: Point) -> Option<repr::Span> {
+        self.graph
+            .block_data(point.block)
+            .actions
+            .get(point.action)
+            .and_then(|action| action.span)
+    }
+
+    /// The label of the action at `point`, if it has one.
+    pub fn point_label(&self, point: Point) -> Option<&'func str> {
+        self.graph
+            .block_data(point.block)
+            .actions
+            .get(point.action)
+            .and_then(|action| action.label.as_ref())
+            .map(|label| &label[..])
+    }
+
+    /// Formats a point for use in dumps, like `START/1 ("second
+    /// read")` if the action there is labeled.
+    pub fn describe_point(&self, point: Point) -> String {
+        match self.point_label(point) {
+            Some(label) => format!("{:?} ({:?})", point, label),
+            None => format!("{:?}", point),
+        }
+    }
+
+    /// Formats a path through the control-flow graph, like `START/1 ->
+    /// START/2 -> END/0`. Mid-points are left out, except at either
+    /// end, since each step of the path passes through one.
+    pub fn describe_path(&self, path: &[Point]) -> String {
+        let last = path.len().saturating_sub(1);
+        let points: Vec<_> = path.iter()
+            .enumerate()
+            .filter(|&(i, p)| !p.is_mid() || i == 0 || i == last)
+            .map(|(_, p)| format!("{:?}", p))
+            .collect();
+        points.join(" -> ")
+    }
+
     pub fn dump_dominators(&self) {
         let tree = self.dominators.dominator_tree();
         self.dump_dominator_tree(&tree, tree.root(), 0)
     }
 
+    /// Like `dump_dominators`, but for post-dominators. The root of
+    /// the tree is a virtual exit node that follows every block
+    /// without successors.
+    pub fn dump_post_dominators(&self) {
+        let tree = dominators::post_dominators(self.graph).dominator_tree();
+        self.dump_dominator_tree(&tree, tree.root(), 0)
+    }
+
     fn dump_dominator_tree<G1>(
         &self,
         tree: &DominatorTree<G1>,
@@ -64,37 +211,86 @@ impl<'func> Environment<'func> {
     ) where
         G1: Graph<Node = BasicBlockIndex>,
     {
-        println!("{0:1$}- {2:?}", "", indent, node);
+        if node.as_usize() == self.graph.num_nodes() {
+            println!("{0:1$}- (exit)", "", indent);
+        } else {
+            println!("{0:1$}- {2:?}", "", indent, node);
+        }
 
         for &child in tree.children(node) {
             self.dump_dominator_tree(tree, child, indent + 2)
         }
     }
 
-    pub fn start_point(&self, block: BasicBlockIndex) -> Point {
-        Point {
-            block: block,
-            action: 0,
+    /// Dumps every table in `analyses`, block by block.
+    pub fn dump_analyses(&self) {
+        let mut out = String::new();
+        self.analyses.dump(self.graph, &mut out).unwrap();
+        print!("{}", out);
+    }
+
+    /// Parses a point written like `START/3` or `Mid(START/3)`, as in
+    /// the assertions.
+    pub fn parse_point(&self, text: &str) -> Result<Point, String> {
+        let invalid = || format!("invalid point `{}`", text);
+        if text.starts_with("Mid(") && text.ends_with(')') {
+            return self.parse_point(&text[4..text.len() - 1]).map(Point::mid);
+        }
+        if text.starts_with("Start(") && text.ends_with(')') {
+            return self.parse_point(&text[6..text.len() - 1]);
+        }
+        let slash = text.find('/').ok_or_else(&invalid)?;
+        let block = self.graph
+            .block_named(repr::BasicBlock::from(&text[..slash]))
+            .ok_or_else(&invalid)?;
+        let action: usize = text[slash + 1..].parse().map_err(|_| invalid())?;
+        if action > self.end_point(block).action {
+            return Err(invalid());
         }
+        Ok(Point::start(block, action))
     }
 
+    pub fn start_point(&self, block: BasicBlockIndex) -> Point {
+        Point::start(block, 0)
+    }
+
+    /// The start of the end of `block`, after its last action.
     pub fn end_point(&self, block: BasicBlockIndex) -> Point {
-        let actions = self.graph.block_data(block).actions().len();
-        Point {
-            block: block,
-            action: actions,
-        }
+        let actions = self.graph.block_data(block).actions.len();
+        Point::start(block, actions)
+    }
+
+    /// The actions of `block`, in order, each with its start point.
+    pub fn actions(
+        &self,
+        block: BasicBlockIndex,
+    ) -> impl DoubleEndedIterator<Item = (Point, &'func repr::Action)> {
+        let graph: &'func FuncGraph = self.graph;
+        graph
+            .block_data(block)
+            .actions
+            .iter()
+            .enumerate()
+            .map(move |(action, data)| (Point::start(block, action), data))
+    }
+
+    /// Every reachable point, block by block in reverse post-order:
+    /// the points of each action, then those of the end of the block.
+    pub fn all_points<'a>(&'a self) -> impl Iterator<Item = Point> + 'a {
+        self.reverse_post_order.iter().flat_map(move |&block| {
+            let points = Point::points_in_block(self.end_point(block).action);
+            (0..points).map(move |index| Point::from_index_in_block(block, index))
+        })
     }
 
+    /// The start of an action leads to its mid-point, which leads to
+    /// the start of the next action; the mid-point of the end of a
+    /// block leads to the start of each successor.
     pub fn successor_points(&self, p: Point) -> Vec<Point> {
-        let end_point = self.end_point(p.block);
-        if p != end_point {
-            vec![
-                Point {
-                    block: p.block,
-                    action: p.action + 1,
-                },
-            ]
+        if !p.is_mid() {
+            vec![p.mid()]
+        } else if p.action_start() != self.end_point(p.block) {
+            vec![Point::start(p.block, p.action + 1)]
         } else {
             self.graph
                 .successors(p.block)
@@ -103,24 +299,160 @@ impl<'func> Environment<'func> {
         }
     }
 
+    /// The shortest path from `from` to `to` that visits only points
+    /// for which `within` holds (including both ends), if there is one.
+    pub fn shortest_path<F>(&self, from: Point, to: Point, within: F) -> Option<Vec<Point>>
+    where
+        F: Fn(Point) -> bool,
+    {
+        if !within(from) {
+            return None;
+        }
+
+        let mut predecessor = HashMap::new();
+        predecessor.insert(from, from);
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        while let Some(p) = queue.pop_front() {
+            if p == to {
+                let mut path = vec![p];
+                let mut q = p;
+                while q != from {
+                    q = predecessor[&q];
+                    path.push(q);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for succ in self.successor_points(p) {
+                if within(succ) && !predecessor.contains_key(&succ) {
+                    predecessor.insert(succ, p);
+                    queue.push_back(succ);
+                }
+            }
+        }
+        None
+    }
+
+    /// If `p` has exactly one successor point, and that successor can
+    /// only be reached from `p`, returns it. Within a block, this is
+    /// just the next point; at the end of a block `B`, it is the
+    /// start of the single successor `S`, provided that `B` is the
+    /// sole predecessor of `S` (and hence its immediate dominator).
+    pub fn straight_line_successor(&self, p: Point) -> Option<Point> {
+        if p.action_start() != self.end_point(p.block) || !p.is_mid() {
+            return self.successor_points(p).pop();
+        }
+
+        let mut successors = self.graph.successors(p.block);
+        match (successors.next(), successors.next()) {
+            (Some(succ), None) if self.is_sole_predecessor(p.block, succ) => {
+                Some(self.start_point(succ))
+            }
+            _ => None,
+        }
+    }
+
+    /// The inverse of `straight_line_successor`.
+    pub fn straight_line_predecessor(&self, p: Point) -> Option<Point> {
+        if p.is_mid() {
+            return Some(p.action_start());
+        }
+        if p.action > 0 {
+            return Some(Point::start(p.block, p.action - 1).mid());
+        }
+
+        let mut predecessors = self.graph.predecessors(p.block);
+        match (predecessors.next(), predecessors.next()) {
+            (Some(pred), None) if self.graph.successors(pred).count() == 1 &&
+                                  self.is_sole_predecessor(pred, p.block) => {
+                Some(self.end_point(pred).mid())
+            }
+            _ => None,
+        }
+    }
+
+    fn is_sole_predecessor(&self, pred: BasicBlockIndex, block: BasicBlockIndex) -> bool {
+        self.graph.predecessors(block).count() == 1 &&
+            self.dominators.is_reachable(block) &&
+            self.dominators.immediate_dominator(block) == pred
+    }
+
+    /// The actions that create loans, in reverse post-order (and, for
+    /// a closure, in the order of its captures; for an init, in the
+    /// order of its operands).
+    pub fn loan_sites(&self) -> Vec<LoanSite<'func>> {
+        let mut sites = vec![];
+        for &block in &self.reverse_post_order {
+            for (point, action) in self.actions(block) {
+                match action.kind {
+                    repr::ActionKind::Borrow(ref dest, region_name, kind, ref path) => {
+                        sites.push(LoanSite { point, dest, region_name, kind, path });
+                    }
+                    repr::ActionKind::Closure(ref dest, ref captures) => {
+                        for capture in captures {
+                            if let repr::CaptureMode::ByRef(region_name, kind) = capture.mode {
+                                sites.push(LoanSite {
+                                    point,
+                                    dest,
+                                    region_name,
+                                    kind,
+                                    path: &capture.path,
+                                });
+                            }
+                        }
+                    }
+                    repr::ActionKind::Init(ref dest, ref operands) => {
+                        for operand in operands {
+                            if let repr::Operand::Borrow(region_name, kind, ref path) = *operand {
+                                sites.push(LoanSite { point, dest, region_name, kind, path });
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        sites
+    }
+
     pub fn var_ty(&self, v: repr::Variable) -> Box<repr::Ty> {
+        self.try_var_ty(v).unwrap_or_else(|message| panic!("{}", message))
+    }
+
+    pub fn path_ty(&self, path: &repr::Path) -> Box<repr::Ty> {
+        self.try_path_ty(path).unwrap_or_else(|message| panic!("{}", message))
+    }
+
+    pub fn field_ty(&self, base_ty: &repr::Ty, field_name: repr::FieldName) -> Box<repr::Ty> {
+        self.try_field_ty(base_ty, field_name).unwrap_or_else(|message| panic!("{}", message))
+    }
+
+    /// Like `var_ty`, but for variables that may not be declared, such
+    /// as those named in a query from outside the function.
+    pub fn try_var_ty(&self, v: repr::Variable) -> Result<Box<repr::Ty>, String> {
         match self.var_map.get(&v) {
-            Some(decl) => decl.ty.clone(),
-            None => panic!("no variable named {:?}", v),
+            Some(decl) => Ok(decl.ty.clone()),
+            None => Err(format!("no variable named {:?}", v)),
         }
     }
 
-    pub fn path_ty(&self, path: &repr::Path) -> Box<repr::Ty> {
+    /// Like `path_ty`, but returns an error for a path that does not
+    /// name a place in this function.
+    pub fn try_path_ty(&self, path: &repr::Path) -> Result<Box<repr::Ty>, String> {
         match *path {
-            repr::Path::Var(v) => self.var_ty(v),
+            repr::Path::Var(v) => self.try_var_ty(v),
             repr::Path::Extension(ref base, field_name) => {
-                let base_ty = self.path_ty(base);
-                self.field_ty(&base_ty, field_name)
+                let base_ty = self.try_path_ty(base)?;
+                self.try_field_ty(&base_ty, field_name)
             }
         }
     }
 
-    pub fn field_ty(&self, base_ty: &repr::Ty, field_name: repr::FieldName) -> Box<repr::Ty> {
+    pub fn try_field_ty(&self,
+                        base_ty: &repr::Ty,
+                        field_name: repr::FieldName)
+                        -> Result<Box<repr::Ty>, String> {
         log!(
             "field_ty(base_ty={:?} field_name={:?})",
             base_ty,
@@ -129,13 +461,24 @@ impl<'func> Environment<'func> {
         match *base_ty {
             repr::Ty::Ref(_, _kind, ref t) => {
                 if field_name == repr::FieldName::star() {
-                    t.clone()
+                    Ok(t.clone())
                 } else {
-                    panic!("cannot index & with field `{:?}`, use `star`", field_name)
+                    Err(format!("cannot index & with field `{:?}`, use `star`", field_name))
                 }
             }
 
-            repr::Ty::Unit => panic!("cannot index `()` type"),
+            repr::Ty::RawPtr(_kind, ref t) => {
+                if field_name == repr::FieldName::star() {
+                    Ok(t.clone())
+                } else {
+                    Err(format!("cannot index raw pointer with field `{:?}`, use `star`",
+                                field_name))
+                }
+            }
+
+            repr::Ty::Unit => Err("cannot index `()` type".to_string()),
+
+            repr::Ty::Int => Err("cannot index `int` type".to_string()),
 
             repr::Ty::Struct(n, ref parameters) => {
                 let struct_decl = self.struct_map[&n];
@@ -143,7 +486,7 @@ impl<'func> Environment<'func> {
                     .fields
                     .iter()
                     .find(|fd| fd.name == field_name)
-                    .unwrap_or_else(|| panic!("no field named `{:?}` in `{:?}`", field_name, n));
+                    .ok_or_else(|| format!("no field named `{:?}` in `{:?}`", field_name, n))?;
                 let field_ty = &field_decl.ty;
                 log!(
                     "field_ty: field_ty={:?} parameters={:?}",
@@ -152,10 +495,10 @@ impl<'func> Environment<'func> {
                 );
                 let field_ty = field_ty.subst(parameters);
                 log!("field_ty: field_ty={:?} post-substitution", field_ty);
-                Box::new(field_ty)
+                Ok(Box::new(field_ty))
             }
 
-            repr::Ty::Bound(_) => panic!("field_ty: unexpected bound type"),
+            repr::Ty::Bound(_) => Err("field_ty: unexpected bound type".to_string()),
         }
     }
 
@@ -221,6 +564,14 @@ impl<'func> Environment<'func> {
                             path = base_path;
                         }
 
+                        // Nothing ties the memory behind a raw pointer
+                        // to the pointer itself, so (as with a shared
+                        // reference) the pointer is not supporting.
+                        repr::Ty::RawPtr(..) => {
+                            assert_eq!(field_name, repr::FieldName::star());
+                            return result;
+                        }
+
                         // If you have borrowed `a.b`, then writing to
                         // `a` would overwrite `a.b`, which is
                         // disallowed.
@@ -229,6 +580,73 @@ impl<'func> Environment<'func> {
                         }
 
                         repr::Ty::Unit => panic!("unit has no fields"),
+                        repr::Ty::Int => panic!("int has no fields"),
+                        repr::Ty::Bound(..) => panic!("unexpected bound type"),
+                    }
+                }
+            }
+        }
+    }
+
+    /// True if `path` goes through the dereference of a raw pointer,
+    /// like `(*p).f` where `p: *mut S`.
+    pub fn derefs_raw_pointer(&self, path: &repr::Path) -> bool {
+        for prefix in path.prefixes() {
+            if let repr::Path::Extension(ref base_path, _) = *prefix {
+                if let repr::Ty::RawPtr(..) = *self.path_ty(base_path) {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// True if every path from the start to `block` goes through
+    /// `dom`. An unreachable block is dominated by nothing.
+    pub fn dominates(&self, dom: BasicBlockIndex, block: BasicBlockIndex) -> bool {
+        self.dominators.is_reachable(block) && self.dominators.is_dominated_by(block, dom)
+    }
+
+    /// True if `block` is the head of a loop.
+    pub fn is_loop_head(&self, block: BasicBlockIndex) -> bool {
+        self.loop_tree.loop_head_of_node(block) == Some(block)
+    }
+
+    /// Whether `v` is a local or a static.
+    pub fn var_kind(&self, v: repr::Variable) -> repr::VariableKind {
+        self.var_map[&v].kind
+    }
+
+    /// If `path` is borrowed, returns a vector of paths which -- if
+    /// overwritten or if the storage went away -- would invalidate
+    /// this reference. This is a subset of the supporting prefixes:
+    /// recursion stops at *any* dereference, since overwriting `r`
+    /// does not affect the memory at `*r`.
+    pub fn frozen_by_borrow_of<'a>(&self, mut path: &'a repr::Path) -> Vec<&'a repr::Path> {
+        let mut result = vec![];
+        loop {
+            result.push(path);
+            match *path {
+                repr::Path::Var(_) => return result,
+                repr::Path::Extension(ref base_path, field_name) => {
+                    match *self.path_ty(base_path) {
+                        // If you borrowed `*r`, writing to `r` does
+                        // not actually affect the memory at `*r`, so
+                        // we can stop iterating backwards now.
+                        repr::Ty::Ref(_, _, _) | repr::Ty::RawPtr(_, _) => {
+                            assert_eq!(field_name, repr::FieldName::star());
+                            return result;
+                        }
+
+                        // If you have borrowed `a.b`, then writing to
+                        // `a` would overwrite `a.b`, which is
+                        // disallowed.
+                        repr::Ty::Struct(..) => {
+                            path = base_path;
+                        }
+
+                        repr::Ty::Unit => panic!("unit has no fields"),
+                        repr::Ty::Int => panic!("int has no fields"),
                         repr::Ty::Bound(..) => panic!("unexpected bound type"),
                     }
                 }
@@ -237,8 +655,121 @@ impl<'func> Environment<'func> {
     }
 }
 
+fn region_origins(
+    graph: &FuncGraph,
+    rpo: &[BasicBlockIndex],
+) -> HashMap<repr::RegionName, String> {
+    let mut origins = HashMap::new();
+
+    for decl in graph.decls() {
+        let context = format!("the type of `{}`", decl.var);
+        record_ty_origins(&mut origins, &decl.ty, &context);
+    }
+
+    for &block in rpo {
+        for (index, action) in graph.block_data(block).actions.iter().enumerate() {
+            let point = Point::start(block, index);
+            match action.kind {
+                repr::ActionKind::Borrow(_, region, _, ref path) => {
+                    let origin = format!("the borrow of `{}` at {:?}", path, point);
+                    record_origin(&mut origins, region, origin);
+                }
+                repr::ActionKind::Closure(_, ref captures) => {
+                    for capture in captures {
+                        if let repr::CaptureMode::ByRef(region, _) = capture.mode {
+                            let origin = format!("the capture of `{}` at {:?}", capture.path, point);
+                            record_origin(&mut origins, region, origin);
+                        }
+                    }
+                }
+                repr::ActionKind::Init(_, ref operands) => {
+                    for operand in operands {
+                        if let repr::Operand::Borrow(region, _, ref path) = *operand {
+                            let origin = format!("the borrow of `{}` at {:?}", path, point);
+                            record_origin(&mut origins, region, origin);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    origins
+}
+
+fn record_ty_origins(
+    origins: &mut HashMap<repr::RegionName, String>,
+    ty: &repr::Ty,
+    context: &str,
+) {
+    match *ty {
+        repr::Ty::Ref(region, _, ref referent) => {
+            let origin = format!("the reference in {}", context);
+            record_origin(origins, region.assert_free(), origin);
+            record_ty_origins(origins, referent, context);
+        }
+        repr::Ty::RawPtr(_, ref referent) => record_ty_origins(origins, referent, context),
+        repr::Ty::Unit | repr::Ty::Int => {}
+        repr::Ty::Struct(name, ref params) => {
+            for (index, param) in params.iter().enumerate() {
+                match *param {
+                    repr::TyParameter::Region(region) => {
+                        let origin = format!("parameter {} of `{}` in {}", index, name, context);
+                        record_origin(origins, region.assert_free(), origin);
+                    }
+                    repr::TyParameter::Ty(ref ty) => record_ty_origins(origins, ty, context),
+                }
+            }
+        }
+        repr::Ty::Bound(_) => panic!("record_ty_origins: unexpected bound type {:?}", ty),
+    }
+}
+
+fn record_origin(
+    origins: &mut HashMap<repr::RegionName, String>,
+    region: repr::RegionName,
+    origin: String,
+) {
+    if region.is_fresh() {
+        origins.insert(region, origin);
+    }
+}
+
 impl fmt::Debug for Point {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(fmt, "{:?}/{}", self.block, self.action)
+        match self.kind {
+            PointKind::Start => write!(fmt, "{:?}/{}", self.block, self.action),
+            PointKind::Mid => write!(fmt, "Mid({:?}/{})", self.block, self.action),
+        }
     }
 }
+
+fn analyses(
+    graph: &FuncGraph,
+    dominators: &Dominators<FuncGraph>,
+    loop_tree: &LoopTree<FuncGraph>,
+) -> AnalysisResults<FuncGraph> {
+    let mut analyses = AnalysisResults::new();
+
+    analyses.insert(
+        IMMEDIATE_DOMINATOR_ANALYSIS,
+        NodeVec::from_fn(graph, |b| dominators.all_immediate_dominators()[b]),
+    );
+
+    let post_dominators = dominators::post_dominators(graph);
+    analyses.insert(
+        IMMEDIATE_POST_DOMINATOR_ANALYSIS,
+        NodeVec::from_fn(graph, |b| {
+            post_dominators.all_immediate_dominators()[b]
+                .filter(|d| d.as_usize() < graph.num_nodes())
+        }),
+    );
+
+    analyses.insert(
+        LOOP_HEAD_ANALYSIS,
+        NodeVec::from_fn(graph, |b| loop_tree.loop_head_of_node(b)),
+    );
+
+    analyses
+}
@@ -1,29 +1,148 @@
-use env::Point;
+use env::{Environment, Point};
+use graph_algorithms::{Graph, NodeIndex};
+use nll_repr::repr::Span;
 use std::collections::HashMap;
 use std::error::Error;
-use std::fmt;
+use std::fmt::{self, Write};
 
 pub struct ErrorReporting {
     reported_errors: Vec<ReportedError>,
     expected_errors: HashMap<Point, String>,
+    labels: HashMap<Point, String>,
+
+    /// The total number of errors expected, if the test says.
+    expected_error_count: Option<usize>,
+
+    /// The error codes that `#[allow(...)]` suppresses at each point.
+    allowed: HashMap<Point, Vec<String>>,
+    suppressed_errors: Vec<ReportedError>,
+
+    /// Conflicts that are not errors, since an unsafe block vouches
+    /// for them; see `report_warning`.
+    warnings: Vec<ReportedError>,
 }
 
 #[derive(Debug)]
 pub struct ReportedError {
-    point: Point,
-    message: String,
+    pub point: Point,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub notes: Vec<Note>,
+
+    /// The labels of the actions at `point` and at the points of the
+    /// notes; filled in by `ErrorReporting::report`.
+    pub labels: HashMap<Point, String>,
+
+    /// The facts of the analysis that the error rests on. A borrow
+    /// error always names its loan; the rest are only filled in with
+    /// `Options::provenance`.
+    pub facts: Vec<Fact>,
+}
+
+/// A fact that an error or the result of an assertion rests on,
+/// identified by an index that is stable for a given program, so that
+/// tools can cross-reference them (see `nll corpus`).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fact {
+    /// The loan with this index in `Environment::loan_sites`.
+    Loan(usize),
+
+    /// The outlives constraint with this index, in the order region
+    /// inference generated them, which carried a point into a region.
+    Constraint(usize),
+
+    /// The liveness bit with this index (see `Liveness::bit_index`),
+    /// which makes a variable or region live at the point in question.
+    Live(usize),
+}
+
+/// Extra information attached to an error, optionally pointing at
+/// some other point in the function (e.g., where a loan was issued).
+#[derive(Debug)]
+pub struct Note {
+    pub point: Option<Point>,
+    pub message: String,
+}
+
+/// The name and text of the `.nll` file being checked, used to
+/// render diagnostics with line numbers and snippets.
+pub struct SourceFile<'a> {
+    pub name: &'a str,
+    pub text: &'a str,
 }
 
 impl ErrorReporting {
     pub fn new() -> Self {
         ErrorReporting {
             expected_errors: HashMap::new(),
+            expected_error_count: None,
             reported_errors: vec![],
+            labels: HashMap::new(),
+            allowed: HashMap::new(),
+            suppressed_errors: vec![],
+            warnings: vec![],
         }
     }
 
     pub fn report_error(&mut self, point: Point, message: String) {
-        self.reported_errors.push(ReportedError { point, message });
+        self.report(ReportedError {
+            point,
+            code: None,
+            message,
+            notes: vec![],
+            labels: HashMap::new(),
+            facts: vec![],
+        });
+    }
+
+    /// Reports `error`, unless its code is allowed at its point, in
+    /// which case it is only recorded as suppressed.
+    pub fn report(&mut self, mut error: ReportedError) {
+        self.attach_labels(&mut error);
+        if self.is_allowed(&error) {
+            self.suppressed_errors.push(error);
+        } else {
+            self.reported_errors.push(error);
+        }
+    }
+
+    /// Reports a warning, which is neither an error nor checked
+    /// against the errors that the test expects; `#[allow(...)]`
+    /// suppresses it all the same.
+    pub fn report_warning(&mut self, mut warning: ReportedError) {
+        self.attach_labels(&mut warning);
+        if self.is_allowed(&warning) {
+            self.suppressed_errors.push(warning);
+        } else {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// Suppresses the errors with the given code at `point`.
+    pub fn allow_error(&mut self, point: Point, code: &str) {
+        self.allowed.entry(point).or_default().push(code.to_string());
+    }
+
+    fn is_allowed(&self, error: &ReportedError) -> bool {
+        match (error.code, self.allowed.get(&error.point)) {
+            (Some(code), Some(codes)) => codes.iter().any(|c| c == code),
+            _ => false,
+        }
+    }
+
+    /// Records the label of the action at `point`, to be echoed by
+    /// any error that refers to it.
+    pub fn label_point(&mut self, point: Point, label: &str) {
+        self.labels.insert(point, label.to_string());
+    }
+
+    fn attach_labels(&self, error: &mut ReportedError) {
+        let points = Some(error.point).into_iter().chain(error.notes.iter().filter_map(|n| n.point));
+        for point in points {
+            if let Some(label) = self.labels.get(&point) {
+                error.labels.insert(point, label.clone());
+            }
+        }
     }
 
     pub fn expect_error(&mut self, point: Point, message: &str) {
@@ -31,24 +150,283 @@ impl ErrorReporting {
         assert!(old_entry.is_none());
     }
 
-    pub fn reconcile_errors(&mut self) -> Result<(), Box<Error>> {
-        while let Some(reported_error) = self.reported_errors.pop() {
+    /// Expects some error at `point`, whatever its message, as declared
+    /// by `assert error at B/3;`. If a `//!` comment already expects an
+    /// error there, it keeps its message.
+    pub fn expect_any_error(&mut self, point: Point) {
+        self.expected_errors.entry(point).or_default();
+    }
+
+    /// Expects exactly `count` errors to be reported in all, as
+    /// declared by `expect N errors;`.
+    pub fn expect_error_count(&mut self, count: usize) {
+        self.expected_error_count = Some(count);
+    }
+
+    /// Sorts the errors, warnings and suppressed errors into the order
+    /// they are emitted in (see `ErrorOrder`), so that the output does
+    /// not depend on the order in which the analyses visit points.
+    pub fn sort(&mut self, env: &Environment) {
+        let order = ErrorOrder::new(env);
+        order.sort(&mut self.reported_errors);
+        order.sort(&mut self.warnings);
+        order.sort(&mut self.suppressed_errors);
+    }
+
+    /// Checks the reported errors against the expected ones. If they
+    /// differ, fails with the mismatch at the earliest point: either
+    /// an error that was not expected (or not as expected), or an
+    /// expected error that was not reported.
+    pub fn reconcile_errors(&mut self, env: &Environment) -> Result<(), Box<Error>> {
+        let order = ErrorOrder::new(env);
+        order.sort(&mut self.reported_errors);
+
+        let reported = self.reported_errors.len();
+        let mut unexpected = None;
+        for reported_error in self.reported_errors.drain(..) {
             if let Some(expected_message) = self.expected_errors.remove(&reported_error.point) {
-                if reported_error.message.contains(&expected_message) {
+                if matches_expected(&reported_error, &expected_message) {
                     continue;
                 }
             }
-            return Err(Box::new(reported_error));
+            unexpected = Some(reported_error);
+            break;
         }
 
-        for &expected_point in self.expected_errors.keys() {
-            return Err(Box::new(ReportedError {
+        // The expected errors left before the unexpected one are
+        // missing: every error reported before it has been matched.
+        let missing = self.expected_errors
+            .keys()
+            .cloned()
+            .min_by_key(|&p| order.key(p, None))
+            .filter(|&p| match unexpected {
+                Some(ref error) => order.key(p, None) < order.key(error.point, error.code),
+                None => true,
+            });
+        if let Some(expected_point) = missing {
+            let mut error = ReportedError {
                 point: expected_point,
-                message: format!("no error reported on this point, but we expected one")
-            }));
+                code: None,
+                message: format!("no error reported on this point, but we expected one"),
+                notes: vec![],
+                labels: HashMap::new(),
+                facts: vec![],
+            };
+            self.attach_labels(&mut error);
+            return Err(Box::new(error));
         }
 
-        Ok(())
+        if let Some(error) = unexpected {
+            return Err(Box::new(error));
+        }
+
+        // Every error matched an expected one; the count guards
+        // against expectations that were dropped from the test.
+        match self.expected_error_count {
+            Some(expected) if expected != reported => {
+                try!(Err(format!("expected {} error{}, but {} {} reported",
+                                 expected,
+                                 if expected == 1 { "" } else { "s" },
+                                 reported,
+                                 if reported == 1 { "was" } else { "were" })))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The reported errors, in the order they were reported (or
+    /// emitted, once sorted).
+    pub fn reported_errors(&self) -> &[ReportedError] {
+        &self.reported_errors
+    }
+
+    /// Like `reported_errors`, but lets the caller attach more to them.
+    pub fn reported_errors_mut(&mut self) -> &mut [ReportedError] {
+        &mut self.reported_errors
+    }
+
+    /// Like `reported_errors`, but takes them.
+    pub fn into_reported_errors(self) -> Vec<ReportedError> {
+        self.reported_errors
+    }
+
+    /// The lines that report the warnings, followed by how many there
+    /// were.
+    pub fn warning_lines(&self) -> Vec<String> {
+        let mut lines: Vec<_> =
+            self.warnings.iter().map(|warning| format!("warning: {}", warning)).collect();
+        match self.warnings.len() {
+            0 => {}
+            1 => lines.push("1 warning".to_string()),
+            n => lines.push(format!("{} warnings", n)),
+        }
+        lines
+    }
+
+    /// The lines that report the errors, without comparing them
+    /// against the expected ones.
+    pub fn error_lines(&self) -> Vec<String> {
+        self.reported_errors.iter().map(|error| error.to_string()).collect()
+    }
+
+    /// Prints the errors that `#[allow(...)]` suppressed, for
+    /// `--show-suppressed`.
+    pub fn dump_suppressed(&self) {
+        println!("{} suppressed errors", self.suppressed_errors.len());
+        for error in &self.suppressed_errors {
+            println!("  {}", error);
+        }
+    }
+
+    /// Renders the reported errors the way rustc's UI tests expect
+    /// them on stderr: an `error[CODE]: message` header, the location
+    /// and source snippet of the offending action, and any notes.
+    pub fn emit_stderr(&self, env: &Environment, source: &SourceFile) -> String {
+        let span_of = |point: Point| env.action_span(point);
+
+        let mut errors: Vec<_> = self.reported_errors.iter().collect();
+        errors.sort_by_key(|e| span_of(e.point).map(|span| span.lo));
+
+        let mut out = String::new();
+        for error in &errors {
+            match error.code {
+                Some(code) => writeln!(out, "error[{}]: {}", code, error.message).unwrap(),
+                None => writeln!(out, "error: {}", error.message).unwrap(),
+            }
+            let gutter = source.gutter_width(
+                Some(error.point)
+                    .into_iter()
+                    .chain(error.notes.iter().filter_map(|n| n.point))
+                    .filter_map(&span_of),
+            );
+            if let Some(span) = span_of(error.point) {
+                source.emit_snippet(&mut out, gutter, span, error.label(error.point));
+            }
+            for note in &error.notes {
+                match note.point.and_then(&span_of) {
+                    Some(span) => {
+                        writeln!(out, "{:1$} |", "", gutter).unwrap();
+                        writeln!(out, "note: {}", note.message).unwrap();
+                        source.emit_snippet(&mut out, gutter, span, error.label(note.point));
+                    }
+                    None => {
+                        writeln!(out, "{:1$} |", "", gutter).unwrap();
+                        writeln!(out, "{:1$} = note: {2}", "", gutter, note.message).unwrap();
+                    }
+                }
+            }
+            writeln!(out, "").unwrap();
+        }
+
+        match errors.len() {
+            0 => {}
+            1 => writeln!(out, "error: aborting due to previous error\n").unwrap(),
+            n => writeln!(out, "error: aborting due to {} previous errors\n", n).unwrap(),
+        }
+
+        out
+    }
+}
+
+/// The order in which errors are emitted and reconciled: by the rank
+/// of their block in reverse post-order, then by action, then by code.
+/// Errors that compare equal stay in the order they were reported.
+struct ErrorOrder {
+    rpo_rank: Vec<usize>,
+}
+
+impl ErrorOrder {
+    fn new(env: &Environment) -> Self {
+        // Unreachable blocks come last.
+        let mut rpo_rank = vec![env.graph.num_nodes(); env.graph.num_nodes()];
+        for (rank, &block) in env.reverse_post_order.iter().enumerate() {
+            rpo_rank[block.as_usize()] = rank;
+        }
+        ErrorOrder { rpo_rank }
+    }
+
+    fn key(&self, point: Point, code: Option<&'static str>)
+           -> (usize, usize, usize, Option<&'static str>) {
+        let block = point.block.as_usize();
+        (self.rpo_rank[block], block, point.index_in_block(), code)
+    }
+
+    fn sort(&self, errors: &mut [ReportedError]) {
+        errors.sort_by_key(|e| self.key(e.point, e.code));
+    }
+}
+
+impl<'a> SourceFile<'a> {
+    /// Returns the 1-based line and column of a byte offset.
+    fn line_col(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = before.matches('\n').count() + 1;
+        (line, before[line_start..].chars().count() + 1)
+    }
+
+    /// Width of the line-number gutter needed to show all the spans.
+    fn gutter_width<I>(&self, spans: I) -> usize
+    where
+        I: Iterator<Item = Span>,
+    {
+        spans
+            .map(|span| self.line_col(span.lo).0.to_string().len())
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Writes the `--> file:line:col` location of the span followed
+    /// by its first line of source, underlined with `^` and the label
+    /// of the action, if any.
+    fn emit_snippet(&self, out: &mut String, gutter: usize, span: Span, label: Option<&str>) {
+        let Span { lo, hi } = span;
+        let (line, col) = self.line_col(lo);
+        let text = self.text.lines().nth(line - 1).unwrap_or("");
+        let line_len = text.chars().count();
+        let width = self.text[lo..hi].chars().count().min(line_len + 1 - col).max(1);
+        writeln!(out, "{:1$}--> {2}:{3}:{4}", "", gutter, self.name, line, col).unwrap();
+        writeln!(out, "{:1$} |", "", gutter).unwrap();
+        writeln!(out, "{:1$} | {2}", line, gutter, text).unwrap();
+        write!(out, "{:1$} | {2:3$}{4}", "", gutter, "", col - 1, "^".repeat(width)).unwrap();
+        match label {
+            Some(label) => writeln!(out, " {}", label).unwrap(),
+            None => writeln!(out).unwrap(),
+        }
+    }
+}
+
+/// An expected error like `[E0499] `foo` is borrowed` matches a
+/// reported error if the reported error has the same code *and*
+/// contains the remaining text. An expected error without a code just
+/// has to appear somewhere in the reported message.
+fn matches_expected(reported: &ReportedError, expected: &str) -> bool {
+    match split_code(expected) {
+        Some((code, rest)) => reported.code == Some(code) && reported.message.contains(rest),
+        None => reported.message.contains(expected),
+    }
+}
+
+/// If `message` starts with an error code like `[E0499]`, returns the
+/// code and the rest of the message.
+fn split_code(message: &str) -> Option<(&str, &str)> {
+    if !message.starts_with("[") {
+        return None;
+    }
+    message.find("]").map(|end| (&message[1..end], message[end + 1..].trim()))
+}
+
+impl ReportedError {
+    fn label<P: Into<Option<Point>>>(&self, point: P) -> Option<&str> {
+        point.into().and_then(|p| self.labels.get(&p)).map(|label| &label[..])
+    }
+
+    /// Writes `point`, followed by its label if it has one.
+    fn fmt_point(&self, f: &mut fmt::Formatter, point: Point) -> Result<(), fmt::Error> {
+        match self.label(point) {
+            Some(label) => write!(f, "{:?} ({:?})", point, label),
+            None => write!(f, "{:?}", point),
+        }
     }
 }
 
@@ -64,6 +442,22 @@ impl Error for ReportedError {
 
 impl fmt::Display for ReportedError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "{:?}: {}", self.point, self.message)
+        self.fmt_point(f, self.point)?;
+        write!(f, ": ")?;
+        if let Some(code) = self.code {
+            write!(f, "[{}] ", code)?;
+        }
+        write!(f, "{}", self.message)?;
+        for note in &self.notes {
+            match note.point {
+                Some(point) => {
+                    write!(f, " (note: {} at ", note.message)?;
+                    self.fmt_point(f, point)?;
+                    write!(f, ")")?;
+                }
+                None => write!(f, " (note: {})", note.message)?,
+            }
+        }
+        Ok(())
     }
 }
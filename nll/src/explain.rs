@@ -0,0 +1,139 @@
+use borrowck::{self, BorrowError};
+use env::{Environment, Point};
+use infer::Provenance;
+use liveness::{BitKind, Liveness};
+use loans_in_scope::LoansInScope;
+use regionck::RegionCheck;
+use std::fmt;
+
+/// The story behind the borrow error reported at one point, as
+/// printed by `--why-error`: the access rule that was broken, where
+/// the conflicting loan was issued, and the chain of constraints that
+/// kept the loan's region alive until the point of the error.
+pub struct ErrorExplanation<'cx> {
+    env: &'cx Environment<'cx>,
+    source_text: &'cx str,
+    pub point: Point,
+    pub error: BorrowError,
+
+    /// Why the loan's region contains `point`.
+    pub provenance: Provenance,
+
+    /// The variables that make `provenance.origin` live at `point`;
+    /// empty if it contains every point regardless.
+    pub origin_live_because: Vec<BitKind>,
+}
+
+pub fn why_error<'cx>(
+    regionck: &RegionCheck<'cx>,
+    liveness: &Liveness,
+    loans_in_scope: &LoansInScope,
+    source_text: &'cx str,
+    point: Point,
+) -> Result<ErrorExplanation<'cx>, String> {
+    let env = regionck.env();
+    let error = match borrowck::check_point(env, loans_in_scope, point) {
+        Some(error) => error,
+        None => return Err(format!("no borrow error at {}", env.describe_point(point))),
+    };
+
+    let provenance = regionck
+        .why_contains(error.loan_region, point)
+        .expect("loan in scope at a point outside its region");
+
+    let mut origin_live_because = vec![];
+    liveness.walk_block(point.block, |p, _action, live_bits| if p == point {
+        origin_live_because = liveness.why_region_live(live_bits, provenance.origin);
+    });
+
+    Ok(ErrorExplanation {
+        env,
+        source_text,
+        point,
+        error,
+        provenance,
+        origin_live_because,
+    })
+}
+
+impl<'cx> ErrorExplanation<'cx> {
+    fn describe_cause(&self, cause: Point) -> String {
+        match self.env.action_span(cause) {
+            Some(span) => {
+                format!("{} (`{}`)",
+                        self.env.describe_point(cause),
+                        &self.source_text[span.lo..span.hi])
+            }
+            None => self.env.describe_point(cause),
+        }
+    }
+}
+
+impl<'cx> fmt::Display for ErrorExplanation<'cx> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let env = self.env;
+        let error = &self.error;
+        writeln!(f, "why error at {}:", env.describe_point(self.point))?;
+        writeln!(f, "  error[{}]: {}", error.kind.code(), error.message)?;
+        writeln!(f, "  rule: {}", error.kind.rule())?;
+        writeln!(f,
+                 "  the loan of `{}` was issued at {}, with region {}",
+                 error.loan_path,
+                 self.describe_cause(error.loan_point),
+                 env.describe_region(error.loan_region))?;
+        if let Some(ref path) = error.live_path {
+            writeln!(f, "  it is still in scope along {}", env.describe_path(path))?;
+        }
+
+        writeln!(f,
+                 "  `{}` includes {:?} because:",
+                 error.loan_region,
+                 self.point)?;
+        for step in &self.provenance.steps {
+            match step.cause {
+                Some(cause) => writeln!(f,
+                                        "    `{}: {}` is required at {:?} by {}",
+                                        step.sup,
+                                        step.sub,
+                                        step.point,
+                                        self.describe_cause(cause))?,
+                None => writeln!(f,
+                                 "    `{}: {}` is required at {:?}",
+                                 step.sup,
+                                 step.sub,
+                                 step.point)?,
+            }
+            writeln!(f,
+                     "      and `{}` includes {}",
+                     step.sub,
+                     env.describe_path(&step.path))?;
+        }
+
+        let origin = self.provenance.origin;
+        if self.origin_live_because.is_empty() {
+            writeln!(f, "    `{}` includes every point", origin)?;
+        }
+        for &bk in &self.origin_live_because {
+            let (var, how) = match bk {
+                BitKind::VariableUsed(var) => (var, "used"),
+                BitKind::VariableDrop(var) => (var, "dropped"),
+                BitKind::LoanRegion(index) => {
+                    writeln!(f,
+                             "    `{}` is live at {:?}, since the reference created at {:?} \
+                              may be used later",
+                             origin,
+                             self.point,
+                             self.env.loan_sites()[index].point)?;
+                    continue;
+                }
+            };
+            writeln!(f,
+                     "    `{}` is live at {:?}, since `{}` may be {} later",
+                     origin,
+                     self.point,
+                     var,
+                     how)?;
+        }
+        Ok(())
+    }
+}
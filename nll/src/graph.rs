@@ -1,21 +1,22 @@
+use env::Point;
 use graph_algorithms as ga;
 use nll_repr::repr;
 use std::collections::BTreeMap;
 use std::cell::RefCell;
+use std::rc::Rc;
 use std::fmt;
 use std::mem;
 use std::iter;
 use std::slice;
+use std::ops::Range;
 
 pub struct FuncGraph {
     func: repr::Func,
     start_block: BasicBlockIndex,
-    blocks: Vec<BasicBlockKind>,
+    blocks: Vec<repr::BasicBlock>,
     successors: Vec<Vec<BasicBlockIndex>>,
     predecessors: Vec<Vec<BasicBlockIndex>>,
     block_indices: BTreeMap<repr::BasicBlock, BasicBlockIndex>,
-    skolemized_end_indices: BTreeMap<repr::RegionName, BasicBlockIndex>,
-    skolemized_end_actions: BTreeMap<repr::RegionName, [repr::Action; 1]>,
 }
 
 #[derive(Copy, Clone, PartialOrd, Ord, PartialEq, Eq, Hash)]
@@ -23,61 +24,15 @@ pub struct BasicBlockIndex {
     index: usize,
 }
 
-#[derive(Copy, Clone, Debug)]
-pub enum BasicBlockKind {
-    Code(repr::BasicBlock),
-    SkolemizedEnd(repr::RegionName),
-}
-
-#[derive(Copy, Clone, Debug)]
-pub enum BasicBlockData<'a> {
-    Code(&'a repr::BasicBlockData),
-    SkolemizedEnd(&'a [repr::Action]),
-}
-
 impl FuncGraph {
     pub fn new(func: repr::Func) -> Self {
-        let blocks: Vec<_> = func.data
-            .keys()
-            .map(|&bb| BasicBlockKind::Code(bb))
-            .chain(
-                func.regions
-                    .iter()
-                    .map(|rd| BasicBlockKind::SkolemizedEnd(rd.name)),
-            )
-            .collect();
+        let blocks: Vec<_> = func.data.keys().cloned().collect();
         let block_indices: BTreeMap<_, _> = func.data
             .keys()
             .cloned()
             .enumerate()
             .map(|(index, block)| (block, BasicBlockIndex { index: index }))
             .collect();
-        let skolemized_end_indices: BTreeMap<_, _> = func.regions
-            .iter()
-            .enumerate()
-            .map(|(index, rd)| {
-                (
-                    rd.name,
-                    BasicBlockIndex {
-                        index: index + block_indices.len(),
-                    },
-                )
-            })
-            .collect();
-        let skolemized_end_actions: BTreeMap<_, _> = func.regions
-            .iter()
-            .map(|rd| {
-                (
-                    rd.name,
-                    [
-                        repr::Action {
-                            kind: repr::ActionKind::SkolemizedEnd(rd.name),
-                            should_have_error: None,
-                        },
-                    ],
-                )
-            })
-            .collect();
         let mut predecessors: Vec<_> = (0..blocks.len()).map(|_| Vec::new()).collect();
         let mut successors: Vec<_> = (0..blocks.len()).map(|_| Vec::new()).collect();
 
@@ -102,8 +57,6 @@ impl FuncGraph {
             predecessors,
             successors,
             block_indices,
-            skolemized_end_indices,
-            skolemized_end_actions,
         }
     }
 
@@ -111,17 +64,12 @@ impl FuncGraph {
         self.block_indices[&name]
     }
 
-    pub fn skolemized_end(&self, name: repr::RegionName) -> BasicBlockIndex {
-        self.skolemized_end_indices[&name]
+    pub fn block_named(&self, name: repr::BasicBlock) -> Option<BasicBlockIndex> {
+        self.block_indices.get(&name).cloned()
     }
 
-    pub fn block_data(&self, index: BasicBlockIndex) -> BasicBlockData {
-        match self.blocks[index.index] {
-            BasicBlockKind::Code(block) => BasicBlockData::Code(&self.func.data[&block]),
-            BasicBlockKind::SkolemizedEnd(r) => BasicBlockData::SkolemizedEnd(
-                &self.skolemized_end_actions[&r],
-            ),
-        }
+    pub fn block_data(&self, index: BasicBlockIndex) -> &repr::BasicBlockData {
+        &self.func.data[&self.blocks[index.index]]
     }
 
     pub fn free_regions(&self) -> &[repr::RegionDecl] {
@@ -139,6 +87,10 @@ impl FuncGraph {
     pub fn struct_decls(&self) -> &[repr::StructDecl] {
         &self.func.structs
     }
+
+    pub fn func(&self) -> &repr::Func {
+        &self.func
+    }
 }
 
 impl ga::Graph for FuncGraph {
@@ -191,44 +143,131 @@ impl Into<usize> for BasicBlockIndex {
     }
 }
 
+/// What the `Debug` and `Display` impls need to know about the graph
+/// being processed (see `with_graph()`).
+#[derive(Default)]
+struct Layout {
+    names: Vec<repr::BasicBlock>,
+
+    /// For each block, the number of actions it contains.
+    num_actions: Vec<usize>,
+
+    /// For each block, its position in reverse post-order; blocks that
+    /// are not reachable come last.
+    rpo_rank: Vec<usize>,
+
+    points: Rc<PointNumbering>,
+}
+
+/// Numbers the points of the graph consecutively, block by block, so
+/// that a set of points can be stored as a bit set.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PointNumbering {
+    /// For each block, the number of its first point; then the total
+    /// number of points.
+    first_point: Vec<usize>,
+}
+
+impl PointNumbering {
+    fn new(num_actions: &[usize]) -> Self {
+        let mut first_point = vec![0];
+        for &actions in num_actions {
+            let next = first_point[first_point.len() - 1] + Point::points_in_block(actions);
+            first_point.push(next);
+        }
+        PointNumbering { first_point }
+    }
+
+    pub fn num_points(&self) -> usize {
+        self.first_point.last().cloned().unwrap_or(0)
+    }
+
+    pub fn index(&self, point: Point) -> usize {
+        self.first_point[point.block.index] + point.index_in_block()
+    }
+
+    /// The numbers of the points of `block`.
+    pub fn block_points(&self, block: BasicBlockIndex) -> Range<usize> {
+        self.first_point[block.index]..self.first_point[block.index + 1]
+    }
+
+    pub fn point(&self, index: usize) -> Point {
+        // Every block has at least one point, so the first points
+        // are strictly increasing.
+        let block = match self.first_point.binary_search(&index) {
+            Ok(block) => block,
+            Err(next) => next - 1,
+        };
+        let index_in_block = index - self.first_point[block];
+        Point::from_index_in_block(BasicBlockIndex { index: block }, index_in_block)
+    }
+}
+
 thread_local! {
-    static NAMES: RefCell<Vec<BasicBlockKind>> = RefCell::new(vec![])
+    static LAYOUT: RefCell<Layout> = RefCell::new(Layout::default())
 }
 
 pub fn with_graph<OP, R>(g: &FuncGraph, op: OP) -> R
 where
     OP: FnOnce() -> R,
 {
-    NAMES.with(|names| {
-        let old_names = mem::replace(&mut *names.borrow_mut(), g.blocks.clone());
+    let num_blocks = g.blocks.len();
+    let mut rpo_rank = vec![num_blocks; num_blocks];
+    for (rank, block) in ga::iterate::reverse_post_order(g, g.start_block).into_iter().enumerate() {
+        rpo_rank[block.index] = rank;
+    }
+    let num_actions: Vec<_> = g.blocks.iter().map(|b| g.func.data[b].actions.len()).collect();
+    let layout = Layout {
+        names: g.blocks.clone(),
+        points: Rc::new(PointNumbering::new(&num_actions)),
+        num_actions,
+        rpo_rank,
+    };
+
+    LAYOUT.with(|cell| {
+        let old_layout = mem::replace(&mut *cell.borrow_mut(), layout);
         let result = op();
-        *names.borrow_mut() = old_names;
+        *cell.borrow_mut() = old_layout;
         result
     })
 }
 
+/// The number of actions in `block`, if called within `with_graph()`.
+pub fn num_actions(block: BasicBlockIndex) -> Option<usize> {
+    LAYOUT.with(|cell| cell.borrow().num_actions.get(block.index).cloned())
+}
+
+/// The numbering of the points of the graph, if called within
+/// `with_graph()`.
+pub fn point_numbering() -> Option<Rc<PointNumbering>> {
+    LAYOUT.with(|cell| {
+        let layout = cell.borrow();
+        if layout.names.is_empty() {
+            None
+        } else {
+            Some(layout.points.clone())
+        }
+    })
+}
+
+/// A key that sorts blocks in reverse post-order within
+/// `with_graph()`, and by index otherwise.
+pub fn rpo_key(block: BasicBlockIndex) -> (usize, usize) {
+    LAYOUT.with(|cell| {
+        let rank = cell.borrow().rpo_rank.get(block.index).cloned().unwrap_or(0);
+        (rank, block.index)
+    })
+}
+
 impl fmt::Debug for BasicBlockIndex {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        NAMES.with(|names| {
-            let names = names.borrow();
+        LAYOUT.with(|cell| {
+            let names = &cell.borrow().names;
             if !names.is_empty() {
-                match names[self.index] {
-                    BasicBlockKind::Code(bb) => write!(fmt, "{}", bb),
-                    BasicBlockKind::SkolemizedEnd(rn) => write!(fmt, "{}", rn),
-                }
+                write!(fmt, "{}", names[self.index])
             } else {
                 write!(fmt, "BB{}", self.index)
             }
         })
     }
 }
-
-impl<'a> BasicBlockData<'a> {
-    pub fn actions(self) -> &'a [repr::Action] {
-        match self {
-            BasicBlockData::Code(d) => &d.actions,
-            BasicBlockData::SkolemizedEnd(actions) => actions,
-        }
-    }
-}
-
@@ -0,0 +1,134 @@
+//! `--implied-bounds`: the outlives relations that the type of a
+//! variable implies. A type is only well-formed if, for each
+//! reference `&'a T` within it, every region in `T` outlives `'a`;
+//! and a struct type is only well-formed if the types of its fields
+//! are. So, like rustc, the analysis may assume `'b: 'a` wherever a
+//! variable of type `&'a Foo<'b>` is live, and throughout the
+//! function if both are free regions.
+
+use env::Environment;
+use nll_repr::repr::{self, RegionName};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// An implied bound `sup: sub`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ImpliedBound {
+    pub sup: RegionName,
+    pub sub: RegionName,
+}
+
+/// The bounds implied by the type of each declared variable.
+pub fn implied_bounds(env: &Environment) -> HashMap<repr::Variable, BTreeSet<ImpliedBound>> {
+    env.graph
+        .decls()
+        .iter()
+        .map(|decl| {
+            let mut wf = WellFormed {
+                env,
+                bounds: BTreeSet::new(),
+                visited: HashSet::new(),
+            };
+            wf.ty(&decl.ty);
+            (decl.var, wf.bounds)
+        })
+        .collect()
+}
+
+/// The bounds that involve only free regions hold for the whole body
+/// of the function: for each free region, the regions that it is
+/// implied to outlive, as if it had been declared to.
+pub fn free_region_bounds(
+    env: &Environment,
+    bounds: &HashMap<repr::Variable, BTreeSet<ImpliedBound>>,
+) -> BTreeMap<RegionName, Vec<RegionName>> {
+    let is_free = |name: RegionName| {
+        name.is_static() || env.graph.free_regions().iter().any(|rd| rd.name == name)
+    };
+    let mut free_bounds: BTreeMap<RegionName, Vec<RegionName>> = BTreeMap::new();
+    for bound in bounds.values().flatten() {
+        if is_free(bound.sup) && is_free(bound.sub) {
+            let subs = free_bounds.entry(bound.sup).or_default();
+            if !subs.contains(&bound.sub) {
+                subs.push(bound.sub);
+            }
+        }
+    }
+    free_bounds
+}
+
+struct WellFormed<'env> {
+    env: &'env Environment<'env>,
+    bounds: BTreeSet<ImpliedBound>,
+
+    /// The struct types whose fields have been visited already, so
+    /// that recursive structs (like a linked list) terminate.
+    visited: HashSet<repr::Ty>,
+}
+
+impl<'env> WellFormed<'env> {
+    fn ty(&mut self, ty: &repr::Ty) {
+        match *ty {
+            repr::Ty::Ref(region, _kind, ref referent) => {
+                let sub = region.assert_free();
+                for sup in referent.walk_regions() {
+                    let sup = sup.assert_free();
+                    if sup != sub {
+                        self.bounds.insert(ImpliedBound { sup, sub });
+                    }
+                }
+                self.ty(referent);
+            }
+            repr::Ty::RawPtr(_kind, ref referent) => self.ty(referent),
+            repr::Ty::Unit | repr::Ty::Int => {}
+            repr::Ty::Struct(name, ref params) => {
+                for param in params {
+                    if let repr::TyParameter::Ty(ref t) = *param {
+                        self.ty(t);
+                    }
+                }
+                // The fields of a struct that is not declared, or whose
+                // parameters do not match its declaration, cannot be
+                // checked; nothing else needs them, either.
+                let decl = match self.env.struct_map.get(&name) {
+                    Some(decl) if decl.fields.iter().all(|f| fits(&f.ty, params)) => decl,
+                    _ => return,
+                };
+                if self.visited.insert(ty.clone()) {
+                    for field in &decl.fields {
+                        self.ty(&field.ty.subst(params));
+                    }
+                }
+            }
+            repr::Ty::Bound(_) => panic!("encountered bound type when checking well-formedness"),
+        }
+    }
+}
+
+/// True if `ty`, from the declaration of a struct, can be substituted
+/// with `params`: each bound type or region it names is a parameter
+/// of that kind.
+fn fits(ty: &repr::Ty, params: &[repr::TyParameter]) -> bool {
+    let kind = |b: usize| {
+        if b >= params.len() {
+            return None;
+        }
+        match params[params.len() - 1 - b] {
+            repr::TyParameter::Region(_) => Some(repr::Kind::Region),
+            repr::TyParameter::Ty(_) => Some(repr::Kind::Type),
+        }
+    };
+    let region_fits = |region: repr::Region| match region {
+        repr::Region::Free(_) => true,
+        repr::Region::Bound(b) => kind(b) == Some(repr::Kind::Region),
+    };
+    match *ty {
+        repr::Ty::Ref(region, _kind, ref referent) => region_fits(region) && fits(referent, params),
+        repr::Ty::RawPtr(_kind, ref referent) => fits(referent, params),
+        repr::Ty::Unit | repr::Ty::Int => true,
+        repr::Ty::Struct(_, ref ty_params) => ty_params.iter().all(|p| match *p {
+            repr::TyParameter::Region(region) => region_fits(region),
+            repr::TyParameter::Ty(ref t) => fits(t, params),
+        }),
+        repr::Ty::Bound(b) => kind(b) == Some(repr::Kind::Type),
+    }
+}
@@ -1,8 +1,17 @@
 use env::{Environment, Point};
+use graph::BasicBlockIndex;
+use graph_algorithms::scc;
+use graph_algorithms::vec_graph::VecGraph;
 use nll_repr::repr;
-use region::Region;
-use std::collections::HashSet;
+use options::{ConstraintOrder, Options};
+use region::{PlaceholderIndex, Region, RegionElement};
+use std::cmp::{self, Reverse};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::mem;
+use std::time::{Duration, Instant};
+use monotone;
+use timeout::{self, TimedOut};
+use universe::{Universe, Universes};
 
 pub struct InferenceContext {
     /// for each region variable, sets of points where live data in
@@ -10,24 +19,100 @@ pub struct InferenceContext {
     definitions: Vec<VarDefinition>,
     constraints: Vec<Constraint>,
 
+    /// One entry for each placeholder created by `add_placeholder()`,
+    /// indexed by `PlaceholderIndex`.
+    placeholders: Vec<PlaceholderDefinition>,
+
+    /// The universes created so far (see `new_universe()`).
+    universes: Universes,
+
+    /// Runs of points where some region is live; these are added to
+    /// the region values, a block at a time, at the start of
+    /// `solve()`.
+    live_runs: Vec<LiveRun>,
+
+    /// With `--self-check`, the live points that the live runs stand
+    /// for, one at a time; see `expect_live_points()`.
+    expected_live_points: Vec<(RegionVariable, Point)>,
+
     /// `solve()`, `add_live_point()` and other such routines can grow
     /// this vector. It is returned by the call to `solve()`.
     errors: Vec<InferenceError>,
+
+    /// Capped variables that have already been reported as exceeding
+    /// their cap. A capped variable never actually grows past its
+    /// cap; it is pinned there, so that the error does not cascade
+    /// into the variables downstream of it, and later violations
+    /// (which have the same root cause) are not reported again.
+    exceeded_caps: HashSet<RegionVariable>,
+
+    /// The action responsible for the constraints currently being
+    /// added; see `set_cause()`.
+    cause: Option<Point>,
+
+    /// With `--hot-constraints`, the work done by `solve()` for each
+    /// constraint, most expensive first.
+    hot_constraints: Vec<HotConstraint>,
+
+    /// With `--why-error` (or `Options::provenance`), the value of
+    /// each variable before `solve()`
+    /// propagated the constraints; see `why_contains()`.
+    initial_values: Vec<Region>,
+
+    /// The work done by the most recent call to `solve()`.
+    solve_stats: SolveStats,
 }
 
 /// Inference errors occur when the constraints would force us to
-/// grow a "locked region".
+/// grow a "locked region", or when a placeholder escapes into a
+/// region that cannot name it.
 pub struct InferenceError {
     /// Due to a constraint at this point...
     pub constraint_point: Point,
 
-    /// ...this capped region exceeded its cap.
+    /// ...this region...
     pub name: repr::RegionName,
+
+    /// ...went wrong in this way.
+    pub kind: InferenceErrorKind,
+}
+
+pub enum InferenceErrorKind {
+    /// The region is capped, and it exceeded its cap.
+    Capped,
+
+    /// The region is a free region, and inference found that it must
+    /// outlive `sub` (a free region or `'static`), although that
+    /// does not follow from its declaration.
+    MustOutlive { sub: repr::RegionName },
+
+    /// The region absorbed the placeholder for the region
+    /// `placeholder`, of the universe `placeholder_universe`, which it
+    /// cannot name: it is either a capped region (a free region, or
+    /// another placeholder), or it belongs to `universe`, which is not
+    /// nested in the placeholder's. The `chain` is the sequence of
+    /// outlives constraints that carried the placeholder from its own
+    /// region variable into `name`, given as `(sup, sub, point)`
+    /// triples.
+    PlaceholderLeak {
+        placeholder: repr::RegionName,
+        placeholder_universe: Universe,
+        universe: Universe,
+        capped: bool,
+        chain: Vec<(repr::RegionName, repr::RegionName, Point)>,
+    },
+}
+
+struct PlaceholderDefinition {
+    /// The region variable whose value *is* this placeholder.
+    var: RegionVariable,
+    universe: Universe,
 }
 
 /// For each inference variable that has been allocated, we have one
 /// of these structures. Inference variables are "named" by their
 /// index in the main vector, using an instance of `RegionVariable`.
+#[derive(Clone)]
 struct VarDefinition {
     name: repr::RegionName,
 
@@ -40,18 +125,101 @@ struct VarDefinition {
     /// a result of inference. If they *do* wind up growing, we will
     /// report an error.
     capped: bool,
+
+    /// For a free region, its value as declared; see
+    /// `declare_universal_var()`.
+    declared: Option<Region>,
+
+    /// The universe of placeholders that this variable may name.
+    universe: Universe,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct RegionVariable {
     index: usize,
 }
 
+/// Says that a region variable includes `entry`, along with the
+/// `len - 1` points that follow it along straight-line code (see
+/// `Environment::straight_line_successor`). Membership of all but the
+/// entry is thus implied by the shape of the control-flow graph.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+struct LiveRun {
+    var: RegionVariable,
+    entry: Point,
+    len: usize,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Constraint {
     sub: RegionVariable,
     sup: RegionVariable,
     point: Point,
+
+    /// The action that gave rise to this constraint, if known.
+    cause: Option<Point>,
+}
+
+/// Why a region variable contains some point (see `why_contains()`).
+pub struct Provenance {
+    /// The variable that contained the point to begin with: either
+    /// it is live there, or it contains every point (as do free
+    /// regions, `'static` and placeholders).
+    pub origin: repr::RegionName,
+
+    /// The constraints that carried the point from `origin` into the
+    /// variable, starting with the one that added it to the variable
+    /// itself.
+    pub steps: Vec<ProvenanceStep>,
+}
+
+/// The constraint `sup: sub @ point`, which added the point in
+/// question to `sup`, since `sub` contains it and it can be reached
+/// from `point` along `path` without leaving `sub`. `constraint` is
+/// its index, in the order the constraints were added.
+pub struct ProvenanceStep {
+    pub constraint: usize,
+    pub sup: repr::RegionName,
+    pub sub: repr::RegionName,
+    pub point: Point,
+    pub cause: Option<Point>,
+    pub path: Vec<Point>,
+}
+
+/// How much work `propagate()` did for one constraint.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ConstraintStats {
+    /// The number of times the constraint was applied.
+    pub applied: usize,
+
+    /// The number of times applying it grew the `sup` region.
+    pub fired: usize,
+
+    /// The number of points visited by the DFS, over all applications.
+    pub points_visited: usize,
+
+    /// The number of elements added to the `sup` region.
+    pub elements_added: usize,
+}
+
+/// How much work `solve()` did overall, as reported by
+/// `--solver-stats` and `nll bench-compare`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SolveStats {
+    pub constraints: usize,
+    pub iterations: usize,
+
+    /// The time spent propagating the constraints.
+    pub time: Duration,
+}
+
+/// A constraint `sup: sub @ point`, along with the work it caused.
+pub struct HotConstraint {
+    pub sup: repr::RegionName,
+    pub sub: repr::RegionName,
+    pub point: Point,
+    pub cause: Option<Point>,
+    pub stats: ConstraintStats,
 }
 
 impl InferenceContext {
@@ -59,85 +227,631 @@ impl InferenceContext {
         InferenceContext {
             definitions: vec![],
             constraints: vec![],
+            placeholders: vec![],
+            universes: Universes::new(),
+            live_runs: vec![],
+            expected_live_points: vec![],
             errors: vec![],
+            exceeded_caps: HashSet::new(),
+            cause: None,
+            hot_constraints: vec![],
+            initial_values: vec![],
+            solve_stats: SolveStats::default(),
         }
     }
 
     pub fn add_var(&mut self, name: repr::RegionName) -> RegionVariable {
+        self.add_var_in_universe(name, Universe::root())
+    }
+
+    pub fn add_var_in_universe(
+        &mut self,
+        name: repr::RegionName,
+        universe: Universe,
+    ) -> RegionVariable {
         let index = self.definitions.len();
         self.definitions.push(VarDefinition {
             name,
             value: Region::new(),
             capped: false,
+            declared: None,
+            universe,
         });
         RegionVariable { index }
     }
 
+    /// Creates a universe nested in `parent`; see `universe.rs`.
+    pub fn new_universe(&mut self, parent: Universe) -> Universe {
+        self.universes.new_child(parent)
+    }
+
+    /// Creates a region variable, in `universe`, whose value includes
+    /// a fresh placeholder element. Like a free region, the caller is
+    /// expected to add the points of the function and then cap it.
+    pub fn add_placeholder(
+        &mut self,
+        name: repr::RegionName,
+        universe: Universe,
+    ) -> RegionVariable {
+        let var = self.add_var_in_universe(name, universe);
+        let placeholder = PlaceholderIndex { index: self.placeholders.len() };
+        self.placeholders.push(PlaceholderDefinition { var, universe });
+        self.add_universal_element(var, RegionElement::Placeholder(placeholder));
+        var
+    }
+
     pub fn cap_var(&mut self, v: RegionVariable) {
         self.definitions[v.index].capped = true;
     }
 
+    /// Records the current value of `v`, a free region whose points
+    /// and universal elements have been added, as its declared value.
+    /// Unlike a capped variable, `v` may grow during inference;
+    /// `solve()` then reports each universal element it gained.
+    pub fn declare_universal_var(&mut self, v: RegionVariable) {
+        let definition = &mut self.definitions[v.index];
+        definition.declared = Some(definition.value.clone());
+    }
+
     pub fn add_live_point(&mut self, v: RegionVariable, point: Point) {
         log!("add_live_point({:?}, {:?})", v, point);
         let definition = &mut self.definitions[v.index];
-        if definition.value.add_point(point) {
-            if definition.capped {
-                self.errors.push(InferenceError {
-                    constraint_point: point,
-                    name: definition.name,
-                });
+        if !definition.capped {
+            definition.value.add_point(point);
+        } else if !definition.value.may_contain(point) && self.exceeded_caps.insert(v) {
+            self.errors.push(InferenceError {
+                constraint_point: point,
+                name: definition.name,
+                kind: InferenceErrorKind::Capped,
+            });
+        }
+    }
+
+    /// Like `add_live_point`, for the points of `block` from `first`
+    /// to `last`, inclusive, by `Point::index_in_block`; they are
+    /// added to the value of `v` as a single run.
+    fn add_live_points(&mut self, v: RegionVariable, block: BasicBlockIndex, first: usize,
+                       last: usize) {
+        log!("add_live_points({:?}, {:?}, {}..={})", v, block, first, last);
+        if !self.definitions[v.index].capped {
+            self.definitions[v.index].value.add_run(block, first, last);
+        } else {
+            for index in first..last + 1 {
+                self.add_live_point(v, Point::from_index_in_block(block, index));
             }
         }
     }
 
+    /// Adds an element that lies outside the function body, like
+    /// `EndOfFn('a)`, to `v`. This is only used to set up the values
+    /// of free regions, before they are capped.
+    pub fn add_universal_element(&mut self, v: RegionVariable, element: RegionElement) {
+        log!("add_universal_element({:?}, {:?})", v, element);
+        if let RegionElement::Location(point) = element {
+            debug_assert!(false, "add_universal_element: {:?} is a point of the function body", point);
+        }
+        let definition = &mut self.definitions[v.index];
+        assert!(!definition.capped);
+        definition.value.add_element(element);
+    }
+
+    pub fn add_live_run(&mut self, var: RegionVariable, entry: Point, len: usize) {
+        log!("add_live_run({:?}, {:?}, {:?})", var, entry, len);
+        self.live_runs.push(LiveRun { var, entry, len });
+    }
+
+    /// Records that the live runs of `var` cover exactly `points`.
+    /// In self-check mode, `solve()` also solves with one live point
+    /// for each of them, and checks that it finds the same values.
+    pub fn expect_live_points<'a, I>(&mut self, var: RegionVariable, points: I)
+        where I: IntoIterator<Item = &'a Point>
+    {
+        self.expected_live_points.extend(points.into_iter().map(|&point| (var, point)));
+    }
+
+    /// Records that the constraints added from now on are due to the
+    /// action at `cause`; this is used only for reporting.
+    pub fn set_cause(&mut self, cause: Option<Point>) {
+        self.cause = cause;
+    }
+
+    /// The action set by `set_cause()`, if any.
+    pub fn cause(&self) -> Option<Point> {
+        self.cause
+    }
+
     pub fn add_outlives(&mut self, sup: RegionVariable, sub: RegionVariable, point: Point) {
         log!("add_outlives({:?}: {:?} @ {:?})", sup, sub, point);
-        self.constraints.push(Constraint { sup, sub, point });
+        let cause = self.cause;
+        self.constraints.push(Constraint { sup, sub, point, cause });
     }
 
     pub fn region(&self, v: RegionVariable) -> &Region {
         &self.definitions[v.index].value
     }
 
-    pub fn solve(&mut self, env: &Environment) -> Vec<InferenceError> {
+    pub fn num_vars(&self) -> usize {
+        self.definitions.len()
+    }
+
+    /// The number of bytes taken up by the values of the variables,
+    /// including the copies kept for `--why-error`.
+    pub fn heap_size(&self) -> usize {
+        self.definitions
+            .iter()
+            .map(|d| &d.value)
+            .chain(&self.initial_values)
+            .map(|value| value.heap_size())
+            .sum()
+    }
+
+    pub fn solve(
+        &mut self,
+        env: &Environment,
+        options: &Options,
+    ) -> Result<Vec<InferenceError>, TimedOut> {
+        // In self-check mode, keep the values from before the live
+        // runs are added, to solve again with the live points that
+        // they stand for.
+        let unexpanded_definitions = if options.self_check {
+            Some(self.definitions.clone())
+        } else {
+            None
+        };
+
+        for run in mem::replace(&mut self.live_runs, vec![]) {
+            let mut entry = Some(run.entry);
+            let mut len = run.len;
+            while len > 0 {
+                let p = entry.expect("live run extends past straight-line code");
+                let points = Point::points_in_block(env.end_point(p.block).action);
+                let first = p.index_in_block();
+                let last = cmp::min(first + len, points) - 1;
+                self.add_live_points(run.var, p.block, first, last);
+                len -= last + 1 - first;
+                entry = env.straight_line_successor(Point::from_index_in_block(p.block, last));
+            }
+        }
+
+        if options.why_error.is_some() || options.provenance {
+            self.initial_values = self.definitions.iter().map(|d| d.value.clone()).collect();
+        }
+
+        // The result should not depend on the order in which we
+        // process the constraints, only the number of iterations. In
+        // self-check mode, verify that by also solving in program
+        // order and comparing.
+        let initial_definitions = if options.self_check {
+            Some(self.definitions.clone())
+        } else {
+            None
+        };
+
+        let constraints = self.ordered_constraints(env, options.constraint_order);
+        let mut stats = vec![ConstraintStats::default(); constraints.len()];
+        let start = Instant::now();
+        let iterations = self.propagate(env, &constraints, &mut stats)?;
+        self.solve_stats = SolveStats {
+            constraints: constraints.len(),
+            iterations,
+            time: start.elapsed(),
+        };
+        if options.solver_stats {
+            println!(
+                "solve: {} constraints, {} iterations (order: {})",
+                constraints.len(),
+                iterations,
+                options.constraint_order
+            );
+        }
+
+        if let Some(definitions) = initial_definitions {
+            let mut reference = InferenceContext::new();
+            reference.definitions = definitions;
+            let constraints = self.constraints.clone();
+            let mut stats = vec![ConstraintStats::default(); constraints.len()];
+            reference.propagate(env, &constraints, &mut stats)?;
+            self.assert_same_values(&reference, "depends on the constraint order");
+        }
+
+        if let Some(definitions) = unexpanded_definitions {
+            let mut reference = InferenceContext::new();
+            reference.definitions = definitions;
+            for &(var, point) in &self.expected_live_points {
+                reference.add_live_point(var, point);
+            }
+            let constraints = self.constraints.clone();
+            let mut stats = vec![ConstraintStats::default(); constraints.len()];
+            reference.propagate(env, &constraints, &mut stats)?;
+            self.assert_same_values(&reference, "differs with one live point at a time");
+        }
+
+        if options.hot_constraints {
+            let mut hot_constraints: Vec<_> = constraints
+                .iter()
+                .zip(stats)
+                .map(|(c, stats)| HotConstraint {
+                    sup: self.definitions[c.sup.index].name,
+                    sub: self.definitions[c.sub.index].name,
+                    point: c.point,
+                    cause: c.cause,
+                    stats,
+                })
+                .collect();
+            hot_constraints.sort_by_key(|h| {
+                (Reverse(h.stats.points_visited), Reverse(h.stats.elements_added))
+            });
+            self.hot_constraints = hot_constraints;
+        }
+
+        self.check_placeholder_leaks();
+        self.check_universal_vars();
+
+        Ok(mem::replace(&mut self.errors, vec![]))
+    }
+
+    /// Asserts that each variable has the same value in `reference`,
+    /// which was solved from the same constraints another way.
+    fn assert_same_values(&self, reference: &InferenceContext, differs: &str) {
+        for (index, (def, ref_def)) in
+            self.definitions.iter().zip(&reference.definitions).enumerate()
+        {
+            assert_eq!(
+                def.value,
+                ref_def.value,
+                "value of {:?} (`{}`) {}",
+                RegionVariable { index },
+                def.name,
+                differs
+            );
+        }
+    }
+
+    pub fn solve_stats(&self) -> SolveStats {
+        self.solve_stats
+    }
+
+    /// Explains why the solved value of `var` contains `point`, as the
+    /// shortest chain of constraints that carried the point into it.
+    /// Returns `None` if `var` does not contain `point`. Must be called
+    /// after `solve()`, with `--why-error` or `Options::provenance` set.
+    pub fn why_contains(
+        &self,
+        env: &Environment,
+        var: RegionVariable,
+        point: Point,
+    ) -> Option<Provenance> {
+        assert!(!self.initial_values.is_empty(), "why_contains() called without --why-error");
+        let element = RegionElement::Location(point);
+
+        // Breadth-first search, starting from the variables that
+        // contained `point` to begin with, and following constraints
+        // `sup: sub` from `sub` to `sup`.
+        let mut via: HashMap<RegionVariable, (usize, &Constraint, Vec<Point>)> = HashMap::new();
+        let mut queue: VecDeque<_> = self.initial_values
+            .iter()
+            .enumerate()
+            .filter(|&(_, value)| value.contains(element))
+            .map(|(index, _)| RegionVariable { index })
+            .collect();
+        let origins: HashSet<_> = queue.iter().cloned().collect();
+        while let Some(v) = queue.pop_front() {
+            if v == var {
+                break;
+            }
+            let sub = &self.definitions[v.index].value;
+            for (index, constraint) in self.constraints.iter().enumerate() {
+                if constraint.sub != v || origins.contains(&constraint.sup) ||
+                    via.contains_key(&constraint.sup) ||
+                    !self.definitions[constraint.sup.index].value.contains(element)
+                {
+                    continue;
+                }
+                let within = |p| sub.may_contain(p);
+                if let Some(path) = env.shortest_path(constraint.point, point, within) {
+                    via.insert(constraint.sup, (index, constraint, path));
+                    queue.push_back(constraint.sup);
+                }
+            }
+        }
+
+        let mut steps = vec![];
+        let mut v = var;
+        while let Some(&(index, constraint, ref path)) = via.get(&v) {
+            steps.push(ProvenanceStep {
+                constraint: index,
+                sup: self.definitions[constraint.sup.index].name,
+                sub: self.definitions[constraint.sub.index].name,
+                point: constraint.point,
+                cause: constraint.cause,
+                path: path.clone(),
+            });
+            v = constraint.sub;
+        }
+        if !origins.contains(&v) {
+            return None;
+        }
+        Some(Provenance { origin: self.definitions[v.index].name, steps })
+    }
+
+    /// With `--hot-constraints`, every constraint along with the work
+    /// that `solve()` did for it, most expensive first.
+    pub fn hot_constraints(&self) -> &[HotConstraint] {
+        &self.hot_constraints
+    }
+
+    /// Returns the constraints in the order in which `propagate()`
+    /// should visit them.
+    fn ordered_constraints(&self, env: &Environment, order: ConstraintOrder) -> Vec<Constraint> {
+        let mut constraints = self.constraints.clone();
+        match order {
+            ConstraintOrder::Program => {}
+
+            ConstraintOrder::Rpo => {
+                let rpo_index: HashMap<_, _> = env.reverse_post_order
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &block)| (block, index))
+                    .collect();
+                constraints.sort_by_key(|c| {
+                    (rpo_index[&c.point.block], c.point.action, c.point.kind)
+                });
+            }
+
+            ConstraintOrder::Random(seed) => {
+                // Fisher-Yates, driven by a xorshift generator (which
+                // must not be seeded with zero).
+                let mut state = seed | 1;
+                for i in (1..constraints.len()).rev() {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    constraints.swap(i, (state % (i as u64 + 1)) as usize);
+                }
+            }
+
+            ConstraintOrder::Scc => {
+                // Visit the constraints in topological order of the
+                // strongly connected components of the constraint
+                // graph, where `sup: sub` is an edge from `sub` to
+                // `sup` (that being the direction in which values
+                // flow). Within an SCC, program order is preserved.
+                let scc_rank = self.scc_ranks();
+                constraints.sort_by_key(|c| scc_rank[c.sub.index]);
+            }
+        }
+        constraints
+    }
+
+    /// Computes the strongly connected components of the constraint
+    /// graph, and returns, for each variable, the position of its SCC
+    /// in topological order.
+    fn scc_ranks(&self) -> Vec<usize> {
+        // Every variable is a node: `VecGraph` has the nodes up to its
+        // start node, whatever edges there are.
+        let num_vars = self.definitions.len();
+        let edges: Vec<_> = self.constraints.iter().map(|c| (c.sub.index, c.sup.index)).collect();
+        let graph = VecGraph::new(num_vars.saturating_sub(1), &edges);
+        let sccs = scc::sccs(&graph);
+
+        // The SCCs are numbered in reverse topological order.
+        let num_sccs = sccs.num_sccs();
+        (0..num_vars).map(|v| num_sccs - 1 - usize::from(sccs.scc(v))).collect()
+    }
+
+    /// Applies `constraints` repeatedly, until a fixed point is
+    /// reached, accumulating the work done for each constraint into
+    /// the corresponding entry of `stats`. Returns the number of
+    /// passes this took.
+    fn propagate(
+        &mut self,
+        env: &Environment,
+        constraints: &[Constraint],
+        stats: &mut [ConstraintStats],
+    ) -> Result<usize, TimedOut> {
+        let mut iterations = 0;
         let mut changed = true;
         let mut dfs = Dfs::new(env);
         while changed {
+            timeout::round()?;
             changed = false;
-            for constraint in &self.constraints {
+            iterations += 1;
+            let previous: Vec<Region> = if cfg!(debug_assertions) {
+                self.definitions.iter().map(|def| def.value.clone()).collect()
+            } else {
+                vec![]
+            };
+            for (constraint, stats) in constraints.iter().zip(stats.iter_mut()) {
                 let sub = &self.definitions[constraint.sub.index].value.clone();
                 let sup_def = &mut self.definitions[constraint.sup.index];
                 log!("constraint: {:?}", constraint);
-                log!("    sub (before): {:?}", sub);
-                log!("    sup (before): {:?}", sup_def.value);
-
-                if dfs.copy(sub, &mut sup_def.value, constraint.point) {
-                    changed = true;
-
-                    if sup_def.capped {
-                        // This is kind of a hack, but when we add a
-                        // constraint, the "point" is always the point
-                        // AFTER the action that induced the
-                        // constraint. So report the error on the
-                        // action BEFORE that.
-                        assert!(constraint.point.action > 0);
-                        let p = Point { block: constraint.point.block,
-                                        action: constraint.point.action - 1 };
+                log!("    sub (before): {}", sub);
+                log!("    sup (before): {}", sup_def.value);
+
+                stats.applied += 1;
+                if !sup_def.capped {
+                    let grew = dfs.copy(sub, &mut sup_def.value, constraint.point);
+                    if grew {
+                        stats.fired += 1;
+                        stats.elements_added += dfs.added.len();
+                    }
+                    stats.points_visited += dfs.visited.len();
+                    changed |= grew;
+                } else {
+                    // A capped variable is pinned at its cap: compute
+                    // what it *would* grow to, but only keep the
+                    // placeholders, which are reported (if need be) by
+                    // `check_placeholder_leaks()` instead.
+                    let mut grown = sup_def.value.clone();
+                    dfs.copy(sub, &mut grown, constraint.point);
+                    stats.points_visited += dfs.visited.len();
+                    let mut exceeded_cap = false;
+                    let mut grew = false;
+                    for &element in &dfs.added {
+                        match element {
+                            RegionElement::Placeholder(_) => {
+                                if sup_def.value.add_element(element) {
+                                    stats.elements_added += 1;
+                                    grew = true;
+                                }
+                            }
+                            _ => exceeded_cap = true,
+                        }
+                    }
+                    if grew {
+                        stats.fired += 1;
+                    }
+                    changed |= grew;
 
+                    if exceeded_cap && self.exceeded_caps.insert(constraint.sup) {
+                        // The constraints that an action induces hold
+                        // at its mid-point; report the error on the
+                        // action itself.
                         self.errors.push(InferenceError {
-                            constraint_point: p,
+                            constraint_point: constraint.point.action_start(),
                             name: sup_def.name,
+                            kind: InferenceErrorKind::Capped,
                         });
                     }
                 }
 
-                log!("    sup (after) : {:?}", sup_def.value);
+                log!("    sup (after) : {}", sup_def.value);
                 log!("    changed     : {:?}", changed);
             }
+            for (def, before) in self.definitions.iter().zip(&previous) {
+                monotone::check_region(&def.name.to_string(), before, &def.value);
+            }
             log!("\n");
         }
+        Ok(iterations)
+    }
+
+    /// Reports an error for each end of a free region (or `'static`)
+    /// that wound up in the value of a free region whose declaration
+    /// does not include it. If `'a` is declared to outlive `'b`, and
+    /// `'b` is reported as having to outlive `'c`, then so must `'a`;
+    /// that is not reported again.
+    fn check_universal_vars(&mut self) {
+        let universal_var = |name: repr::RegionName| {
+            self.definitions
+                .iter()
+                .find(|d| d.name == name && d.declared.is_some())
+        };
+
+        let mut errors = vec![];
+        for (index, definition) in self.definitions.iter().enumerate() {
+            let declared = match definition.declared {
+                Some(ref declared) => declared,
+                None => continue,
+            };
+            for element in definition.value.universal_elements() {
+                let sub = match element {
+                    RegionElement::EndOfFn(name) => name,
+                    RegionElement::Static => repr::RegionName::from("'static"),
+                    RegionElement::Placeholder(_) => continue,
+                    RegionElement::Location(_) => unreachable!(),
+                };
+                if declared.contains(element) {
+                    continue;
+                }
+
+                let implied = declared.universal_elements().any(|e| match e {
+                    RegionElement::EndOfFn(name) if name != definition.name => {
+                        match universal_var(name) {
+                            Some(d) => d.value.contains(element),
+                            None => false,
+                        }
+                    }
+                    _ => false,
+                });
+                if implied {
+                    continue;
+                }
+
+                // Blame the first constraint that carried the element
+                // into the region.
+                let var = RegionVariable { index };
+                let constraint = self.constraints
+                    .iter()
+                    .find(|c| {
+                        c.sup == var && self.definitions[c.sub.index].value.contains(element)
+                    })
+                    .expect("universal element added without a constraint");
+                errors.push(InferenceError {
+                    constraint_point: constraint.cause.unwrap_or(constraint.point),
+                    name: definition.name,
+                    kind: InferenceErrorKind::MustOutlive { sub },
+                });
+            }
+        }
+        self.errors.extend(errors);
+    }
+
+    /// Reports an error for each placeholder that wound up in the
+    /// value of a region variable that cannot name it. Only the
+    /// variable closest to the placeholder (in terms of outlives
+    /// constraints) is reported, since everything downstream of it
+    /// is tainted as well.
+    fn check_placeholder_leaks(&mut self) {
+        for (index, placeholder) in self.placeholders.iter().enumerate() {
+            let element = RegionElement::Placeholder(PlaceholderIndex { index });
+            let can_name = |var: RegionVariable| {
+                let definition = &self.definitions[var.index];
+                var == placeholder.var ||
+                    (!definition.capped &&
+                         self.universes.can_name(definition.universe, placeholder.universe))
+            };
+
+            // Breadth-first search, starting from the placeholder's
+            // own variable and following constraints `sup: sub` from
+            // `sub` to `sup`, through variables that contain the
+            // placeholder.
+            let mut via: HashMap<RegionVariable, &Constraint> = HashMap::new();
+            let mut queue = VecDeque::new();
+            queue.push_back(placeholder.var);
+            let mut leaked_into = None;
+            while let Some(var) = queue.pop_front() {
+                if !can_name(var) {
+                    leaked_into = Some(var);
+                    break;
+                }
+                for constraint in &self.constraints {
+                    if constraint.sub == var && constraint.sup != placeholder.var &&
+                        !via.contains_key(&constraint.sup) &&
+                        self.definitions[constraint.sup.index].value.contains(element)
+                    {
+                        via.insert(constraint.sup, constraint);
+                        queue.push_back(constraint.sup);
+                    }
+                }
+            }
 
-        mem::replace(&mut self.errors, vec![])
+            if let Some(var) = leaked_into {
+                let mut chain = vec![];
+                let mut v = var;
+                while let Some(constraint) = via.get(&v) {
+                    chain.push((
+                        self.definitions[constraint.sup.index].name,
+                        self.definitions[constraint.sub.index].name,
+                        constraint.point,
+                    ));
+                    v = constraint.sub;
+                }
+                chain.reverse();
+                let definition = &self.definitions[var.index];
+                self.errors.push(InferenceError {
+                    constraint_point: chain[0].2,
+                    name: definition.name,
+                    kind: InferenceErrorKind::PlaceholderLeak {
+                        placeholder: self.definitions[placeholder.var.index].name,
+                        placeholder_universe: placeholder.universe,
+                        universe: definition.universe,
+                        capped: definition.capped,
+                        chain,
+                    },
+                });
+            }
+        }
     }
 }
 
@@ -145,6 +859,9 @@ struct Dfs<'env> {
     stack: Vec<Point>,
     visited: HashSet<Point>,
     env: &'env Environment<'env>,
+
+    /// The elements added to the target region by the last `copy()`.
+    added: Vec<RegionElement>,
 }
 
 impl<'env> Dfs<'env> {
@@ -153,6 +870,7 @@ impl<'env> Dfs<'env> {
             stack: vec![],
             visited: HashSet::new(),
             env,
+            added: vec![],
         }
     }
 
@@ -161,6 +879,7 @@ impl<'env> Dfs<'env> {
 
         self.stack.clear();
         self.visited.clear();
+        self.added.clear();
 
         self.stack.push(start_point);
         while let Some(p) = self.stack.pop() {
@@ -176,17 +895,21 @@ impl<'env> Dfs<'env> {
                 continue;
             }
 
-            changed |= to_region.add_point(p);
+            if to_region.add_point(p) {
+                self.added.push(RegionElement::Location(p));
+                changed = true;
+            }
 
             let successor_points = self.env.successor_points(p);
             if successor_points.is_empty() {
-                // If we reach the END point in the graph, then copy
-                // over any skolemized end points in the `from_region`
-                // and make sure they are included in the `to_region`.
-                for region_decl in self.env.graph.free_regions() {
-                    let block = self.env.graph.skolemized_end(region_decl.name);
-                    let skolemized_end_point = Point { block, action: 0 };
-                    changed |= to_region.add_point(skolemized_end_point);
+                // If we reach the END point in the graph, then the
+                // from-region flows out of the function: copy over
+                // its `EndOfFn` (and `Static`) elements as well.
+                for element in from_region.universal_elements() {
+                    if to_region.add_element(element) {
+                        self.added.push(element);
+                        changed = true;
+                    }
                 }
             } else {
                 self.stack.extend(successor_points);
@@ -0,0 +1,133 @@
+//! `--dump-joins`: an SSA-flavored view of the inferred regions. At a
+//! block with several predecessors, the value of a region is the join
+//! of the values flowing in along each incoming edge. For each region,
+//! this materializes a `join` pseudo-action at the merge points where
+//! the incoming values can differ -- the iterated dominance frontier
+//! of the blocks where the region begins or ends -- recording which
+//! predecessors the region extends in from.
+
+use env::{Environment, Point};
+use graph::BasicBlockIndex;
+use graph_algorithms::Graph;
+use graph_algorithms::dominators;
+use nll_repr::repr::RegionName;
+use region::Region;
+use regionck::RegionValues;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+pub struct Joins {
+    pub merges: BTreeMap<BasicBlockIndex, Merge>,
+}
+
+/// The joins at the start of one block with several predecessors.
+pub struct Merge {
+    pub predecessors: Vec<BasicBlockIndex>,
+    pub joins: Vec<Join>,
+}
+
+/// `'r = join(B1/2, -)`: the region contains the start of the merge
+/// block, and of the end points of the predecessors (in the order of
+/// `Merge::predecessors`), those given. A predecessor whose end point
+/// is missing did not force the region into the block.
+pub struct Join {
+    pub region: RegionName,
+    pub incoming: Vec<Option<Point>>,
+}
+
+pub fn joins(env: &Environment, regions: &RegionValues) -> Joins {
+    let frontiers = dominators::dominance_frontiers(env.graph, &env.dominators);
+    let mut merges = BTreeMap::new();
+
+    for (&name, region) in regions {
+        // The iterated dominance frontier of the blocks where the
+        // region begins or ends.
+        let mut worklist: Vec<_> = changing_blocks(env, region).into_iter().collect();
+        let mut frontier = BTreeSet::new();
+        while let Some(block) = worklist.pop() {
+            for &merge_block in &frontiers[block] {
+                if frontier.insert(merge_block) {
+                    worklist.push(merge_block);
+                }
+            }
+        }
+
+        for merge_block in frontier {
+            if !region.may_contain(env.start_point(merge_block)) {
+                continue;
+            }
+            let merge = merges.entry(merge_block).or_insert_with(|| Merge {
+                predecessors: predecessors(env, merge_block),
+                joins: vec![],
+            });
+            let incoming = merge.predecessors
+                .iter()
+                .map(|&pred| Some(env.end_point(pred).mid()).filter(|&p| region.may_contain(p)))
+                .collect();
+            merge.joins.push(Join {
+                region: name,
+                incoming,
+            });
+        }
+    }
+
+    Joins { merges }
+}
+
+fn predecessors(env: &Environment, block: BasicBlockIndex) -> Vec<BasicBlockIndex> {
+    env.graph
+        .predecessors(block)
+        .filter(|&pred| env.dominators.is_reachable(pred))
+        .collect()
+}
+
+/// The blocks in which the region begins or ends: those containing
+/// a point whose membership in the region differs from that of one
+/// of its predecessors, or the start of the function if the region
+/// contains it. These play the part of the definitions in SSA form.
+fn changing_blocks(env: &Environment, region: &Region) -> BTreeSet<BasicBlockIndex> {
+    let mut blocks = BTreeSet::new();
+    for &block in &env.reverse_post_order {
+        let points = Point::points_in_block(env.end_point(block).action);
+        let changes = (0..points).any(|index| {
+            let point = Point::from_index_in_block(block, index);
+            let contained = region.may_contain(point);
+            if index > 0 {
+                let previous = Point::from_index_in_block(block, index - 1);
+                region.may_contain(previous) != contained
+            } else if block == env.graph.start_node() {
+                contained
+            } else {
+                predecessors(env, block)
+                    .into_iter()
+                    .any(|pred| region.may_contain(env.end_point(pred).mid()) != contained)
+            }
+        });
+        if changes {
+            blocks.insert(block);
+        }
+    }
+    blocks
+}
+
+impl fmt::Display for Joins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "joins:")?;
+        for (block, merge) in &self.merges {
+            let predecessors: Vec<_> =
+                merge.predecessors.iter().map(|p| format!("{:?}", p)).collect();
+            writeln!(f, "  {:?} (from {}):", block, predecessors.join(", "))?;
+            for join in &merge.joins {
+                let incoming: Vec<_> = join.incoming
+                    .iter()
+                    .map(|p| match *p {
+                        Some(point) => format!("{:?}", point),
+                        None => "-".to_string(),
+                    })
+                    .collect();
+                writeln!(f, "    {} = join({})", join.region, incoming.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
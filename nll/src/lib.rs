@@ -0,0 +1,52 @@
+#![feature(conservative_impl_trait)]
+
+//! The analysis behind the `nll` test runner, as a library, so that
+//! it can also be embedded (see the `nll-capi` crate).
+
+#[macro_use]
+extern crate lazy_static;
+
+extern crate lalrpop_intern;
+extern crate graph_algorithms;
+extern crate nll_repr;
+extern crate rustc_serialize;
+
+#[macro_use]
+pub mod log;
+pub mod annotate_diff;
+pub mod baseline;
+pub mod bench;
+pub mod borrowck;
+pub mod cache;
+pub mod complexity;
+pub mod corpus;
+mod csv;
+pub mod datalog;
+mod dead_stores;
+pub mod dot;
+pub mod elaborate_drops;
+pub mod env;
+pub mod errors;
+mod explain;
+mod implied_bounds;
+mod infer;
+mod joins;
+mod loans_in_scope;
+mod maybe_init;
+mod memory;
+mod monotone;
+mod liveness;
+pub mod graph;
+pub mod observer;
+pub mod options;
+mod path_table;
+pub mod point_graph;
+mod region;
+mod region_repr;
+pub mod regionck;
+pub mod shard;
+mod snapshot;
+pub mod timeout;
+pub mod transform;
+mod universe;
+pub mod what_if;
@@ -1,10 +1,13 @@
-use env::{Environment, Point};
+use env::{Environment, LoanSite, Point};
 use graph::{BasicBlockIndex, FuncGraph};
 use graph_algorithms::Graph;
-use graph_algorithms::bit_set::{BitBuf, BitSet, BitSlice};
+use graph_algorithms::bit_set::{BitBuf, BitSet, BitSlice, HybridBitSet, NodeBitSet};
 use nll_repr::repr;
-use std::collections::{BTreeSet, HashMap};
+use region::Region;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::iter::once;
+use monotone;
+use timeout::{self, TimedOut};
 
 /// Compute the set of live variables at each point.
 pub struct Liveness<'env> {
@@ -12,6 +15,29 @@ pub struct Liveness<'env> {
     bits: Vec<BitKind>,
     bits_map: HashMap<BitKind, usize>,
     liveness: BitSet<FuncGraph>,
+
+    /// The combined effect of the actions of each block: the bits
+    /// live on entry to the block are `(exit & !kill) | gen`, where
+    /// `exit` are the bits live on exit from it.
+    gen: BitSet<FuncGraph>,
+    kill: BitSet<FuncGraph>,
+
+    /// The loans, in the order of `Environment::loan_sites`, and
+    /// whether the reference each creates is tracked: it is not if it
+    /// escapes (see `escapes`), and may then be used anywhere.
+    loans: Vec<(LoanSite<'env>, bool)>,
+
+    /// The index of each variable, in declaration order.
+    var_index: HashMap<repr::Variable, usize>,
+
+    /// The loans issued by the action at each point, by index.
+    loans_at: HashMap<Point, Vec<usize>>,
+
+    /// Going forwards, which variables may hold the reference created
+    /// by each tracked loan, directly or behind another reference, on
+    /// exit from each block; see `hold_bit`. Each variable holds only
+    /// a few of the loans, so most blocks have few bits set.
+    holds: HybridBitSet<FuncGraph>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
@@ -22,13 +48,33 @@ pub enum BitKind {
     /// If this bit is set, current value of the variable will be **dropped** later on.
     VariableDrop(repr::Variable),
 
-    /// If this bit is set, then the given free region will be
-    /// **used**.
-    FreeRegion(repr::RegionName),
+    /// If this bit is set, the reference created by the loan with this
+    /// index (in `Environment::loan_sites`) may be used later on,
+    /// through one of the variables it may have flowed into. This
+    /// makes the region of the loan live, even though no variable is
+    /// declared with it. Only the loans whose reference does not
+    /// escape (see `escapes`) have such a bit.
+    LoanRegion(usize),
 }
 
 impl<'env> Liveness<'env> {
-    pub fn new(env: &'env Environment<'env>) -> Liveness {
+    pub fn new(env: &'env Environment<'env>) -> Result<Self, TimedOut> {
+        let sites = env.loan_sites();
+        let escaping = escaping_loans(env, &sites)?;
+        let loans: Vec<_> = sites.into_iter().zip(escaping).map(|(site, e)| (site, !e)).collect();
+        let mut loans_at: HashMap<_, Vec<_>> = HashMap::new();
+        for (index, &(site, tracked)) in loans.iter().enumerate() {
+            if tracked {
+                loans_at.entry(site.point).or_default().push(index);
+            }
+        }
+        let var_index: HashMap<_, _> = env.graph
+            .decls()
+            .iter()
+            .enumerate()
+            .map(|(index, d)| (d.var, index))
+            .collect();
+
         let bits: Vec<_> = {
             let used_bits = env.graph
                 .decls()
@@ -38,12 +84,12 @@ impl<'env> Liveness<'env> {
                 .decls()
                 .iter()
                 .map(|d| BitKind::VariableDrop(d.var));
-            let free_region_bits = env.graph
-                .free_regions()
+            let loan_bits = loans
                 .iter()
-                .cloned()
-                .map(|rd| BitKind::FreeRegion(rd.name));
-            used_bits.chain(drop_bits).chain(free_region_bits).collect()
+                .enumerate()
+                .filter(|&(_, &(_, tracked))| tracked)
+                .map(|(index, _)| BitKind::LoanRegion(index));
+            used_bits.chain(drop_bits).chain(loan_bits).collect()
         };
 
         let bits_map: HashMap<_, _> = bits.iter()
@@ -53,14 +99,31 @@ impl<'env> Liveness<'env> {
             .collect();
 
         let liveness = BitSet::new(env.graph, bits.len());
+        let gen = BitSet::new(env.graph, bits.len());
+        let kill = BitSet::new(env.graph, bits.len());
+        let holds = HybridBitSet::new(env.graph, loans.len() * var_index.len() * 2);
         let mut this = Liveness {
             env,
             bits,
             liveness,
             bits_map,
+            gen,
+            kill,
+            loans,
+            var_index,
+            loans_at,
+            holds,
         };
-        this.compute();
-        this
+        this.compute_holds()?;
+        this.compile_blocks();
+        this.compute()?;
+        Ok(this)
+    }
+
+    /// The number of bytes taken up by the bit sets of the analysis.
+    pub fn heap_size(&self) -> usize {
+        self.liveness.heap_size() + self.gen.heap_size() + self.kill.heap_size() +
+            self.holds.heap_size()
     }
 
     pub fn var_live_on_entry(&self, var_name: repr::Variable, b: BasicBlockIndex) -> bool {
@@ -73,6 +136,16 @@ impl<'env> Liveness<'env> {
         set.contains(&region_name)
     }
 
+    /// Whether `region_name` is live at `point`, on entry to its
+    /// action.
+    pub fn region_live_at(&self, region_name: repr::RegionName, point: Point) -> bool {
+        let mut live = false;
+        self.walk_block(point.block, |p, _action, live_bits| if p == point {
+            live = self.regions_set(live_bits).contains(&region_name);
+        });
+        live
+    }
+
     pub fn live_regions<'a>(
         &'a self,
         live_bits: BitSlice<'a>,
@@ -80,33 +153,82 @@ impl<'env> Liveness<'env> {
         self.regions_set(live_bits).into_iter()
     }
 
+    /// The index of the bit `bk`: the bits of the variables being used
+    /// come first, in declaration order, then those of the variables
+    /// being dropped, and then those of the tracked loans.
+    pub fn bit_index(&self, bk: BitKind) -> usize {
+        self.bits_map[&bk]
+    }
+
+    /// The live bits, according to `live_bits`, that make
+    /// `region_name` live; used by `--why-error`.
+    pub fn why_region_live(&self, live_bits: BitSlice, region_name: repr::RegionName) -> Vec<BitKind> {
+        self.bits
+            .iter()
+            .enumerate()
+            .filter(|&(index, &bk)| {
+                if !live_bits.get(index) {
+                    return false;
+                }
+                let mut set = BTreeSet::new();
+                self.bit_regions(&mut set, bk);
+                set.contains(&region_name)
+            })
+            .map(|(_, &bk)| bk)
+            .collect()
+    }
+
     fn regions_set(&self, live_bits: BitSlice) -> BTreeSet<repr::RegionName> {
         let mut set = BTreeSet::new();
-        for (index, &bk) in self.bits.iter().enumerate() {
-            if live_bits.get(index) {
-                match bk {
-                    BitKind::VariableUsed(v) => {
-                        let var_ty = &self.env.var_ty(v);
-                        self.use_ty(&mut set, var_ty);
-                    }
+        for index in live_bits.iter() {
+            self.bit_regions(&mut set, self.bits[index]);
+        }
+        set
+    }
 
-                    BitKind::VariableDrop(v) => {
-                        let var_ty = &self.env.var_ty(v);
-                        self.drop_ty(&mut set, var_ty);
-                    }
+    /// Adds the regions that are live when `bk` is set to `set`.
+    fn bit_regions(&self, set: &mut BTreeSet<repr::RegionName>, bk: BitKind) {
+        match bk {
+            BitKind::VariableUsed(v) => {
+                let var_ty = &self.env.var_ty(v);
+                self.use_ty(set, var_ty);
+            }
 
-                    BitKind::FreeRegion(rn) => {
-                        self.use_region(&mut set, rn);
-                    }
-                }
+            BitKind::VariableDrop(v) => {
+                let var_ty = &self.env.var_ty(v);
+                self.drop_ty(set, var_ty);
+            }
+
+            BitKind::LoanRegion(index) => {
+                self.use_region(set, self.loans[index].0.region_name);
             }
         }
-        set
     }
 
-    /// Invokes callback once for each action with (A) the point of
-    /// the action; (B) the action itself and (C) the set of live
-    /// variables on entry to the action.
+    /// For each loan, in the order of `Environment::loan_sites`, the
+    /// points where the reference it creates may be used later on;
+    /// `None` if the reference escapes, so that it may be used
+    /// anywhere. A loan need not be in scope where its reference is
+    /// dead, even if its region includes the point.
+    pub fn loan_values_live(&self) -> Vec<Option<Region>> {
+        let mut live: Vec<_> = self.loans
+            .iter()
+            .map(|&(_, tracked)| if tracked { Some(Region::new()) } else { None })
+            .collect();
+        self.walk(|point, _action, live_bits| {
+            for bit in live_bits.iter() {
+                if let BitKind::LoanRegion(index) = self.bits[bit] {
+                    live[index].as_mut().unwrap().add_point(point);
+                }
+            }
+        });
+        live
+    }
+
+    /// Invokes callback once for each point with (A) the point; (B)
+    /// the action that starts there, if any, and (C) the set of live
+    /// variables at the point: on entry to the action at its start,
+    /// and after it at its mid-point.
     pub fn walk<CB>(&self, mut callback: CB)
     where
         CB: FnMut(Point, Option<&repr::Action>, BitSlice),
@@ -117,19 +239,291 @@ impl<'env> Liveness<'env> {
         }
     }
 
-    fn compute(&mut self) {
+    /// Like `walk`, but only for the points in `block`.
+    pub fn walk_block<CB>(&self, block: BasicBlockIndex, callback: CB)
+    where
+        CB: FnMut(Point, Option<&repr::Action>, BitSlice),
+    {
         let mut bits = self.liveness.empty_buf();
+        self.simulate_block(&mut bits, block, callback);
+    }
+
+    /// The variables whose current value will be used later on,
+    /// according to `live_bits`.
+    pub fn live_variables<'a>(
+        &'a self,
+        live_bits: BitSlice<'a>,
+    ) -> impl Iterator<Item = repr::Variable> + 'a {
+        self.bits.iter().enumerate().filter_map(move |(index, &bk)| match bk {
+            BitKind::VariableUsed(v) if live_bits.get(index) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// The variables whose current value will be dropped later on,
+    /// according to `live_bits`.
+    pub fn drop_live_variables<'a>(
+        &'a self,
+        live_bits: BitSlice<'a>,
+    ) -> impl Iterator<Item = repr::Variable> + 'a {
+        self.bits.iter().enumerate().filter_map(move |(index, &bk)| match bk {
+            BitKind::VariableDrop(v) if live_bits.get(index) => Some(v),
+            _ => None,
+        })
+    }
+
+    /// The bit of `holds` that is set if `var` may hold the reference
+    /// created by the loan with index `loan`: `indirect` if it may be
+    /// reachable only through another reference, so that reading
+    /// `var` through a reference may yield it.
+    fn hold_bit(&self, loan: usize, var: repr::Variable, indirect: bool) -> usize {
+        (loan * self.var_index.len() + self.var_index[&var]) * 2 + indirect as usize
+    }
+
+    /// The loan, variable and indirection of a bit of `holds`, the
+    /// inverse of `hold_bit`.
+    fn hold_of_bit(&self, bit: usize) -> (usize, repr::Variable, bool) {
+        let (loan, var) = (bit / 2 / self.var_index.len(), bit / 2 % self.var_index.len());
+        (loan, self.env.graph.decls()[var].var, bit % 2 == 1)
+    }
+
+    /// Computes `holds` to a fixed point: a variable holds the
+    /// reference created by a loan after the loan is issued into it,
+    /// or after (part of) another variable holding it flows into it,
+    /// until it is completely overwritten; see `carry_loans`.
+    fn compute_holds(&mut self) -> Result<(), TimedOut> {
+        if !self.loans.iter().any(|&(_, tracked)| tracked) {
+            return Ok(());
+        }
+        let mut bits = self.holds.empty_buf();
         let mut changed = true;
         while changed {
+            timeout::round()?;
+            changed = false;
+            for &block in &self.env.reverse_post_order {
+                self.holds_on_entry(&mut bits, block);
+                for (point, action) in self.env.actions(block) {
+                    self.carry_loans(&mut bits, point, action);
+                }
+                // copying the bits out is only worth it to check them
+                if cfg!(debug_assertions) {
+                    let old_bits = self.holds.to_buf(block);
+                    monotone::check_bits(self.env, "loan holders", block,
+                                         self.holds.bits_per_node(), old_bits.as_slice(),
+                                         bits.as_slice(), |bit| self.describe_hold_bit(bit));
+                }
+                changed |= self.holds.insert_bits_from_slice(block, bits.as_slice());
+            }
+        }
+        Ok(())
+    }
+
+    /// The meaning of a bit of `holds`.
+    fn describe_hold_bit(&self, bit: usize) -> String {
+        let (loan, var, indirect) = self.hold_of_bit(bit);
+        let site = &self.loans[loan].0;
+        format!("`{:?}` holding the loan of `{}` at {:?}{}",
+                var, site.path, site.point, if indirect { " indirectly" } else { "" })
+    }
+
+    /// Sets `bits` to the holders on entry to `block`: those on exit
+    /// from any of its predecessors.
+    fn holds_on_entry(&self, bits: &mut BitBuf, block: BasicBlockIndex) {
+        bits.clear();
+        for pred in self.env.graph.predecessors(block) {
+            self.holds.union_into(pred, bits);
+        }
+    }
+
+    /// The bits of `holds` that are set at each point of `block`, by
+    /// `Point::index_in_block`.
+    fn holds_in_block(&self, block: BasicBlockIndex) -> Vec<Vec<usize>> {
+        let mut bits = self.holds.empty_buf();
+        self.holds_on_entry(&mut bits, block);
+        let actions = self.env.end_point(block).action;
+        let mut result = Vec::with_capacity(Point::points_in_block(actions));
+        for (point, action) in self.env.actions(block) {
+            result.push(bits.as_slice().iter().collect());
+            self.carry_loans(&mut bits, point, action);
+            result.push(bits.as_slice().iter().collect());
+        }
+        // the end of the block has no effect
+        let end: Vec<_> = bits.as_slice().iter().collect();
+        result.push(end.clone());
+        result.push(end);
+        result
+    }
+
+    /// Applies `action` to `bits`. A value stored by the action holds
+    /// what each of its sources holds, except the reference that a
+    /// source reads through. A borrow of a source holds everything
+    /// the source does behind a reference: what it holds directly is
+    /// only kept if the borrow goes through it, as a reborrow of
+    /// `*r` where the new reference must not outlive `r`. (Like in
+    /// `supporting_prefixes`, reborrowing through a shared reference
+    /// behind `r` does not need `r`.)
+    fn carry_loans(&self, bits: &mut BitBuf, point: Point, action: &repr::Action) {
+        let (dest, sources) = match value_flow(action) {
+            Some(flow) => flow,
+            None => {
+                if let repr::ActionKind::StorageDead(v) = action.kind {
+                    self.kill_holds(bits, v);
+                }
+                return;
+            }
+        };
+
+        let mut carried: Vec<_> = self.loans_at
+            .get(&point)
+            .into_iter()
+            .flatten()
+            .map(|&loan| (loan, false))
+            .collect();
+
+        // For each source, its base, the number of derefs it reads
+        // through, and whether a borrow of it goes through all of them.
+        let sources: Vec<_> = sources
+            .into_iter()
+            .map(|(source, borrowed)| {
+                let derefs = source.prefixes().iter().filter(|p| p.is_deref()).count();
+                let through = derefs > 0 && match self.env.try_path_ty(source) {
+                    Ok(_) => {
+                        let supporting = self.env.supporting_prefixes(source);
+                        supporting.iter().filter(|p| p.is_deref()).count() == derefs
+                    }
+                    Err(_) => true,
+                };
+                (source.base(), derefs, borrowed, through)
+            })
+            .collect();
+        for bit in bits.as_slice().iter() {
+            let (loan, var, indirect) = self.hold_of_bit(bit);
+            for &(base, derefs, borrowed, through) in &sources {
+                if base != var {
+                    continue;
+                }
+                if !indirect {
+                    if derefs == 0 {
+                        carried.push((loan, borrowed));
+                    } else if borrowed && through {
+                        carried.push((loan, true));
+                    }
+                } else {
+                    carried.push((loan, true));
+                    if derefs > 0 && !borrowed {
+                        carried.push((loan, false));
+                    }
+                }
+            }
+        }
+
+        if let Some(v) = dest.write_def() {
+            self.kill_holds(bits, v);
+        }
+        for (loan, indirect) in carried {
+            bits.set(self.hold_bit(loan, dest.base(), indirect));
+        }
+    }
+
+    fn kill_holds(&self, bits: &mut BitBuf, var: repr::Variable) {
+        for (loan, &(_, tracked)) in self.loans.iter().enumerate() {
+            if tracked {
+                bits.kill(self.hold_bit(loan, var, false));
+                bits.kill(self.hold_bit(loan, var, true));
+            }
+        }
+    }
+
+    /// Folds the effects of the actions of each block into its `gen`
+    /// and `kill` bits. Going backwards, an action that kills `k` and
+    /// generates `g`, applied after the actions that follow it,
+    /// leaves `kill | k` killed and `(gen & !k) | g` generated.
+    fn compile_blocks(&mut self) {
+        let mut gen = self.gen.empty_buf();
+        let mut kill = self.kill.empty_buf();
+        for &block in &self.env.reverse_post_order {
+            gen.clear();
+            kill.clear();
+            for action in self.env.graph.block_data(block).actions.iter().rev() {
+                let (action_kill, action_gen) = self.kill_gen(action);
+                for bit in action_kill {
+                    gen.kill(bit);
+                    kill.set(bit);
+                }
+                for bit in action_gen {
+                    gen.set(bit);
+                }
+            }
+            self.gen.insert_bits_from_slice(block, gen.as_slice());
+            self.kill.insert_bits_from_slice(block, kill.as_slice());
+        }
+    }
+
+    fn compute(&mut self) -> Result<(), TimedOut> {
+        let mut bits = self.liveness.empty_buf();
+        let mut replayed = self.liveness.empty_buf();
+        let mut changed = true;
+        while changed {
+            timeout::round()?;
             changed = false;
 
             for &block in &self.env.reverse_post_order {
-                self.simulate_block(&mut bits, block, |_p, _a, _s| ());
+                bits.clear();
+                for succ in self.env.graph.successors(block) {
+                    bits.set_from(self.liveness.bits(succ));
+                }
+                bits.kill_from(self.kill.bits(block));
+                bits.set_from(self.gen.bits(block));
+
+                if cfg!(debug_assertions) {
+                    self.simulate_block(&mut replayed, block, |_p, _a, _s| ());
+                    assert_eq!(bits.as_slice(), replayed.as_slice(),
+                               "compiled liveness of {:?} disagrees with its actions",
+                               block);
+                }
+
+                monotone::check_bits(self.env, "liveness", block, self.bits.len(),
+                                     self.liveness.bits(block), bits.as_slice(),
+                                     |bit| format!("{:?}", self.bits[bit]));
                 changed |= self.liveness.insert_bits_from_slice(block, bits.as_slice());
             }
         }
+        Ok(())
     }
 
+    /// The bits that `action` kills, and those it then generates,
+    /// going backwards.
+    fn kill_gen(&self, action: &repr::Action) -> (Vec<usize>, Vec<usize>) {
+        let (def_var, use_var) = action.def_use();
+
+        // anything we write to is no longer live
+        let kill = def_var
+            .into_iter()
+            .flat_map(|v| vec![BitKind::VariableUsed(v), BitKind::VariableDrop(v)])
+            .map(|bk| self.bits_map[&bk])
+            .collect();
+
+        // some actions are special
+        let dropped = match action.kind {
+            repr::ActionKind::Drop(ref path) => Some(path.base()),
+            _ => None,
+        };
+
+        // any variables we read from, we make live
+        let gen: Vec<_> = use_var
+            .iter()
+            .map(|&v| self.bits_map[&BitKind::VariableUsed(v)])
+            .chain(dropped.map(|v| self.bits_map[&BitKind::VariableDrop(v)]))
+            .collect();
+
+        (kill, gen)
+    }
+
+    /// Replays the actions of `block` one at a time, for `walk` (and,
+    /// in debug builds, to check `compile_blocks`). The `LoanRegion`
+    /// bits are not part of the compiled liveness: the callback sees
+    /// the bit of a loan set where a variable that may hold its
+    /// reference is live.
     fn simulate_block<CB>(&self, buf: &mut BitBuf, block: BasicBlockIndex, mut callback: CB)
     where
         CB: FnMut(Point, Option<&repr::Action>, BitSlice),
@@ -141,48 +535,51 @@ impl<'env> Liveness<'env> {
             buf.set_from(self.liveness.bits(succ));
         }
 
-        // callback for the "goto" point
-        callback(self.env.end_point(block), None, buf.as_slice());
+        let holds = if self.holds.bits_per_node() > 0 {
+            Some(self.holds_in_block(block))
+        } else {
+            None
+        };
+        let mut callback = |point: Point, action, buf: &BitBuf| match holds {
+            Some(ref holds) => {
+                let with_loans = self.with_loan_bits(buf, &holds[point.index_in_block()]);
+                callback(point, action, with_loans.as_slice())
+            }
+            None => callback(point, action, buf.as_slice()),
+        };
+
+        // callbacks for the "goto" points
+        let end_point = self.env.end_point(block);
+        callback(end_point.mid(), None, buf);
+        callback(end_point, None, buf);
 
         // walk backwards through the actions
-        for (index, action) in self.env
-            .graph
-            .block_data(block)
-            .actions()
-            .iter()
-            .enumerate()
-            .rev()
-        {
-            let (def_var, use_var) = action.def_use();
-
-            // anything we write to is no longer live
-            for v in def_var {
-                buf.kill(self.bits_map[&BitKind::VariableUsed(v)]);
-                buf.kill(self.bits_map[&BitKind::VariableDrop(v)]);
+        for (point, action) in self.env.actions(block).rev() {
+            callback(point.mid(), None, buf);
+            let (kill, gen) = self.kill_gen(action);
+            for bit in kill {
+                buf.kill(bit);
             }
-
-            // any variables we read from, we make live
-            for v in use_var {
-                buf.set(self.bits_map[&BitKind::VariableUsed(v)]);
+            for bit in gen {
+                buf.set(bit);
             }
+            callback(point, Some(action), buf);
+        }
+    }
 
-            // some actions are special
-            match action.kind {
-                repr::ActionKind::Drop(ref path) => {
-                    buf.set(self.bits_map[&BitKind::VariableDrop(path.base())]);
-                }
-                repr::ActionKind::SkolemizedEnd(name) => {
-                    buf.set(self.bits_map[&BitKind::FreeRegion(name)]);
-                }
-                _ => {}
+    /// A copy of `live_bits` with the `LoanRegion` bit of each loan
+    /// set if, according to `holds` (the bits of `holds` that are set),
+    /// a live variable may hold its reference.
+    fn with_loan_bits(&self, live_bits: &BitBuf, holds: &[usize]) -> BitBuf {
+        let mut bits = live_bits.as_slice().to_buf();
+        for &bit in holds {
+            let (loan, var, _) = self.hold_of_bit(bit);
+            if live_bits.get(self.bits_map[&BitKind::VariableUsed(var)]) ||
+               live_bits.get(self.bits_map[&BitKind::VariableDrop(var)]) {
+                bits.set(self.bits_map[&BitKind::LoanRegion(loan)]);
             }
-
-            let point = Point {
-                block,
-                action: index,
-            };
-            callback(point, Some(action), buf.as_slice());
         }
+        bits
     }
 
     fn use_ty(&self, buf: &mut BTreeSet<repr::RegionName>, ty: &repr::Ty) {
@@ -198,8 +595,10 @@ impl<'env> Liveness<'env> {
     fn drop_ty(&self, buf: &mut BTreeSet<repr::RegionName>, ty: &repr::Ty) {
         match *ty {
             repr::Ty::Ref(..) |
-            repr::Ty::Unit => {
-                // Dropping a reference (or `()`) does not require it to be live; it's a no-op.
+            repr::Ty::RawPtr(..) |
+            repr::Ty::Unit |
+            repr::Ty::Int => {
+                // Dropping a reference (or a scalar) does not require it to be live; it's a no-op.
             }
 
             repr::Ty::Struct(struct_name, ref params) => {
@@ -210,6 +609,10 @@ impl<'env> Liveness<'env> {
                         repr::TyParameter::Region(region) => {
                             if !param_decl.may_dangle {
                                 self.use_region(buf, region.assert_free());
+                            } else {
+                                log!("drop_ty: may_dangle allows {} to dangle when {} is dropped",
+                                     region.assert_free(),
+                                     struct_name);
                             }
                         }
 
@@ -217,6 +620,10 @@ impl<'env> Liveness<'env> {
                             if !param_decl.may_dangle {
                                 self.use_ty(buf, ty);
                             } else {
+                                log!("drop_ty: may_dangle allows the regions of {:?} to dangle \
+                                      when {} is dropped",
+                                     ty,
+                                     struct_name);
                                 self.drop_ty(buf, ty);
                             }
                         }
@@ -229,6 +636,124 @@ impl<'env> Liveness<'env> {
     }
 }
 
+/// The destination of the value an action stores, and the paths it is
+/// made of, each with whether it is borrowed (rather than copied or
+/// moved): a reference held by (part of) one of those variables may
+/// flow into the destination. `Const` and `Write` store a value made
+/// of nothing.
+fn value_flow(action: &repr::Action) -> Option<(&repr::Path, Vec<(&repr::Path, bool)>)> {
+    match action.kind {
+        repr::ActionKind::Borrow(ref a, _, _, ref b) |
+        repr::ActionKind::RawBorrow(ref a, _, ref b) => Some((a, vec![(b, true)])),
+        repr::ActionKind::Assign(ref a, ref b) => Some((a, vec![(b, false)])),
+        repr::ActionKind::Init(ref a, ref operands) => {
+            let sources = operands
+                .iter()
+                .map(|operand| match *operand {
+                    repr::Operand::Path(ref b) => (&**b, false),
+                    repr::Operand::Borrow(_, _, ref b) => (&**b, true),
+                })
+                .collect();
+            Some((a, sources))
+        }
+        repr::ActionKind::Closure(ref a, ref captures) => {
+            let sources = captures
+                .iter()
+                .map(|c| (&*c.path, c.mode != repr::CaptureMode::ByMove))
+                .collect();
+            Some((a, sources))
+        }
+        repr::ActionKind::Const(ref a, _) |
+        repr::ActionKind::Write(ref a, _) => Some((a, vec![])),
+        _ => None,
+    }
+}
+
+/// For each of `sites`, true if the reference created there escapes,
+/// so that it may be used anywhere: if its region names a free region
+/// (so that it may be returned to the caller) or one related by an
+/// outlives constraint action (which stands for a flow that the
+/// actions do not spell out), or if it may be stored through a
+/// reference (behind which it could be reached from anywhere) or into
+/// a variable whose type names such a region. Which variables a
+/// reference may flow into is computed for the whole function at
+/// once, ignoring the order of the actions, and for all the sites
+/// together: a site escapes if the variable it stores into does.
+fn escaping_loans(env: &Environment, sites: &[LoanSite]) -> Result<Vec<bool>, TimedOut> {
+    let mut constrained = HashSet::new();
+    for &block in &env.reverse_post_order {
+        for action in &env.graph.block_data(block).actions {
+            if let repr::ActionKind::Constraint(ref c) = action.kind {
+                constraint_regions(c, &mut constrained);
+            }
+        }
+    }
+    let escaping = |name: repr::RegionName| {
+        name.is_static() || constrained.contains(&name) ||
+            env.graph.free_regions().iter().any(|rd| rd.name == name)
+    };
+    let stores_through_deref = |dest: &repr::Path| dest.prefixes().iter().any(|p| p.is_deref());
+
+    // The variables that a reference escapes from: those whose type
+    // names an escaping region, and those whose value may be stored
+    // through a reference, or flow into another such variable.
+    let mut escaping_vars: HashSet<_> = env.graph
+        .decls()
+        .iter()
+        .filter(|decl| env.var_ty(decl.var).walk_regions().any(|r| escaping(r.assert_free())))
+        .map(|decl| decl.var)
+        .collect();
+    let mut changed = true;
+    while changed {
+        timeout::round()?;
+        changed = false;
+        for &block in &env.reverse_post_order {
+            for action in &env.graph.block_data(block).actions {
+                let (dest, sources) = match value_flow(action) {
+                    Some(flow) => flow,
+                    None => continue,
+                };
+                if stores_through_deref(dest) || escaping_vars.contains(&dest.base()) {
+                    for (source, _) in sources {
+                        changed |= escaping_vars.insert(source.base());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(sites
+        .iter()
+        .map(|site| {
+            escaping(site.region_name) || stores_through_deref(site.dest) ||
+                escaping_vars.contains(&site.dest.base())
+        })
+        .collect())
+}
+
+fn constraint_regions(c: &repr::Constraint, regions: &mut HashSet<repr::RegionName>) {
+    match *c {
+        repr::Constraint::ForAll(_, ref c) |
+        repr::Constraint::Exists(_, ref c) => constraint_regions(c, regions),
+        repr::Constraint::Implies(ref bounds, ref c) => {
+            for bound in bounds {
+                regions.insert(bound.sup);
+                regions.insert(bound.sub);
+            }
+            constraint_regions(c, regions);
+        }
+        repr::Constraint::All(ref cs) => {
+            for c in cs {
+                constraint_regions(c, regions);
+            }
+        }
+        repr::Constraint::Outlives(ref c) => {
+            regions.insert(c.sup);
+            regions.insert(c.sub);
+        }
+    }
+}
+
 pub trait DefUse {
     /// Returns (defs, uses), where `defs` contains variables whose
     /// current value is completely overwritten, and `uses` contains
@@ -246,19 +771,37 @@ impl DefUse for repr::Action {
                     a.write_def().into_iter().collect(),
                     params
                         .iter()
-                        .map(|p| p.base())
+                        .map(|p| p.path().base())
                         .chain(a.write_use())
                         .collect(),
                 )
             }
-            repr::ActionKind::Assign(ref a, ref b) => {
+            // the constant itself uses nothing, nor does the value
+            // that `write` conjures up
+            repr::ActionKind::Const(ref a, _) |
+            repr::ActionKind::Write(ref a, _) => {
+                (a.write_def().into_iter().collect(), a.write_use().into_iter().collect())
+            }
+            repr::ActionKind::Assign(ref a, ref b) |
+            repr::ActionKind::RawBorrow(ref a, _, ref b) => {
                 (
                     a.write_def().into_iter().collect(),
                     once(b.base()).chain(a.write_use()).collect(),
                 )
             }
             repr::ActionKind::Constraint(ref _c) => (vec![], vec![]),
-            repr::ActionKind::Use(ref v) => (vec![], vec![v.base()]),
+            repr::ActionKind::Use(ref v) |
+            repr::ActionKind::Read(ref v, _) => (vec![], vec![v.base()]),
+            repr::ActionKind::Closure(ref a, ref captures) => {
+                (
+                    a.write_def().into_iter().collect(),
+                    captures
+                        .iter()
+                        .map(|c| c.path.base())
+                        .chain(a.write_use())
+                        .collect(),
+                )
+            }
 
             // drop is special; it is not considered a "full use" of
             // the variable that is being dropped
@@ -267,8 +810,6 @@ impl DefUse for repr::Action {
             repr::ActionKind::Noop => (vec![], vec![]),
 
             repr::ActionKind::StorageDead(_) => (vec![], vec![]),
-
-            repr::ActionKind::SkolemizedEnd(_) => (vec![], vec![]),
         }
     }
 }
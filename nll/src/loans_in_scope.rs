@@ -1,86 +1,294 @@
 use env::{Environment, Point};
 use graph::{BasicBlockIndex, FuncGraph};
 use graph_algorithms::Graph;
-use graph_algorithms::bit_set::{BitBuf, BitSet, BitSlice};
+use graph_algorithms::bit_set::{BitBuf, BitSet, BitSlice, HybridBitSet, NodeBitSet};
+use liveness::Liveness;
 use nll_repr::repr;
+use options::{KillMode, LoanGranularity, LoanSetRepr};
+use path_table::{PathIndex, PathTable};
 use region::Region;
 use regionck::RegionCheck;
 use std::collections::HashMap;
+use std::iter::once;
+use monotone;
+use timeout::{self, TimedOut};
 
+/// The loans in scope at each point.
 pub struct LoansInScope<'cx> {
     env: &'cx Environment<'cx>,
+    paths: PathTable,
     loans: Vec<Loan<'cx>>,
-    loans_in_scope_after_block: BitSet<FuncGraph>,
-    loans_by_point: HashMap<Point, usize>,
+    loans_in_scope_after_block: LoanSets,
+    loans_by_point: HashMap<Point, Vec<usize>>,
+    kill_mode: KillMode,
 }
 
-#[derive(Debug)]
+/// The loans in scope on exit from each block, in the representation
+/// selected by `Options::loan_set_repr`; with thousands of loans, a
+/// `HybridBitSet` takes up much less room than a `BitSet`.
+enum LoanSets {
+    Dense(BitSet<FuncGraph>),
+    Hybrid(HybridBitSet<FuncGraph>),
+}
+
+impl LoanSets {
+    fn new(graph: &FuncGraph, loans: usize, repr: LoanSetRepr) -> Self {
+        match repr {
+            LoanSetRepr::Dense => LoanSets::Dense(BitSet::new(graph, loans)),
+            LoanSetRepr::Hybrid => LoanSets::Hybrid(HybridBitSet::new(graph, loans)),
+        }
+    }
+
+    fn heap_size(&self) -> usize {
+        match *self {
+            LoanSets::Dense(ref sets) => sets.heap_size(),
+            LoanSets::Hybrid(ref sets) => sets.heap_size(),
+        }
+    }
+
+    fn empty_buf(&self) -> BitBuf {
+        match *self {
+            LoanSets::Dense(ref sets) => sets.empty_buf(),
+            LoanSets::Hybrid(ref sets) => sets.empty_buf(),
+        }
+    }
+
+    fn to_buf(&self, block: BasicBlockIndex) -> BitBuf {
+        match *self {
+            LoanSets::Dense(ref sets) => sets.to_buf(block),
+            LoanSets::Hybrid(ref sets) => sets.to_buf(block),
+        }
+    }
+
+    fn insert_bits_from_slice(&mut self, block: BasicBlockIndex, bits: BitSlice) -> bool {
+        match *self {
+            LoanSets::Dense(ref mut sets) => sets.insert_bits_from_slice(block, bits),
+            LoanSets::Hybrid(ref mut sets) => sets.insert_bits_from_slice(block, bits),
+        }
+    }
+
+    fn union_into(&self, block: BasicBlockIndex, buf: &mut BitBuf) -> bool {
+        match *self {
+            LoanSets::Dense(ref sets) => sets.union_into(block, buf),
+            LoanSets::Hybrid(ref sets) => sets.union_into(block, buf),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Loan<'cx> {
+    /// The index of the loan in `Environment::loan_sites`; for loans
+    /// merged by region, that of the first.
+    pub site: usize,
     pub point: Point,
     pub path: &'cx repr::Path,
+    pub path_index: PathIndex,
     pub kind: repr::BorrowKind,
+    pub region_name: repr::RegionName,
     pub region: &'cx Region,
+
+    /// The points where the reference created by the loan may be used
+    /// later on (see `Liveness::loan_values_live`); the loan goes out
+    /// of scope wherever it is dead. `None` if the reference escapes,
+    /// if region inference was skipped, or if the `KillMode` does not
+    /// kill loans whose reference is dead.
+    pub value_live: Option<Region>,
+}
+
+/// The scope of a loan: the points where it is in scope, as the
+/// loans-in-scope computation finds, which is what Polonius calls
+/// `loan_live_at`. This is contained in the loan's region, but may be
+/// smaller: the loan only comes into scope after it is issued, and
+/// goes out of scope when its path is overwritten.
+#[derive(Clone, Debug)]
+pub struct LoanScope {
+    pub issued_at: Vec<Point>,
+    pub path: repr::Path,
+    pub kind: repr::BorrowKind,
+    pub region_name: repr::RegionName,
+    pub scope: Region,
 }
 
 impl<'cx> LoansInScope<'cx> {
-    pub fn new(regionck: &'cx RegionCheck<'cx>) -> Self {
+    /// Computes the loans in scope at each point. Without `liveness`
+    /// (that is, when region inference is skipped), loans only go out
+    /// of scope where their regions end or their paths are overwritten.
+    pub fn new(regionck: &'cx RegionCheck<'cx>,
+               liveness: Option<&Liveness>)
+               -> Result<Self, TimedOut> {
         let env = regionck.env();
+        let paths = PathTable::new(env);
 
         // Collect the full set of loans; these are just the set of
-        // `&foo` expressions.
-        let loans: Vec<_> = env.reverse_post_order
-            .iter()
-            .flat_map(|&block| {
-                env.graph
-                    .block_data(block)
-                    .actions()
-                    .iter()
-                    .enumerate()
-                    .flat_map(move |(index, action)| match action.kind {
-                        repr::ActionKind::Borrow(_, region, kind, ref path) => {
-                            let point = Point {
-                                block,
-                                action: index,
-                            };
-                            let region = regionck.region(region);
-                            Some(Loan {
-                                point,
-                                region,
-                                kind,
-                                path,
-                            })
-                        }
-
-                        _ => None,
-                    })
+        // `&foo` expressions, along with any by-ref closure captures.
+        // A loan whose region extends beyond the function may be used
+        // by the caller, whatever becomes of its reference here.
+        let kill_mode = regionck.options().kill_mode;
+        let values_live = match liveness {
+            Some(liveness) if kill_mode.kills_dead_references() => liveness.loan_values_live(),
+            _ => vec![],
+        };
+        let loans: Vec<_> = env.loan_sites()
+            .into_iter()
+            .enumerate()
+            .map(|(index, site)| {
+                let region = regionck.region(site.region_name);
+                let value_live = if region.universal_elements().next().is_none() {
+                    values_live.get(index).cloned().unwrap_or(None)
+                } else {
+                    None
+                };
+                Loan {
+                    site: index,
+                    point: site.point,
+                    region_name: site.region_name,
+                    region,
+                    kind: site.kind,
+                    path: site.path,
+                    path_index: paths.index(site.path),
+                    value_live,
+                }
             })
             .collect();
 
-        log!("loans: {:#?}", loans);
+        // Make a convenient hash map for getting the indices of the
+        // loans based on where they appear. If we are merging loans by
+        // region, each merged loan is issued at several points; its
+        // `point` is the first of them (in reverse post-order).
+        let mut loans_by_point: HashMap<_, Vec<_>> = HashMap::new();
+        let loans = match regionck.options().loan_granularity {
+            LoanGranularity::PerBorrow => {
+                for (index, loan) in loans.iter().enumerate() {
+                    loans_by_point.entry(loan.point).or_insert(vec![]).push(index);
+                }
+                loans
+            }
 
-        // Make a convenient hash map for getting the index of a loan
-        // based on where it appears.
-        let loans_by_point: HashMap<_, _> = loans
-            .iter()
-            .enumerate()
-            .map(|(index, loan)| (loan.point, index))
-            .collect();
+            LoanGranularity::PerRegion => {
+                let mut merged: Vec<Loan> = vec![];
+                let mut merged_indices = HashMap::new();
+                for loan in loans {
+                    let key = (loan.region_name, loan.path_index, loan.kind);
+                    let point = loan.point;
+                    let value_live = loan.value_live.clone();
+                    let index = *merged_indices.entry(key).or_insert_with(|| {
+                        merged.push(loan);
+                        merged.len() - 1
+                    });
+                    merged[index].merge_value_live(value_live);
+                    loans_by_point.entry(point).or_insert(vec![]).push(index);
+                }
+                merged
+            }
+        };
+
+        log!("loans: {:#?}", loans);
 
         // Get a bit set with the set of in-scope loans at each point
         // in the graph. These correspond to the set of loans in scope
         // at the end of the block.
-        let loans_in_scope_after_block = BitSet::new(env.graph, loans.len());
+        let loans_in_scope_after_block =
+            LoanSets::new(env.graph, loans.len(), regionck.options().loan_set_repr);
 
         // iterate until fixed point
         let mut this = LoansInScope {
             env,
+            paths,
             loans,
             loans_by_point,
             loans_in_scope_after_block,
+            kill_mode,
         };
-        this.compute();
+        this.compute()?;
 
-        this
+        Ok(this)
+    }
+
+    pub fn paths(&self) -> &PathTable {
+        &self.paths
+    }
+
+    /// The number of bytes taken up by the loans in scope on exit from
+    /// each block, and by the points where each loan's reference is
+    /// live; the path table is counted apart.
+    pub fn heap_size(&self) -> usize {
+        let value_live: usize = self.loans
+            .iter()
+            .filter_map(|loan| loan.value_live.as_ref())
+            .map(|region| region.heap_size())
+            .sum();
+        self.loans_in_scope_after_block.heap_size() + value_live
+    }
+
+    /// All the loans, indexed like the bits given to `walk_bits`.
+    pub fn loans(&self) -> &[Loan<'cx>] {
+        &self.loans
+    }
+
+    /// Each loan, along with the point(s) where it is issued (in
+    /// order). A loan has several issue points only if loans are
+    /// merged by region.
+    pub fn loans_with_issue_points(&self) -> Vec<(&Loan<'cx>, Vec<Point>)> {
+        let mut issue_points: Vec<Vec<Point>> = self.loans.iter().map(|_| vec![]).collect();
+        for (&point, indices) in &self.loans_by_point {
+            for &index in indices {
+                issue_points[index].push(point);
+            }
+        }
+        for points in &mut issue_points {
+            points.sort();
+        }
+        self.loans.iter().zip(issue_points).collect()
+    }
+
+    /// The scope of each loan, in the order of `loans_with_issue_points`.
+    pub fn loan_scopes(&self) -> Vec<LoanScope> {
+        let mut scopes: Vec<Region> = self.loans.iter().map(|_| Region::new()).collect();
+        let mut bits = self.loans_in_scope_after_block.empty_buf();
+        for &block in &self.env.reverse_post_order {
+            self.simulate_block(&mut bits, block, |point, _action, bits| {
+                for (loan_index, scope) in scopes.iter_mut().enumerate() {
+                    if bits.get(loan_index) {
+                        scope.add_point(point);
+                    }
+                }
+            });
+        }
+        self.loans_with_issue_points()
+            .into_iter()
+            .zip(scopes)
+            .map(|((loan, issued_at), scope)| LoanScope {
+                issued_at,
+                path: loan.path.clone(),
+                kind: loan.kind,
+                region_name: loan.region_name,
+                scope,
+            })
+            .collect()
+    }
+
+    /// Prints the set of loans, along with the point(s) where each
+    /// one is issued and its scope.
+    pub fn dump(&self) {
+        let loans = self.loan_scopes();
+        println!("loans ({}):", loans.len());
+        for (index, loan) in loans.into_iter().enumerate() {
+            let kind = match loan.kind {
+                repr::BorrowKind::Mut => "mut ",
+                repr::BorrowKind::Shared => "",
+            };
+            let points: Vec<_> =
+                loan.issued_at.iter().map(|&p| self.env.describe_point(p)).collect();
+            println!(
+                "  L{}: &{} {}{} issued at [{}]",
+                index,
+                loan.region_name,
+                kind,
+                loan.path,
+                points.join(", ")
+            );
+            println!("    scope: {}", loan.scope);
+        }
     }
 
     /// Invokes `callback` with the loans in scope at each point.
@@ -89,39 +297,73 @@ impl<'cx> LoansInScope<'cx> {
         CB: FnMut(Point, Option<&repr::Action>, &[&Loan]),
     {
         let mut loans = Vec::with_capacity(self.loans.len());
+        self.walk_bits(env, |point, action, bits| {
+            // Convert from the bitset into a vector of references to loans.
+            loans.clear();
+            loans.extend(bits.iter().map(|loan_index| &self.loans[loan_index]));
+
+            // Invoke the callback.
+            callback(point, action, &loans);
+        });
+    }
+
+    /// Like `walk`, but gives the loans in scope as bits, indexed like
+    /// `loans()`.
+    pub fn walk_bits<CB>(&self, env: &Environment<'cx>, mut callback: CB)
+    where
+        CB: FnMut(Point, Option<&repr::Action>, BitSlice),
+    {
         let mut bits = self.loans_in_scope_after_block.empty_buf();
         for &block in &env.reverse_post_order {
-            self.simulate_block(&mut bits, block, |point, action, bits| {
-                // Convert from the bitset into a vector of references to loans.
-                loans.clear();
-                loans.extend(self.loans.iter().enumerate().filter_map(
-                    |(loan_index, loan)| if bits.get(loan_index) {
-                        Some(loan)
-                    } else {
-                        None
-                    },
-                ));
-
-                // Invoke the callback.
-                callback(point, action, &loans);
-            });
+            self.simulate_block(&mut bits, block, &mut callback);
         }
     }
 
+    /// Like `walk`, but only for the points in `block`.
+    pub fn walk_block<CB>(&self, block: BasicBlockIndex, mut callback: CB)
+    where
+        CB: FnMut(Point, Option<&repr::Action>, &[&Loan<'cx>]),
+    {
+        self.walk_block_bits(block, |point, action, bits| {
+            let loans: Vec<_> = bits.iter().map(|loan_index| &self.loans[loan_index]).collect();
+            callback(point, action, &loans);
+        });
+    }
+
+    /// Like `walk_bits`, but only for the points in `block`.
+    pub fn walk_block_bits<CB>(&self, block: BasicBlockIndex, callback: CB)
+    where
+        CB: FnMut(Point, Option<&repr::Action>, BitSlice),
+    {
+        let mut bits = self.loans_in_scope_after_block.empty_buf();
+        self.simulate_block(&mut bits, block, callback);
+    }
+
     /// Iterates until a fixed point, computing the loans in scope
     /// after each block terminates.
-    fn compute(&mut self) {
+    fn compute(&mut self) -> Result<(), TimedOut> {
         let mut bits = self.loans_in_scope_after_block.empty_buf();
         let mut changed = true;
         while changed {
+            timeout::round()?;
             changed = false;
 
             for &block in &self.env.reverse_post_order {
                 self.simulate_block(&mut bits, block, |_p, _a, _s| ());
+                // copying the bits out is only worth it to check them
+                if cfg!(debug_assertions) {
+                    let old_bits = self.loans_in_scope_after_block.to_buf(block);
+                    monotone::check_bits(self.env, "loans in scope", block, self.loans.len(),
+                                         old_bits.as_slice(), bits.as_slice(),
+                                         |bit| format!("the loan of `{}` at {:?}",
+                                                       self.loans[bit].path,
+                                                       self.loans[bit].point));
+                }
                 changed |= self.loans_in_scope_after_block
                     .insert_bits_from_slice(block, bits.as_slice());
             }
         }
+        Ok(())
     }
 
     fn simulate_block<CB>(&self, buf: &mut BitBuf, block: BasicBlockIndex, mut callback: CB)
@@ -133,22 +375,11 @@ impl<'cx> LoansInScope<'cx> {
 
         // everything live at end of a pred  is live at the exit of the block
         for succ in self.env.graph.predecessors(block) {
-            buf.set_from(self.loans_in_scope_after_block.bits(succ));
+            self.loans_in_scope_after_block.union_into(succ, buf);
         }
 
         // walk through the actions on by one
-        for (index, action) in self.env
-            .graph
-            .block_data(block)
-            .actions()
-            .iter()
-            .enumerate()
-        {
-            let point = Point {
-                block,
-                action: index,
-            };
-
+        for (point, action) in self.env.actions(block) {
             // kill any loans where `point` is not in their region
             for loan_index in self.loans_not_in_scope_at(point) {
                 buf.kill(loan_index);
@@ -157,9 +388,12 @@ impl<'cx> LoansInScope<'cx> {
             // callback at start of the action
             callback(point, Some(action), buf.as_slice());
 
-            // bring the loan into scope after the borrow
-            if let Some(&loan_index) = self.loans_by_point.get(&point) {
-                buf.set(loan_index);
+            // the effects of the action take hold at its mid-point;
+            // first, bring the loans into scope after the borrow
+            if let Some(loan_indices) = self.loans_by_point.get(&point) {
+                for &loan_index in loan_indices {
+                    buf.set(loan_index);
+                }
             }
 
             // figure out which path is overwritten by this action;
@@ -169,24 +403,47 @@ impl<'cx> LoansInScope<'cx> {
                     buf.kill(loan_index);
                 }
             }
+
+            // once a variable is dead, paths based on it no longer
+            // evaluate to anything
+            if let repr::ActionKind::StorageDead(var) = action.kind {
+                if self.kill_mode.kills_storage_dead() {
+                    for loan_index in self.loans_killed_by_write_to(&repr::Path::Var(var)) {
+                        buf.kill(loan_index);
+                    }
+                }
+            }
+
+            // callback at the mid-point of the action
+            let mid_point = point.mid();
+            for loan_index in self.loans_not_in_scope_at(mid_point) {
+                buf.kill(loan_index);
+            }
+            callback(mid_point, None, buf.as_slice());
         }
 
-        // final callback for the terminator
+        // final callbacks for the terminator
         let point = self.env.end_point(block);
-        for loan_index in self.loans_not_in_scope_at(point) {
-            buf.kill(loan_index);
+        for point in once(point).chain(once(point.mid())) {
+            for loan_index in self.loans_not_in_scope_at(point) {
+                buf.kill(loan_index);
+            }
+            callback(point, None, buf.as_slice());
         }
-        callback(point, None, buf.as_slice());
     }
 
     fn loans_not_in_scope_at<'a>(&'a self, point: Point) -> impl Iterator<Item = usize> + 'a {
-        self.loans.iter().enumerate().filter_map(
-            move |(loan_index, loan)| if !loan.region.may_contain(point) {
+        self.loans.iter().enumerate().filter_map(move |(loan_index, loan)| {
+            let value_dead = match loan.value_live {
+                Some(ref live) => !live.may_contain(point),
+                None => false,
+            };
+            if !loan.region.may_contain(point) || value_dead {
                 Some(loan_index)
             } else {
                 None
-            },
-        )
+            }
+        })
     }
 
     fn loans_killed_by_write_to<'a>(
@@ -196,8 +453,17 @@ impl<'cx> LoansInScope<'cx> {
         // When an assignment like `a.b.c = ...` occurs, we kill all
         // the loans for `a.b.c` or some subpath like `a.b.c.d`, since
         // the path no longer evaluates to the same thing.
+        //
+        // This is type-aware, however: we only kill loans for which
+        // `a.b.c` is a *supporting* prefix. Overwriting `a.b.c` kills
+        // a loan of `(*a.b.c).d` when `a.b.c` is an `&mut` reference
+        // -- the overwritten reference was the only way to reach that
+        // memory (see `borrowck-walk-linked-list.nll`) -- but not when
+        // it is a shared reference, since the referent memory survives
+        // and may still be reachable through copies of the reference.
+        let path = self.paths.index(path);
         self.loans.iter().enumerate().filter_map(
-            move |(index, loan)| if loan.path.prefixes().iter().any(|&p| p == path) {
+            move |(index, loan)| if self.paths.is_supporting_prefix_of(path, loan.path_index) {
                 Some(index)
             } else {
                 None
@@ -206,6 +472,20 @@ impl<'cx> LoansInScope<'cx> {
     }
 }
 
+impl<'cx> Loan<'cx> {
+    /// Merges the points where the reference of another loan may be
+    /// used into those of this one, when loans are merged by region.
+    fn merge_value_live(&mut self, other: Option<Region>) {
+        self.value_live = match (self.value_live.take(), other) {
+            (Some(mut live), Some(other)) => {
+                live.union(&other);
+                Some(live)
+            }
+            _ => None,
+        };
+    }
+}
+
 pub trait Overwrites {
     /// Returns path that this action overwrites, if any.
     fn overwrites(&self) -> Option<&repr::Path>;
@@ -217,11 +497,15 @@ impl Overwrites for repr::Action {
             repr::ActionKind::Borrow(ref p, _name, _, _) => Some(p),
             repr::ActionKind::Init(ref a, _) => Some(a),
             repr::ActionKind::Assign(ref a, _) => Some(a),
+            repr::ActionKind::Const(ref a, _) => Some(a),
+            repr::ActionKind::Closure(ref a, _) => Some(a),
+            repr::ActionKind::RawBorrow(ref a, _, _) => Some(a),
+            repr::ActionKind::Write(ref a, _) => Some(a),
             repr::ActionKind::Constraint(ref _c) => None,
             repr::ActionKind::Use(_) => None,
+            repr::ActionKind::Read(..) => None,
             repr::ActionKind::Drop(_) => None,
             repr::ActionKind::Noop => None,
-            repr::ActionKind::SkolemizedEnd(_) => None,
             repr::ActionKind::StorageDead(_) => None,
         }
     }
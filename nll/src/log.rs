@@ -1,3 +1,11 @@
+//! Debug logging. Each `log!` line goes to a sink: it is printed if
+//! `NLL_DEBUG` is set, and recorded if the line is logged within
+//! `capture`, so that tests can check what the analysis decided along
+//! the way, and not only the errors it reported.
+
+use std::cell::RefCell;
+use std::mem;
+
 lazy_static! {
     pub static ref DEBUG_ENABLED: bool = {
         use std::env;
@@ -5,10 +13,53 @@ lazy_static! {
     };
 }
 
+thread_local! {
+    /// The lines logged so far within the innermost `capture`, if any.
+    static CAPTURED: RefCell<Option<Vec<String>>> = RefCell::new(None);
+}
+
 macro_rules! log {
     ($($t:tt)*) => {
-        if *::log::DEBUG_ENABLED {
-            println!($($t)*)
+        if ::log::enabled() {
+            ::log::write(format!($($t)*))
+        }
+    }
+}
+
+/// True if a logged line would go anywhere; `log!` checks this before
+/// formatting the line.
+pub fn enabled() -> bool {
+    *DEBUG_ENABLED || CAPTURED.with(|captured| captured.borrow().is_some())
+}
+
+pub fn write(line: String) {
+    if *DEBUG_ENABLED {
+        println!("{}", line);
+    }
+    CAPTURED.with(|captured| if let Some(ref mut lines) = *captured.borrow_mut() {
+        lines.push(line);
+    });
+}
+
+/// Runs `f`, returning what it returns along with the lines it logged
+/// (on this thread). Captures nest: the lines logged within an inner
+/// `capture` are returned by it, and not by the outer one.
+pub fn capture<F, R>(f: F) -> (R, Vec<String>)
+where
+    F: FnOnce() -> R,
+{
+    // Restores the enclosing capture even if `f` panics.
+    struct Restore(Option<Vec<String>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let outer = self.0.take();
+            CAPTURED.with(|captured| *captured.borrow_mut() = outer);
         }
     }
+
+    let outer = CAPTURED.with(|captured| mem::replace(&mut *captured.borrow_mut(), Some(vec![])));
+    let _restore = Restore(outer);
+    let result = f();
+    let lines = CAPTURED.with(|captured| captured.borrow_mut().take()).unwrap_or_default();
+    (result, lines)
 }
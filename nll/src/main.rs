@@ -1,90 +1,669 @@
 #![feature(conservative_impl_trait)]
 
-#[macro_use]
-extern crate lazy_static;
-
 extern crate docopt;
-extern crate lalrpop_intern;
-extern crate graph_algorithms;
+extern crate nll;
 extern crate nll_repr;
 extern crate rustc_serialize;
 
 use docopt::Docopt;
+use nll::annotate_diff;
+use nll::baseline;
+use nll::bench;
+use nll::cache::{Cache, Outcome};
+use nll::complexity::{Complexity, Limits};
+use nll::corpus;
+use nll::datalog;
+use nll::dot;
+use nll::elaborate_drops;
+use nll::env::Environment;
+use nll::errors::SourceFile;
+use nll::graph::{self, FuncGraph};
+use nll::options::{ConstraintOrder, KillMode, LoanGranularity, LoanSetRepr, Options, Preset,
+                   RegionRepr, Transform};
+use nll::regionck;
+use nll::shard::Shard;
+use nll::timeout;
+use nll::what_if;
 use nll_repr::repr::*;
 use std::env::args;
 use std::error::Error;
-use std::fs::File;
-use std::io::Read;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 use std::process;
+use std::time::Duration;
 
-#[macro_use]
-mod log;
-mod borrowck;
-mod env;
-mod errors;
-use self::env::Environment;
-mod infer;
-mod loans_in_scope;
-mod liveness;
-mod graph;
-mod region;
-mod regionck;
-use self::graph::FuncGraph;
+/// Lets `nll bench-compare` measure the memory used by each engine.
+#[global_allocator]
+static ALLOCATOR: bench::PeakAllocator = bench::PeakAllocator;
 
 fn main() {
     let args: Args = Docopt::new(USAGE)
         .and_then(|d| d.argv(args()).decode())
         .unwrap_or_else(|e| e.exit());
 
+    if args.flag_emit_datalog_rules {
+        print!("{}", datalog::RULES);
+        return;
+    }
+
+    if args.cmd_what_if {
+        if let Err(err) = run_what_if(&args) {
+            println!("{}: {}", args.arg_file, err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.cmd_annotate_diff {
+        if let Err(err) = run_annotate_diff(&args) {
+            println!("{}: {}", args.arg_file, err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.cmd_bench_compare {
+        if let Err(err) = run_bench_compare(&args) {
+            println!("{}: {}", args.arg_dir, err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.cmd_corpus {
+        if let Err(err) = run_corpus(&args) {
+            println!("{}: {}", args.arg_dir, err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.cmd_baseline {
+        if let Err(err) = run_baseline(&args) {
+            println!("{}: {}", args.arg_dir, err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if args.cmd_query {
+        if let Err(err) = run_query(&args) {
+            println!("{}: {}", args.flag_db, err);
+            process::exit(1);
+        }
+        return;
+    }
+
+    let limits = match limits(&args) {
+        Ok(limits) => limits,
+        Err(err) => {
+            println!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let inputs = match shard_inputs(&args) {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            println!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    if !args.flag_preset.is_empty() {
+        match args.flag_preset.parse::<Preset>() {
+            Ok(preset) => println!("Using {}", preset.describe()),
+            Err(err) => {
+                println!("{}", err);
+                process::exit(1);
+            }
+        }
+    }
+
     let mut errors = 0;
-    for input in &args.arg_inputs {
-        match process_input(&args, input) {
-            Ok(()) => { }
+    let mut skipped = 0;
+    for input in &inputs {
+        match process_input(&args, &limits, input) {
+            Ok(Processed::Analyzed) => { }
+            Ok(Processed::Skipped) => skipped += 1,
             Err(err) => {
                 println!("{}: {}", input, err);
                 errors += 1;
             }
         }
     }
+    if skipped > 0 {
+        println!("{} of {} inputs skipped", skipped, inputs.len());
+    }
     if errors > 0 {
         process::exit(1);
     }
 }
 
-fn process_input(args: &Args, input: &str) -> Result<(), Box<Error>> {
-    let mut file_text = String::new();
-    let mut file = try!(File::open(input));
-    if file.read_to_string(&mut file_text).is_err() {
-        return try!(Err(String::from("not UTF-8")));
-    }
-    let func = try!(Func::parse(&file_text));
+enum Processed {
+    Analyzed,
+
+    /// The input exceeded `--max-blocks` or `--max-constraints`.
+    Skipped,
+}
+
+fn process_input(args: &Args, limits: &Limits, input: &str) -> Result<Processed, Box<Error>> {
+    let (file_text, func) = try!(read_input(args, input));
+    let options = try!(options(args));
+    let timeout = try!(timeout(args));
+
+    // Only the outcome of the analysis is cached, so runs that print
+    // anything more than that always run the analysis.
+    let cache = if args.flag_cache_dir.is_empty() || args.prints_analysis_output() {
+        None
+    } else {
+        let key = Cache::key(&func, &options);
+        Some((try!(Cache::new(&args.flag_cache_dir)), key))
+    };
+
     let graph = FuncGraph::new(func);
+    let source = SourceFile {
+        name: input,
+        text: &file_text,
+    };
     graph::with_graph(&graph, || {
         let env = Environment::new(&graph);
 
+        // The limits are checked before the cache, so that whether an
+        // input is skipped does not depend on what was run before.
+        if args.flag_complexity || limits.max_blocks.is_some() || limits.max_constraints.is_some() {
+            let complexity = Complexity::estimate(&env);
+            if args.flag_complexity {
+                println!("Complexity of `{}`: {}", input, complexity);
+            }
+            if let Err(reason) = limits.check(&complexity) {
+                println!("Skipping `{}`: {}", input, reason);
+                return Ok(Processed::Skipped);
+            }
+        }
+
+        if let Some((ref cache, ref key)) = cache {
+            if let Some(outcome) = cache.lookup(key) {
+                println!("Testing `{}`...", input);
+                return report(outcome);
+            }
+        }
+
         if args.flag_dominators {
             env.dump_dominators();
         }
 
+        if args.flag_post_dominators {
+            env.dump_post_dominators();
+        }
+
+        if args.flag_dump_analyses {
+            env.dump_analyses();
+        }
+
+        if args.flag_dump_dot {
+            print!("{}", dot::dot(&env, &file_text));
+        }
+
         println!("Testing `{}`...", input);
-        try!(regionck::region_check(&env));
+
+        // Render any error while the block names are still known, so
+        // that it reads the same whether or not it came from the cache.
+        // An analysis that timed out has no outcome to cache.
+        let check = || {
+            let mut output = vec![];
+            let result = regionck::region_check(&env, &options, &source, &mut output);
+            Outcome { output, error: result.err().map(|e| e.to_string()) }
+        };
+        let outcome = match timeout {
+            Some(limit) => try!(timeout::run(limit, check)),
+            None => check(),
+        };
+        if let Some((ref cache, ref key)) = cache {
+            try!(cache.store(key, &outcome));
+        }
+        report(outcome)
+    })
+}
+
+/// Prints the lines that the check of an input printed, then fails
+/// with its error, if any.
+fn report(outcome: Outcome) -> Result<Processed, Box<Error>> {
+    for line in &outcome.output {
+        println!("{}", line);
+    }
+    match outcome.error {
+        Some(error) => Err(error.into()),
+        None => Ok(Processed::Analyzed),
+    }
+}
+
+/// `nll what-if`: prints what inserting the `--action` at the point
+/// `--at` would change.
+fn run_what_if(args: &Args) -> Result<(), Box<Error>> {
+    let (file_text, func) = try!(read_input(args, &args.arg_file));
+    let options = try!(options(args));
+    let graph = FuncGraph::new(func);
+    let source = SourceFile {
+        name: &args.arg_file,
+        text: &file_text,
+    };
+    graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        let point = try!(env.parse_point(&args.flag_at));
+        print!("{}", try!(what_if::what_if(&env, &options, &source, point, &args.flag_action)));
+        Ok(())
+    })
+}
+
+/// `nll annotate-diff`: prints what removing the region annotations
+/// of the borrows at the point `--flip` would change.
+fn run_annotate_diff(args: &Args) -> Result<(), Box<Error>> {
+    let (file_text, func) = try!(read_input(args, &args.arg_file));
+    let options = try!(options(args));
+    let graph = FuncGraph::new(func);
+    let source = SourceFile {
+        name: &args.arg_file,
+        text: &file_text,
+    };
+    graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        let point = try!(env.parse_point(&args.flag_flip));
+        print!("{}", try!(annotate_diff::annotate_diff(&env, &options, &source, point)));
         Ok(())
     })
 }
 
+/// The `.nll` files in `dir`, sorted by name.
+fn nll_files(dir: &str) -> Result<Vec<String>, Box<Error>> {
+    let mut inputs = vec![];
+    for entry in try!(fs::read_dir(dir)) {
+        let path = try!(entry).path();
+        if path.extension().map_or(false, |ext| ext == "nll") {
+            inputs.push(path.to_string_lossy().into_owned());
+        }
+    }
+    inputs.sort();
+    Ok(inputs)
+}
+
+fn run_bench_compare(args: &Args) -> Result<(), Box<Error>> {
+    let options = try!(options(args));
+    let inputs = try!(nll_files(&args.arg_dir));
+
+    let mut comparisons = vec![];
+    for input in &inputs {
+        let (file_text, func) = match read_input(args, input) {
+            Ok(result) => result,
+            Err(err) => {
+                println!("{}: {}", input, err);
+                continue;
+            }
+        };
+        let source = SourceFile {
+            name: input,
+            text: &file_text,
+        };
+        let comparison = bench::compare(input, &func, &options, &source);
+        println!("{}", comparison);
+        comparisons.push(comparison);
+    }
+    print!("{}", bench::Summary { comparisons: &comparisons });
+    Ok(())
+}
+
+/// `nll corpus`: analyzes every file in the directory, and writes the
+/// records for each to the database `--db`, for `nll query`.
+fn run_corpus(args: &Args) -> Result<(), Box<Error>> {
+    let options = try!(options(args));
+    let inputs = try!(nll_files(&args.arg_dir));
+    let mut db = BufWriter::new(try!(File::create(&args.flag_db)));
+    let mut num_records = 0;
+    for input in &inputs {
+        let (file_text, func) = match read_input(args, input) {
+            Ok(result) => result,
+            Err(err) => {
+                println!("{}: {}", input, err);
+                continue;
+            }
+        };
+        let source = SourceFile {
+            name: input,
+            text: &file_text,
+        };
+        for record in corpus::records(input, &func, &options, &source) {
+            try!(writeln!(db, "{}", record));
+            num_records += 1;
+        }
+    }
+    try!(db.flush());
+    println!("{} records for {} inputs written to `{}`", num_records, inputs.len(), args.flag_db);
+    Ok(())
+}
+
+/// `nll baseline save`: analyzes every file in the directory, and
+/// writes what it found for each to `--db`. `nll baseline compare`:
+/// analyzes them again, and reports how the results differ from those
+/// in `--db`; any difference is an error.
+fn run_baseline(args: &Args) -> Result<(), Box<Error>> {
+    let options = try!(options(args));
+    let inputs = try!(nll_files(&args.arg_dir));
+    let mut current = vec![];
+    for input in &inputs {
+        let file = std::path::Path::new(input)
+            .strip_prefix(&args.arg_dir)
+            .map(|file| file.display().to_string())
+            .unwrap_or(input.clone());
+        current.push(match read_input(args, input) {
+            Ok((file_text, func)) => {
+                let source = SourceFile {
+                    name: input,
+                    text: &file_text,
+                };
+                baseline::Results::analyze(&file, &func, &options, &source)
+            }
+            Err(err) => baseline::Results::failed(&file, err.to_string()),
+        });
+    }
+
+    if args.cmd_save {
+        let mut db = BufWriter::new(try!(File::create(&args.flag_db)));
+        for results in &current {
+            try!(writeln!(db, "{}", results.to_json()));
+        }
+        try!(db.flush());
+        println!("results for {} inputs written to `{}`", current.len(), args.flag_db);
+        return Ok(());
+    }
+
+    let mut saved = vec![];
+    for (index, line) in BufReader::new(try!(File::open(&args.flag_db))).lines().enumerate() {
+        let line = try!(line);
+        let json = match rustc_serialize::json::Json::from_str(&line) {
+            Ok(json) => json,
+            Err(err) => try!(Err(format!("line {}: {}", index + 1, err))),
+        };
+        match baseline::Results::from_json(&json) {
+            Ok(results) => saved.push(results),
+            Err(err) => try!(Err(format!("line {}: {}", index + 1, err))),
+        }
+    }
+    let comparison = baseline::compare(&saved, &current);
+    print!("{}", comparison);
+    if !comparison.changed.is_empty() {
+        try!(Err(format!("results differ from the baseline `{}`", args.flag_db)));
+    }
+    Ok(())
+}
+
+/// `nll query`: answers the query from the records in `--db`.
+fn run_query(args: &Args) -> Result<(), Box<Error>> {
+    let query = try!(corpus::Query::parse(&args.arg_query));
+    let mut records = vec![];
+    for (index, line) in BufReader::new(try!(File::open(&args.flag_db))).lines().enumerate() {
+        let line = try!(line);
+        match rustc_serialize::json::Json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(err) => try!(Err(format!("line {}: {}", index + 1, err))),
+        }
+    }
+    print!("{}", query.run(&records));
+    Ok(())
+}
+
+fn read_input(args: &Args, input: &str) -> Result<(String, Func), Box<Error>> {
+    let mut file_text = String::new();
+    let mut file = try!(File::open(input));
+    if file.read_to_string(&mut file_text).is_err() {
+        return try!(Err(String::from("not UTF-8")));
+    }
+    let mut func = try!(Func::parse_file(std::path::Path::new(input), &file_text));
+    if args.flag_elaborate_drops {
+        elaborate_drops::elaborate_drops(&mut func);
+    }
+    Ok((file_text, func))
+}
+
+fn limits(args: &Args) -> Result<Limits, Box<Error>> {
+    let limit = |flag: &str, value: &str| -> Result<Option<usize>, Box<Error>> {
+        if value.is_empty() {
+            return Ok(None);
+        }
+        match value.parse() {
+            Ok(limit) => Ok(Some(limit)),
+            Err(_) => try!(Err(format!("{} expects a number, not `{}`", flag, value))),
+        }
+    };
+    Ok(Limits {
+        max_blocks: try!(limit("--max-blocks", &args.flag_max_blocks)),
+        max_constraints: try!(limit("--max-constraints", &args.flag_max_constraints)),
+    })
+}
+
+/// The inputs to analyze: all of them, or with `--shard`, those
+/// that belong to the given shard.
+fn shard_inputs(args: &Args) -> Result<Vec<&str>, Box<Error>> {
+    let inputs = args.arg_inputs.iter().map(|input| &input[..]);
+    if args.flag_shard.is_empty() {
+        return Ok(inputs.collect());
+    }
+    let shard = try!(args.flag_shard.parse::<Shard>());
+    Ok(inputs.filter(|input| shard.contains(input)).collect())
+}
+
+fn timeout(args: &Args) -> Result<Option<Duration>, Box<Error>> {
+    if args.flag_timeout.is_empty() {
+        return Ok(None);
+    }
+    match args.flag_timeout.parse::<f64>() {
+        Ok(secs) if secs > 0.0 => Ok(Some(Duration::from_secs_f64(secs))),
+        _ => try!(Err(format!("--timeout expects a positive number of seconds, not `{}`",
+                              args.flag_timeout))),
+    }
+}
+
+fn options(args: &Args) -> Result<Options, Box<Error>> {
+    let mut options = Options {
+        self_check: args.flag_self_check,
+        loan_granularity: if args.flag_merge_loans_by_region {
+            LoanGranularity::PerRegion
+        } else {
+            LoanGranularity::PerBorrow
+        },
+        kill_mode: if args.flag_kill_mode.is_empty() {
+            KillMode::default()
+        } else {
+            try!(args.flag_kill_mode.parse::<KillMode>())
+        },
+        dump_loans: args.flag_dump_loans,
+        dump_joins: args.flag_dump_joins,
+        emit_stderr_format: args.flag_emit_stderr_format,
+        show_suppressed: args.flag_show_suppressed,
+        constraint_order: try!(args.flag_constraint_order.parse::<ConstraintOrder>()),
+        solver_stats: args.flag_solver_stats,
+        region_repr: try!(args.flag_region_repr.parse::<RegionRepr>()),
+        loan_set_repr: try!(args.flag_loan_set_repr.parse::<LoanSetRepr>()),
+        memory_stats: args.flag_memory_stats,
+        hot_constraints: args.flag_hot_constraints,
+        implied_bounds: args.flag_implied_bounds,
+        warn_dead_stores: args.flag_warn_dead_stores,
+        skip_regionck: args.flag_no_regionck || args.flag_liveness_only,
+        skip_borrowck: args.flag_no_borrowck || args.flag_liveness_only,
+        state_at: if args.flag_state_at.is_empty() {
+            None
+        } else {
+            Some(args.flag_state_at.clone())
+        },
+        why_error: if args.flag_why_error.is_empty() {
+            None
+        } else {
+            Some(args.flag_why_error.clone())
+        },
+        trace_path: if args.flag_trace_path.is_empty() {
+            None
+        } else {
+            Some(args.flag_trace_path.clone())
+        },
+        // only for tools embedding the analysis; set `NLL_DEBUG` to
+        // see the log here
+        capture_log: false,
+        // set by `nll corpus` for its records
+        provenance: false,
+        transforms: if args.flag_transform.is_empty() {
+            vec![]
+        } else {
+            try!(args.flag_transform.split(',').map(|t| t.trim().parse::<Transform>()).collect())
+        },
+        emit_csv: if args.flag_emit_csv.is_empty() {
+            None
+        } else {
+            Some(args.flag_emit_csv.clone())
+        },
+    };
+
+    // A preset replaces the settings it chooses, so any of them given
+    // explicitly as well must agree with it.
+    if !args.flag_preset.is_empty() {
+        let preset = try!(args.flag_preset.parse::<Preset>());
+        let explicit = options.clone();
+        preset.apply(&mut options);
+        let conflict = if !args.flag_kill_mode.is_empty() && explicit.kill_mode != options.kill_mode {
+            Some(format!("--kill-mode {}", args.flag_kill_mode))
+        } else if args.flag_implied_bounds && !options.implied_bounds {
+            Some("--implied-bounds".to_string())
+        } else if args.flag_merge_loans_by_region &&
+                  options.loan_granularity != LoanGranularity::PerRegion {
+            Some("--merge-loans-by-region".to_string())
+        } else {
+            None
+        };
+        if let Some(flag) = conflict {
+            try!(Err(format!("`{}` conflicts with the {}", flag, preset.describe())));
+        }
+    }
+    Ok(options)
+}
+
 const USAGE: &'static str = "
-Usage: nll [options] <inputs>...
+Usage: nll what-if [options] <file> --at <point> --action <action>
+       nll annotate-diff [options] <file> --flip <point>
+       nll bench-compare [options] <dir>
+       nll corpus [options] <dir> --db <file>
+       nll baseline save [options] <dir> --db <file>
+       nll baseline compare [options] <dir> --db <file>
+       nll query --db <file> <query>
+       nll [options] <inputs>...
+       nll --emit-datalog-rules
 
 Options:
   --help
   --dominators
   --post-dominators
+  --dump-analyses
+  --dump-dot
+  --elaborate-drops
+  --transform <transforms>
+  --cache-dir <dir>
+  --db <file>
+  --complexity
+  --max-blocks <n>
+  --max-constraints <n>
+  --timeout <secs>
+  --shard <shard>
+  --self-check
+  --merge-loans-by-region
+  --preset <preset>
+  --kill-mode <mode>
+  --dump-loans
+  --dump-joins
+  --emit-stderr-format
+  --show-suppressed
+  --constraint-order <order>  [default: program]
+  --solver-stats
+  --region-repr <repr>  [default: set]
+  --loan-set-repr <repr>  [default: dense]
+  --memory-stats
+  --hot-constraints
+  --implied-bounds
+  --warn-dead-stores
+  --no-borrowck
+  --no-regionck
+  --liveness-only
+  --state-at <point>
+  --why-error <point>
+  --trace-path <path>
+  --emit-csv <dir>
+  --at <point>
+  --action <action>
+  --flip <point>
+  --emit-datalog-rules
 ";
 
 #[derive(Debug, RustcDecodable)]
 struct Args {
     arg_inputs: Vec<String>,
+    cmd_what_if: bool,
+    cmd_annotate_diff: bool,
+    arg_file: String,
+    flag_at: String,
+    flag_action: String,
+    flag_flip: String,
+    cmd_bench_compare: bool,
+    arg_dir: String,
+    cmd_corpus: bool,
+    flag_db: String,
+    cmd_baseline: bool,
+    cmd_save: bool,
+    cmd_query: bool,
+    arg_query: String,
     flag_dominators: bool,
     flag_post_dominators: bool,
+    flag_dump_analyses: bool,
+    flag_dump_dot: bool,
+    flag_elaborate_drops: bool,
+    flag_transform: String,
+    flag_cache_dir: String,
+    flag_complexity: bool,
+    flag_max_blocks: String,
+    flag_max_constraints: String,
+    flag_timeout: String,
+    flag_shard: String,
+    flag_self_check: bool,
+    flag_merge_loans_by_region: bool,
+    flag_preset: String,
+    flag_kill_mode: String,
+    flag_dump_loans: bool,
+    flag_dump_joins: bool,
+    flag_emit_stderr_format: bool,
+    flag_show_suppressed: bool,
+    flag_constraint_order: String,
+    flag_solver_stats: bool,
+    flag_region_repr: String,
+    flag_loan_set_repr: String,
+    flag_memory_stats: bool,
+    flag_hot_constraints: bool,
+    flag_implied_bounds: bool,
+    flag_warn_dead_stores: bool,
+    flag_no_borrowck: bool,
+    flag_no_regionck: bool,
+    flag_liveness_only: bool,
+    flag_state_at: String,
+    flag_why_error: String,
+    flag_trace_path: String,
+    flag_emit_csv: String,
+    flag_emit_datalog_rules: bool,
     flag_help: bool,
 }
+
+impl Args {
+    /// True if any of the flags asks for output beyond whether each
+    /// file passed.
+    fn prints_analysis_output(&self) -> bool {
+        self.flag_dominators || self.flag_post_dominators || self.flag_dump_analyses ||
+            self.flag_dump_dot || self.flag_dump_loans || self.flag_dump_joins ||
+            self.flag_emit_stderr_format || self.flag_show_suppressed || self.flag_solver_stats ||
+            self.flag_memory_stats || self.flag_hot_constraints || !self.flag_state_at.is_empty() ||
+            !self.flag_why_error.is_empty() || !self.flag_trace_path.is_empty() ||
+            !self.flag_emit_csv.is_empty()
+    }
+}
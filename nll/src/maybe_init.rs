@@ -0,0 +1,117 @@
+use env::{Environment, Point};
+use graph::{BasicBlockIndex, FuncGraph};
+use graph_algorithms::Graph;
+use graph_algorithms::bit_set::{BitBuf, BitSet, BitSlice};
+use loans_in_scope::Overwrites;
+use nll_repr::repr;
+use std::collections::HashMap;
+use monotone;
+use timeout::{self, TimedOut};
+
+/// Computes the set of variables that *may* be initialized at each
+/// point: those for which some path from the start assigns the
+/// variable, without a later move out of it (or `StorageDead`).
+pub struct MaybeInit<'env> {
+    env: &'env Environment<'env>,
+    vars: Vec<repr::Variable>,
+    var_bits: HashMap<repr::Variable, usize>,
+    init_after_block: BitSet<FuncGraph>,
+}
+
+impl<'env> MaybeInit<'env> {
+    pub fn new(env: &'env Environment<'env>) -> Result<Self, TimedOut> {
+        let vars: Vec<_> = env.graph.decls().iter().map(|d| d.var).collect();
+        let var_bits = vars.iter().cloned().enumerate().map(|(i, v)| (v, i)).collect();
+        let init_after_block = BitSet::new(env.graph, vars.len());
+        let mut this = MaybeInit {
+            env,
+            vars,
+            var_bits,
+            init_after_block,
+        };
+        this.compute()?;
+        Ok(this)
+    }
+
+    /// The variables whose bits are set in `bits`.
+    pub fn variables<'a>(&'a self, bits: BitSlice<'a>) -> impl Iterator<Item = repr::Variable> + 'a {
+        self.vars
+            .iter()
+            .enumerate()
+            .filter(move |&(index, _)| bits.get(index))
+            .map(|(_, &var)| var)
+    }
+
+    /// Invokes `callback` with the variables that may be initialized
+    /// on entry to each point in `block`.
+    pub fn walk_block<CB>(&self, block: BasicBlockIndex, callback: CB)
+    where
+        CB: FnMut(Point, Option<&repr::Action>, BitSlice),
+    {
+        let mut bits = self.init_after_block.empty_buf();
+        self.simulate_block(&mut bits, block, callback);
+    }
+
+    fn compute(&mut self) -> Result<(), TimedOut> {
+        let mut bits = self.init_after_block.empty_buf();
+        let mut changed = true;
+        while changed {
+            timeout::round()?;
+            changed = false;
+
+            for &block in &self.env.reverse_post_order {
+                self.simulate_block(&mut bits, block, |_p, _a, _s| ());
+                monotone::check_bits(self.env, "maybe-initialized", block, self.vars.len(),
+                                     self.init_after_block.bits(block), bits.as_slice(),
+                                     |bit| format!("`{:?}`", self.vars[bit]));
+                changed |= self.init_after_block
+                    .insert_bits_from_slice(block, bits.as_slice());
+            }
+        }
+        Ok(())
+    }
+
+    fn simulate_block<CB>(&self, buf: &mut BitBuf, block: BasicBlockIndex, mut callback: CB)
+    where
+        CB: FnMut(Point, Option<&repr::Action>, BitSlice),
+    {
+        buf.clear();
+
+        // anything initialized at the exit of a pred may be
+        // initialized on entry to the block
+        for pred in self.env.graph.predecessors(block) {
+            buf.set_from(self.init_after_block.bits(pred));
+        }
+
+        for (point, action) in self.env.actions(block) {
+            callback(point, Some(action), buf.as_slice());
+
+            // moving out of a variable, or killing its storage, leaves
+            // it uninitialized
+            let moved: Vec<_> = match action.kind {
+                repr::ActionKind::Drop(ref path) => path.write_def().into_iter().collect(),
+                repr::ActionKind::Closure(_, ref captures) => captures
+                    .iter()
+                    .filter(|c| c.mode == repr::CaptureMode::ByMove)
+                    .filter_map(|c| c.path.write_def())
+                    .collect(),
+                repr::ActionKind::StorageDead(var) => vec![var],
+                _ => vec![],
+            };
+            for var in moved {
+                buf.kill(self.var_bits[&var]);
+            }
+
+            // overwriting the variable as a whole initializes it
+            if let Some(var) = action.overwrites().and_then(|p| p.write_def()) {
+                buf.set(self.var_bits[&var]);
+            }
+
+            callback(point.mid(), None, buf.as_slice());
+        }
+
+        let end_point = self.env.end_point(block);
+        callback(end_point, None, buf.as_slice());
+        callback(end_point.mid(), None, buf.as_slice());
+    }
+}
@@ -0,0 +1,62 @@
+//! `--memory-stats`: where the memory of the analysis of a function
+//! goes. Memory, more than time, limits the size of the programs that
+//! can be analyzed, so for each analysis we count the bit sets and
+//! region values it keeps, along with the number of paths and region
+//! variables they are indexed by. There are no arenas to report on:
+//! paths are the only things interned per function (see `PathTable`),
+//! and types are boxed where they are used.
+
+use options::RegionRepr;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+
+pub struct MemoryStats {
+    /// The paths of the function, and the bytes of their prefix bit
+    /// sets; `None` without borrow checking.
+    pub paths: Option<(usize, usize)>,
+
+    pub region_repr: RegionRepr,
+
+    /// The region variables, and the bytes of their values.
+    pub regions: (usize, usize),
+
+    /// The bytes of the bit sets of `Liveness`.
+    pub liveness: usize,
+
+    /// The loans, and the bytes of the bit sets and regions of
+    /// `LoansInScope`; `None` without borrow checking.
+    pub loans: Option<(usize, usize)>,
+}
+
+impl fmt::Display for MemoryStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((paths, bytes)) = self.paths {
+            writeln!(f, "memory: path table: {} paths, {} bytes", paths, bytes)?;
+        }
+        let (regions, bytes) = self.regions;
+        writeln!(f, "memory: region values: {} variables, {} bytes ({})",
+                 regions, bytes, self.region_repr)?;
+        writeln!(f, "memory: liveness: {} bytes", self.liveness)?;
+        if let Some((loans, bytes)) = self.loans {
+            writeln!(f, "memory: loans in scope: {} loans, {} bytes", loans, bytes)?;
+        }
+        match peak_rss() {
+            Some(kib) => writeln!(f, "memory: peak RSS: {}KiB", kib),
+            None => writeln!(f, "memory: peak RSS: unknown"),
+        }
+    }
+}
+
+/// The most memory the process has had resident so far, in KiB, as
+/// the `VmHWM` line of `/proc/self/status` has it; `None` where there
+/// is no such file.
+fn peak_rss() -> Option<usize> {
+    let mut status = String::new();
+    File::open("/proc/self/status").and_then(|mut file| file.read_to_string(&mut status)).ok()?;
+    status
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|kib| kib.parse().ok())
+}
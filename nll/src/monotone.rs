@@ -0,0 +1,55 @@
+//! Debug checks that the fixed-point loops of the analysis are
+//! monotone: each pass may only add to what the passes before it
+//! found. The loops merge what a pass computes into their results, so
+//! a transfer function that loses a bit or a point would not show up
+//! as a shrinking result; it would silently give a wrong fixed point,
+//! or none at all once a loop stops merging. These checks catch it at
+//! the pass that does it, with a dump of the values before and after.
+
+use env::Environment;
+use graph::BasicBlockIndex;
+use graph_algorithms::bit_set::BitSlice;
+use region::Region;
+
+/// Checks that `computed`, the `bits` bits computed by a pass of the
+/// fixed point `phase` for `block`, has every bit of `previous`, those
+/// found for it by the passes before. `describe` names a bit.
+pub fn check_bits<D>(env: &Environment,
+                     phase: &str,
+                     block: BasicBlockIndex,
+                     bits: usize,
+                     previous: BitSlice,
+                     computed: BitSlice,
+                     describe: D)
+    where D: Fn(usize) -> String
+{
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    if let Some(lost) = previous.first_not_in(computed) {
+        let dump = |slice: BitSlice| -> String {
+            let set: Vec<_> = (0..bits).filter(|&bit| slice.get(bit)).map(&describe).collect();
+            format!("{{{}}}", set.join(", "))
+        };
+        panic!("{} is not monotone: a pass lost {} in `{}`\n  before: {}\n  after : {}",
+               phase,
+               describe(lost),
+               env.graph.block_data(block).name,
+               dump(previous),
+               dump(computed));
+    }
+}
+
+/// Checks that `after`, the value of the region variable `name` after
+/// a pass of the region solver, has every element of `before`, its
+/// value before the pass.
+pub fn check_region(name: &str, before: &Region, after: &Region) {
+    if !cfg!(debug_assertions) {
+        return;
+    }
+    if let Some(lost) = before.iter().find(|&e| !after.contains(e)) {
+        panic!("region inference is not monotone: a pass removed `{:?}` from `{}`\n  \
+                before: {}\n  after : {}",
+               lost, name, before, after);
+    }
+}
@@ -0,0 +1,70 @@
+//! Hooks into the phases of the analysis, for experiments (metrics,
+//! alternative checkers, visualizers) that want to look at what each
+//! phase computed without patching `RegionCheck`. Pass an
+//! `AnalysisObserver` to `regionck::analysis_with_observer`.
+
+use env::Point;
+use errors::ReportedError;
+use liveness::Liveness;
+use loans_in_scope::LoanScope;
+use nll_repr::repr::Variable;
+use regionck::RegionValues;
+use std::collections::BTreeMap;
+
+/// Called back by the analysis at the end of each phase, with a
+/// read-only view of what the phase computed. Each method does
+/// nothing by default, so an observer only implements the ones it
+/// is interested in.
+pub trait AnalysisObserver {
+    /// Liveness has been computed.
+    fn after_liveness(&mut self, _liveness: &LivenessView) {}
+
+    /// Region inference has solved the constraints, giving each
+    /// region its value; not called with `skip_regionck`.
+    fn after_inference(&mut self, _regions: &RegionValues) {}
+
+    /// The scope of each loan has been computed, and the borrow check
+    /// is about to use it; not called with `skip_borrowck`.
+    fn after_loans(&mut self, _loan_scopes: &[LoanScope]) {}
+
+    /// Called for each error, in the order they are emitted, once the
+    /// analysis is done. Suppressed errors are not included.
+    fn on_diagnostic(&mut self, _error: &ReportedError) {}
+}
+
+/// The observer that observes nothing.
+pub struct NoObserver;
+
+impl AnalysisObserver for NoObserver {}
+
+/// What liveness found. The variables live at each point are only
+/// gathered when asked for.
+pub struct LivenessView<'a> {
+    liveness: &'a Liveness<'a>,
+}
+
+impl<'a> LivenessView<'a> {
+    pub fn new(liveness: &'a Liveness<'a>) -> Self {
+        LivenessView { liveness }
+    }
+
+    /// The variables whose current value may be used later on, at
+    /// the entry of each point.
+    pub fn live_variables(&self) -> BTreeMap<Point, Vec<Variable>> {
+        let mut live_at = BTreeMap::new();
+        self.liveness.walk(|point, _action, live_bits| {
+            live_at.insert(point, self.liveness.live_variables(live_bits).collect());
+        });
+        live_at
+    }
+
+    /// The variables whose current value may be dropped later on, at
+    /// the entry of each point.
+    pub fn drop_live_variables(&self) -> BTreeMap<Point, Vec<Variable>> {
+        let mut drop_live_at = BTreeMap::new();
+        self.liveness.walk(|point, _action, live_bits| {
+            drop_live_at.insert(point, self.liveness.drop_live_variables(live_bits).collect());
+        });
+        drop_live_at
+    }
+}
@@ -0,0 +1,452 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Settings that control *how* the analysis runs, as opposed to what
+/// is being analyzed. These are mostly set from the command line.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Cross-check optimized computations against their naive
+    /// equivalents, panicking if they ever disagree.
+    pub self_check: bool,
+
+    /// How borrow expressions are grouped into loans.
+    pub loan_granularity: LoanGranularity,
+
+    /// Which events, besides leaving its region, take a loan out of
+    /// scope.
+    pub kill_mode: KillMode,
+
+    /// Print the set of loans before running the borrow check.
+    pub dump_loans: bool,
+
+    /// Print the `join` of each region at the merge points where the
+    /// values flowing in from different predecessors can differ.
+    pub dump_joins: bool,
+
+    /// Print the reported errors to stderr in the layout used by
+    /// rustc's UI tests, so they can be diffed against the
+    /// `.stderr` file of the corresponding Rust program.
+    pub emit_stderr_format: bool,
+
+    /// Print the errors that `#[allow(...)]` annotations suppressed.
+    pub show_suppressed: bool,
+
+    /// The order in which the region solver visits the constraints.
+    /// This affects only how many iterations it takes to reach a
+    /// fixed point, not the result.
+    pub constraint_order: ConstraintOrder,
+
+    /// Print the number of solver iterations.
+    pub solver_stats: bool,
+
+    /// How the elements of each region are stored. This affects only
+    /// the time and memory the analysis takes, not the result.
+    pub region_repr: RegionRepr,
+
+    /// How the loans in scope on exit from each block are stored.
+    /// Like `region_repr`, this does not affect the result.
+    pub loan_set_repr: LoanSetRepr,
+
+    /// Print the memory taken by the bit sets and region values of
+    /// each analysis, and the peak memory of the process.
+    pub memory_stats: bool,
+
+    /// Print the constraints on which the solver spent the most
+    /// work, along with the actions that gave rise to them.
+    pub hot_constraints: bool,
+
+    /// Assume the outlives relations implied by the types of the
+    /// variables: `'b: 'a` wherever a variable of type `&'a Foo<'b>`
+    /// is live.
+    pub implied_bounds: bool,
+
+    /// Warn about each store to a local variable whose value is never
+    /// read or dropped afterwards (see `dead_stores`).
+    pub warn_dead_stores: bool,
+
+    /// Skip region inference; every loan is then in scope everywhere.
+    pub skip_regionck: bool,
+
+    /// Skip the borrow check itself (loans and conflict detection).
+    pub skip_borrowck: bool,
+
+    /// Print a snapshot of the analysis state at this point (written
+    /// like `START/3`).
+    pub state_at: Option<String>,
+
+    /// Explain the borrow error reported at this point: where the
+    /// loan was issued, why its region includes the point, and which
+    /// access rule was broken.
+    pub why_error: Option<String>,
+
+    /// Attach to each borrow error and each assertion result the facts
+    /// of the analysis that it rests on: the loan, the constraints that
+    /// carried the point into its region, and the liveness bits that
+    /// made the region live there (see `errors::Fact`).
+    pub provenance: bool,
+
+    /// Print each borrow check decision that touches this path
+    /// (written like `x.f`): the accesses checked, the loans in scope
+    /// that they were checked against, and which rule matched.
+    pub trace_path: Option<String>,
+
+    /// Rewrite the control-flow graph in these ways, in order, before
+    /// analyzing the function. The errors found are checked against
+    /// those the original function expects; with `self_check`, they
+    /// must also be the ones found in the original function.
+    pub transforms: Vec<Transform>,
+
+    /// Capture the `log!` output of the analysis into the `log` of
+    /// `regionck::analysis`.
+    pub capture_log: bool,
+
+    /// Write the liveness of each variable, the loans in scope and the
+    /// points of each region, at each point, as CSV tables to this
+    /// directory.
+    pub emit_csv: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ConstraintOrder {
+    /// The order in which the constraints were generated.
+    Program,
+
+    /// Sorted by the reverse post-order of the points where the
+    /// constraints arise.
+    Rpo,
+
+    /// Shuffled, using the given seed.
+    Random(u64),
+
+    /// Sorted so that constraints flowing *out* of a group of
+    /// mutually dependent regions come after those flowing in.
+    Scc,
+}
+
+impl Default for ConstraintOrder {
+    fn default() -> Self {
+        ConstraintOrder::Program
+    }
+}
+
+impl FromStr for ConstraintOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "program" => return Ok(ConstraintOrder::Program),
+            "rpo" => return Ok(ConstraintOrder::Rpo),
+            "scc" => return Ok(ConstraintOrder::Scc),
+            _ => {}
+        }
+
+        if s.starts_with("random(") && s.ends_with(")") {
+            if let Ok(seed) = s["random(".len()..s.len() - 1].parse() {
+                return Ok(ConstraintOrder::Random(seed));
+            }
+        }
+
+        Err(format!(
+            "invalid constraint order `{}` (expected `program`, `rpo`, `random(SEED)` or `scc`)",
+            s
+        ))
+    }
+}
+
+impl fmt::Display for ConstraintOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ConstraintOrder::Program => write!(f, "program"),
+            ConstraintOrder::Rpo => write!(f, "rpo"),
+            ConstraintOrder::Random(seed) => write!(f, "random({})", seed),
+            ConstraintOrder::Scc => write!(f, "scc"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoanGranularity {
+    /// Every borrow expression is its own loan, identified by the
+    /// point where it occurs -- even if several borrows name the same
+    /// region (as happens, e.g., when the CFG has been duplicated).
+    PerBorrow,
+
+    /// Borrows of the same path, with the same kind, for the same
+    /// region are collapsed into a single loan that is issued at each
+    /// of their points.
+    PerRegion,
+}
+
+impl Default for LoanGranularity {
+    fn default() -> Self {
+        LoanGranularity::PerBorrow
+    }
+}
+
+/// Which events take a loan out of scope, besides leaving its region.
+/// Each mode kills a loan wherever the one before it does, and more:
+/// running the tests under each of them shows how much precision each
+/// kind of kill buys.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KillMode {
+    /// Only overwriting a supporting prefix of the borrowed path.
+    Overwrite,
+
+    /// Also the `StorageDead` of the variable at the base of such a
+    /// prefix.
+    OverwriteStorageDead,
+
+    /// Also the death of the reference created by the loan (see
+    /// `Liveness::loan_values_live`), as in the full NLL model.
+    OverwriteDeadReference,
+}
+
+impl KillMode {
+    pub fn kills_storage_dead(self) -> bool {
+        self >= KillMode::OverwriteStorageDead
+    }
+
+    pub fn kills_dead_references(self) -> bool {
+        self >= KillMode::OverwriteDeadReference
+    }
+}
+
+impl Default for KillMode {
+    fn default() -> Self {
+        KillMode::OverwriteDeadReference
+    }
+}
+
+impl FromStr for KillMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "overwrite-only" => Ok(KillMode::Overwrite),
+            "overwrite+storage-dead" => Ok(KillMode::OverwriteStorageDead),
+            "overwrite+dead-reference" => Ok(KillMode::OverwriteDeadReference),
+            _ => Err(format!(
+                "invalid kill mode `{}` (expected `overwrite-only`, `overwrite+storage-dead` \
+                 or `overwrite+dead-reference`)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for KillMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            KillMode::Overwrite => write!(f, "overwrite-only"),
+            KillMode::OverwriteStorageDead => write!(f, "overwrite+storage-dead"),
+            KillMode::OverwriteDeadReference => write!(f, "overwrite+dead-reference"),
+        }
+    }
+}
+
+/// A named bundle of the settings that choose the rules of the
+/// borrow check, matching a documented configuration. The tool has no
+/// two-phase borrows, and regions are always sets of points, so these
+/// only choose the loan granularity, the kill mode and whether the
+/// implied bounds hold.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// The model of the NLL RFC: the defaults.
+    NllRfc,
+
+    /// Loans end with the scope of what holds them: killed by
+    /// overwrites and `StorageDead`, but not by the death of their
+    /// reference.
+    Lexical,
+
+    /// The NLL model, with the outlives relations implied by the
+    /// types of the variables taken as given, as Polonius does.
+    PoloniusLike,
+
+    /// The fewest kills and the coarsest loans, with the optimized
+    /// computations cross-checked against the naive ones.
+    Strict,
+}
+
+impl Preset {
+    /// Sets the settings of `options` that the preset chooses.
+    pub fn apply(self, options: &mut Options) {
+        let (loan_granularity, kill_mode, implied_bounds) = match self {
+            Preset::NllRfc => {
+                (LoanGranularity::PerBorrow, KillMode::OverwriteDeadReference, false)
+            }
+            Preset::Lexical => (LoanGranularity::PerBorrow, KillMode::OverwriteStorageDead, false),
+            Preset::PoloniusLike => {
+                (LoanGranularity::PerBorrow, KillMode::OverwriteDeadReference, true)
+            }
+            Preset::Strict => (LoanGranularity::PerRegion, KillMode::Overwrite, false),
+        };
+        options.loan_granularity = loan_granularity;
+        options.kill_mode = kill_mode;
+        options.implied_bounds = implied_bounds;
+        options.self_check |= self == Preset::Strict;
+    }
+
+    /// The settings the preset chooses, for echoing with the output.
+    pub fn describe(self) -> String {
+        let mut options = Options::default();
+        self.apply(&mut options);
+        format!("preset `{}`: {}, kill mode `{}`, implied bounds {}{}",
+                self,
+                match options.loan_granularity {
+                    LoanGranularity::PerBorrow => "a loan per borrow",
+                    LoanGranularity::PerRegion => "loans merged by region",
+                },
+                options.kill_mode,
+                if options.implied_bounds { "on" } else { "off" },
+                if options.self_check { ", self-check" } else { "" })
+    }
+}
+
+impl FromStr for Preset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "nll-rfc" => Ok(Preset::NllRfc),
+            "lexical" => Ok(Preset::Lexical),
+            "polonius-like" => Ok(Preset::PoloniusLike),
+            "strict" => Ok(Preset::Strict),
+            _ => Err(format!(
+                "invalid preset `{}` (expected `nll-rfc`, `lexical`, `polonius-like` or `strict`)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Preset::NllRfc => write!(f, "nll-rfc"),
+            Preset::Lexical => write!(f, "lexical"),
+            Preset::PoloniusLike => write!(f, "polonius-like"),
+            Preset::Strict => write!(f, "strict"),
+        }
+    }
+}
+
+/// The representations of a region value; see `region_repr.rs`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RegionRepr {
+    /// A sorted set of elements.
+    #[default]
+    Set,
+
+    /// A bit per point of the control-flow graph.
+    Dense,
+
+    /// The runs of consecutive points in each block.
+    Intervals,
+}
+
+impl FromStr for RegionRepr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "set" => Ok(RegionRepr::Set),
+            "dense" => Ok(RegionRepr::Dense),
+            "intervals" => Ok(RegionRepr::Intervals),
+            _ => Err(format!(
+                "invalid region representation `{}` (expected `set`, `dense` or `intervals`)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RegionRepr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            RegionRepr::Set => write!(f, "set"),
+            RegionRepr::Dense => write!(f, "dense"),
+            RegionRepr::Intervals => write!(f, "intervals"),
+        }
+    }
+}
+
+/// The representations of the loans in scope on exit from each block;
+/// see `LoansInScope`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum LoanSetRepr {
+    /// A row of bits per block (`BitSet`).
+    #[default]
+    Dense,
+
+    /// A sorted list of loans per block, which becomes a row of bits
+    /// once it is large (`HybridBitSet`).
+    Hybrid,
+}
+
+impl FromStr for LoanSetRepr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "dense" => Ok(LoanSetRepr::Dense),
+            "hybrid" => Ok(LoanSetRepr::Hybrid),
+            _ => Err(format!(
+                "invalid loan set representation `{}` (expected `dense` or `hybrid`)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LoanSetRepr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            LoanSetRepr::Dense => write!(f, "dense"),
+            LoanSetRepr::Hybrid => write!(f, "hybrid"),
+        }
+    }
+}
+
+/// A rewrite of the control-flow graph that does not change what the
+/// function does; see `transform.rs`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Transform {
+    /// Puts an empty block on each edge from a block with several
+    /// successors to a block with several predecessors.
+    SplitCriticalEdges,
+
+    /// Peels the first iteration off each outermost loop, into a copy
+    /// of its blocks that the loop is entered through.
+    UnrollLoops,
+
+    /// Puts a `noop` before each action.
+    PadNoops,
+}
+
+impl FromStr for Transform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "split-critical-edges" => Ok(Transform::SplitCriticalEdges),
+            "unroll-loops" => Ok(Transform::UnrollLoops),
+            "pad-noops" => Ok(Transform::PadNoops),
+            _ => Err(format!(
+                "invalid transformation `{}` (expected `split-critical-edges`, `unroll-loops` \
+                 or `pad-noops`)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Transform::SplitCriticalEdges => write!(f, "split-critical-edges"),
+            Transform::UnrollLoops => write!(f, "unroll-loops"),
+            Transform::PadNoops => write!(f, "pad-noops"),
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Assigns an index to every path that appears in the function, so
+//! that the borrow checker can compare paths by index rather than by
+//! walking `Path` trees. For each path we also precompute, as bit
+//! sets over path indices, the relationships between paths that the
+//! borrow checker asks about.
+
+use env::Environment;
+use graph_algorithms::bit_set::BitBuf;
+use nll_repr::repr;
+use std::collections::HashMap;
+
+pub struct PathTable {
+    paths: Vec<repr::Path>,
+    indices: HashMap<repr::Path, PathIndex>,
+
+    /// For each path `P`, the set of all prefixes of `P` (see
+    /// `Path::prefixes`).
+    prefixes: Vec<BitBuf>,
+
+    /// For each path `P`, the supporting prefixes of `P` (see
+    /// `Environment::supporting_prefixes`).
+    supporting_prefixes: Vec<BitBuf>,
+
+    /// For each path `P`, the paths that are frozen when `P` is
+    /// borrowed (see `Environment::frozen_by_borrow_of`).
+    frozen_prefixes: Vec<BitBuf>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathIndex {
+    index: usize,
+}
+
+impl PathIndex {
+    pub fn as_usize(self) -> usize {
+        self.index
+    }
+}
+
+impl PathTable {
+    pub fn new(env: &Environment) -> Self {
+        let mut table = PathTable {
+            paths: vec![],
+            indices: HashMap::new(),
+            prefixes: vec![],
+            supporting_prefixes: vec![],
+            frozen_prefixes: vec![],
+        };
+
+        for &block in &env.reverse_post_order {
+            for action in &env.graph.block_data(block).actions {
+                for path in action_paths(action) {
+                    table.intern(&path);
+                }
+            }
+        }
+
+        let num_paths = table.paths.len();
+        for index in 0..num_paths {
+            let (prefixes, supporting_prefixes, frozen_prefixes) = {
+                let path = &table.paths[index];
+                (
+                    table.to_bits(num_paths, path.prefixes()),
+                    table.to_bits(num_paths, env.supporting_prefixes(path)),
+                    table.to_bits(num_paths, env.frozen_by_borrow_of(path)),
+                )
+            };
+            table.prefixes.push(prefixes);
+            table.supporting_prefixes.push(supporting_prefixes);
+            table.frozen_prefixes.push(frozen_prefixes);
+        }
+
+        log!("path table: {:#?}", table.paths);
+
+        table
+    }
+
+    /// Adds `path`, along with all of its prefixes, to the table.
+    fn intern(&mut self, path: &repr::Path) -> PathIndex {
+        if let Some(&index) = self.indices.get(path) {
+            return index;
+        }
+
+        if let repr::Path::Extension(ref base, _) = *path {
+            self.intern(base);
+        }
+
+        let index = PathIndex {
+            index: self.paths.len(),
+        };
+        self.paths.push(path.clone());
+        self.indices.insert(path.clone(), index);
+        index
+    }
+
+    fn to_bits(&self, num_paths: usize, paths: Vec<&repr::Path>) -> BitBuf {
+        let mut bits = BitBuf::new(num_paths);
+        for path in paths {
+            bits.set(self.index(path).index);
+        }
+        bits
+    }
+
+    /// The number of paths in the table.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// The number of bytes taken up by the bit sets of each path.
+    pub fn heap_size(&self) -> usize {
+        self.prefixes
+            .iter()
+            .chain(&self.supporting_prefixes)
+            .chain(&self.frozen_prefixes)
+            .map(|bits| bits.heap_size())
+            .sum()
+    }
+
+    /// The index of each path in the table.
+    pub fn indices(&self) -> impl Iterator<Item = PathIndex> {
+        (0..self.paths.len()).map(|index| PathIndex { index })
+    }
+
+    /// Returns the index for `path`, which must appear somewhere in
+    /// the function.
+    pub fn index(&self, path: &repr::Path) -> PathIndex {
+        match self.indices.get(path) {
+            Some(&index) => index,
+            None => panic!("path `{}` not found in path table", path),
+        }
+    }
+
+    /// The path at `index`.
+    pub fn path(&self, index: PathIndex) -> &repr::Path {
+        &self.paths[index.index]
+    }
+
+    /// The index of the path written as `text` (ignoring whitespace),
+    /// if it appears in the function.
+    pub fn find(&self, text: &str) -> Option<PathIndex> {
+        let text: String = text.split_whitespace().collect();
+        self.indices().find(|&index| self.path(index).to_string() == text)
+    }
+
+    /// True if `prefix` is a prefix of `path` (or equal to it).
+    pub fn is_prefix_of(&self, prefix: PathIndex, path: PathIndex) -> bool {
+        self.prefixes[path.index].get(prefix.index)
+    }
+
+    /// True if `prefix` is a supporting prefix of `path`.
+    pub fn is_supporting_prefix_of(&self, prefix: PathIndex, path: PathIndex) -> bool {
+        self.supporting_prefixes[path.index].get(prefix.index)
+    }
+
+    /// True if borrowing `path` freezes `prefix`.
+    pub fn is_frozen_by_borrow_of(&self, prefix: PathIndex, path: PathIndex) -> bool {
+        self.frozen_prefixes[path.index].get(prefix.index)
+    }
+}
+
+/// All the paths that an action reads, writes, or borrows.
+pub fn action_paths(action: &repr::Action) -> Vec<repr::Path> {
+    match action.kind {
+        repr::ActionKind::Init(ref a, ref bs) => {
+            Some(&**a).into_iter().chain(bs.iter().map(|b| b.path())).cloned().collect()
+        }
+        repr::ActionKind::Borrow(ref a, _, _, ref b) |
+        repr::ActionKind::RawBorrow(ref a, _, ref b) |
+        repr::ActionKind::Assign(ref a, ref b) => vec![(**a).clone(), (**b).clone()],
+        repr::ActionKind::Closure(ref a, ref captures) => {
+            Some(a)
+                .into_iter()
+                .chain(captures.iter().map(|c| &c.path))
+                .map(|p| (**p).clone())
+                .collect()
+        }
+        repr::ActionKind::Const(ref p, _) |
+        repr::ActionKind::Use(ref p) |
+        repr::ActionKind::Read(ref p, _) |
+        repr::ActionKind::Write(ref p, _) |
+        repr::ActionKind::Drop(ref p) => vec![(**p).clone()],
+        repr::ActionKind::StorageDead(v) => vec![repr::Path::Var(v)],
+        repr::ActionKind::Constraint(_) |
+        repr::ActionKind::Noop => vec![],
+    }
+}
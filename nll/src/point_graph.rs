@@ -0,0 +1,198 @@
+//! A view of a `FuncGraph` with one node per point, for analyses that
+//! are simpler (or more precise) at the granularity of actions than
+//! of blocks. Any of the `graph_algorithms` (dominators,
+//! reachability, dataflow over a `BitSet`, ...) can be run over a
+//! `PointGraph` instead of the `FuncGraph`.
+//!
+//! The edges are those of `Environment::successor_points`: each point
+//! leads to the next one in its block, and the last point of a block
+//! (the mid-point of its end, after its last action) leads to the
+//! first point of each successor block.
+
+use env::Point;
+use graph::{self, BasicBlockIndex, FuncGraph};
+use graph_algorithms as ga;
+use graph_algorithms::NodeIndex;
+use std::fmt;
+use std::iter;
+use std::option;
+use std::slice;
+
+pub struct PointGraph<'a> {
+    graph: &'a FuncGraph,
+
+    /// The index of the first point of each block, and then the total
+    /// number of points. The points of a block are numbered
+    /// consecutively, from the start of its first action to the
+    /// mid-point of its end.
+    block_starts: Vec<usize>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PointIndex {
+    index: usize,
+}
+
+impl<'a> PointGraph<'a> {
+    pub fn new(graph: &'a FuncGraph) -> Self {
+        let mut block_starts = vec![0];
+        for index in 0..ga::Graph::num_nodes(graph) {
+            let actions = graph.block_data(BasicBlockIndex::from(index)).actions.len();
+            let num_points = Point::points_in_block(actions);
+            let next_start = block_starts[index] + num_points;
+            block_starts.push(next_start);
+        }
+        PointGraph { graph, block_starts }
+    }
+
+    pub fn index(&self, point: Point) -> PointIndex {
+        let start = self.block_starts[point.block.as_usize()];
+        let index = start + point.index_in_block();
+        assert!(index < self.block_starts[point.block.as_usize() + 1],
+                "no point {:?}",
+                point);
+        PointIndex { index }
+    }
+
+    pub fn point(&self, index: PointIndex) -> Point {
+        // Every block has at least one point, so the starts are
+        // strictly increasing.
+        let block = match self.block_starts.binary_search(&index.index) {
+            Ok(block) => block,
+            Err(next_block) => next_block - 1,
+        };
+        Point::from_index_in_block(BasicBlockIndex::from(block),
+                                   index.index - self.block_starts[block])
+    }
+
+    fn first_point(&self, block: BasicBlockIndex) -> PointIndex {
+        PointIndex { index: self.block_starts[block.as_usize()] }
+    }
+
+    fn last_point(&self, block: BasicBlockIndex) -> PointIndex {
+        PointIndex { index: self.block_starts[block.as_usize() + 1] - 1 }
+    }
+}
+
+impl<'a> ga::Graph for PointGraph<'a> {
+    type Node = PointIndex;
+
+    fn num_nodes(&self) -> usize {
+        *self.block_starts.last().unwrap()
+    }
+
+    fn start_node(&self) -> PointIndex {
+        self.first_point(ga::Graph::start_node(self.graph))
+    }
+
+    fn predecessors<'graph>(
+        &'graph self,
+        node: PointIndex,
+    ) -> <Self as ga::GraphPredecessors<'graph>>::Iter {
+        let block = self.point(node).block;
+        if node != self.first_point(block) {
+            let previous = PointIndex { index: node.index - 1 };
+            BlockPoints::new(&self.block_starts, Some(previous), None, true)
+        } else {
+            let blocks = ga::Graph::predecessors(self.graph, block);
+            BlockPoints::new(&self.block_starts, None, Some(blocks), true)
+        }
+    }
+
+    fn successors<'graph>(
+        &'graph self,
+        node: PointIndex,
+    ) -> <Self as ga::GraphSuccessors<'graph>>::Iter {
+        let block = self.point(node).block;
+        if node != self.last_point(block) {
+            let next = PointIndex { index: node.index + 1 };
+            BlockPoints::new(&self.block_starts, Some(next), None, false)
+        } else {
+            let blocks = ga::Graph::successors(self.graph, block);
+            BlockPoints::new(&self.block_starts, None, Some(blocks), false)
+        }
+    }
+}
+
+impl<'a, 'graph> ga::GraphPredecessors<'graph> for PointGraph<'a> {
+    type Item = PointIndex;
+    type Iter = BlockPoints<'graph>;
+}
+
+impl<'a, 'graph> ga::GraphSuccessors<'graph> for PointGraph<'a> {
+    type Item = PointIndex;
+    type Iter = BlockPoints<'graph>;
+}
+
+/// The neighbors of a point: either the adjacent point in the same
+/// block, or the first (for successors) or last (for predecessors)
+/// point of each neighboring block.
+pub struct BlockPoints<'graph> {
+    block_starts: &'graph [usize],
+    within_block: option::IntoIter<PointIndex>,
+    blocks: Option<iter::Cloned<slice::Iter<'graph, BasicBlockIndex>>>,
+    last_points: bool,
+}
+
+impl<'graph> BlockPoints<'graph> {
+    fn new(block_starts: &'graph [usize],
+           within_block: Option<PointIndex>,
+           blocks: Option<iter::Cloned<slice::Iter<'graph, BasicBlockIndex>>>,
+           last_points: bool)
+           -> Self {
+        BlockPoints {
+            block_starts,
+            within_block: within_block.into_iter(),
+            blocks,
+            last_points,
+        }
+    }
+}
+
+impl<'graph> Iterator for BlockPoints<'graph> {
+    type Item = PointIndex;
+
+    fn next(&mut self) -> Option<PointIndex> {
+        if let Some(point) = self.within_block.next() {
+            return Some(point);
+        }
+        let block = self.blocks.as_mut()?.next()?.as_usize();
+        if self.last_points {
+            Some(PointIndex { index: self.block_starts[block + 1] - 1 })
+        } else {
+            Some(PointIndex { index: self.block_starts[block] })
+        }
+    }
+}
+
+impl ga::NodeIndex for PointIndex {}
+
+impl From<usize> for PointIndex {
+    fn from(v: usize) -> PointIndex {
+        PointIndex { index: v }
+    }
+}
+
+impl From<PointIndex> for usize {
+    fn from(v: PointIndex) -> usize {
+        v.index
+    }
+}
+
+/// Within `graph::with_graph()`, which knows how many actions each
+/// block has, a point index is written as the point it stands for.
+impl fmt::Debug for PointIndex {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let mut block = BasicBlockIndex::from(0);
+        let mut start = 0;
+        while let Some(num_actions) = graph::num_actions(block) {
+            let num_points = Point::points_in_block(num_actions);
+            if self.index < start + num_points {
+                return write!(fmt, "{:?}", Point::from_index_in_block(block, self.index - start));
+            }
+            start += num_points;
+            block = BasicBlockIndex::from(block.as_usize() + 1);
+        }
+        write!(fmt, "P{}", self.index)
+    }
+}
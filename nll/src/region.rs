@@ -1,44 +1,313 @@
-use env::Point;
-use std::collections::BTreeSet;
+use env::{Environment, Point};
+use graph::{self, BasicBlockIndex};
+use graph_algorithms::{Graph, NodeIndex};
+use graph_algorithms::bit_set::BitBuf;
+use nll_repr::repr;
+use options::RegionRepr;
+use point_graph::PointGraph;
+use region_repr::{DenseRegion, ElementSet, IntervalRegion, RegionValue};
+use std::cell::Cell;
 use std::fmt;
 
-/// A region is a set of points where, within any given basic block,
-/// the points must be continuous. We represent this as a map:
+/// A region is a set of elements. Most elements are points in the
+/// control-flow graph, but a region may also extend beyond the body
+/// of the function: a free region like `'a` includes the
+/// `EndOfFn('a)` element, standing for the portion of `'a` that lies
+/// in the caller, and any region that outlives `'static` includes the
+/// `Static` element. Finally, the region bound by a `forall<'x>`
+/// constraint is represented by a `Placeholder` element that stands
+/// for "some unknown region `'x`".
 ///
-///     B -> start..end
-///
-/// where `B` is a basic block identifier and start/end are indices.
-#[derive(Clone, PartialEq, Eq)]
+/// The elements are stored in one of the representations of
+/// `region_repr.rs`: the one selected (see `select_repr`) when the
+/// region was created.
+#[derive(Clone)]
 pub struct Region {
-    points: BTreeSet<Point>,
+    elements: Elements,
+}
+
+#[derive(Clone)]
+enum Elements {
+    Set(ElementSet),
+    Dense(DenseRegion),
+    Intervals(IntervalRegion),
+}
+
+thread_local! {
+    /// The representation of the regions created on this thread.
+    static REPR: Cell<RegionRepr> = const { Cell::new(RegionRepr::Set) };
+}
+
+/// Restores the representation that was selected before
+/// `select_repr`, when dropped.
+pub struct SelectedRepr {
+    previous: RegionRepr,
+}
+
+/// Selects the representation of the regions created on this thread,
+/// until the returned guard is dropped.
+pub fn select_repr(repr: RegionRepr) -> SelectedRepr {
+    SelectedRepr { previous: REPR.with(|cell| cell.replace(repr)) }
+}
+
+impl Drop for SelectedRepr {
+    fn drop(&mut self) {
+        let previous = self.previous;
+        REPR.with(|cell| cell.set(previous));
+    }
+}
+
+/// Only `Location`s are points of the control-flow graph. The other
+/// elements have no actions and no successors: they are never walked
+/// like blocks, and only assertions can name them (the end of `'a` as
+/// `'a/0`, and the end of `'static` as `'static/0`).
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RegionElement {
+    /// A point in the control-flow graph of the function.
+    Location(Point),
+
+    /// The end of the free region `'a`, after the function returns.
+    EndOfFn(repr::RegionName),
+
+    /// The `'static` region, which outlives the function and every
+    /// free region.
+    Static,
+
+    /// The skolemized region introduced by a `forall` constraint.
+    Placeholder(PlaceholderIndex),
+}
+
+/// Identifies a placeholder; see `InferenceContext::add_placeholder`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PlaceholderIndex {
+    pub index: usize,
 }
 
 impl Region {
+    /// An empty region, in the selected representation. A dense
+    /// region needs the points of the graph to be numbered, so
+    /// outside of `graph::with_graph()`, it is a set instead.
     pub fn new() -> Self {
-        Region {
-            points: BTreeSet::new(),
+        let elements = match REPR.with(|cell| cell.get()) {
+            RegionRepr::Set => Elements::Set(ElementSet::default()),
+            RegionRepr::Dense => match graph::point_numbering() {
+                Some(points) => Elements::Dense(DenseRegion::new(points)),
+                None => Elements::Set(ElementSet::default()),
+            },
+            RegionRepr::Intervals => Elements::Intervals(IntervalRegion::default()),
+        };
+        Region { elements }
+    }
+
+    fn value(&self) -> &RegionValue {
+        match self.elements {
+            Elements::Set(ref set) => set,
+            Elements::Dense(ref dense) => dense,
+            Elements::Intervals(ref intervals) => intervals,
+        }
+    }
+
+    fn value_mut(&mut self) -> &mut RegionValue {
+        match self.elements {
+            Elements::Set(ref mut set) => set,
+            Elements::Dense(ref mut dense) => dense,
+            Elements::Intervals(ref mut intervals) => intervals,
         }
     }
 
     pub fn add_point(&mut self, point: Point) -> bool {
-        self.points.insert(point)
+        self.value_mut().add_point(point)
+    }
+
+    /// See `RegionValue::add_run`.
+    pub fn add_run(&mut self, block: BasicBlockIndex, first: usize, last: usize) -> bool {
+        self.value_mut().add_run(block, first, last)
+    }
+
+    pub fn add_element(&mut self, element: RegionElement) -> bool {
+        self.value_mut().add_element(element)
     }
 
     pub fn may_contain(&self, point: Point) -> bool {
-        self.points.contains(&point)
+        self.contains(RegionElement::Location(point))
+    }
+
+    pub fn contains(&self, element: RegionElement) -> bool {
+        self.value().contains(element)
+    }
+
+    /// Adds the elements of `other`, returning true if any was not
+    /// there already.
+    pub fn union(&mut self, other: &Region) -> bool {
+        match self.elements {
+            Elements::Set(ref mut a) => if let Elements::Set(ref b) = other.elements {
+                return a.union(b);
+            },
+            Elements::Dense(ref mut a) => if let Elements::Dense(ref b) = other.elements {
+                return a.union(b);
+            },
+            Elements::Intervals(ref mut a) => if let Elements::Intervals(ref b) = other.elements {
+                return a.union(b);
+            },
+        }
+        // Stored differently: go element by element.
+        other.iter().fold(false, |grew, element| self.add_element(element) | grew)
+    }
+
+    /// The elements of the region, in order.
+    pub fn iter<'a>(&'a self) -> Box<Iterator<Item = RegionElement> + 'a> {
+        self.value().iter()
+    }
+
+    /// See `RegionValue::heap_size`.
+    pub fn heap_size(&self) -> usize {
+        self.value().heap_size()
+    }
+
+    /// The points in the control-flow graph that the region contains.
+    pub fn locations<'a>(&'a self) -> impl Iterator<Item = Point> + 'a {
+        self.iter().filter_map(|e| match e {
+            RegionElement::Location(point) => Some(point),
+            _ => None,
+        })
+    }
+
+    /// The points of `block` that the region contains, in order, each
+    /// by its `Point::index_in_block`. Unlike filtering `locations`,
+    /// this only looks at the points of `block` (with intervals, only
+    /// at its runs).
+    pub fn points_in_block<'a>(&'a self, block: BasicBlockIndex) -> impl Iterator<Item = usize> + 'a {
+        self.value().points_in_block(block)
+    }
+
+    /// The blocks that the region contains a point of, by index.
+    pub fn blocks<'a>(&'a self) -> impl Iterator<Item = BasicBlockIndex> + 'a {
+        self.value().blocks()
+    }
+
+    /// The elements that lie outside the function body (i.e., all
+    /// but the `Location` elements).
+    pub fn universal_elements<'a>(&'a self) -> impl Iterator<Item = RegionElement> + 'a {
+        self.iter().filter(|e| match *e {
+            RegionElement::Location(_) => false,
+            RegionElement::EndOfFn(_) |
+            RegionElement::Static |
+            RegionElement::Placeholder(_) => true,
+        })
+    }
+
+    /// The points of the region that are reachable from `from` along
+    /// the edges of the control-flow graph without leaving the region
+    /// (including `from`, if the region contains it). The result has
+    /// no elements outside the function body.
+    pub fn reachable_within(&self, env: &Environment, from: Point) -> Region {
+        let points = PointGraph::new(env.graph);
+        let mut visited = BitBuf::new(points.num_nodes());
+        let mut reachable = Region::new();
+        let mut stack = vec![points.index(from)];
+        while let Some(index) = stack.pop() {
+            let point = points.point(index);
+            if self.may_contain(point) && visited.set(index.as_usize()) {
+                reachable.add_point(point);
+                stack.extend(points.successors(index));
+            }
+        }
+        reachable
+    }
+}
+
+/// A shortest path in the control-flow graph from `from`, the point
+/// where a loan of `region` is issued, to `to`, along which the loan
+/// stays in scope: every point after `from` lies within `region`. The
+/// path takes at least one step, so if `from == to`, it is a cycle.
+pub fn witness_path(env: &Environment, region: &Region, from: Point, to: Point) -> Option<Vec<Point>> {
+    env.successor_points(from)
+        .into_iter()
+        .filter_map(|start| env.shortest_path(start, to, |p| region.may_contain(p)))
+        .min_by_key(|path| path.len())
+        .map(|path| Some(from).into_iter().chain(path).collect())
+}
+
+/// Regions are equal if they have the same elements, whatever their
+/// representations.
+impl PartialEq for Region {
+    fn eq(&self, other: &Region) -> bool {
+        self.iter().eq(other.iter())
     }
 }
 
+impl Eq for Region {}
+
 impl fmt::Debug for Region {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         write!(fmt, "{{")?;
-        for (index, point) in self.points.iter().enumerate() {
+        for (index, element) in self.iter().enumerate() {
             if index > 0 {
                 write!(fmt, ", ")?;
             }
-            write!(fmt, "{:?}", point)?;
+            write!(fmt, "{:?}", element)?;
         }
         write!(fmt, "}}")?;
         Ok(())
     }
 }
+
+/// Lists the points block by block, in reverse post-order, with each
+/// run of consecutive points written as a range, as in
+/// `{B1/0..=Mid(B1/3), all(B2), end('a)}`. A region without mid-points
+/// (such as one compared with a region literal that names none) is
+/// listed by its actions instead, as in `{B1/0..=B1/3}`, like such a
+/// literal is written. Whole blocks are only recognized within
+/// `graph::with_graph()`, which knows how many actions each block has.
+impl fmt::Display for Region {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let mut blocks: Vec<_> = self.blocks().collect();
+        blocks.sort_by_key(|&block| graph::rpo_key(block));
+
+        // The distance between consecutive points, by their index in
+        // the block.
+        let step = if self.locations().any(|p| p.is_mid()) { 1 } else { 2 };
+
+        let mut items = vec![];
+        for block in blocks {
+            let indices: Vec<_> = self.points_in_block(block).collect();
+            let all = graph::num_actions(block).map(|n| Point::points_in_block(n) / step);
+            if all == Some(indices.len()) {
+                items.push(format!("all({:?})", block));
+                continue;
+            }
+
+            let mut index = 0;
+            while index < indices.len() {
+                let first = indices[index];
+                while index + 1 < indices.len() && indices[index + 1] == indices[index] + step {
+                    index += 1;
+                }
+                let last = indices[index];
+                index += 1;
+
+                let first_point = Point::from_index_in_block(block, first);
+                if first == last {
+                    items.push(format!("{:?}", first_point));
+                } else {
+                    let last_point = Point::from_index_in_block(block, last);
+                    items.push(format!("{:?}..={:?}", first_point, last_point));
+                }
+            }
+        }
+        items.extend(self.universal_elements().map(|e| format!("{:?}", e)));
+
+        write!(fmt, "{{{}}}", items.join(", "))
+    }
+}
+
+impl fmt::Debug for RegionElement {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            RegionElement::Location(point) => write!(fmt, "{:?}", point),
+            RegionElement::EndOfFn(name) => write!(fmt, "end({})", name),
+            RegionElement::Static => write!(fmt, "'static"),
+            RegionElement::Placeholder(p) => write!(fmt, "!{}", p.index),
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! The ways a region value can be stored. Which one is fastest, or
+//! smallest, depends on the shape of the regions: the sorted set of
+//! elements costs a node per point, the dense bit set a bit per point
+//! of the whole graph, and the intervals a pair of numbers per run of
+//! consecutive points in a block. `Region` stores its elements in the
+//! representation selected by `--region-repr`, so that the trade-offs
+//! can be measured on the same programs (see `nll bench-compare`).
+
+use env::Point;
+use graph::{BasicBlockIndex, PointNumbering};
+use graph_algorithms::bit_set::BitBuf;
+use region::RegionElement;
+use std::collections::{BTreeMap, BTreeSet};
+use std::iter::once;
+use std::mem;
+use std::rc::Rc;
+
+/// The operations the analysis needs of a region value. `iter`
+/// yields the elements in the order of `RegionElement`, whatever the
+/// representation, so that values can be compared element by element.
+pub trait RegionValue {
+    /// Adds `element`, returning true if it was not there already.
+    fn add_element(&mut self, element: RegionElement) -> bool;
+
+    /// Adds the point `point`, returning true if it was not there
+    /// already.
+    fn add_point(&mut self, point: Point) -> bool {
+        self.add_element(RegionElement::Location(point))
+    }
+
+    fn contains(&self, element: RegionElement) -> bool;
+
+    /// Adds the points of `block` from `first` to `last`, inclusive,
+    /// by `Point::index_in_block`, returning true if any was not there
+    /// already. The representations that store runs of points add
+    /// them as one.
+    fn add_run(&mut self, block: BasicBlockIndex, first: usize, last: usize) -> bool {
+        (first..last + 1).fold(false, |grew, index| {
+            self.add_point(Point::from_index_in_block(block, index)) | grew
+        })
+    }
+
+    /// Adds the elements of `other`, returning true if any was not
+    /// there already.
+    fn union(&mut self, other: &Self) -> bool where Self: Sized;
+
+    fn iter<'a>(&'a self) -> Box<Iterator<Item = RegionElement> + 'a>;
+
+    /// The points of `block` that the value contains, in order, each
+    /// by its `Point::index_in_block`.
+    fn points_in_block<'a>(&'a self, block: BasicBlockIndex) -> Box<Iterator<Item = usize> + 'a> {
+        Box::new(self.iter().filter_map(move |element| match element {
+            RegionElement::Location(point) if point.block == block => Some(point.index_in_block()),
+            _ => None,
+        }))
+    }
+
+    /// The blocks that the value contains a point of, in order.
+    fn blocks<'a>(&'a self) -> Box<Iterator<Item = BasicBlockIndex> + 'a> {
+        let mut previous = None;
+        Box::new(self.iter().filter_map(move |element| match element {
+            RegionElement::Location(point) if previous != Some(point.block) => {
+                previous = Some(point.block);
+                previous
+            }
+            _ => None,
+        }))
+    }
+
+    /// The number of bytes the value holds on the heap, for
+    /// `--memory-stats`: the elements, runs or bits it stores, but not
+    /// the bookkeeping of the collections that hold them.
+    fn heap_size(&self) -> usize;
+}
+
+/// A sorted set of elements.
+#[derive(Clone, Default)]
+pub struct ElementSet {
+    elements: BTreeSet<RegionElement>,
+}
+
+impl RegionValue for ElementSet {
+    fn add_element(&mut self, element: RegionElement) -> bool {
+        self.elements.insert(element)
+    }
+
+    fn contains(&self, element: RegionElement) -> bool {
+        self.elements.contains(&element)
+    }
+
+    fn union(&mut self, other: &Self) -> bool {
+        let len = self.elements.len();
+        self.elements.extend(other.elements.iter().cloned());
+        self.elements.len() != len
+    }
+
+    fn iter<'a>(&'a self) -> Box<Iterator<Item = RegionElement> + 'a> {
+        Box::new(self.elements.iter().cloned())
+    }
+
+    fn points_in_block<'a>(&'a self, block: BasicBlockIndex) -> Box<Iterator<Item = usize> + 'a> {
+        let first = RegionElement::Location(Point::start(block, 0));
+        let last = RegionElement::Location(Point::start(block, usize::MAX).mid());
+        Box::new(self.elements.range(first..=last).filter_map(|element| match *element {
+            RegionElement::Location(point) => Some(point.index_in_block()),
+            _ => None,
+        }))
+    }
+
+    fn heap_size(&self) -> usize {
+        elements_size(&self.elements)
+    }
+}
+
+/// A bit for each point of the control-flow graph, numbered by
+/// `PointNumbering`; the elements outside the function body are kept
+/// in a set of their own.
+#[derive(Clone)]
+pub struct DenseRegion {
+    points: Rc<PointNumbering>,
+    bits: BitBuf,
+    universal: BTreeSet<RegionElement>,
+}
+
+impl DenseRegion {
+    pub fn new(points: Rc<PointNumbering>) -> Self {
+        DenseRegion {
+            bits: BitBuf::new(points.num_points()),
+            points,
+            universal: BTreeSet::new(),
+        }
+    }
+}
+
+impl RegionValue for DenseRegion {
+    fn add_element(&mut self, element: RegionElement) -> bool {
+        match element {
+            RegionElement::Location(point) => self.bits.set(self.points.index(point)),
+            _ => self.universal.insert(element),
+        }
+    }
+
+    fn contains(&self, element: RegionElement) -> bool {
+        match element {
+            RegionElement::Location(point) => self.bits.get(self.points.index(point)),
+            _ => self.universal.contains(&element),
+        }
+    }
+
+    fn add_run(&mut self, block: BasicBlockIndex, first: usize, last: usize) -> bool {
+        let start = self.points.block_points(block).start;
+        (start + first..start + last + 1).fold(false, |grew, index| self.bits.set(index) | grew)
+    }
+
+    fn union(&mut self, other: &Self) -> bool {
+        let len = self.universal.len();
+        self.universal.extend(other.universal.iter().cloned());
+        let grew = self.universal.len() != len;
+        if self.points == other.points {
+            self.bits.set_from(other.bits.as_slice()) | grew
+        } else {
+            // Numbered for another graph: go point by point.
+            other.iter().fold(grew, |grew, element| self.add_element(element) | grew)
+        }
+    }
+
+    fn iter<'a>(&'a self) -> Box<Iterator<Item = RegionElement> + 'a> {
+        let locations = (0..self.points.num_points())
+            .filter(move |&index| self.bits.get(index))
+            .map(move |index| RegionElement::Location(self.points.point(index)));
+        Box::new(locations.chain(self.universal.iter().cloned()))
+    }
+
+    fn points_in_block<'a>(&'a self, block: BasicBlockIndex) -> Box<Iterator<Item = usize> + 'a> {
+        let points = self.points.block_points(block);
+        let first = points.start;
+        Box::new(points.filter(move |&index| self.bits.get(index)).map(move |index| index - first))
+    }
+
+    fn heap_size(&self) -> usize {
+        self.bits.heap_size() + elements_size(&self.universal)
+    }
+}
+
+/// For each block, the runs of consecutive points the region contains
+/// (by `Point::index_in_block`), as sorted, disjoint and non-adjacent
+/// inclusive ranges;
+/// the elements outside the function body are kept in a set of their
+/// own. Regions are mostly made of such runs, as they flow from a
+/// point to its successors.
+#[derive(Clone, Default)]
+pub struct IntervalRegion {
+    blocks: BTreeMap<BasicBlockIndex, Vec<(usize, usize)>>,
+    universal: BTreeSet<RegionElement>,
+}
+
+impl RegionValue for IntervalRegion {
+    fn add_element(&mut self, element: RegionElement) -> bool {
+        match element {
+            RegionElement::Location(point) => {
+                let runs = self.blocks.entry(point.block).or_insert_with(Vec::new);
+                add_index(runs, point.index_in_block())
+            }
+            _ => self.universal.insert(element),
+        }
+    }
+
+    fn contains(&self, element: RegionElement) -> bool {
+        match element {
+            RegionElement::Location(point) => match self.blocks.get(&point.block) {
+                Some(runs) => {
+                    let index = point.index_in_block();
+                    let next = runs.partition_point(|&(first, _)| first <= index);
+                    next > 0 && runs[next - 1].1 >= index
+                }
+                None => false,
+            },
+            _ => self.universal.contains(&element),
+        }
+    }
+
+    fn add_run(&mut self, block: BasicBlockIndex, first: usize, last: usize) -> bool {
+        add_range(self.blocks.entry(block).or_default(), first, last)
+    }
+
+    fn union(&mut self, other: &Self) -> bool {
+        let len = self.universal.len();
+        self.universal.extend(other.universal.iter().cloned());
+        let mut grew = self.universal.len() != len;
+        for (&block, other_runs) in &other.blocks {
+            let runs = self.blocks.entry(block).or_insert_with(Vec::new);
+            grew |= union_runs(runs, other_runs);
+        }
+        grew
+    }
+
+    fn iter<'a>(&'a self) -> Box<Iterator<Item = RegionElement> + 'a> {
+        let locations = self.blocks.iter().flat_map(|(&block, runs)| {
+            runs.iter().flat_map(move |&(first, last)| {
+                (first..last + 1).map(move |index| {
+                    RegionElement::Location(Point::from_index_in_block(block, index))
+                })
+            })
+        });
+        Box::new(locations.chain(self.universal.iter().cloned()))
+    }
+
+    fn points_in_block<'a>(&'a self, block: BasicBlockIndex) -> Box<Iterator<Item = usize> + 'a> {
+        let runs = self.blocks.get(&block).map_or(&[][..], |runs| &runs[..]);
+        Box::new(runs.iter().flat_map(|&(first, last)| first..last + 1))
+    }
+
+    fn blocks<'a>(&'a self) -> Box<Iterator<Item = BasicBlockIndex> + 'a> {
+        Box::new(self.blocks.iter().filter(|&(_, runs)| !runs.is_empty()).map(|(&block, _)| block))
+    }
+
+    fn heap_size(&self) -> usize {
+        let runs: usize = self.blocks.values().map(|runs| runs.len()).sum();
+        self.blocks.len() * mem::size_of::<BasicBlockIndex>() +
+            runs * mem::size_of::<(usize, usize)>() + elements_size(&self.universal)
+    }
+}
+
+fn elements_size(elements: &BTreeSet<RegionElement>) -> usize {
+    elements.len() * mem::size_of::<RegionElement>()
+}
+
+/// Adds `index` to `runs`, extending or joining the runs next to it.
+fn add_index(runs: &mut Vec<(usize, usize)>, index: usize) -> bool {
+    // The runs before `next` start at or before `index`.
+    let next = runs.partition_point(|&(first, _)| first <= index);
+    if next > 0 && runs[next - 1].1 >= index {
+        return false;
+    }
+    let extends_previous = next > 0 && runs[next - 1].1 + 1 == index;
+    let extends_next = next < runs.len() && runs[next].0 == index + 1;
+    match (extends_previous, extends_next) {
+        (true, true) => {
+            runs[next - 1].1 = runs[next].1;
+            runs.remove(next);
+        }
+        (true, false) => runs[next - 1].1 = index,
+        (false, true) => runs[next].0 = index,
+        (false, false) => runs.insert(next, (index, index)),
+    }
+    true
+}
+
+/// Adds the indices `first..=last` to `runs`, joining the runs they
+/// overlap or touch.
+fn add_range(runs: &mut Vec<(usize, usize)>, first: usize, last: usize) -> bool {
+    // The runs before `start` end short of `first`, and those from
+    // `end` on start past `last`, without touching the new run.
+    let start = runs.partition_point(|&(_, run_last)| run_last + 1 < first);
+    let end = runs.partition_point(|&(run_first, _)| run_first <= last + 1);
+    if start < end && runs[start].0 <= first && runs[start].1 >= last {
+        return false;
+    }
+    let (mut first, mut last) = (first, last);
+    if start < end {
+        first = first.min(runs[start].0);
+        last = last.max(runs[end - 1].1);
+    }
+    runs.splice(start..end, once((first, last)));
+    true
+}
+
+/// Adds the points of `other` to `runs`.
+fn union_runs(runs: &mut Vec<(usize, usize)>, other: &[(usize, usize)]) -> bool {
+    let mut all: Vec<_> = runs.iter().chain(other).cloned().collect();
+    all.sort();
+    let mut merged: Vec<(usize, usize)> = vec![];
+    for (first, last) in all {
+        match merged.last_mut() {
+            Some(previous) if first <= previous.1 + 1 => previous.1 = previous.1.max(last),
+            _ => merged.push((first, last)),
+        }
+    }
+    let grew = merged != *runs;
+    *runs = merged;
+    grew
+}
@@ -1,27 +1,299 @@
 use borrowck;
+use csv;
+use dead_stores;
 use env::{Environment, Point};
-use errors::ErrorReporting;
+use errors::{ErrorReporting, Fact, Note, ReportedError, SourceFile};
+use explain;
+use implied_bounds::{self, ImpliedBound};
+use joins;
+pub use loans_in_scope::LoanScope;
 use loans_in_scope::LoansInScope;
-use liveness::Liveness;
-use infer::{InferenceContext, RegionVariable};
+use liveness::{BitKind, Liveness};
+use log;
+use maybe_init::MaybeInit;
+use memory::MemoryStats;
+use observer::{AnalysisObserver, LivenessView, NoObserver};
+use infer::{InferenceContext, InferenceErrorKind, Provenance, RegionVariable, SolveStats};
 use nll_repr::repr::{self, RegionName, Variance, RegionDecl};
-use std::collections::HashMap;
+use options::Options;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::error::Error;
-use region::Region;
+use std::fmt;
+use region::{self, Region, RegionElement};
+use snapshot;
+use timeout::{self, TimedOut};
+use transform;
+use universe::Universe;
 
-pub fn region_check(env: &Environment) -> Result<(), Box<Error>> {
+/// The number of constraints listed by `--hot-constraints`.
+const HOT_CONSTRAINTS: usize = 10;
+
+/// Checks the function of `env` against the assertions and errors
+/// of the test. The lines that report what the check found -- the
+/// assertions that fail, and the warnings -- are added to `output`
+/// rather than printed, so that the caller can keep them along with
+/// the outcome (see `Cache`).
+pub fn region_check(env: &Environment,
+                    options: &Options,
+                    source: &SourceFile,
+                    output: &mut Vec<String>)
+                    -> Result<(), Box<Error>> {
+    if !options.transforms.is_empty() {
+        return transform::check(env, options, source, output);
+    }
     let ck = &mut RegionCheck {
         env,
+        options,
         infer: InferenceContext::new(),
         region_map: HashMap::new(),
+        type_errors: vec![],
     };
-    ck.check()
+    ck.check(source, output)
+}
+
+/// Checks `found`, the errors found by some other means than
+/// analyzing the function of `env` (such as by analyzing a
+/// transformed copy of it), against the errors that it expects.
+/// Like `region_check`, adds what it reports to `output`.
+pub fn check_errors(env: &Environment,
+                    options: &Options,
+                    found: Vec<ReportedError>,
+                    output: &mut Vec<String>)
+                    -> Result<(), Box<Error>> {
+    let mut errors = expected_errors(env);
+    for &block in &env.reverse_post_order {
+        for (point, action) in env.actions(block) {
+            if let Some(ref label) = action.label {
+                errors.label_point(point, label);
+            }
+        }
+    }
+    for error in found {
+        errors.report(error);
+    }
+    errors.sort(env);
+    reconcile_errors(env, options, &mut errors, output)
+}
+
+/// An `ErrorReporting` that expects the errors annotated in the
+/// function of `env`, those its `assert error at` assertions name, and
+/// the number of errors it asserts.
+fn expected_errors(env: &Environment) -> ErrorReporting {
+    let mut errors = ErrorReporting::new();
+    for &block in &env.reverse_post_order {
+        for (point, action) in env.actions(block) {
+            if let Some(ref expected) = action.should_have_error {
+                errors.expect_error(point, &expected.string);
+            }
+        }
+    }
+    for assertion in env.graph.assertions() {
+        match *assertion {
+            repr::Assertion::ErrorCount(count) => errors.expect_error_count(count),
+            // an invalid point is reported by `check_assertions`
+            repr::Assertion::ErrorAt(repr::Point {
+                block: repr::PointName::Code(name),
+                action,
+                kind,
+            }) => {
+                if let Some(block) = env.graph.block_named(name) {
+                    if action <= env.end_point(block).action {
+                        errors.expect_any_error(Point { block, action, kind });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    errors
+}
+
+/// The result of checking one assertion of the program.
+pub struct AssertionResult {
+    /// The index of the assertion among those of the program.
+    pub index: usize,
+
+    /// What is wrong, or `None` if the assertion holds.
+    pub failure: Option<String>,
+
+    /// With `Options::provenance`, the facts that the result rests on:
+    /// why a region contains a point, or why a variable or region is
+    /// live.
+    pub facts: Vec<Fact>,
+}
+
+/// Reports the assertions that do not hold, if any, failing; each is
+/// added to `output`.
+fn check_assertion_results(results: &[AssertionResult],
+                           output: &mut Vec<String>)
+                           -> Result<(), Box<Error>> {
+    let mut errors = 0;
+    for failure in results.iter().filter_map(|result| result.failure.as_ref()) {
+        errors += 1;
+        output.push(format!("error: {}", failure));
+    }
+    if errors > 0 {
+        try!(Err(format!("{} errors found", errors)));
+    }
+    Ok(())
+}
+
+/// The live bits that make the region `name` live at `point`.
+fn live_facts(liveness: &Liveness, name: RegionName, point: Point) -> Vec<Fact> {
+    let mut facts = vec![];
+    liveness.walk_block(point.block, |p, _action, live_bits| if p == point {
+        facts = liveness
+            .why_region_live(live_bits, name)
+            .into_iter()
+            .map(|bk| Fact::Live(liveness.bit_index(bk)))
+            .collect();
+    });
+    facts
+}
+
+/// Checks that we found the errors we expect to. If some phase was
+/// skipped, its expected errors will be missing, so just show
+/// whatever errors we did find, adding them to `output`.
+fn reconcile_errors(env: &Environment,
+                    options: &Options,
+                    errors: &mut ErrorReporting,
+                    output: &mut Vec<String>)
+                    -> Result<(), Box<Error>> {
+    if options.skip_regionck || options.skip_borrowck {
+        output.extend(errors.error_lines());
+        Ok(())
+    } else {
+        errors.reconcile_errors(env)
+    }
+}
+
+/// Like `region_check`, but returns every error that was found,
+/// rather than checking them against the errors that the test
+/// expects. This is what tools embedding the analysis want.
+pub fn diagnostics(env: &Environment,
+                   options: &Options,
+                   source: &SourceFile)
+                   -> Result<Vec<ReportedError>, Box<Error>> {
+    let ck = &mut RegionCheck {
+        env,
+        options,
+        infer: InferenceContext::new(),
+        region_map: HashMap::new(),
+        type_errors: vec![],
+    };
+    let mut errors = ErrorReporting::new();
+    let (_, assertions) = ck.analyze(source, &mut errors, &mut NoObserver)?;
+    let mut output = vec![];
+    let checked = check_assertion_results(&assertions, &mut output);
+    for line in output {
+        println!("{}", line);
+    }
+    checked?;
+    Ok(errors.into_reported_errors())
+}
+
+/// The value inferred for each region, by name.
+pub type RegionValues = BTreeMap<RegionName, Region>;
+
+/// What `analysis` found: the errors, as `diagnostics` returns them,
+/// along with the value inferred for each region, the scope of each
+/// loan (none with `skip_borrowck`), the results of the assertions
+/// (which, unlike for `region_check`, need not hold), the work the
+/// solver did (none with `skip_regionck`) and, with `capture_log`, the
+/// lines it logged. Used by `nll what-if` and `nll bench-compare`.
+pub struct Analysis {
+    pub errors: Vec<ReportedError>,
+    pub regions: RegionValues,
+    pub loan_scopes: Vec<LoanScope>,
+    pub assertions: Vec<AssertionResult>,
+    pub solve_stats: SolveStats,
+    pub log: Vec<String>,
+}
+
+pub fn analysis(env: &Environment,
+                options: &Options,
+                source: &SourceFile)
+                -> Result<Analysis, Box<Error>> {
+    analysis_with_observer(env, options, source, &mut NoObserver)
+}
+
+/// Like `analysis`, calling back `observer` as each phase is done.
+pub fn analysis_with_observer(env: &Environment,
+                              options: &Options,
+                              source: &SourceFile,
+                              observer: &mut AnalysisObserver)
+                              -> Result<Analysis, Box<Error>> {
+    let ck = &mut RegionCheck {
+        env,
+        options,
+        infer: InferenceContext::new(),
+        region_map: HashMap::new(),
+        type_errors: vec![],
+    };
+    let mut errors = ErrorReporting::new();
+    let ((loan_scopes, assertions), log) = if options.capture_log {
+        let (results, log) = log::capture(|| ck.analyze(source, &mut errors, observer));
+        (results?, log)
+    } else {
+        (ck.analyze(source, &mut errors, observer)?, vec![])
+    };
+    Ok(Analysis {
+        errors: errors.into_reported_errors(),
+        regions: ck.region_values(),
+        loan_scopes,
+        assertions,
+        solve_stats: ck.infer.solve_stats(),
+        log,
+    })
+}
+
+/// Whether accessing `path` at `point`, in the way given by `kind`,
+/// would be legal, without adding an action to the function: returns
+/// the errors that such an access would be reported with, which is
+/// empty if it is legal. This lets tools ask "what if" questions
+/// about a program. Errors found by region inference are not
+/// reported; `options` are honored as far as they affect the loans in
+/// scope at `point`.
+pub fn check_access(env: &Environment,
+                    options: &Options,
+                    point: Point,
+                    path: &repr::Path,
+                    kind: borrowck::AccessKind)
+                    -> Result<Vec<ReportedError>, Box<Error>> {
+    if point.action > env.end_point(point.block).action {
+        try!(Err(format!("invalid point `{:?}`", point)));
+    }
+    env.try_path_ty(path)?;
+
+    let _repr = region::select_repr(options.region_repr);
+    let ck = &mut RegionCheck {
+        env,
+        options,
+        infer: InferenceContext::new(),
+        region_map: HashMap::new(),
+        type_errors: vec![],
+    };
+    let liveness = Liveness::new(env)?;
+    if options.skip_regionck {
+        ck.populate_loan_regions_everywhere();
+    } else {
+        ck.populate_inference(&liveness);
+        ck.solve(&mut ErrorReporting::new())?;
+    }
+    let liveness = if options.skip_regionck { None } else { Some(&liveness) };
+    let loans_in_scope = LoansInScope::new(ck, liveness)?;
+    Ok(borrowck::check_access(env, &loans_in_scope, point, path, kind))
 }
 
 pub struct RegionCheck<'env> {
     env: &'env Environment<'env>,
+    options: &'env Options,
     infer: InferenceContext,
     region_map: HashMap<repr::RegionName, RegionVariable>,
+
+    /// Types that `relate_tys` found could not be related, such as a
+    /// `&T` stored where a `&mut T` is expected; reported by `solve()`.
+    type_errors: Vec<ReportedError>,
 }
 
 impl<'env> RegionCheck<'env> {
@@ -29,6 +301,10 @@ impl<'env> RegionCheck<'env> {
         self.env
     }
 
+    pub fn options(&self) -> &'env Options {
+        self.options
+    }
+
     pub fn region(&self, name: RegionName) -> &Region {
         let var = match self.region_map.get(&name) {
             Some(&var) => var,
@@ -37,152 +313,683 @@ impl<'env> RegionCheck<'env> {
         self.infer.region(var)
     }
 
-    fn check(&mut self) -> Result<(), Box<Error>> {
-        let mut errors = ErrorReporting::new();
+    pub fn region_values(&self) -> RegionValues {
+        self.region_map
+            .keys()
+            .map(|&name| (name, self.region(name).clone()))
+            .collect()
+    }
+
+    /// Why the region `name` contains `point`; see
+    /// `InferenceContext::why_contains()`.
+    pub fn why_contains(&self, name: RegionName, point: Point) -> Option<Provenance> {
+        self.infer.why_contains(self.env, self.region_map[&name], point)
+    }
+
+    fn check(&mut self, source: &SourceFile, output: &mut Vec<String>) -> Result<(), Box<Error>> {
+        let mut errors = expected_errors(self.env);
+        let (_, assertions) = self.analyze(source, &mut errors, &mut NoObserver)?;
+        check_assertion_results(&assertions, output)?;
+        output.extend(errors.warning_lines());
+        reconcile_errors(self.env, self.options, &mut errors, output)
+    }
+
+    /// Runs the analyses, adding the errors they find to `errors`, and
+    /// returns the scope of each loan and the results of the
+    /// assertions. `observer` is called back as each phase is done.
+    fn analyze(&mut self,
+               source: &SourceFile,
+               errors: &mut ErrorReporting,
+               observer: &mut AnalysisObserver)
+               -> Result<(Vec<LoanScope>, Vec<AssertionResult>), Box<Error>> {
+        let _repr = region::select_repr(self.options.region_repr);
 
-        // Register expected errors.
         for &block in &self.env.reverse_post_order {
-            let actions = self.env.graph.block_data(block).actions();
-            for (index, action) in actions.iter().enumerate() {
-                let point = Point { block, action: index };
-                if let Some(ref expected) = action.should_have_error {
-                    errors.expect_error(point, &expected.string);
+            let block_data = self.env.graph.block_data(block);
+            for index in 0..Point::points_in_block(block_data.actions.len()) {
+                for code in &block_data.allow {
+                    errors.allow_error(Point::from_index_in_block(block, index), code);
+                }
+            }
+            for (point, action) in self.env.actions(block) {
+                if let Some(ref label) = action.label {
+                    errors.label_point(point, label);
+                }
+                for code in &action.allow {
+                    errors.allow_error(point, code);
                 }
             }
         }
 
         // Compute liveness.
-        let liveness = &Liveness::new(self.env);
+        timeout::enter_phase("liveness");
+        let liveness = &Liveness::new(self.env)?;
+        observer.after_liveness(&LivenessView::new(liveness));
+        if self.options.warn_dead_stores {
+            dead_stores::report_dead_stores(self.env, liveness, errors);
+        }
 
-        // Add inference constraints.
-        self.populate_inference(liveness);
+        if self.options.skip_regionck {
+            // Without region inference, every loan is treated as
+            // being in scope everywhere.
+            self.populate_loan_regions_everywhere();
+        } else {
+            // Add inference constraints, then solve them.
+            timeout::enter_phase("region inference");
+            self.populate_inference(liveness);
+            self.solve(errors)?;
+            observer.after_inference(&self.region_values());
+            if self.options.hot_constraints {
+                self.dump_hot_constraints(source);
+            }
+            if self.options.dump_joins {
+                print!("{}", joins::joins(self.env, &self.region_values()));
+            }
+        }
 
-        // Solve inference constraints, reporting any errors.
-        for error in self.infer.solve(self.env) {
-            errors.report_error(error.constraint_point,
-                                format!("capped variable `{}` exceeded its limits",
-                                        error.name));
+        let loans_in_scope = if !self.options.skip_borrowck {
+            // Compute loans in scope at each point.
+            timeout::enter_phase("loans in scope");
+            let loans_in_scope = LoansInScope::new(
+                self,
+                if self.options.skip_regionck { None } else { Some(liveness) },
+            )?;
+            observer.after_loans(&loans_in_scope.loan_scopes());
+            if self.options.dump_loans {
+                loans_in_scope.dump();
+            }
+            if self.options.self_check && !self.options.skip_regionck {
+                self.check_loan_regions(&loans_in_scope, errors);
+            }
+
+            // Run the borrow check, reporting any errors.
+            timeout::enter_phase("borrow check");
+            let trace = match self.options.trace_path {
+                Some(ref text) => match loans_in_scope.paths().find(text) {
+                    Some(index) => Some(index),
+                    None => try!(Err(format!("--trace-path: `{}` is not accessed or borrowed \
+                                              in the function",
+                                             text))),
+                },
+                None => None,
+            };
+            borrowck::borrow_check(self.env, &loans_in_scope, trace, errors);
+            if self.options.provenance && !self.options.skip_regionck {
+                let sites = self.env.loan_sites();
+                for error in errors.reported_errors_mut() {
+                    if let Some(&Fact::Loan(site)) = error.facts.first() {
+                        let facts = self.region_facts(liveness, sites[site].region_name, error.point);
+                        error.facts.extend(facts);
+                    }
+                }
+            }
+            Some(loans_in_scope)
+        } else {
+            None
+        };
+
+        if self.options.memory_stats {
+            print!("{}", MemoryStats {
+                paths: loans_in_scope.as_ref().map(|l| (l.paths().len(), l.paths().heap_size())),
+                region_repr: self.options.region_repr,
+                regions: (self.infer.num_vars(), self.infer.heap_size()),
+                liveness: liveness.heap_size(),
+                loans: loans_in_scope.as_ref().map(|l| (l.loans().len(), l.heap_size())),
+            });
+        }
+
+        if let Some(ref point_name) = self.options.state_at {
+            let point = self.env.parse_point(point_name)?;
+            let maybe_init = &MaybeInit::new(self.env)?;
+            print!(
+                "{}",
+                snapshot::state_at(self.env, liveness, maybe_init, loans_in_scope.as_ref(), point)
+            );
+        }
+
+        if let Some(ref point_name) = self.options.why_error {
+            let point = self.env.parse_point(point_name)?;
+            let loans_in_scope = match loans_in_scope {
+                Some(ref loans_in_scope) if !self.options.skip_regionck => loans_in_scope,
+                _ => try!(Err("--why-error needs both region and borrow checking")),
+            };
+            print!("{}", explain::why_error(self, liveness, loans_in_scope, source.text, point)?);
+        }
+
+        if let Some(ref dir) = self.options.emit_csv {
+            let loan_scopes = loans_in_scope.as_ref().map(|l| l.loan_scopes()).unwrap_or_default();
+            csv::write_tables(self.env, liveness, &loan_scopes, &self.region_values(), dir, source.name)?;
+        }
+
+        // Check the assertions; the caller decides what to do about
+        // any that fail.
+        let assertions = self.check_assertions(liveness, errors.reported_errors())?;
+
+        errors.sort(self.env);
+        for error in errors.reported_errors() {
+            observer.on_diagnostic(error);
+        }
+        if self.options.show_suppressed {
+            errors.dump_suppressed();
+        }
+
+        if self.options.emit_stderr_format {
+            eprint!("{}", errors.emit_stderr(self.env, source));
+        }
+
+        Ok((loans_in_scope.map(|l| l.loan_scopes()).unwrap_or_default(), assertions))
+    }
+
+    /// Solves the inference constraints, reporting any errors.
+    fn solve(&mut self, errors: &mut ErrorReporting) -> Result<(), TimedOut> {
+        for error in self.type_errors.drain(..) {
+            errors.report(error);
+        }
+        for error in self.infer.solve(self.env, self.options)? {
+            match error.kind {
+                InferenceErrorKind::Capped => {
+                    errors.report_error(error.constraint_point,
+                                        format!("capped variable {} exceeded its limits",
+                                                self.env.describe_region(error.name)));
+                }
+                InferenceErrorKind::MustOutlive { sub } => {
+                    errors.report(ReportedError {
+                        point: error.constraint_point,
+                        code: None,
+                        message: format!("lifetime may not live long enough: \
+                                          {} must outlive {}",
+                                         self.env.describe_region(error.name),
+                                         self.env.describe_region(sub)),
+                        notes: vec![Note {
+                            point: None,
+                            message: format!("consider adding the bound `{}: {}`",
+                                             error.name,
+                                             sub),
+                        }],
+                        labels: HashMap::new(),
+                        facts: vec![],
+                    });
+                }
+                InferenceErrorKind::PlaceholderLeak {
+                    placeholder,
+                    placeholder_universe,
+                    universe,
+                    capped,
+                    chain,
+                } => {
+                    let region = self.env.describe_region(error.name);
+                    let why = if capped {
+                        format!("{} is a free region or a placeholder, whose value is fixed",
+                                region)
+                    } else {
+                        format!("{} is in universe {}, which is not nested in {}",
+                                region, universe, placeholder_universe)
+                    };
+                    let notes = Some(Note { point: None, message: why })
+                        .into_iter()
+                        .chain(chain.into_iter().map(|(sup, sub, point)| Note {
+                            point: Some(point),
+                            message: format!("`{}: {}` is required here", sup, sub),
+                        }))
+                        .collect();
+                    errors.report(ReportedError {
+                        point: error.constraint_point,
+                        code: None,
+                        message: format!("higher-ranked subtype check failed: \
+                                          {} cannot name the placeholder for `{}` of universe {}",
+                                         region,
+                                         placeholder,
+                                         placeholder_universe),
+                        notes,
+                        labels: HashMap::new(),
+                        facts: vec![],
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Prints the constraints on which the solver did the most work
+    /// (measured in points visited), with the action that generated
+    /// each one.
+    fn dump_hot_constraints(&self, source: &SourceFile) {
+        let hot_constraints = self.infer.hot_constraints();
+        println!("hot constraints (top {} of {}, by points visited):",
+                 HOT_CONSTRAINTS.min(hot_constraints.len()),
+                 hot_constraints.len());
+        for hot in hot_constraints.iter().take(HOT_CONSTRAINTS) {
+            println!("  {}: {} @ {}: applied {}, fired {}, {} points visited, {} elements added",
+                     hot.sup,
+                     hot.sub,
+                     self.env.describe_point(hot.point),
+                     hot.stats.applied,
+                     hot.stats.fired,
+                     hot.stats.points_visited,
+                     hot.stats.elements_added);
+            if let Some(cause) = hot.cause {
+                let action = &self.env.graph.block_data(cause.block).actions[cause.action];
+                match action.span {
+                    Some(span) => {
+                        println!("    due to {}: `{}`",
+                                 self.env.describe_point(cause),
+                                 &source.text[span.lo..span.hi])
+                    }
+                    None => println!("    due to {}", self.env.describe_point(cause)),
+                }
+            }
+        }
+    }
+
+    /// Part of `--self-check`. A loan comes into scope at the
+    /// mid-point of the action that issues it, and goes out of scope
+    /// at the first point not in its region; so `LoansInScope` assumes that
+    /// each point in the region can be reached from an issue point
+    /// without leaving the region. Reports every loan for which this
+    /// does not hold as an internal error.
+    ///
+    /// Several borrows may share a region name; each of them is an
+    /// issue point for the region. Regions that extend past the end
+    /// of the fn contain every point, and so are not checked.
+    fn check_loan_regions(&self, loans_in_scope: &LoansInScope, errors: &mut ErrorReporting) {
+        let loans = loans_in_scope.loans_with_issue_points();
+
+        let mut issue_points: BTreeMap<RegionName, Vec<Point>> = BTreeMap::new();
+        for &(loan, ref points) in &loans {
+            issue_points.entry(loan.region_name).or_insert(vec![]).extend(points);
         }
 
-        // Compute loans in scope at each point.
-        let loans_in_scope = &LoansInScope::new(self);
+        for (loan, _) in loans {
+            let region = loan.region;
+            if region.universal_elements().next().is_some() {
+                continue;
+            }
 
-        // Run the borrow check, reporting any errors.
-        borrowck::borrow_check(self.env, loans_in_scope, &mut errors);
+            let mut reached = Region::new();
+            for &point in &issue_points[&loan.region_name] {
+                for p in region.reachable_within(self.env, point.mid()).locations() {
+                    reached.add_point(p);
+                }
+            }
 
-        // Check that all assertions are obeyed.
-        self.check_assertions(liveness)?;
+            let unreached: Vec<_> = region.locations().filter(|&p| !reached.may_contain(p)).collect();
+            if unreached.is_empty() {
+                continue;
+            }
+            let message = if reached.locations().next().is_none() {
+                format!("internal error: region of loan of `{}` does not contain \
+                         the point after its issue point",
+                        loan.path)
+            } else {
+                format!("internal error: region of loan of `{}` contains \
+                         points unreachable from the borrow: {:?}",
+                        loan.path,
+                        unreached)
+            };
+            errors.report_error(loan.point, message);
+        }
+    }
 
-        // Check that we found the errors we expect to.
-        errors.reconcile_errors()
+    /// Used in place of region inference by `--no-regionck`: the
+    /// region of each loan (i.e., of each borrow or by-ref capture)
+    /// includes every point.
+    fn populate_loan_regions_everywhere(&mut self) {
+        for &block in &self.env.reverse_post_order {
+            for action in &self.env.graph.block_data(block).actions {
+                let regions: Vec<_> = match action.kind {
+                    repr::ActionKind::Borrow(_, region_name, _, _) => vec![region_name],
+                    repr::ActionKind::Closure(_, ref captures) => captures
+                        .iter()
+                        .filter_map(|capture| match capture.mode {
+                            repr::CaptureMode::ByRef(region_name, _) => Some(region_name),
+                            repr::CaptureMode::ByMove => None,
+                        })
+                        .collect(),
+                    repr::ActionKind::Init(_, ref operands) => operands
+                        .iter()
+                        .filter_map(|operand| match *operand {
+                            repr::Operand::Borrow(region_name, _, _) => Some(region_name),
+                            repr::Operand::Path(_) => None,
+                        })
+                        .collect(),
+                    _ => vec![],
+                };
+                for region_name in regions {
+                    let rv = self.region_variable(region_name);
+                    self.add_all_points(rv);
+                }
+            }
+        }
     }
 
-    fn check_assertions(&self, liveness: &Liveness) -> Result<(), Box<Error>> {
-        let mut errors = 0;
+    /// Checks each assertion of the program against the analyses and
+    /// the errors `reported`, except those about region values if
+    /// region inference was skipped, and those about errors if either
+    /// region inference or borrow checking was (as some errors would
+    /// then be missing).
+    fn check_assertions(&self,
+                        liveness: &Liveness,
+                        reported: &[ReportedError])
+                        -> Result<Vec<AssertionResult>, Box<Error>> {
+        let mut results = vec![];
+
+        for (index, assertion) in self.env.graph.assertions().iter().enumerate() {
+            if self.options.skip_regionck {
+                match *assertion {
+                    repr::Assertion::Eq(..) |
+                    repr::Assertion::UpperBound(..) |
+                    repr::Assertion::In(..) |
+                    repr::Assertion::NotIn(..) => continue,
+                    _ => {}
+                }
+            }
+            if self.options.skip_regionck || self.options.skip_borrowck {
+                match *assertion {
+                    repr::Assertion::ErrorAt(..) |
+                    repr::Assertion::NoErrorAt(..) => continue,
+                    _ => {}
+                }
+            }
 
-        for assertion in self.env.graph.assertions() {
-            match *assertion {
+            let mut facts = vec![];
+            let failure = match *assertion {
                 repr::Assertion::Eq(region_name, ref region_literal) => {
                     let region_var = self.region_map[&region_name];
-                    let region_value = self.to_region(region_literal);
-                    if *self.infer.region(region_var) != region_value {
-                        errors += 1;
-                        println!("error: region variable `{:?}` has wrong value", region_name);
-                        println!("  expected: {:?}", region_value);
-                        println!("  found   : {:?}", self.infer.region(region_var));
+                    let region_value = self.to_region(region_literal)?;
+                    let found = self.literal_view(region_literal, self.infer.region(region_var));
+                    if found != region_value {
+                        Some(format!("region variable {} has wrong value\n  \
+                                      expected: {}\n  found   : {}",
+                                     self.env.describe_region(region_name),
+                                     region_value,
+                                     found))
+                    } else {
+                        None
                     }
                 }
 
-                repr::Assertion::In(region_name, ref point) => {
+                repr::Assertion::UpperBound(region_name, ref region_literal) => {
                     let region_var = self.region_map[&region_name];
-                    let point = self.to_point(point);
-                    if !self.infer.region(region_var).may_contain(point) {
-                        errors += 1;
-                        println!(
-                            "error: region variable `{:?}` does not contain `{:?}`",
-                            region_name,
-                            point
-                        );
-                        println!("  found   : {:?}", self.infer.region(region_var));
+                    let bound = self.to_region(region_literal)?;
+                    let found = self.literal_view(region_literal, self.infer.region(region_var));
+                    let mut excess = Region::new();
+                    for element in found.iter() {
+                        if !bound.contains(element) {
+                            excess.add_element(element);
+                        }
+                    }
+                    if excess.iter().next().is_some() {
+                        Some(format!("region variable {} exceeds its bound\n  \
+                                      bound   : {}\n  found   : {}\n  excess  : {}",
+                                     self.env.describe_region(region_name),
+                                     bound,
+                                     found,
+                                     excess))
+                    } else {
+                        None
                     }
                 }
 
+                repr::Assertion::In(region_name, ref point) |
                 repr::Assertion::NotIn(region_name, ref point) => {
                     let region_var = self.region_map[&region_name];
-                    let point = self.to_point(point);
-                    if self.infer.region(region_var).may_contain(point) {
-                        errors += 1;
-                        println!(
-                            "error: region variable `{:?}` contains `{:?}`",
-                            region_name,
-                            point
-                        );
-                        println!("  found   : {:?}", self.infer.region(region_var));
+                    let element = self.to_element(point)?;
+                    let contains = self.infer.region(region_var).contains(element);
+                    if let RegionElement::Location(location) = element {
+                        if contains && self.options.provenance {
+                            facts = self.region_facts(liveness, region_name, location);
+                        }
+                    }
+                    match (assertion, contains) {
+                        (&repr::Assertion::In(..), false) => {
+                            Some(format!("region variable {} does not contain `{:?}`\n  \
+                                          found   : {}",
+                                         self.env.describe_region(region_name),
+                                         element,
+                                         self.infer.region(region_var)))
+                        }
+                        (&repr::Assertion::NotIn(..), true) => {
+                            Some(format!("region variable {} contains `{:?}`\n  found   : {}",
+                                         self.env.describe_region(region_name),
+                                         element,
+                                         self.infer.region(region_var)))
+                        }
+                        _ => None,
                     }
                 }
 
-                repr::Assertion::Live(var, block_name) => {
+                repr::Assertion::Live(var, block_name) |
+                repr::Assertion::NotLive(var, block_name) => {
                     let block = self.env.graph.block(block_name);
-                    if !liveness.var_live_on_entry(var, block) {
-                        errors += 1;
-                        println!(
-                            "error: variable `{:?}` not live on entry to `{:?}`",
-                            var,
-                            block_name
-                        );
+                    let live = liveness.var_live_on_entry(var, block);
+                    if live && self.options.provenance {
+                        facts.push(Fact::Live(liveness.bit_index(BitKind::VariableUsed(var))));
+                    }
+                    match (assertion, live) {
+                        (&repr::Assertion::Live(..), false) => {
+                            Some(format!("variable `{:?}` not live on entry to `{:?}`",
+                                         var,
+                                         block_name))
+                        }
+                        (&repr::Assertion::NotLive(..), true) => {
+                            Some(format!("variable `{:?}` live on entry to `{:?}`",
+                                         var,
+                                         block_name))
+                        }
+                        _ => None,
                     }
                 }
 
-                repr::Assertion::NotLive(var, block_name) => {
+                repr::Assertion::RegionLive(region_name, block_name) |
+                repr::Assertion::RegionNotLive(region_name, block_name) => {
                     let block = self.env.graph.block(block_name);
-                    if liveness.var_live_on_entry(var, block) {
-                        errors += 1;
-                        println!(
-                            "error: variable `{:?}` live on entry to `{:?}`",
-                            var,
-                            block_name
-                        );
+                    let live = liveness.region_live_on_entry(region_name, block);
+                    if live && self.options.provenance {
+                        facts = live_facts(liveness, region_name, self.env.start_point(block));
+                    }
+                    match (assertion, live) {
+                        (&repr::Assertion::RegionLive(..), false) => {
+                            Some(format!("region {} not live on entry to `{:?}`",
+                                         self.env.describe_region(region_name),
+                                         block_name))
+                        }
+                        (&repr::Assertion::RegionNotLive(..), true) => {
+                            Some(format!("region {} live on entry to `{:?}`",
+                                         self.env.describe_region(region_name),
+                                         block_name))
+                        }
+                        _ => None,
+                    }
+                }
+
+                repr::Assertion::RegionLiveAt(region_name, ref point) |
+                repr::Assertion::RegionNotLiveAt(region_name, ref point) => {
+                    let location = self.to_location(point)?;
+                    let live = liveness.region_live_at(region_name, location);
+                    if live && self.options.provenance {
+                        facts = live_facts(liveness, region_name, location);
+                    }
+                    match (assertion, live) {
+                        (&repr::Assertion::RegionLiveAt(..), false) => {
+                            Some(format!("region {} not live at `{:?}`",
+                                         self.env.describe_region(region_name),
+                                         location))
+                        }
+                        (&repr::Assertion::RegionNotLiveAt(..), true) => {
+                            Some(format!("region {} live at `{:?}`",
+                                         self.env.describe_region(region_name),
+                                         location))
+                        }
+                        _ => None,
                     }
                 }
 
-                repr::Assertion::RegionLive(region_name, block_name) => {
+                repr::Assertion::Dominates(dom_name, block_name) => {
+                    let dom = self.env.graph.block(dom_name);
                     let block = self.env.graph.block(block_name);
-                    if !liveness.region_live_on_entry(region_name, block) {
-                        errors += 1;
-                        println!(
-                            "error: region `{:?}` not live on entry to `{:?}`",
-                            region_name,
-                            block_name
-                        );
+                    if !self.env.dominates(dom, block) {
+                        Some(format!("`{}` does not dominate `{}`", dom_name, block_name))
+                    } else {
+                        None
                     }
                 }
 
-                repr::Assertion::RegionNotLive(region_name, block_name) => {
+                repr::Assertion::NotDominates(dom_name, block_name) => {
+                    let dom = self.env.graph.block(dom_name);
                     let block = self.env.graph.block(block_name);
-                    if liveness.region_live_on_entry(region_name, block) {
-                        errors += 1;
-                        println!(
-                            "error: region `{:?}` live on entry to `{:?}`",
-                            region_name,
-                            block_name
-                        );
+                    if self.env.dominates(dom, block) {
+                        Some(format!("`{}` dominates `{}`", dom_name, block_name))
+                    } else {
+                        None
                     }
                 }
-            }
+
+                repr::Assertion::LoopHead(block_name) => {
+                    let block = self.env.graph.block(block_name);
+                    if !self.env.is_loop_head(block) {
+                        Some(format!("`{}` is not the head of a loop", block_name))
+                    } else {
+                        None
+                    }
+                }
+
+                repr::Assertion::NotLoopHead(block_name) => {
+                    let block = self.env.graph.block(block_name);
+                    if self.env.is_loop_head(block) {
+                        Some(format!("`{}` is the head of a loop", block_name))
+                    } else {
+                        None
+                    }
+                }
+
+                repr::Assertion::ErrorAt(ref point) |
+                repr::Assertion::NoErrorAt(ref point) => {
+                    let location = self.to_error_location(point)?;
+                    let error = reported.iter().find(|error| error.point == location);
+                    match (assertion, error) {
+                        (&repr::Assertion::ErrorAt(..), None) => {
+                            Some(format!("no error reported at `{:?}`", location))
+                        }
+                        (&repr::Assertion::NoErrorAt(..), Some(error)) => {
+                            let code = error.code.map(|code| format!("[{}] ", code));
+                            Some(format!("error reported at `{:?}`: {}{}",
+                                         location,
+                                         code.unwrap_or_default(),
+                                         error.message))
+                        }
+                        _ => None,
+                    }
+                }
+
+                // checked along with the expected errors, in `check`
+                repr::Assertion::ErrorCount(_) => continue,
+            };
+            results.push(AssertionResult { index, failure, facts });
+        }
+
+        Ok(results)
+    }
+
+    /// The facts that make the region `name` contain `point`: the
+    /// constraints that carried the point into it, and the live bits
+    /// that put it into the region it came from. Empty if the region
+    /// does not contain `point`. Only with `Options::provenance`.
+    fn region_facts(&self, liveness: &Liveness, name: RegionName, point: Point) -> Vec<Fact> {
+        let provenance = match self.why_contains(name, point) {
+            Some(provenance) => provenance,
+            None => return vec![],
+        };
+        let mut facts: Vec<_> = provenance.steps
+            .iter()
+            .map(|step| Fact::Constraint(step.constraint))
+            .collect();
+        facts.extend(live_facts(liveness, provenance.origin, point));
+        facts
+    }
+
+    fn add_all_points(&mut self, rv: RegionVariable) {
+        for point in self.env.all_points() {
+            self.infer.add_live_point(rv, point);
         }
+    }
 
-        if errors > 0 {
-            try!(Err(format!("{} errors found", errors)));
+    /// Adds the constraint `c`, from a constraint action at `point`.
+    /// `scope` maps the regions bound by enclosing `forall` and
+    /// `exists` binders to their variables, and `universe` is the
+    /// universe of the innermost enclosing `forall` (see
+    /// `universe.rs`).
+    ///
+    /// `forall<'x> C` is proven by skolemization: `'x` becomes a
+    /// placeholder, a capped region that (like a free region) includes
+    /// every point of the function, plus a placeholder element of its
+    /// own, in a universe nested in `universe`. `exists<'x> C` just
+    /// introduces a fresh inference variable for `'x`, which may name
+    /// the placeholders of `universe` and the universes it is nested
+    /// in.
+    fn add_constraint(
+        &mut self,
+        point: Point,
+        scope: &HashMap<RegionName, RegionVariable>,
+        universe: Universe,
+        c: &repr::Constraint,
+    ) {
+        match *c {
+            repr::Constraint::Outlives(c) => {
+                let sup_v = self.scoped_region_variable(scope, c.sup);
+                let sub_v = self.scoped_region_variable(scope, c.sub);
+                self.infer.add_outlives(sup_v, sub_v, point);
+            }
+            repr::Constraint::All(ref cs) => {
+                for c in cs {
+                    self.add_constraint(point, scope, universe, c);
+                }
+            }
+            repr::Constraint::ForAll(ref names, ref c) => {
+                let universe = self.infer.new_universe(universe);
+                let mut scope = scope.clone();
+                for &name in names {
+                    let rv = self.infer.add_placeholder(name, universe);
+                    self.add_all_points(rv);
+                    self.infer.cap_var(rv);
+                    scope.insert(name, rv);
+                }
+                self.add_constraint(point, &scope, universe, c);
+            }
+            repr::Constraint::Exists(ref names, ref c) => {
+                let mut scope = scope.clone();
+                for &name in names {
+                    let rv = self.infer.add_var_in_universe(name, universe);
+                    scope.insert(name, rv);
+                }
+                self.add_constraint(point, &scope, universe, c);
+            }
+            repr::Constraint::Implies(..) => {
+                panic!("unimplemented rich constraint: {:?}", c);
+            }
         }
+    }
 
-        Ok(())
+    fn scoped_region_variable(
+        &mut self,
+        scope: &HashMap<RegionName, RegionVariable>,
+        name: RegionName,
+    ) -> RegionVariable {
+        match scope.get(&name) {
+            Some(&rv) => rv,
+            None => self.region_variable(name),
+        }
     }
 
+    /// Adds the ends of the regions that the free region of `rv`
+    /// outlives, directly (`outlives`) or transitively. With
+    /// `--implied-bounds`, the free regions that each one is implied
+    /// to outlive (`implied`) count as well.
     fn populate_outlives(
         &mut self,
         rv: RegionVariable,
         visited: &mut Vec<RegionName>, // memoization
-        outlives: &Vec<RegionName>,
+        outlives: &[RegionName],
+        implied: &BTreeMap<RegionName, Vec<RegionName>>,
     ) {
         for &region in outlives {
             // avoid recomputation
@@ -190,21 +997,29 @@ impl<'env> RegionCheck<'env> {
                 continue;
             }
 
-            let skolemized_block = self.env.graph.skolemized_end(region);
-            self.infer.add_live_point(rv, Point { block: skolemized_block,  action: 0, });
-            let outlives = {
+            visited.push(region);
+
+            // `'static` outlives everything, so there is nothing
+            // further to add.
+            if region.is_static() {
+                self.infer.add_universal_element(rv, RegionElement::Static);
+                continue;
+            }
+
+            self.infer.add_universal_element(rv, RegionElement::EndOfFn(region));
+            let mut outlives = {
                 let mut possible_matches = self.env.graph
                     .free_regions()
                     .iter()
                     .filter(|rd| region == rd.name);
                 match possible_matches.next() {
-                    Some(region_decl) => &region_decl.outlives,
+                    Some(region_decl) => region_decl.outlives.clone(),
                     None => continue
                 }
             };
+            outlives.extend(implied.get(&region).into_iter().flatten());
 
-            visited.push(region);
-            self.populate_outlives(rv, visited, &outlives);
+            self.populate_outlives(rv, visited, &outlives, implied);
         }
     }
 
@@ -213,37 +1028,70 @@ impl<'env> RegionCheck<'env> {
         // we will wind up with a region variable. We want that region
         // variable to be inferred to precisely the set: `{G, ...,
         // End(r)}`, where `G` is all the points in the control-flow
-        // graph, and `End(r)` is the end-point of `r`. We also want
-        // to include the endpoints of any free-regions that `r`
-        // outlives. We're not enforcing (in inference) that `r` doesn't
-        // get inferred to some *larger* region (that would be a kind of
-        // constraint we would need to add, and inference right now
-        // doesn't permit such constraints -- you could also view it
-        // an assertion that we add to the tests).
+        // graph, and `End(r)` is the `EndOfFn(r)` element. We also
+        // want to include the ends of any free-regions that `r`
+        // outlives.  The `'static` region likewise includes every
+        // point, as well as the ends of all free regions and the
+        // `Static` element. Inference may still grow `r` to include the
+        // end of some other free region; this means the body requires
+        // a relationship that `r` was not declared with, which
+        // `solve()` reports once inference is done.
+        let static_region = RegionName::from("'static");
+        let rv = self.region_variable(static_region);
+        self.add_all_points(rv);
+        self.infer.add_universal_element(rv, RegionElement::Static);
+        for region_decl in self.env.graph.free_regions() {
+            self.infer.add_universal_element(rv, RegionElement::EndOfFn(region_decl.name));
+        }
+        self.infer.cap_var(rv);
+
+        // With `--implied-bounds`, the bounds implied by the type of
+        // each variable (see `implied_bounds`). Those between free
+        // regions are treated as if they were declared.
+        let (implied_bounds, free_region_bounds) = if self.options.implied_bounds {
+            let bounds = implied_bounds::implied_bounds(self.env);
+            let free_bounds = implied_bounds::free_region_bounds(self.env, &bounds);
+            (bounds, free_bounds)
+        } else {
+            (HashMap::new(), BTreeMap::new())
+        };
+
         for region_decl in self.env.graph.free_regions() {
             let &RegionDecl{ name: region, ref outlives } = region_decl;
             let rv = self.region_variable(region);
-            for &block in &self.env.reverse_post_order {
-                let end_point = self.env.end_point(block);
-                for action in 0 .. end_point.action {
-                    self.infer.add_live_point(rv, Point { block, action });
-                }
-                self.infer.add_live_point(rv, end_point);
-            }
-
-            let skolemized_block = self.env.graph.skolemized_end(region);
-            self.infer.add_live_point(rv, Point { block: skolemized_block, action: 0 });
-            self.populate_outlives(rv, &mut vec![region], outlives);
-            self.infer.cap_var(rv);
-            log!("Region for {:?}:\n{:#?}\n", region, self.infer.region(rv));
+            self.add_all_points(rv);
+            self.infer.add_universal_element(rv, RegionElement::EndOfFn(region));
+            let mut outlives = outlives.clone();
+            outlives.extend(free_region_bounds.get(&region).into_iter().flatten());
+            self.populate_outlives(rv, &mut vec![region], &outlives, &free_region_bounds);
+            self.infer.declare_universal_var(rv);
+            log!(
+                "Region for {}:\n{}\n",
+                self.env.describe_region(region),
+                self.infer.region(rv)
+            );
         }
 
+        // The points where each region is live; these are added to the
+        // inference context once the walk is done (see `add_live_runs`).
+        let mut live_points: BTreeMap<RegionVariable, BTreeSet<Point>> = BTreeMap::new();
+
         liveness.walk(|point, action, live_on_entry| {
             // To start, find every variable `x` that is live. All regions
             // in the type of `x` must include `point`.
             for region_name in liveness.live_regions(live_on_entry) {
                 let rv = self.region_variable(region_name);
-                self.infer.add_live_point(rv, point);
+                live_points.entry(rv).or_insert(BTreeSet::new()).insert(point);
+            }
+
+            // The bounds implied by the type of each live variable
+            // hold at `point`.
+            for var in liveness.live_variables(live_on_entry) {
+                for &ImpliedBound { sup, sub } in implied_bounds.get(&var).into_iter().flatten() {
+                    let sup_v = self.region_variable(sup);
+                    let sub_v = self.region_variable(sub);
+                    self.infer.add_outlives(sup_v, sub_v, point);
+                }
             }
 
             let action = if let Some(action) = action {
@@ -253,14 +1101,13 @@ impl<'env> RegionCheck<'env> {
             };
 
             // Next, walk the actions and establish any additional constraints
-            // that may arise from subtyping.
-            let successor_point = Point {
-                block: point.block,
-                action: point.action + 1,
-            };
+            // that may arise from subtyping. These hold where the effects
+            // of the action take hold, at its mid-point.
+            let mid_point = point.mid();
+            self.infer.set_cause(Some(point));
             match action.kind {
-                // `p = &'x` -- first, `'x` must include this point @ P,
-                // and second `&'x <: typeof(p) @ succ(P)`
+                // `p = &'x` -- `&'x <: typeof(p) @ Mid(P)`, and `'x`
+                // must not outlive the references it goes through
                 repr::ActionKind::Borrow(
                     ref dest_path,
                     region_name,
@@ -274,8 +1121,18 @@ impl<'env> RegionCheck<'env> {
                         borrow_kind,
                         source_ty,
                     ));
-                    self.relate_tys(successor_point, repr::Variance::Contra, &dest_ty, &ref_ty);
-                    self.ensure_borrow_source(successor_point, region_name, source_path);
+                    self.relate_tys(mid_point, repr::Variance::Contra, &dest_ty, &ref_ty);
+                    self.ensure_borrow_source(mid_point, region_name, source_path);
+                }
+
+                // `p = &raw q` -- `*q <: typeof(p) @ Mid(P)`; there
+                // is no region, so the pointer does not keep `q`
+                // borrowed.
+                repr::ActionKind::RawBorrow(ref dest_path, kind, ref source_path) => {
+                    let dest_ty = self.env.path_ty(dest_path);
+                    let source_ty = self.env.path_ty(source_path);
+                    let ptr_ty = Box::new(repr::Ty::RawPtr(kind, source_ty));
+                    self.relate_tys(mid_point, repr::Variance::Contra, &dest_ty, &ptr_ty);
                 }
 
                 // a = b
@@ -284,65 +1141,275 @@ impl<'env> RegionCheck<'env> {
                     let b_ty = self.env.path_ty(b);
 
                     // `b` must be a subtype of `a` to be assignable:
-                    self.relate_tys(successor_point, repr::Variance::Co, &b_ty, &a_ty);
+                    self.relate_tys(mid_point, repr::Variance::Co, &b_ty, &a_ty);
+                }
+
+                // `p = closure(...)` -- a by-ref capture `capture q by
+                // ref 'x` is treated like the borrow `&'x q`, and a
+                // by-move capture just moves `q` into the closure.
+                // Either way, everything captured must outlive the
+                // closure stored into `p`.
+                repr::ActionKind::Closure(ref dest_path, ref captures) => {
+                    let dest_ty = self.env.path_ty(dest_path);
+                    for capture in captures {
+                        let source_ty = self.env.path_ty(&capture.path);
+                        let captured_ty = match capture.mode {
+                            repr::CaptureMode::ByRef(region_name, borrow_kind) => {
+                                self.ensure_borrow_source(
+                                    mid_point,
+                                    region_name,
+                                    &capture.path,
+                                );
+                                Box::new(repr::Ty::Ref(
+                                    repr::Region::Free(region_name),
+                                    borrow_kind,
+                                    source_ty,
+                                ))
+                            }
+                            repr::CaptureMode::ByMove => source_ty,
+                        };
+                        self.ensure_capture_outlives(mid_point, &captured_ty, &dest_ty);
+                    }
                 }
 
-                // 'X: 'Y
+                // 'X: 'Y, possibly under some binders
                 repr::ActionKind::Constraint(ref c) => {
-                    match **c {
-                        repr::Constraint::Outlives(c) => {
-                            let sup_v = self.region_variable(c.sup);
-                            let sub_v = self.region_variable(c.sub);
-                            self.infer.add_outlives(sup_v, sub_v, point);
-                        }
-                        _ => {
-                            panic!("unimplemented rich constraint: {:?}", c);
+                    self.add_constraint(point, &HashMap::new(), Universe::root(), c);
+                }
+
+                // `p = use(q, ...)` -- each operand must be a subtype of
+                // the part of `p` it is stored into @ Mid(P); see
+                // `init_targets()`. A borrow operand `&'x q` is
+                // treated like the borrow `r = &'x q`; `'x` exists even
+                // if the reference is not stored anywhere in `p`.
+                repr::ActionKind::Init(ref dest_path, ref operands) => {
+                    let dest_ty = self.env.path_ty(dest_path);
+                    for operand in operands {
+                        if let repr::Operand::Borrow(region_name, _, ref source_path) = *operand {
+                            self.region_variable(region_name);
+                            self.ensure_borrow_source(mid_point, region_name, source_path);
                         }
                     }
+                    for (operand_ty, target_ty) in self.init_targets(&dest_ty, operands) {
+                        self.relate_tys(mid_point, repr::Variance::Co, &operand_ty, &target_ty);
+                    }
                 }
 
-                repr::ActionKind::Init(..) |
+                repr::ActionKind::Const(..) |
                 repr::ActionKind::Use(..) |
+                repr::ActionKind::Read(..) |
+                repr::ActionKind::Write(..) |
                 repr::ActionKind::Drop(..) |
                 repr::ActionKind::StorageDead(..) |
-                repr::ActionKind::SkolemizedEnd(_) |
                 repr::ActionKind::Noop => {
                     // no add'l constriants needed here; basic liveness
                     // suffices.
                 }
             }
+            self.infer.set_cause(None);
         });
+
+        for (rv, points) in live_points {
+            self.add_live_runs(rv, &points);
+        }
+    }
+
+    /// The typing rule for `p = use(q, ...)`, where `p` has type
+    /// `dest_ty`: pairs each operand with the type it must be a
+    /// subtype of. If `p` is a struct with as many fields as there are
+    /// operands, and each operand has the same shape as its field, the
+    /// operands are the fields, in order (`pair = use(a, b)`).
+    /// Otherwise, the operands with the same shape as `p` may each
+    /// become its value (`r = use(a, b)`, with references `a` and
+    /// `b`). The remaining operands are only read, which liveness
+    /// already accounts for. Returns the type of each operand that is
+    /// stored, with the type of where it is stored; a borrow operand
+    /// `&'x q` has the type of the reference it creates.
+    fn init_targets(&self,
+                    dest_ty: &repr::Ty,
+                    operands: &[repr::Operand])
+                    -> Vec<(Box<repr::Ty>, Box<repr::Ty>)> {
+        let operand_tys: Vec<_> = operands
+            .iter()
+            .map(|operand| match *operand {
+                repr::Operand::Path(ref q) => self.env.path_ty(q),
+                repr::Operand::Borrow(region_name, borrow_kind, ref q) => {
+                    Box::new(repr::Ty::Ref(repr::Region::Free(region_name),
+                                           borrow_kind,
+                                           self.env.path_ty(q)))
+                }
+            })
+            .collect();
+
+        if let repr::Ty::Struct(name, _) = *dest_ty {
+            let fields = &self.env.struct_map[&name].fields;
+            if fields.len() == operands.len() && !operands.is_empty() {
+                let field_tys: Vec<_> = fields
+                    .iter()
+                    .map(|field| self.env.field_ty(dest_ty, field.name))
+                    .collect();
+                if field_tys.iter().zip(&operand_tys).all(|(f, q)| f.same_shape(q)) {
+                    return operand_tys.into_iter().zip(field_tys).collect();
+                }
+            }
+        }
+
+        operand_tys
+            .into_iter()
+            .filter(|q_ty| q_ty.same_shape(dest_ty))
+            .map(|q_ty| (q_ty, Box::new(dest_ty.clone())))
+            .collect()
+    }
+
+    /// Adds the points in `points` to the region variable `rv`. Rather
+    /// than adding each point individually, we find the "entry" points
+    /// -- those whose straight-line predecessor (if any) is not in
+    /// `points` -- and add a single run from each of them. The
+    /// remaining points are implied by CFG connectivity, since each is
+    /// dominated by the entry of its run. In straight-line heavy code,
+    /// this is far fewer constraints than one per point. Runs stop at
+    /// branches and joins: a run is just an entry and a length, which
+    /// cannot describe a subtree of the dominator tree, and a region
+    /// is rarely live on every branch of one anyway.
+    fn add_live_runs(&mut self, rv: RegionVariable, points: &BTreeSet<Point>) {
+        let mut num_runs = 0;
+        for &point in points {
+            let is_entry = match self.env.straight_line_predecessor(point) {
+                Some(pred) => !points.contains(&pred),
+                None => true,
+            };
+            if !is_entry {
+                continue;
+            }
+
+            let mut len = 0;
+            let mut next = Some(point);
+            while let Some(p) = next {
+                if !points.contains(&p) {
+                    break;
+                }
+                len += 1;
+                next = self.env.straight_line_successor(p);
+            }
+
+            self.infer.add_live_run(rv, point, len);
+            num_runs += 1;
+        }
+
+        log!(
+            "add_live_runs({:?}): {} live points as {} runs",
+            rv,
+            points.len(),
+            num_runs
+        );
+
+        if self.options.self_check {
+            self.infer.expect_live_points(rv, points);
+        }
     }
 
     fn region_variable(&mut self, n: repr::RegionName) -> RegionVariable {
         let infer = &mut self.infer;
         let r = *self.region_map.entry(n).or_insert_with(|| infer.add_var(n));
-        log!("{:?} => {:?}", n, r);
+        log!("{} => {:?}", self.env.describe_region(n), r);
         r
     }
 
-    fn to_point(&self, point: &repr::Point) -> Point {
-        let block = match point.block {
-            repr::PointName::Code(b) => self.env.graph.block(b),
-            repr::PointName::SkolemizedEnd(r) => self.env.graph.skolemized_end(r),
-        };
-        Point {
-            block: block,
-            action: point.action,
+    /// The element named by `point` in an assertion. The end of a
+    /// free region `'a` is written `'a/0`; it lies outside the body of
+    /// the function, and has no actions, so no other point of it can
+    /// be named.
+    fn to_element(&self, point: &repr::Point) -> Result<RegionElement, String> {
+        match point.block {
+            repr::PointName::Code(b) => {
+                let block = match self.env.graph.block_named(b) {
+                    Some(block) => block,
+                    None => return Err(format!("assertion names `{}/{}`, but there is no \
+                                                block `{}`",
+                                               b, point.action, b)),
+                };
+                let end_point = self.env.end_point(block);
+                if point.action > end_point.action {
+                    return Err(format!("assertion names `{}/{}`, but `{}` ends at `{}/{}`",
+                                       b, point.action, b, b, end_point.action));
+                }
+                Ok(RegionElement::Location(Point { block, action: point.action, kind: point.kind }))
+            }
+            repr::PointName::EndOfFn(r) => {
+                if point.action != 0 {
+                    return Err(format!("assertion names `{}/{}`, but the end of a region \
+                                        has no actions; it is written `{}/0`",
+                                       r, point.action, r));
+                }
+                if r.is_static() {
+                    Ok(RegionElement::Static)
+                } else if self.env.graph.free_regions().iter().any(|decl| decl.name == r) {
+                    Ok(RegionElement::EndOfFn(r))
+                } else {
+                    Err(format!("assertion names `{}/0`, the end of `{}`, but `{}` is not \
+                                 a free region of the function",
+                                r, r, r))
+                }
+            }
+        }
+    }
+
+    /// The point of the function body named by `point` in an
+    /// assertion about liveness, which is only computed there.
+    fn to_location(&self, point: &repr::Point) -> Result<Point, String> {
+        match self.to_element(point)? {
+            RegionElement::Location(location) => Ok(location),
+            element => Err(format!("assertion names `{:?}`, but liveness is only computed at \
+                                    the points of the function body",
+                                   element)),
+        }
+    }
+
+    fn to_error_location(&self, point: &repr::Point) -> Result<Point, String> {
+        match self.to_element(point)? {
+            RegionElement::Location(location) => Ok(location),
+            element => Err(format!("assertion names `{:?}`, but errors are only reported at \
+                                    the points of the function body",
+                                   element)),
         }
     }
 
-    fn to_region(&self, user_region: &repr::RegionLiteral) -> Region {
+    fn to_region(&self, user_region: &repr::RegionLiteral) -> Result<Region, String> {
         let mut region = Region::new();
         for p in &user_region.points {
-            region.add_point(self.to_point(p));
+            region.add_element(self.to_element(p)?);
+        }
+        Ok(region)
+    }
+
+    /// `region` as `user_region` describes it: a literal that names no
+    /// mid-point only says which actions the region starts, as before
+    /// there were mid-points, so the mid-points of `region` are left
+    /// out.
+    fn literal_view(&self, user_region: &repr::RegionLiteral, region: &Region) -> Region {
+        if user_region.points.iter().any(|p| p.kind == repr::PointKind::Mid) {
+            return region.clone();
+        }
+        let mut view = Region::new();
+        for element in region.iter() {
+            match element {
+                RegionElement::Location(point) if point.is_mid() => {}
+                _ => {
+                    view.add_element(element);
+                }
+            }
         }
-        region
+        view
     }
 
+    /// Relates `a` and `b` with `variance` @ `mid_point`: with
+    /// `Co`, `a` must be a subtype of `b`. Types that cannot be
+    /// related are reported (see `type_errors`), and the parts of them
+    /// that can be are related still, so that the rest of the function
+    /// is analyzed as usual.
     fn relate_tys(
         &mut self,
-        successor_point: Point,
+        mid_point: Point,
         variance: repr::Variance,
         a: &repr::Ty,
         b: &repr::Ty,
@@ -352,51 +1419,134 @@ impl<'env> RegionCheck<'env> {
             a,
             variance,
             b,
-            successor_point
+            mid_point
         );
         match (a, b) {
-            (&repr::Ty::Ref(r_a, bk_a, ref t_a), &repr::Ty::Ref(r_b, bk_b, ref t_b)) => {
-                assert_eq!(bk_a, bk_b, "cannot relate {:?} and {:?}", a, b);
+            (&repr::Ty::Ref(r_a, _, ref t_a), &repr::Ty::Ref(r_b, _, ref t_b)) => {
+                let bk = match self.relate_borrow_kinds(mid_point, variance, a, b) {
+                    Some(bk) => bk,
+                    None => return,
+                };
                 self.relate_regions(
-                    successor_point,
+                    mid_point,
                     variance.invert(),
                     r_a.assert_free(),
                     r_b.assert_free(),
                 );
-                let referent_variance = variance.xform(bk_a.variance());
-                self.relate_tys(successor_point, referent_variance, t_a, t_b);
+                let referent_variance = variance.xform(bk.variance());
+                self.relate_tys(mid_point, referent_variance, t_a, t_b);
+            }
+            (&repr::Ty::RawPtr(_, ref t_a), &repr::Ty::RawPtr(_, ref t_b)) => {
+                let bk = match self.relate_borrow_kinds(mid_point, variance, a, b) {
+                    Some(bk) => bk,
+                    None => return,
+                };
+                let referent_variance = variance.xform(bk.variance());
+                self.relate_tys(mid_point, referent_variance, t_a, t_b);
             }
             (&repr::Ty::Unit, &repr::Ty::Unit) => {}
+            (&repr::Ty::Int, &repr::Ty::Int) => {}
             (&repr::Ty::Struct(s_a, ref ps_a), &repr::Ty::Struct(s_b, ref ps_b)) => {
                 if s_a != s_b {
-                    panic!("cannot compare `{:?}` and `{:?}`", s_a, s_b);
+                    self.report_mismatch(mid_point, variance, a, b, None);
+                    return;
                 }
                 let s_decl = self.env.struct_map[&s_a];
-                if ps_a.len() != s_decl.parameters.len() {
-                    panic!("wrong number of parameters for `{:?}`", a);
-                }
-                if ps_b.len() != s_decl.parameters.len() {
-                    panic!("wrong number of parameters for `{:?}`", b);
+                for ty in &[a, b] {
+                    let ps = match **ty {
+                        repr::Ty::Struct(_, ref ps) => ps,
+                        _ => unreachable!(),
+                    };
+                    if ps.len() != s_decl.parameters.len() {
+                        let point = self.infer.cause().unwrap_or(mid_point);
+                        self.type_errors.push(ReportedError {
+                            point,
+                            code: Some("E0107"),
+                            message: format!("wrong number of parameters: struct `{}` takes {}, \
+                                              but `{}` has {}",
+                                             s_a,
+                                             s_decl.parameters.len(),
+                                             ty,
+                                             ps.len()),
+                            notes: vec![],
+                            labels: HashMap::new(),
+                            facts: vec![],
+                        });
+                        return;
+                    }
                 }
                 for (sp, (p_a, p_b)) in s_decl.parameters.iter().zip(ps_a.iter().zip(ps_b)) {
                     let v = variance.xform(sp.variance);
-                    self.relate_parameters(successor_point, v, p_a, p_b);
+                    self.relate_parameters(mid_point, v, p_a, p_b);
                 }
             }
+            _ => self.report_mismatch(mid_point, variance, a, b, None),
+        }
+    }
+
+    /// Relates the kinds of two references (or raw pointers) `a` and
+    /// `b`, returning the kind whose variance applies to the referents.
+    /// A `&mut T` coerces to a `&T`, as a shared reborrow would, so
+    /// where the mutable one is the subtype, the two are related as
+    /// shared references: the referents are then covariant, rather
+    /// than invariant. Any other difference is reported, and `None` is
+    /// returned.
+    fn relate_borrow_kinds(
+        &mut self,
+        mid_point: Point,
+        variance: repr::Variance,
+        a: &repr::Ty,
+        b: &repr::Ty,
+    ) -> Option<repr::BorrowKind> {
+        let (bk_a, bk_b) = match (a, b) {
+            (&repr::Ty::Ref(_, bk_a, _), &repr::Ty::Ref(_, bk_b, _)) |
+            (&repr::Ty::RawPtr(bk_a, _), &repr::Ty::RawPtr(bk_b, _)) => (bk_a, bk_b),
+            _ => panic!("relate_borrow_kinds: `{}` and `{}` are not pointers", a, b),
+        };
+        let (expected, found) = expected_found(variance, bk_a, bk_b);
+        match (expected, found) {
+            (x, y) if x == y => Some(x),
+            (repr::BorrowKind::Shared, repr::BorrowKind::Mut) if variance != Variance::In => {
+                Some(repr::BorrowKind::Shared)
+            }
             _ => {
-                panic!(
-                    "cannot relate types `{:?}` and `{:?}` at {:?}",
-                    a,
-                    b,
-                    successor_point
-                )
+                self.report_mismatch(mid_point,
+                                     variance,
+                                     a,
+                                     b,
+                                     Some("types differ in mutability"));
+                None
             }
         }
     }
 
+    /// Reports that `a` cannot be related to `b` with `variance`, as
+    /// a "mismatched types" error at the action being processed.
+    fn report_mismatch<T: fmt::Display>(
+        &mut self,
+        mid_point: Point,
+        variance: repr::Variance,
+        a: &T,
+        b: &T,
+        note: Option<&str>,
+    ) {
+        let (expected, found) = expected_found(variance, a, b);
+        let point = self.infer.cause().unwrap_or(mid_point);
+        self.type_errors.push(ReportedError {
+            point,
+            code: Some("E0308"),
+            message: format!("mismatched types: expected `{}`, found `{}`", expected, found),
+            notes: note.into_iter()
+                .map(|message| Note { point: None, message: message.to_string() })
+                .collect(),
+            labels: HashMap::new(),
+            facts: vec![],
+        });
+    }
+
     fn relate_regions(
         &mut self,
-        successor_point: Point,
+        mid_point: Point,
         variance: repr::Variance,
         a: repr::RegionName,
         b: repr::RegionName,
@@ -406,44 +1556,70 @@ impl<'env> RegionCheck<'env> {
             a,
             variance,
             b,
-            successor_point
+            mid_point
         );
         let r_a = self.region_variable(a);
         let r_b = self.region_variable(b);
         match variance {
             Variance::Co =>
                 // "a Co b" == "a <= b"
-                self.infer.add_outlives(r_b, r_a, successor_point),
+                self.infer.add_outlives(r_b, r_a, mid_point),
             Variance::Contra =>
                 // "a Contra b" == "a >= b"
-                self.infer.add_outlives(r_a, r_b, successor_point),
+                self.infer.add_outlives(r_a, r_b, mid_point),
             Variance::In => {
-                self.infer.add_outlives(r_a, r_b, successor_point);
-                self.infer.add_outlives(r_b, r_a, successor_point);
+                self.infer.add_outlives(r_a, r_b, mid_point);
+                self.infer.add_outlives(r_b, r_a, mid_point);
             }
         }
     }
 
     fn relate_parameters(
         &mut self,
-        successor_point: Point,
+        mid_point: Point,
         variance: repr::Variance,
         a: &repr::TyParameter,
         b: &repr::TyParameter,
     ) {
         match (a, b) {
             (&repr::TyParameter::Ty(ref t_a), &repr::TyParameter::Ty(ref t_b)) => {
-                self.relate_tys(successor_point, variance, t_a, t_b)
+                self.relate_tys(mid_point, variance, t_a, t_b)
             }
             (&repr::TyParameter::Region(r_a), &repr::TyParameter::Region(r_b)) => {
                 self.relate_regions(
-                    successor_point,
+                    mid_point,
                     variance,
                     r_a.assert_free(),
                     r_b.assert_free(),
                 )
             }
-            _ => panic!("cannot relate parameters `{:?}` and `{:?}`", a, b),
+            _ => self.report_mismatch(mid_point, variance, a, b, None),
+        }
+    }
+
+    /// When a value of type `captured_ty` is captured by a closure of
+    /// type `closure_ty`, every region in the captured type must
+    /// outlive every region in the closure type.
+    fn ensure_capture_outlives(
+        &mut self,
+        mid_point: Point,
+        captured_ty: &repr::Ty,
+        closure_ty: &repr::Ty,
+    ) {
+        log!(
+            "ensure_capture_outlives({:?}, {:?}, {:?})",
+            mid_point,
+            captured_ty,
+            closure_ty
+        );
+        let closure_regions: Vec<_> = closure_ty.walk_regions().map(|r| r.assert_free()).collect();
+        let captured_regions: Vec<_> = captured_ty.walk_regions().map(|r| r.assert_free()).collect();
+        for &captured_region in &captured_regions {
+            for &closure_region in &closure_regions {
+                let sup_v = self.region_variable(captured_region);
+                let sub_v = self.region_variable(closure_region);
+                self.infer.add_outlives(sup_v, sub_v, mid_point);
+            }
         }
     }
 
@@ -451,49 +1627,85 @@ impl<'env> RegionCheck<'env> {
     /// that reborrows live long enough. Specifically, if we borrow
     /// something like `*r` for `'a`, where `r: &'b i32`, then `'b:
     /// 'a` is required.
+    ///
+    /// The borrowed path is valid as long as each of its supporting
+    /// prefixes is, so each of them is considered in turn, from the
+    /// borrowed path outwards, with the rules of
+    /// `borrow_source_layer`. For `&'a mut (*(*x).f).g`, this requires
+    /// the region of the reference `(*x).f` to outlive `'a`, and then,
+    /// if that is an `&mut` reference (so that the prefixes go on past
+    /// it), the region of `x` as well.
     fn ensure_borrow_source(
         &mut self,
-        successor_point: Point,
+        mid_point: Point,
         borrow_region_name: RegionName,
         source_path: &repr::Path,
     ) {
         log!(
             "ensure_borrow_source({:?}, {:?}, {:?})",
-            successor_point,
+            mid_point,
             borrow_region_name,
             source_path
         );
 
         for supporting_path in self.env.supporting_prefixes(source_path) {
-            match *supporting_path {
-                repr::Path::Var(_) => {
-                    // No lifetime constraints are needed to ensure the
-                    // validity of a variable. That is ensured by borrowck
-                    // preventing the storage of variables from being killed
-                    // while data owned by that variable is in scope.
-                    return;
-                }
-                repr::Path::Extension(ref base_path, field_name) => {
-                    let ty = self.env.path_ty(base_path);
-                    log!("ensure_borrow_source: ty={:?}", ty);
-                    match *ty {
-                        repr::Ty::Ref(ref_region, _, _) => {
-                            assert_eq!(field_name, repr::FieldName::star());
-                            let ref_region_name = ref_region.assert_free();
-                            let borrow_region_variable = self.region_variable(borrow_region_name);
-                            let ref_region_variable = self.region_variable(ref_region_name);
-                            self.infer.add_outlives(
-                                ref_region_variable,
-                                borrow_region_variable,
-                                successor_point,
-                            );
-                        }
-                        repr::Ty::Unit => {}
-                        repr::Ty::Struct(..) => {}
-                        repr::Ty::Bound(..) => {}
+            if let Some(ref_region_name) = self.borrow_source_layer(supporting_path) {
+                let borrow_region_variable = self.region_variable(borrow_region_name);
+                let ref_region_variable = self.region_variable(ref_region_name);
+                self.infer.add_outlives(
+                    ref_region_variable,
+                    borrow_region_variable,
+                    mid_point,
+                );
+            }
+        }
+    }
+
+    /// The region that must outlive a borrow for one supporting
+    /// prefix of the borrowed path to remain valid, if any.
+    fn borrow_source_layer(&self, supporting_path: &repr::Path) -> Option<RegionName> {
+        match *supporting_path {
+            // No lifetime constraints are needed to ensure the
+            // validity of a variable. That is ensured by borrowck
+            // preventing the storage of variables from being killed
+            // while data owned by that variable is in scope. A
+            // variable is always the last supporting prefix.
+            repr::Path::Var(_) => None,
+
+            repr::Path::Extension(ref base_path, field_name) => {
+                let ty = self.env.path_ty(base_path);
+                log!("borrow_source_layer: ty={:?}", ty);
+                match *ty {
+                    // `*r`: the referent is only valid as long as the
+                    // reference `r` is.
+                    repr::Ty::Ref(ref_region, _, _) => {
+                        assert_eq!(field_name, repr::FieldName::star());
+                        Some(ref_region.assert_free())
                     }
+
+                    // `*p`: nothing ties the memory behind a raw
+                    // pointer to the pointer (and it is the last
+                    // supporting prefix).
+                    repr::Ty::RawPtr(..) => None,
+
+                    // `s.f`: a field is stored within the struct that
+                    // owns it, so it is valid as long as the struct
+                    // is, which the shorter prefixes take care of.
+                    repr::Ty::Struct(..) => None,
+
+                    repr::Ty::Unit | repr::Ty::Int | repr::Ty::Bound(..) => None,
                 }
             }
         }
     }
 }
+
+/// Of the two sides `a` and `b` of a relation with `variance`, the one
+/// expected (the supertype) and the one found. With `In`, neither is
+/// the supertype, and `a` is taken to be the one expected.
+fn expected_found<T>(variance: Variance, a: T, b: T) -> (T, T) {
+    match variance {
+        Variance::Co => (b, a),
+        Variance::Contra | Variance::In => (a, b),
+    }
+}
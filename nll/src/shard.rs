@@ -0,0 +1,48 @@
+//! `--shard i/n`: splits the inputs between `n` processes, so that a
+//! large corpus can be checked in parallel. Each input goes to the
+//! shard picked by a hash of its path, as given on the command line,
+//! so every process agrees on the split without talking to the others
+//! -- as long as they are all given the same paths. Each file is
+//! analyzed on its own, so its results do not depend on which other
+//! files share its shard.
+
+use cache::StableHasher;
+use std::fmt;
+use std::hash::Hasher;
+use std::str::FromStr;
+
+/// The `index`th of `count` shards, numbered from 1.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Shard {
+    /// True if `input` belongs to this shard.
+    pub fn contains(&self, input: &str) -> bool {
+        let mut hasher = StableHasher::new();
+        hasher.write(input.as_bytes());
+        hasher.finish() % self.count as u64 == (self.index - 1) as u64
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut parts = s.splitn(2, '/').map(|part| part.trim().parse::<usize>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(index)), Some(Ok(count))) if index >= 1 && index <= count => {
+                Ok(Shard { index, count })
+            }
+            _ => Err(format!("invalid shard `{}` (expected `i/n`, with 1 <= i <= n)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
@@ -0,0 +1,89 @@
+use env::{Environment, Point};
+use liveness::Liveness;
+use loans_in_scope::{Loan, LoansInScope};
+use maybe_init::MaybeInit;
+use nll_repr::repr;
+use std::fmt;
+
+/// Everything the analyses know about the state on entry to a single
+/// point. The per-block analyses only store their results at block
+/// boundaries, so `state_at` recomputes the state within the block.
+pub struct StateSnapshot<'cx> {
+    env: &'cx Environment<'cx>,
+    pub point: Point,
+    pub live_variables: Vec<repr::Variable>,
+    pub live_regions: Vec<repr::RegionName>,
+    pub maybe_init: Vec<repr::Variable>,
+
+    /// `None` if the borrow check was not run.
+    pub loans_in_scope: Option<Vec<Loan<'cx>>>,
+}
+
+pub fn state_at<'cx>(
+    env: &'cx Environment<'cx>,
+    liveness: &Liveness,
+    maybe_init: &MaybeInit,
+    loans_in_scope: Option<&LoansInScope<'cx>>,
+    point: Point,
+) -> StateSnapshot<'cx> {
+    let mut snapshot = StateSnapshot {
+        env,
+        point,
+        live_variables: vec![],
+        live_regions: vec![],
+        maybe_init: vec![],
+        loans_in_scope: None,
+    };
+
+    liveness.walk_block(point.block, |p, _action, live_bits| if p == point {
+        snapshot.live_variables = liveness.live_variables(live_bits).collect();
+        snapshot.live_regions = liveness.live_regions(live_bits).collect();
+    });
+
+    maybe_init.walk_block(point.block, |p, _action, init_bits| if p == point {
+        snapshot.maybe_init = maybe_init.variables(init_bits).collect();
+    });
+
+    if let Some(loans_in_scope) = loans_in_scope {
+        loans_in_scope.walk_block(point.block, |p, _action, loans| if p == point {
+            snapshot.loans_in_scope = Some(loans.iter().map(|&loan| loan.clone()).collect());
+        });
+    }
+
+    snapshot
+}
+
+impl<'cx> fmt::Display for StateSnapshot<'cx> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "state at {}:", self.env.describe_point(self.point))?;
+        writeln!(f, "  live variables: {}", comma_list(&self.live_variables))?;
+        writeln!(f, "  live regions: {}", comma_list(&self.live_regions))?;
+        writeln!(f, "  maybe init: {}", comma_list(&self.maybe_init))?;
+        match self.loans_in_scope {
+            Some(ref loans) => {
+                writeln!(f, "  loans in scope:")?;
+                for loan in loans {
+                    let kind = match loan.kind {
+                        repr::BorrowKind::Mut => "mut ",
+                        repr::BorrowKind::Shared => "",
+                    };
+                    writeln!(
+                        f,
+                        "    &{} {}{} issued at {}",
+                        loan.region_name,
+                        kind,
+                        loan.path,
+                        self.env.describe_point(loan.point)
+                    )?;
+                }
+            }
+            None => writeln!(f, "  loans in scope: (borrow check not run)")?,
+        }
+        Ok(())
+    }
+}
+
+fn comma_list<T: fmt::Display>(items: &[T]) -> String {
+    let items: Vec<_> = items.iter().map(|item| item.to_string()).collect();
+    format!("[{}]", items.join(", "))
+}
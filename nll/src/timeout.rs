@@ -0,0 +1,155 @@
+//! `--timeout`: a time limit for the analysis of a single input, so
+//! that a pathological input does not hang a whole test run. The
+//! limit is enforced cooperatively: the fixed-point loops of the
+//! analysis call `round()` once per pass, which returns `Err` once the
+//! deadline has passed, and pass the error up so that the analysis
+//! gives up. What was done until then -- the phases entered, with the
+//! time they took and the passes they made -- is reported in the
+//! `TimedOut` error, which `run()` returns whatever the analysis made
+//! of it on the way out.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::time::{Duration, Instant};
+
+thread_local! {
+    /// The deadline of the innermost `run`, if any, and what it
+    /// has done so far.
+    static CURRENT: RefCell<Option<Progress>> = RefCell::new(None);
+}
+
+struct Progress {
+    deadline: Instant,
+    limit: Duration,
+    phases: Vec<PhaseStats>,
+    phase_start: Instant,
+
+    /// Set by the `round()` that found the deadline had passed.
+    timed_out: Option<TimedOut>,
+}
+
+/// The work done in one phase of an analysis that timed out.
+#[derive(Clone, Debug)]
+pub struct PhaseStats {
+    pub name: &'static str,
+    pub time: Duration,
+
+    /// The passes made by the fixed-point loops of the phase.
+    pub rounds: usize,
+}
+
+/// The error of an analysis that did not finish within its time
+/// limit. The last of the `phases` is the one that was interrupted.
+#[derive(Clone, Debug)]
+pub struct TimedOut {
+    pub limit: Duration,
+    pub phases: Vec<PhaseStats>,
+}
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "timed out after {:?}", self.limit)?;
+        if let Some(last) = self.phases.last() {
+            write!(f, ", in {}", last.name)?;
+        }
+        let phases: Vec<_> = self.phases
+            .iter()
+            .map(|phase| format!("{}: {} rounds in {}ms",
+                                 phase.name, phase.rounds, phase.time.as_millis()))
+            .collect();
+        if !phases.is_empty() {
+            write!(f, " ({})", phases.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for TimedOut {
+    fn description(&self) -> &str {
+        "timed out"
+    }
+}
+
+/// Runs `f`, failing if a `round()` within it found that `limit` had
+/// passed; `f` is expected to return soon after that. `run`s do not
+/// nest: within an inner one, only its own limit applies.
+pub fn run<F, R>(limit: Duration, f: F) -> Result<R, TimedOut>
+where
+    F: FnOnce() -> R,
+{
+    // Restores the enclosing deadline even if `f` panics.
+    struct Restore(Option<Progress>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let outer = self.0.take();
+            CURRENT.with(|current| *current.borrow_mut() = outer);
+        }
+    }
+
+    let now = Instant::now();
+    let progress = Progress {
+        deadline: now + limit,
+        limit,
+        phases: vec![],
+        phase_start: now,
+        timed_out: None,
+    };
+    let outer = CURRENT.with(|current| mem::replace(&mut *current.borrow_mut(), Some(progress)));
+    let _restore = Restore(outer);
+    let result = f();
+    let timed_out = CURRENT.with(|current| {
+        current.borrow_mut().as_mut().and_then(|progress| progress.timed_out.take())
+    });
+    match timed_out {
+        Some(timed_out) => Err(timed_out),
+        None => Ok(result),
+    }
+}
+
+/// Records that the analysis starts the phase `name`, ending the
+/// previous one.
+pub fn enter_phase(name: &'static str) {
+    CURRENT.with(|current| if let Some(ref mut progress) = *current.borrow_mut() {
+        progress.end_phase();
+        progress.phases.push(PhaseStats { name, time: Duration::from_secs(0), rounds: 0 });
+    });
+}
+
+/// Called once per pass of a fixed-point loop: fails if the deadline
+/// of the enclosing `run` has passed, in which case the caller should
+/// give up on the analysis.
+pub fn round() -> Result<(), TimedOut> {
+    CURRENT.with(|current| {
+        let mut current = current.borrow_mut();
+        let progress = match *current {
+            Some(ref mut progress) => progress,
+            None => return Ok(()),
+        };
+        if let Some(ref timed_out) = progress.timed_out {
+            return Err(timed_out.clone());
+        }
+        if let Some(phase) = progress.phases.last_mut() {
+            phase.rounds += 1;
+        }
+        if Instant::now() < progress.deadline {
+            return Ok(());
+        }
+        progress.end_phase();
+        let timed_out = TimedOut { limit: progress.limit, phases: progress.phases.clone() };
+        progress.timed_out = Some(timed_out.clone());
+        Err(timed_out)
+    })
+}
+
+impl Progress {
+    /// Adds the time since the current phase was entered to it.
+    fn end_phase(&mut self) {
+        let now = Instant::now();
+        if let Some(phase) = self.phases.last_mut() {
+            phase.time += now - self.phase_start;
+        }
+        self.phase_start = now;
+    }
+}
@@ -0,0 +1,304 @@
+//! `--transform`: rewrites the control-flow graph of a function in
+//! ways that do not change what it does, before analyzing it, to
+//! study whether the analysis gives the same results however the
+//! same program is laid out. Each point of the transformed function
+//! stands for a point of the original one (the copies of an action
+//! stand for the action, and an inserted block or `noop` for the
+//! point it was inserted at), so the errors found in the transformed
+//! function can be checked against those the original expects -- and,
+//! with `--self-check`, against those found in the original.
+
+use env::{Environment, Point};
+use errors::{Note, ReportedError, SourceFile};
+use graph::{self, FuncGraph};
+use graph_algorithms::loop_tree;
+use nll_repr::repr::{Action, ActionKind, BasicBlock, BasicBlockData, Func};
+use options::{Options, Transform};
+use regionck;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::error::Error;
+
+/// A point, as the name of its block and the index of its action.
+type RawPoint = (BasicBlock, usize);
+
+/// For each point of a transformed function, the point it stands for
+/// in the function it was transformed from.
+type Origins = HashMap<RawPoint, RawPoint>;
+
+pub struct Transformed {
+    pub func: Func,
+    origins: Origins,
+}
+
+impl Transformed {
+    /// The point of the original function that the point `action` of
+    /// `block` stands for.
+    pub fn origin(&self, block: BasicBlock, action: usize) -> RawPoint {
+        self.origins[&(block, action)]
+    }
+}
+
+/// Applies `transforms` to `func`, in order. The transformed function
+/// has no assertions, and expects no errors: those of the original
+/// function are about its own points.
+pub fn apply(func: &Func, transforms: &[Transform]) -> Transformed {
+    let mut func = func.clone();
+    func.assertions.clear();
+    for block in func.data.values_mut() {
+        for action in &mut block.actions {
+            action.should_have_error = None;
+        }
+    }
+
+    let mut origins = identity(&func);
+    for &transform in transforms {
+        let step = match transform {
+            Transform::SplitCriticalEdges => split_critical_edges(&mut func),
+            Transform::UnrollLoops => unroll_loops(&mut func),
+            Transform::PadNoops => pad_noops(&mut func),
+        };
+        origins = step.into_iter().map(|(point, from)| (point, origins[&from])).collect();
+    }
+    Transformed { func, origins }
+}
+
+/// Analyzes the function of `env` transformed as `options` asks, and
+/// checks the errors found against those the function expects. With
+/// `self_check`, the errors must also be those found in the function
+/// itself. What it reports is added to `output`, as with
+/// `regionck::region_check`.
+pub fn check(env: &Environment,
+             options: &Options,
+             source: &SourceFile,
+             output: &mut Vec<String>)
+             -> Result<(), Box<Error>> {
+    let transformed = apply(env.graph.func(), &options.transforms);
+    let found = diagnostics(env.graph, &transformed, options, source)?;
+
+    if options.self_check {
+        let expected = regionck::diagnostics(env, &Options { transforms: vec![], ..options.clone() },
+                                             source)?;
+        // The notes are left out: they may describe paths through
+        // the transformed graph.
+        let describe = |errors: &[ReportedError]| -> BTreeSet<String> {
+            errors
+                .iter()
+                .map(|error| match error.code {
+                    Some(code) => format!("{:?}: [{}] {}", error.point, code, error.message),
+                    None => format!("{:?}: {}", error.point, error.message),
+                })
+                .collect()
+        };
+        let (expected, found) = (describe(&expected), describe(&found));
+        if expected != found {
+            let transforms: Vec<_> = options.transforms.iter().map(|t| t.to_string()).collect();
+            let mut message = format!("the errors differ once transformed by `{}`",
+                                      transforms.join(","));
+            for error in found.difference(&expected) {
+                message.push_str(&format!("\n  only when transformed: {}", error));
+            }
+            for error in expected.difference(&found) {
+                message.push_str(&format!("\n  only when not transformed: {}", error));
+            }
+            try!(Err(message));
+        }
+    }
+
+    regionck::check_errors(env, options, found, output)
+}
+
+/// The errors found in `transformed`, with their points (and those of
+/// their notes) mapped back to the points of `original` that they
+/// stand for; the text of the notes, such as the path along which a
+/// loan is live, is still about the transformed function. Errors that
+/// only differ in the copy of an action they were reported at are
+/// reported once.
+pub fn diagnostics(original: &FuncGraph,
+                   transformed: &Transformed,
+                   options: &Options,
+                   source: &SourceFile)
+                   -> Result<Vec<ReportedError>, Box<Error>> {
+    let graph = FuncGraph::new(transformed.func.clone());
+    let errors = graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        regionck::diagnostics(&env, options, source)
+    })?;
+
+    let origin = |point: Point| {
+        let (block, action) = transformed.origin(graph.block_data(point.block).name, point.action);
+        Point { block: original.block(block), action, kind: point.kind }
+    };
+    let mut seen = HashSet::new();
+    let mut mapped = vec![];
+    for error in errors {
+        let point = origin(error.point);
+        if !seen.insert((point, error.code, error.message.clone())) {
+            continue;
+        }
+        mapped.push(ReportedError {
+            point,
+            code: error.code,
+            message: error.message,
+            notes: error.notes
+                .into_iter()
+                .map(|note| Note { point: note.point.map(origin), message: note.message })
+                .collect(),
+            labels: HashMap::new(),
+            facts: vec![],
+        });
+    }
+    Ok(mapped)
+}
+
+/// Each point of `func`, standing for itself.
+fn identity(func: &Func) -> Origins {
+    func.data
+        .values()
+        .flat_map(|block| (0..block.actions.len() + 1).map(move |i| (block.name, i)))
+        .map(|point| (point, point))
+        .collect()
+}
+
+/// A name for a new block, based on `base`, that no block of `func`
+/// has yet.
+fn fresh_block(func: &Func, base: &str) -> BasicBlock {
+    let mut name = BasicBlock::from(base);
+    let mut suffix = 1;
+    while func.data.contains_key(&name) {
+        suffix += 1;
+        name = BasicBlock::from(&format!("{}_{}", base, suffix)[..]);
+    }
+    name
+}
+
+fn empty_block(name: BasicBlock, successors: Vec<BasicBlock>) -> BasicBlockData {
+    BasicBlockData {
+        name,
+        actions: vec![],
+        successors,
+        params: vec![],
+        successor_args: vec![],
+        scope_exit: false,
+        allow: vec![],
+    }
+}
+
+/// Puts an empty block on each critical edge. The new block stands
+/// for the end of the block the edge leaves.
+fn split_critical_edges(func: &mut Func) -> Origins {
+    let mut origins = identity(func);
+    let mut num_predecessors = HashMap::new();
+    for block in func.data.values() {
+        for &successor in &block.successors {
+            *num_predecessors.entry(successor).or_insert(0) += 1;
+        }
+    }
+
+    let critical: Vec<_> = func.data
+        .values()
+        .filter(|block| block.successors.len() > 1)
+        .flat_map(|block| {
+            block.successors.iter().enumerate().map(move |(index, &s)| (block.name, index, s))
+        })
+        .filter(|&(_, _, successor)| num_predecessors[&successor] > 1)
+        .collect();
+    for (from, index, to) in critical {
+        let name = fresh_block(func, &format!("{}_{}", from, to));
+        let end = func.data[&from].actions.len();
+        func.data.insert(name, empty_block(name, vec![to]));
+        func.data.get_mut(&from).unwrap().successors[index] = name;
+        origins.insert((name, 0), (from, end));
+    }
+    origins
+}
+
+/// Peels the first iteration off each outermost loop: its blocks are
+/// copied, with the copies of the back edges going to the loop head,
+/// and the edges that enter the loop going to the copy of the head
+/// instead. A loop headed by `START` is left alone, as nothing enters
+/// it from outside.
+fn unroll_loops(func: &mut Func) -> Origins {
+    let mut origins = identity(func);
+    let graph = FuncGraph::new(func.clone());
+    let loop_tree = loop_tree::loop_tree(&graph);
+
+    // The blocks of each outermost loop, by its head.
+    let mut loops: HashMap<BasicBlock, HashSet<BasicBlock>> = HashMap::new();
+    for &name in func.data.keys() {
+        if let Some(loop_id) = loop_tree.loop_id(graph.block(name)) {
+            let outermost = loop_tree.parents(loop_id).last().unwrap_or(loop_id);
+            let head = graph.block_data(loop_tree.loop_head(outermost)).name;
+            loops.entry(head).or_default().insert(name);
+        }
+    }
+
+    let mut heads: Vec<_> = loops.keys().cloned().collect();
+    heads.sort();
+    for head in heads.into_iter().filter(|&head| head != BasicBlock::start()) {
+        let body = &loops[&head];
+        let mut copies = HashMap::new();
+        let mut sorted_body: Vec<_> = body.iter().cloned().collect();
+        sorted_body.sort();
+        for &name in &sorted_body {
+            let copy = fresh_block(func, &format!("{}_peeled", name));
+            copies.insert(name, copy);
+            func.data.insert(copy, empty_block(copy, vec![]));
+        }
+
+        for &name in &sorted_body {
+            let mut data = func.data[&name].clone();
+            data.name = copies[&name];
+            data.successors = data.successors
+                .iter()
+                .map(|&s| if s != head && body.contains(&s) { copies[&s] } else { s })
+                .collect();
+            for action in 0..data.actions.len() + 1 {
+                origins.insert((data.name, action), origins[&(name, action)]);
+            }
+            func.data.insert(data.name, data);
+        }
+
+        let copy_of_head = copies[&head];
+        for block in func.data.values_mut() {
+            if body.contains(&block.name) || copies.values().any(|&c| c == block.name) {
+                continue;
+            }
+            for successor in &mut block.successors {
+                if *successor == head {
+                    *successor = copy_of_head;
+                }
+            }
+        }
+    }
+    origins
+}
+
+/// Puts a `noop` before each action. The `noop` stands for the point
+/// of the action, where nothing has happened yet.
+fn pad_noops(func: &mut Func) -> Origins {
+    let mut origins = HashMap::new();
+    for block in func.data.values_mut() {
+        let actions = block.actions.len();
+        let mut padded = Vec::with_capacity(2 * actions);
+        for (index, action) in block.actions.drain(..).enumerate() {
+            origins.insert((block.name, padded.len()), (block.name, index));
+            padded.push(noop());
+            origins.insert((block.name, padded.len()), (block.name, index));
+            padded.push(action);
+        }
+        origins.insert((block.name, padded.len()), (block.name, actions));
+        block.actions = padded;
+    }
+    origins
+}
+
+fn noop() -> Action {
+    Action {
+        kind: ActionKind::Noop,
+        span: None,
+        label: None,
+        allow: vec![],
+        in_unsafe_block: false,
+        should_have_error: None,
+    }
+}
@@ -0,0 +1,75 @@
+//! Universes: which placeholders a region variable may name. Each
+//! `forall` constraint creates a universe nested in the one it is
+//! written in, holding the placeholders it introduces; a variable
+//! created in a universe (by `exists`, say) may name the placeholders
+//! of that universe and of the universes it is nested in, but not
+//! those of a universe nested deeper, nor of a sibling:
+//!
+//! ```text
+//! forall<'x> (                // U1, in U0
+//!     forall<'y> exists<'a>   // U2, in U1: `'a` may name `'x`, `'y`
+//!         ...,
+//!     forall<'z> exists<'b>   // U3, in U1: `'b` may name `'x`, `'z`
+//!         ...,
+//! )
+//! ```
+//!
+//! The universes are numbered in the order they are created, so that
+//! numbering alone cannot tell `U2` from an ancestor of `U3`; the
+//! tree of `Universes` can.
+
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Universe {
+    index: usize,
+}
+
+impl Universe {
+    /// The universe of the function body, which has no placeholders.
+    pub fn root() -> Self {
+        Universe { index: 0 }
+    }
+}
+
+impl fmt::Display for Universe {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "U{}", self.index)
+    }
+}
+
+/// The universes created so far, each with the one it is nested in.
+pub struct Universes {
+    /// The parent of each universe, by index; the root has none.
+    parents: Vec<Option<Universe>>,
+}
+
+impl Universes {
+    pub fn new() -> Self {
+        Universes { parents: vec![None] }
+    }
+
+    /// Creates a universe nested in `parent`.
+    pub fn new_child(&mut self, parent: Universe) -> Universe {
+        let universe = Universe { index: self.parents.len() };
+        self.parents.push(Some(parent));
+        universe
+    }
+
+    pub fn parent(&self, universe: Universe) -> Option<Universe> {
+        self.parents[universe.index]
+    }
+
+    /// Whether a variable in `universe` may name the placeholders of
+    /// `other`: whether `other` is `universe` or one it is nested in.
+    pub fn can_name(&self, universe: Universe, other: Universe) -> bool {
+        let mut next = Some(universe);
+        while let Some(u) = next {
+            if u == other {
+                return true;
+            }
+            next = self.parent(u);
+        }
+        false
+    }
+}
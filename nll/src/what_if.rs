@@ -0,0 +1,197 @@
+//! `nll what-if`: analyzes a program with one hypothetical action
+//! inserted, and reports what that changes -- whether the action
+//! itself is an error, which other actions it makes into errors, and
+//! which regions have to grow to accommodate it.
+//!
+//! Inserting an action at `B/i` shifts the later actions of `B` down
+//! by one. Everything is reported in terms of the original program:
+//! the points of the modified program after the inserted action are
+//! mapped back to the points they came from, and the inserted action
+//! itself is at `B/i`, both its start and its mid-point: a region that
+//! holds across it holds between the actions it was inserted between,
+//! which in the original program is just `B/i`.
+
+use borrowck;
+use env::{Environment, Point};
+use errors::{ReportedError, SourceFile};
+use graph::{self, FuncGraph};
+use nll_repr::repr::{Action, ActionKind, RegionName};
+use options::Options;
+use region::Region;
+use regionck;
+use std::error::Error;
+use std::fmt;
+
+pub struct WhatIf {
+    /// The action, as written on the command line.
+    pub action: String,
+    pub point: Point,
+
+    /// The errors reported for the hypothetical action: one for each
+    /// loan in scope at `point` in the original program that one of
+    /// its accesses conflicts with (see `regionck::check_access`), and
+    /// any other error the analysis of the modified program reports
+    /// for it.
+    pub errors: Vec<ReportedError>,
+
+    /// The errors reported elsewhere that the original program does
+    /// not have.
+    pub new_errors: Vec<ReportedError>,
+
+    /// For each region that would have to grow, the elements it
+    /// would gain.
+    pub grown: Vec<(RegionName, Region)>,
+}
+
+/// Inserts `action` before the action at `point` (or at the end of
+/// its block) and compares the results of the analysis against those
+/// for `env`. The assertions of the program are not checked for the
+/// modified program, since they name the points of the original. Fails
+/// if `action` does not parse, or names a path that is not in the
+/// function.
+pub fn what_if(env: &Environment,
+               options: &Options,
+               source: &SourceFile,
+               point: Point,
+               action: &str)
+               -> Result<WhatIf, Box<Error>> {
+    let text = action.trim();
+    let kind = if text.ends_with(';') {
+        ActionKind::parse(text)
+    } else {
+        ActionKind::parse(&format!("{};", text))
+    };
+    let kind = kind.map_err(|e| format!("in action `{}`: {}", text, e))?;
+    let action = Action {
+        kind,
+        span: None,
+        label: None,
+        allow: vec![],
+        in_unsafe_block: false,
+        should_have_error: None,
+    };
+
+    // Checking the accesses first also rejects any path the function
+    // has no variable or field for, before it gets to the analysis.
+    let mut errors = vec![];
+    for (kind, path) in borrowck::accesses(&action) {
+        let access_errors = regionck::check_access(env, options, point, &path, kind)
+            .map_err(|e| format!("in action `{}`: {}", text, e))?;
+        errors.extend(access_errors);
+    }
+
+    let old = regionck::analysis(env, options, source)?;
+
+    let mut func = env.graph.func().clone();
+    func.assertions.clear();
+    let block_name = env.graph.block_data(point.block).name;
+    func.data.get_mut(&block_name).unwrap().actions.insert(point.action, action);
+
+    // The blocks are the same, so the block indices of the two graphs
+    // agree; only the action indices need mapping.
+    let unshift = |p: Point| if p.block != point.block || p.action < point.action {
+        p
+    } else if p.action == point.action {
+        point
+    } else {
+        Point { action: p.action - 1, ..p }
+    };
+    let graph = FuncGraph::new(func);
+    let new = graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        regionck::analysis(&env, options, source)
+    })?;
+
+    let mut result = WhatIf {
+        action: text.to_string(),
+        point,
+        errors,
+        new_errors: vec![],
+        grown: vec![],
+    };
+
+    for mut error in new.errors {
+        let at_action = error.point == point;
+        error.point = unshift(error.point);
+        for note in &mut error.notes {
+            note.point = note.point.map(&unshift);
+        }
+        if at_action {
+            if !result.errors.iter().any(|e| same_error(e, &error)) {
+                result.errors.push(error);
+            }
+        } else if !old.errors.iter().any(|old| same_error(old, &error)) {
+            result.new_errors.push(error);
+        }
+    }
+
+    for (name, new_region) in new.regions {
+        let old_region = match old.regions.get(&name) {
+            Some(region) => region,
+            None => continue,
+        };
+        let mut gained = Region::new();
+        for p in new_region.locations().map(&unshift).filter(|&p| !old_region.may_contain(p)) {
+            gained.add_point(p);
+        }
+        for e in new_region.universal_elements().filter(|&e| !old_region.contains(e)) {
+            gained.add_element(e);
+        }
+        if gained != Region::new() {
+            result.grown.push((name, gained));
+        }
+    }
+
+    Ok(result)
+}
+
+fn same_error(a: &ReportedError, b: &ReportedError) -> bool {
+    a.point == b.point && a.code == b.code && a.message == b.message
+}
+
+fn write_error(f: &mut fmt::Formatter, error: &ReportedError) -> Result<(), fmt::Error> {
+    match error.code {
+        Some(code) => writeln!(f, "    error[{}] at {:?}: {}", code, error.point, error.message)?,
+        None => writeln!(f, "    error at {:?}: {}", error.point, error.message)?,
+    }
+    for note in &error.notes {
+        match note.point {
+            Some(point) => writeln!(f, "      note at {:?}: {}", point, note.message)?,
+            None => writeln!(f, "      note: {}", note.message)?,
+        }
+    }
+    Ok(())
+}
+
+/// Must be displayed within `graph::with_graph` for the original
+/// program.
+impl fmt::Display for WhatIf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        writeln!(f, "what if `{}` at {:?}:", self.action, self.point)?;
+        if self.errors.is_empty() {
+            writeln!(f, "  the action is accepted")?;
+        } else {
+            writeln!(f, "  the action is an error:")?;
+            for error in &self.errors {
+                write_error(f, error)?;
+            }
+        }
+
+        if !self.new_errors.is_empty() {
+            writeln!(f, "  it makes other actions into errors:")?;
+            for error in &self.new_errors {
+                write_error(f, error)?;
+            }
+        }
+
+        if self.grown.is_empty() {
+            writeln!(f, "  no region has to grow")?;
+        } else {
+            writeln!(f, "  regions that have to grow:")?;
+            for &(name, ref gained) in &self.grown {
+                writeln!(f, "    `{}` gains {}", name, gained)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+//! Runs `nll` twice over the same inputs with one `--cache-dir`: the
+//! second run takes the outcome of each input from the cache, and
+//! must print the same as the first.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{self, Command};
+
+fn run(cache_dir: &PathBuf, input: &str) -> String {
+    let input = format!("{}/../test/{}", env!("CARGO_MANIFEST_DIR"), input);
+    let output = Command::new(env!("CARGO_BIN_EXE_nll"))
+        .arg("--cache-dir")
+        .arg(cache_dir)
+        .arg("--warn-dead-stores")
+        .arg(&input)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn cached_runs_print_warnings() {
+    let cache_dir = env::temp_dir().join(format!("nll-cache-test-{}", process::id()));
+    for &(input, warnings) in &[("unsafe-raw-pointer-conflict.nll", "2 warnings"),
+                                ("borrowck-read-write-actions.nll", "1 warning")] {
+        let first = run(&cache_dir, input);
+        assert!(first.lines().any(|line| line == warnings), "{}", first);
+        assert_eq!(run(&cache_dir, input), first);
+    }
+    fs::remove_dir_all(&cache_dir).unwrap();
+}
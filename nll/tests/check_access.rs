@@ -0,0 +1,149 @@
+//! `regionck::check_access` agrees with the borrow check: asked about
+//! each access that an action of a test program makes, at the point of
+//! the action, it finds the error that `borrow_check` reports for the
+//! action, and none where the borrow check reports none.
+
+extern crate nll;
+extern crate nll_repr;
+
+use nll::borrowck::{self, AccessKind};
+use nll::env::{Environment, Point};
+use nll::errors::SourceFile;
+use nll::graph::{self, FuncGraph};
+use nll::options::Options;
+use nll::regionck;
+use nll_repr::repr::{Func, Path as ReprPath, Variable};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The codes of the errors that `borrow_check` reports for conflicts
+/// with loans.
+const CONFLICT_CODES: &[&str] = &["E0499", "E0502", "E0503", "E0505", "E0506", "E0597"];
+
+/// The programs of the test corpus, outside the subdirectories, which
+/// are run with options of their own.
+fn corpus() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../test");
+    let mut files: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "nll"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn check_access_agrees_with_borrow_check() {
+    let options = Options::default();
+    let mut kinds_checked = vec![];
+    let mut errors_matched = 0;
+    for path in corpus() {
+        let text = fs::read_to_string(&path).unwrap();
+        let func = match Func::parse_file(&path, &text) {
+            Ok(func) => func,
+            Err(_) => continue,
+        };
+        let name = path.display().to_string();
+        let graph = FuncGraph::new(func);
+        let source = SourceFile { name: &name, text: &text };
+        graph::with_graph(&graph, || {
+            let env = Environment::new(&graph);
+            let analysis = match regionck::analysis(&env, &options, &source) {
+                Ok(analysis) => analysis,
+                Err(_) => return,
+            };
+            for &block in &env.reverse_post_order {
+                if !env.graph.block_data(block).allow.is_empty() {
+                    continue;
+                }
+                for (point, action) in env.actions(block) {
+                    let errors: Vec<_> =
+                        analysis.errors.iter().filter(|e| e.point == point).collect();
+                    // Errors that are reported instead of the borrow
+                    // check of the action, or that it may be allowed
+                    // or downgraded to a warning.
+                    let checked = !action.in_unsafe_block && action.allow.is_empty() &&
+                        errors.iter().all(|e| e.code.map_or(true, |c| CONFLICT_CODES.contains(&c)));
+                    if !checked {
+                        continue;
+                    }
+                    let reported: Vec<_> = errors
+                        .iter()
+                        .filter(|e| e.code.is_some())
+                        .map(|e| (e.code, e.message.clone()))
+                        .collect();
+
+                    // `borrow_check` reports the first loan that the
+                    // first conflicting access conflicts with.
+                    let mut first = None;
+                    for (kind, path) in borrowck::accesses(action) {
+                        if !kinds_checked.contains(&kind) {
+                            kinds_checked.push(kind);
+                        }
+                        let found = regionck::check_access(&env, &options, point, &path, kind)
+                            .unwrap();
+                        if first.is_none() {
+                            first = found.into_iter().next();
+                        }
+                    }
+                    let first: Vec<_> = first.into_iter().map(|e| (e.code, e.message)).collect();
+                    assert_eq!(first, reported, "{} at {:?}", name, point);
+                    errors_matched += reported.len();
+                }
+            }
+        });
+    }
+
+    for kind in &[AccessKind::Read, AccessKind::ShallowRead, AccessKind::Write,
+                  AccessKind::DeepWrite, AccessKind::MutBorrow, AccessKind::Move,
+                  AccessKind::Drop, AccessKind::StorageDead] {
+        assert!(kinds_checked.contains(kind), "no {:?} access was checked", kind);
+    }
+    assert!(errors_matched > 0);
+}
+
+const PROGRAM: &str = "\
+let x: ();
+let p: &'p ();
+
+block START {
+    x = use();
+    p = &'b0 x;
+    use(p);
+}
+";
+
+/// `check_access` for `PROGRAM`, with the errors as text.
+fn check(block_action: usize, path: ReprPath, kind: AccessKind) -> Result<Vec<String>, String> {
+    let func = Func::parse(PROGRAM).unwrap();
+    let graph = FuncGraph::new(func);
+    graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        let point = Point::start(env.reverse_post_order[0], block_action);
+        regionck::check_access(&env, &Options::default(), point, &path, kind)
+            .map(|errors| errors.into_iter().map(|e| e.message).collect())
+            .map_err(|e| e.to_string())
+    })
+}
+
+#[test]
+fn check_access_between_borrow_and_use() {
+    let x = || ReprPath::Var(Variable::from("x"));
+    assert_eq!(check(2, x(), AccessKind::Read), Ok(vec![]));
+    assert_eq!(check(2, x(), AccessKind::Write),
+               Ok(vec!["cannot write `x` because `x` is borrowed".to_string()]));
+    assert_eq!(check(3, x(), AccessKind::Write), Ok(vec![]));
+}
+
+#[test]
+fn check_access_rejects_invalid_point() {
+    let x = ReprPath::Var(Variable::from("x"));
+    assert_eq!(check(9, x, AccessKind::Read), Err("invalid point `START/9`".to_string()));
+}
+
+#[test]
+fn check_access_rejects_path_not_in_function() {
+    let zz = ReprPath::Var(Variable::from("zz"));
+    assert!(check(1, zz, AccessKind::Read).unwrap_err().contains("no variable named"));
+}
@@ -0,0 +1,41 @@
+//! The `points` of a `function` record count every point of the
+//! program: the start and mid-point of each action, and of the end of
+//! each block.
+
+extern crate nll;
+extern crate nll_repr;
+
+use nll::corpus;
+use nll::errors::SourceFile;
+use nll::options::Options;
+use nll_repr::repr::Func;
+use std::path::Path;
+
+const PROGRAM: &str = "\
+let x: ();
+let p: &'p ();
+
+block START {
+    x = use();
+    p = &'b0 x;
+    goto END;
+}
+
+block END {
+    use(p);
+}
+";
+
+#[test]
+fn function_points_count_start_and_mid_points() {
+    let func = Func::parse_file(Path::new("points.nll"), PROGRAM).unwrap();
+    let source = SourceFile { name: "points.nll", text: PROGRAM };
+    let records = corpus::records("points.nll", &func, &Options::default(), &source);
+    let function = records
+        .iter()
+        .find(|record| record.find("kind").and_then(|kind| kind.as_string()) == Some("function"))
+        .unwrap();
+    // two actions and the end of `START`, one action and the end of
+    // `END`, with two points each
+    assert_eq!(function.find("points").and_then(|points| points.as_u64()), Some(10));
+}
@@ -0,0 +1,78 @@
+//! The points where a region is live are added to its value as runs
+//! of straight-line code, so a representation that stores runs keeps
+//! such a region down to a run per block, however long the block.
+//! With `self_check`, the solution is checked against the one found
+//! with a live point at a time.
+
+extern crate nll;
+extern crate nll_repr;
+
+use nll::env::Environment;
+use nll::errors::SourceFile;
+use nll::graph::{self, BasicBlockIndex, FuncGraph};
+use nll::options::{Options, RegionRepr};
+use nll::regionck;
+use nll_repr::repr::{Func, RegionName};
+use std::fs;
+use std::mem;
+use std::path::Path;
+
+/// A block of `n` uses of `p`, which is live across all of them.
+fn program(n: usize) -> String {
+    let mut text = String::from("let x: ();\nlet p: &'p ();\n\nblock START {\n");
+    text.push_str("    x = use();\n    p = &'x x;\n");
+    for _ in 0..n {
+        text.push_str("    use(p);\n");
+    }
+    text.push_str("}\n");
+    text
+}
+
+/// The number of bytes that the value of `'p` holds on the heap.
+fn heap_size_of_p(text: &str, region_repr: RegionRepr) -> usize {
+    let func = Func::parse_file(Path::new("live-runs.nll"), text).unwrap();
+    let graph = FuncGraph::new(func);
+    let source = SourceFile { name: "live-runs.nll", text };
+    let options = Options { region_repr, ..Options::default() };
+    graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        let analysis = regionck::analysis(&env, &options, &source).unwrap();
+        analysis.regions[&RegionName::from("'p")].heap_size()
+    })
+}
+
+#[test]
+fn intervals_hold_one_run_per_block() {
+    let one_run = mem::size_of::<BasicBlockIndex>() + mem::size_of::<(usize, usize)>();
+    for &n in &[1, 10, 100] {
+        assert_eq!(heap_size_of_p(&program(n), RegionRepr::Intervals), one_run);
+    }
+}
+
+#[test]
+fn corpus_solves_as_with_one_live_point_at_a_time() {
+    let options = Options { self_check: true, ..Options::default() };
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../test");
+    let mut analyzed = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if !path.extension().is_some_and(|e| e == "nll") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        let func = match Func::parse_file(&path, &text) {
+            Ok(func) => func,
+            Err(_) => continue,
+        };
+        let name = path.display().to_string();
+        let graph = FuncGraph::new(func);
+        let source = SourceFile { name: &name, text: &text };
+        graph::with_graph(&graph, || {
+            let env = Environment::new(&graph);
+            if regionck::analysis(&env, &options, &source).is_ok() {
+                analyzed += 1;
+            }
+        });
+    }
+    assert!(analyzed > 0);
+}
@@ -0,0 +1,55 @@
+//! How the loans in scope are stored does not change the result: with
+//! each `LoanSetRepr`, the corpus has the same errors.
+
+extern crate nll;
+extern crate nll_repr;
+
+use nll::env::Environment;
+use nll::errors::SourceFile;
+use nll::graph::{self, FuncGraph};
+use nll::options::{LoanSetRepr, Options};
+use nll::regionck;
+use nll_repr::repr::Func;
+use std::fs;
+use std::path::Path;
+
+/// The errors found in `func` with `loan_set_repr`, as text.
+fn errors(name: &str, text: &str, func: Func, loan_set_repr: LoanSetRepr) -> Vec<String> {
+    let graph = FuncGraph::new(func);
+    let source = SourceFile { name, text };
+    let options = Options { loan_set_repr, ..Options::default() };
+    graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        match regionck::analysis(&env, &options, &source) {
+            Ok(analysis) => analysis
+                .errors
+                .iter()
+                .map(|e| format!("{:?}: {:?} {}", e.point, e.code, e.message))
+                .collect(),
+            Err(e) => vec![e.to_string()],
+        }
+    })
+}
+
+#[test]
+fn dense_and_hybrid_find_the_same_errors() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../test");
+    let mut errors_compared = 0;
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if !path.extension().is_some_and(|e| e == "nll") {
+            continue;
+        }
+        let text = fs::read_to_string(&path).unwrap();
+        let func = match Func::parse_file(&path, &text) {
+            Ok(func) => func,
+            Err(_) => continue,
+        };
+        let name = path.display().to_string();
+        let dense = errors(&name, &text, func.clone(), LoanSetRepr::Dense);
+        let hybrid = errors(&name, &text, func, LoanSetRepr::Hybrid);
+        assert_eq!(dense, hybrid, "{}", name);
+        errors_compared += dense.len();
+    }
+    assert!(errors_compared > 0);
+}
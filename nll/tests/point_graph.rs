@@ -0,0 +1,116 @@
+//! A `PointGraph` has the points and edges of
+//! `Environment::successor_points`, and the dominators found over it
+//! agree with those of the blocks.
+
+extern crate graph_algorithms;
+extern crate nll;
+extern crate nll_repr;
+
+use graph_algorithms::Graph;
+use graph_algorithms::dominators;
+use nll::env::{Environment, Point};
+use nll::graph::{self, FuncGraph};
+use nll::point_graph::PointGraph;
+use nll_repr::repr::Func;
+
+/// A branch, a block with no actions, and a loop.
+const PROGRAM: &str = "\
+let x: ();
+
+block START {
+    x = use();
+    goto LEFT RIGHT;
+}
+
+block LEFT {
+    use(x);
+    goto JOIN;
+}
+
+block RIGHT {
+    goto JOIN;
+}
+
+block JOIN {
+    use(x);
+    goto LOOP EXIT;
+}
+
+block LOOP {
+    use(x);
+    use(x);
+    goto JOIN;
+}
+
+block EXIT {
+    use(x);
+}
+";
+
+fn with_program<R>(f: impl FnOnce(&Environment, &PointGraph) -> R) -> R {
+    let graph = FuncGraph::new(Func::parse(PROGRAM).unwrap());
+    graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        let points = PointGraph::new(&graph);
+        f(&env, &points)
+    })
+}
+
+#[test]
+fn points_round_trip_through_indices() {
+    with_program(|env, points| {
+        let all: Vec<Point> = env.all_points().collect();
+        assert_eq!(points.num_nodes(), all.len());
+        for &p in &all {
+            assert_eq!(points.point(points.index(p)), p);
+        }
+    });
+}
+
+#[test]
+fn edges_are_those_of_successor_points() {
+    with_program(|env, points| {
+        let all: Vec<Point> = env.all_points().collect();
+        for &p in &all {
+            let mut expected = env.successor_points(p);
+            expected.sort();
+            let mut successors: Vec<Point> =
+                points.successors(points.index(p)).map(|q| points.point(q)).collect();
+            successors.sort();
+            assert_eq!(successors, expected, "successors of {:?}", p);
+
+            let mut expected: Vec<Point> = all.iter()
+                .cloned()
+                .filter(|&q| env.successor_points(q).contains(&p))
+                .collect();
+            expected.sort();
+            let mut predecessors: Vec<Point> =
+                points.predecessors(points.index(p)).map(|q| points.point(q)).collect();
+            predecessors.sort();
+            assert_eq!(predecessors, expected, "predecessors of {:?}", p);
+        }
+    });
+}
+
+#[test]
+fn dominators_agree_with_those_of_blocks() {
+    with_program(|env, points| {
+        let point_dominators = dominators::dominators(points);
+        let all: Vec<Point> = env.all_points().collect();
+        for &p in &all {
+            for &q in &all {
+                // within a block, each point dominates those after it
+                let expected = if p.block == q.block {
+                    q.index_in_block() <= p.index_in_block()
+                } else {
+                    env.dominators.is_dominated_by(p.block, q.block)
+                };
+                assert_eq!(point_dominators.is_dominated_by(points.index(p), points.index(q)),
+                           expected,
+                           "{:?} dominated by {:?}",
+                           p,
+                           q);
+            }
+        }
+    });
+}
@@ -0,0 +1,32 @@
+//! Runs `nll` with a `--timeout` too short for any analysis to finish:
+//! each input fails saying where it timed out, the run goes on with
+//! the next one, and nothing is cached.
+
+use std::env;
+use std::fs;
+use std::process::{self, Command};
+
+#[test]
+fn timed_out_inputs_fail_and_are_not_cached() {
+    let cache_dir = env::temp_dir().join(format!("nll-timeout-test-{}", process::id()));
+    let inputs = ["borrowck-read-write-actions.nll", "unsafe-raw-pointer-conflict.nll"];
+    let output = Command::new(env!("CARGO_BIN_EXE_nll"))
+        .arg("--cache-dir")
+        .arg(&cache_dir)
+        .arg("--timeout")
+        .arg("0.000000001")
+        .args(inputs.iter().map(|input| {
+            format!("{}/../test/{}", env!("CARGO_MANIFEST_DIR"), input)
+        }))
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success(), "{}", stdout);
+    for input in &inputs {
+        let prefix = format!("{}: timed out after 1ns, in liveness", input);
+        assert!(stdout.lines().any(|line| line.contains(&prefix)), "{}", stdout);
+    }
+    let cached = fs::read_dir(&cache_dir).map(|entries| entries.count()).unwrap_or(0);
+    assert_eq!(cached, 0);
+    let _ = fs::remove_dir_all(&cache_dir);
+}
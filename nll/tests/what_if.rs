@@ -0,0 +1,104 @@
+//! `nll what-if` on a small program: an action that is accepted, one
+//! that is an error, one that makes regions grow, and ones that name
+//! paths or points the program does not have.
+
+extern crate nll;
+extern crate nll_repr;
+
+use nll::env::Environment;
+use nll::errors::SourceFile;
+use nll::graph::{self, FuncGraph};
+use nll::options::Options;
+use nll::what_if::{self, WhatIf};
+use nll_repr::repr::{Func, RegionName};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+
+const PROGRAM: &str = "\
+let x: ();
+let p: &'p ();
+
+block START {
+    x = use();
+    p = &'b0 x;
+    use(p);
+    use(x);
+}
+";
+
+fn what_if(at: &str, action: &str) -> Result<WhatIf, String> {
+    let func = Func::parse_file(Path::new("what-if.nll"), PROGRAM).unwrap();
+    let graph = FuncGraph::new(func);
+    let source = SourceFile { name: "what-if.nll", text: PROGRAM };
+    graph::with_graph(&graph, || {
+        let env = Environment::new(&graph);
+        let point = env.parse_point(at).unwrap();
+        what_if::what_if(&env, &Options::default(), &source, point, action)
+            .map_err(|e| e.to_string())
+    })
+}
+
+#[test]
+fn accepted_action() {
+    let result = what_if("START/2", "use(x)").unwrap();
+    assert!(result.errors.is_empty());
+    assert!(result.new_errors.is_empty());
+    assert!(result.grown.is_empty());
+}
+
+#[test]
+fn action_that_errors() {
+    let result = what_if("START/2", "x = use()").unwrap();
+    let codes: Vec<_> = result.errors.iter().map(|e| e.code).collect();
+    assert_eq!(codes, vec![Some("E0506")]);
+    assert!(result.new_errors.is_empty());
+}
+
+#[test]
+fn regions_grow_to_a_later_use() {
+    let result = what_if("START/3", "use(p)").unwrap();
+    assert!(result.errors.is_empty());
+    let grown: Vec<_> = result.grown.iter().map(|&(name, _)| name).collect();
+    assert_eq!(grown.len(), 2);
+    assert!(grown.contains(&RegionName::from("'p")));
+    assert!(grown.contains(&RegionName::from("'b0")));
+}
+
+#[test]
+fn actions_naming_missing_paths_fail() {
+    assert!(what_if("START/2", "use(zz)").err().unwrap().contains("no variable named"));
+    assert!(what_if("START/2", "use(p.f)").err().unwrap().contains("cannot index &"));
+    assert!(what_if("START/2", "use(").err().unwrap().starts_with("in action `use(`"));
+}
+
+/// Runs `nll what-if` on `PROGRAM`, returning whether it succeeded,
+/// and what it printed.
+fn run(at: &str, action: &str) -> (bool, String) {
+    let input = env::temp_dir().join(format!("nll-what-if-test-{}.nll", process::id()));
+    fs::write(&input, PROGRAM).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_nll"))
+        .arg("what-if")
+        .arg(&input)
+        .args(&["--at", at, "--action", action])
+        .output()
+        .unwrap();
+    fs::remove_file(&input).unwrap();
+    (output.status.success(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn cli_reports_and_rejects() {
+    let (ok, stdout) = run("START/3", "use(p)");
+    assert!(ok, "{}", stdout);
+    assert!(stdout.contains("regions that have to grow:"), "{}", stdout);
+
+    let (ok, stdout) = run("START/9", "use(p)");
+    assert!(!ok);
+    assert!(stdout.contains("invalid point `START/9`"), "{}", stdout);
+
+    let (ok, stdout) = run("START/2", "use(zz)");
+    assert!(!ok);
+    assert!(stdout.contains("in action `use(zz)`"), "{}", stdout);
+}